@@ -0,0 +1,50 @@
+//! Measures looking card types up by name and tag in a large card pool against going through
+//! the whole pool, which is how names were looked up before the pool indexed them.
+//!
+//! Run with `cargo bench --bench lookup`.
+
+use card_game::cards::MemorySource;
+use card_game::prelude::*;
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const CARDS: usize = 10_000;
+const LOOKUPS: usize = 10_000;
+
+/// Times the lookup over every name, returning how long it took on average
+fn time<'a>(names: &'a [String], lookup: impl Fn(&'a str) -> usize) -> Duration {
+    let start = Instant::now();
+    for name in names.iter().cycle().take(LOOKUPS) {
+        black_box(lookup(name));
+    }
+    start.elapsed() / LOOKUPS as u32
+}
+
+fn main() {
+    let files = (0..CARDS)
+        .map(|i| {
+            let tags = if i % 10 == 0 { "[\"rare\"]" } else { "[]" };
+            format!("name = \"Card {}\"\ndefense = 1\nattack = 1\ntags = {}", i, tags)
+        })
+        .collect();
+    let cards = Cards::from_source(MemorySource { files }).unwrap();
+    let names: Vec<String> = (0..CARDS).map(|i| format!("Card {}", i)).collect();
+
+    let scanned = time(&names, |name| cards.iter()
+        .filter(|card_type| card_type.name == name)
+        .max_by_key(|card_type| card_type.version)
+        .map_or(0, |card_type| card_type.id.0 as usize));
+    let indexed = time(&names, |name| cards.card(name.to_owned()).map_or(0, |card_type| card_type.id.0 as usize));
+    println!("by name in a pool of {} card types:", CARDS);
+    println!("  going through the pool {:?}, indexed {:?}, {:.0}x faster", scanned, indexed, ratio(scanned, indexed));
+
+    let scanned = time(&names, |_| cards.iter().filter(|card_type| card_type.has_tag("rare")).count());
+    let indexed = time(&names, |_| cards.tagged("rare").len());
+    println!("by tag:");
+    println!("  going through the pool {:?}, indexed {:?}, {:.0}x faster", scanned, indexed, ratio(scanned, indexed));
+}
+
+fn ratio(slow: Duration, fast: Duration) -> f64 {
+    slow.as_secs_f64() / fast.as_secs_f64().max(1e-12)
+}
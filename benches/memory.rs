@@ -0,0 +1,54 @@
+//! Measures how much memory a game takes up, which matters for servers hosting many games and for
+//! searches holding many copies of a game state.
+//!
+//! Run with `cargo bench --bench memory` from the repository root so the card data is found.
+
+use card_game::prelude::*;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::mem::size_of;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Wraps the system allocator to count the bytes currently allocated on the heap
+struct Counting;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::SeqCst);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// Builds a left deck, center deck, right deck and starting hand by cycling through the card pool
+fn build_decks(cards: &Cards, instances: &mut CardInstances) -> (Vec<Card>, Vec<Card>, Vec<Card>, Vec<Card>) {
+    let mut pool = cards.iter().cycle();
+    let mut deck = |size| (0..size).map(|_| instances.instantiate(pool.next().unwrap())).collect();
+    (deck(15), deck(5), deck(15), deck(5))
+}
+
+fn main() {
+    let cards = Cards::get();
+    let games = 1000;
+    // the games themselves are allocated up front so only the heap they use is counted
+    let mut hosted: Vec<GameState> = Vec::with_capacity(games);
+    let before = ALLOCATED.load(Ordering::SeqCst);
+    for _ in 0..games {
+        let mut instances = CardInstances::default();
+        hosted.push(GameState::start(build_decks(cards, &mut instances), build_decks(cards, &mut instances)));
+    }
+    let heap = ALLOCATED.load(Ordering::SeqCst) - before;
+    println!("size_of::<Card>() = {} bytes", size_of::<Card>());
+    println!("size_of::<GameState>() = {} bytes", size_of::<GameState>());
+    println!("{} games of 80 cards use {} bytes of heap, {} bytes per game", hosted.len(), heap, heap / games);
+    println!("{} bytes per game in total", size_of::<GameState>() + heap / games);
+}
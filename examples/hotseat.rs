@@ -0,0 +1,55 @@
+//! Two players sharing one terminal, taking turns to pick their options by number.
+//!
+//! Run with `cargo run --example hotseat` from the repository root so the card data is found.
+
+use card_game::prelude::*;
+
+use std::error::Error;
+use std::io::{self, BufRead, Write};
+
+/// Builds a left deck, center deck, right deck and starting hand by cycling through the card pool
+fn build_decks(cards: &Cards, instances: &mut CardInstances) -> (Vec<Card>, Vec<Card>, Vec<Card>, Vec<Card>) {
+    let mut pool = cards.iter().cycle();
+    let mut deck = |size| (0..size).map(|_| instances.instantiate(pool.next().unwrap())).collect();
+    (deck(5), vec![], deck(5), deck(3))
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let cards = Cards::get();
+    let mut instances = CardInstances::default();
+    let mut game = GameState::start(build_decks(cards, &mut instances), build_decks(cards, &mut instances));
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut seen = EventSeq::default();
+    loop {
+        if let Some(result) = game.result(cards) {
+            match result.winner() {
+                Some(winner) => println!("{:?} wins! ({:?})", winner, result.reason()),
+                None => println!("Nobody wins ({:?})", result.reason()),
+            }
+            return Ok(());
+        }
+        let options = game.priority_player_options(cards);
+        println!("{:?}", game);
+        println!("Player {:?} to choose:", game.priority());
+        for (i, option) in options.iter().enumerate() {
+            println!("  {}: {:?}", i, option);
+        }
+        print!("> ");
+        io::stdout().flush()?;
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => return Ok(()),
+        };
+        let option = match line.trim().parse::<usize>().ok().and_then(|i| options.get(i)) {
+            Some(option) => option.clone(),
+            None => {
+                println!("Choose one of the numbers listed");
+                continue;
+            }
+        };
+        game.priorty_player_take_option(cards, option)?;
+        println!("{}", describe_turn(game.events_since(seen), cards));
+        seen = game.event_seq();
+    }
+}
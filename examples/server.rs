@@ -0,0 +1,435 @@
+//! Hosting a game for two players over TCP. Each player connects with a line based client such as
+//! `nc 127.0.0.1 7878` and sends `auth <token>`, the first to do so plays as player one. Any
+//! token is accepted as the player's name unless the server is started with
+//! `--tokens=<token>:<name>,<token>:<name>`, in which case only those are. The player with
+//! priority is sent their options and replies with the number of the option they choose, and
+//! both players are sent a summary of what happened after every choice.
+//!
+//! Casual games can be hosted with `--allow-rewind`, which lets the player with priority reply
+//! `rewind` to ask their opponent to undo the last option taken. The opponent replies `y` to
+//! agree. With `--auto-forced` players aren't sent options when they only have one, which is
+//! taken for them. At the end of the game both players are sent how long each player took to
+//! make their decisions.
+//!
+//! Each connection is first sent the server's `rules <engine version> <fingerprint>`. Clients
+//! which simulate the game themselves can send their own `rules` line before authenticating,
+//! and are turned away if it doesn't match rather than drifting out of step with the server.
+//!
+//! Players who stay quiet while they are being waited on are sent `ping`, and any line they send
+//! back such as `pong` shows they are still connected. A player who is quiet for too long is
+//! considered disconnected, and with `--forfeit-on-disconnect` they lose rather than the game
+//! being stopped. Players who send too many messages have them ignored, and players who send
+//! an oversized message are disconnected.
+//!
+//! Built with the `ws` feature, `--ws` hosts the game over WebSockets instead, with each line
+//! sent as a text message, so browser clients can connect to `ws://127.0.0.1:7878`.
+//!
+//! With `--admin-secret=<secret>` operators can connect to 127.0.0.1:7879, send `auth <secret>`
+//! and then manage the game with `list`, `inspect 0`, `end 0`, `adjudicate 0 p1` (or `p2` or
+//! `draw`) and `broadcast <notice>`.
+//!
+//! Built with the `metrics` feature, `--metrics` serves counts of games, actions, decision times,
+//! desyncs and disconnects for Prometheus to scrape from `http://127.0.0.1:7880/metrics`.
+//!
+//! Run with `cargo run --example server` (or `cargo run --example server --features ws -- --ws`) from the repository root so the card data is found.
+
+use card_game::prelude::*;
+use card_game::admin::{authenticate, AdminCommand, LiveGames};
+use card_game::driver::{DriverConfig, GameDriver};
+use card_game::ids::PlayerId;
+#[cfg(feature = "metrics")]
+use card_game::metrics::Metrics;
+use card_game::net::{
+    auth_token, read_limited_line, AnyToken, Limits, Liveness, LivenessConfig, RateLimiter, RulesVersion,
+    StaticTokens, TokenValidator, PING, PONG,
+};
+#[cfg(feature = "ws")]
+use card_game::net::ws::WebSocket;
+use card_game::rewind::RewindConfig;
+
+use std::error::Error;
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Builds a left deck, center deck, right deck and starting hand by cycling through the card pool
+fn build_decks(cards: &Cards, instances: &mut CardInstances) -> (Vec<Card>, Vec<Card>, Vec<Card>, Vec<Card>) {
+    let mut pool = cards.iter().cycle();
+    let mut deck = |size| (0..size).map(|_| instances.instantiate(pool.next().unwrap())).collect();
+    (deck(5), vec![], deck(5), deck(3))
+}
+
+/// How lines are sent to and from a player
+enum Transport {
+    Lines {
+        reader: BufReader<TcpStream>,
+        writer: TcpStream,
+    },
+    #[cfg(feature = "ws")]
+    WebSocket(Box<WebSocket<TcpStream>>),
+}
+
+impl Transport {
+    fn stream(&self) -> &TcpStream {
+        match self {
+            Transport::Lines { writer, .. } => writer,
+            #[cfg(feature = "ws")]
+            Transport::WebSocket(socket) => socket.get_ref(),
+        }
+    }
+
+    fn write_line(&mut self, message: &str) -> io::Result<()> {
+        match self {
+            Transport::Lines { writer, .. } => writeln!(writer, "{}", message),
+            #[cfg(feature = "ws")]
+            Transport::WebSocket(socket) => socket.send(message),
+        }
+    }
+
+    /// Appends the next line to the string, as with `BufRead::read_line`, failing if it is
+    /// longer than the limit
+    fn read_line(&mut self, line: &mut String, limits: &Limits) -> io::Result<usize> {
+        match self {
+            Transport::Lines { reader, .. } => read_limited_line(reader, line, limits.max_message),
+            #[cfg(feature = "ws")]
+            Transport::WebSocket(socket) => Ok(match socket.receive()? {
+                Some(message) => {
+                    line.push_str(&message);
+                    line.push('\n');
+                    message.len() + 1
+                },
+                None => 0,
+            }),
+        }
+    }
+}
+
+struct Connection {
+    transport: Transport,
+    liveness: Liveness,
+    // set once the player disconnects, after which nothing more is sent to them
+    closed: bool,
+    // the start of a line which hasn't finished arriving
+    pending: String,
+    identity: PlayerId,
+    limits: Limits,
+    rate: RateLimiter,
+}
+
+impl Connection {
+    /// Waits for a connection which authenticates with a valid token, and binds who it belongs
+    /// to to the player
+    fn accept(
+        listener: &TcpListener,
+        player: Player,
+        websocket: bool,
+        validator: &dyn TokenValidator,
+        limits: &Limits,
+        game: &mut GameDriver,
+    ) -> Result<Self, Box<dyn Error>> {
+        loop {
+            let (stream, address) = listener.accept()?;
+            println!("Connection from {}", address);
+            let transport = if websocket {
+                #[cfg(feature = "ws")]
+                {
+                    let mut socket = WebSocket::accept(stream)?;
+                    socket.set_max_message(limits.max_message);
+                    Transport::WebSocket(Box::new(socket))
+                }
+                #[cfg(not(feature = "ws"))]
+                { return Err("WebSockets need the ws feature".into()) }
+            } else {
+                Transport::Lines {
+                    reader: BufReader::new(stream.try_clone()?),
+                    writer: stream,
+                }
+            };
+            let mut connection = Connection {
+                transport,
+                liveness: Liveness::new(LivenessConfig::default(), Instant::now()),
+                closed: false,
+                pending: String::new(),
+                identity: PlayerId(String::new()),
+                limits: *limits,
+                rate: RateLimiter::new(limits, Instant::now()),
+            };
+            let rules = RulesVersion::current();
+            connection.send(&rules.to_string())?;
+            connection.send("Send auth <token> to play")?;
+            let mut received = connection.receive(&|| false)?;
+            // clients which simulate the game themselves send their rules first
+            if let Received::Line(line) = &received {
+                if let Some(theirs) = RulesVersion::parse(line) {
+                    if let Err(mismatch) = rules.check(&theirs) {
+                        println!("Turned away a client: {}", mismatch);
+                        connection.send(&format!("Rules mismatch: {}", mismatch))?;
+                        continue;
+                    }
+                    received = connection.receive(&|| false)?;
+                }
+            }
+            let identity = match received {
+                Received::Line(line) => auth_token(&line).and_then(|token| validator.validate(token)),
+                _ => None,
+            };
+            match identity {
+                Some(identity) if game.bind(player, identity.clone()).is_ok() => {
+                    println!("{} is playing as player {:?}", identity.0, player);
+                    connection.send(&format!("You are player {:?}", player))?;
+                    connection.identity = identity;
+                    return Ok(connection);
+                },
+                _ => connection.send("Not authenticated")?,
+            }
+        }
+    }
+
+    fn send(&mut self, message: &str) -> Result<(), Box<dyn Error>> {
+        if self.closed {
+            return Ok(());
+        }
+        self.transport.write_line(message)?;
+        Ok(())
+    }
+
+    /// Reads the next line from the player, pinging them while they are quiet and checking
+    /// every second if they should stop being waited on
+    fn receive(&mut self, interrupted: &dyn Fn() -> bool) -> Result<Received, Box<dyn Error>> {
+        self.transport.stream().set_read_timeout(Some(Duration::from_secs(1)))?;
+        loop {
+            match self.transport.read_line(&mut self.pending, &self.limits) {
+                Ok(0) => break,
+                Ok(_) => {
+                    let now = Instant::now();
+                    self.liveness.heard(now);
+                    let line = std::mem::take(&mut self.pending);
+                    if !self.rate.allow(now) {
+                        self.send("Too many messages, this one was ignored")?;
+                        continue;
+                    }
+                    match line.trim() {
+                        PING => self.send(PONG)?,
+                        PONG => (),
+                        _ => return Ok(Received::Line(line)),
+                    }
+                },
+                Err(error) if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    if interrupted() {
+                        return Ok(Received::Interrupted);
+                    }
+                    let now = Instant::now();
+                    if !self.liveness.is_alive(now) {
+                        break;
+                    }
+                    if self.liveness.ping_due(now) {
+                        self.send(PING)?;
+                    }
+                },
+                Err(error) if error.kind() == ErrorKind::InvalidData => {
+                    println!("A player sent an invalid message: {}", error);
+                    break;
+                },
+                Err(error) => return Err(error.into()),
+            }
+        }
+        println!("A player disconnected");
+        self.closed = true;
+        Ok(Received::Disconnected)
+    }
+}
+
+enum Received {
+    Line(String),
+    Disconnected,
+    /// Something happened to the game while waiting, such as an operator ending it
+    Interrupted,
+}
+
+/// Serves an operator's admin connection until they disconnect
+fn serve_admin(stream: TcpStream, games: &Mutex<LiveGames>, secret: &str, cards: &Cards) -> Result<(), Box<dyn Error>> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    if !authenticate(&line, secret) {
+        writeln!(writer, "Not authenticated")?;
+        return Ok(());
+    }
+    writeln!(writer, "Authenticated")?;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        let reply = line.parse::<AdminCommand>()
+            .and_then(|command| games.lock().unwrap().execute(command, cards))
+            .unwrap_or_else(|error| format!("Error: {}", error));
+        writeln!(writer, "{}", reply)?;
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let cards = Cards::get();
+    let games = Arc::new(Mutex::new(LiveGames::default()));
+    if let Some(secret) = std::env::args().find_map(|arg| arg.strip_prefix("--admin-secret=").map(str::to_owned)) {
+        let admin = TcpListener::bind("127.0.0.1:7879")?;
+        println!("Listening for operators on {}", admin.local_addr()?);
+        let games = games.clone();
+        thread::spawn(move || {
+            for stream in admin.incoming().flatten() {
+                let games = games.clone();
+                let secret = secret.clone();
+                thread::spawn(move || serve_admin(stream, &games, &secret, cards).ok());
+            }
+        });
+    }
+    #[cfg(feature = "metrics")]
+    let metrics = Arc::new(Metrics::default());
+    #[cfg(feature = "metrics")]
+    if std::env::args().any(|arg| arg == "--metrics") {
+        let scrapes = TcpListener::bind("127.0.0.1:7880")?;
+        println!("Serving metrics on http://{}/metrics", scrapes.local_addr()?);
+        let metrics = metrics.clone();
+        thread::spawn(move || {
+            for mut stream in scrapes.incoming().flatten() {
+                // every path gets the metrics, so the request is read only to be discarded
+                let mut request = [0; 1024];
+                let _ = io::Read::read(&mut stream, &mut request);
+                let body = metrics.render();
+                let _ = write!(
+                    stream,
+                    "HTTP/1.0 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\n\r\n{}",
+                    body.len(),
+                    body,
+                );
+            }
+        });
+    }
+    let listener = TcpListener::bind("127.0.0.1:7878")?;
+    println!("Waiting for players on {}", listener.local_addr()?);
+    let websocket = std::env::args().any(|arg| arg == "--ws");
+    let validator: Box<dyn TokenValidator> = match std::env::args().find_map(|arg| arg.strip_prefix("--tokens=").map(str::to_owned)) {
+        Some(tokens) => {
+            let mut validator = StaticTokens::default();
+            for (token, name) in tokens.split(',').filter_map(|pair| pair.split_once(':')) {
+                validator.insert(token, PlayerId(name.to_owned()));
+            }
+            Box::new(validator)
+        },
+        None => Box::new(AnyToken),
+    };
+    let mut instances = CardInstances::default();
+    let game = GameState::start(build_decks(cards, &mut instances), build_decks(cards, &mut instances));
+    let config = DriverConfig {
+        rewind: RewindConfig {
+            allow_rewind: std::env::args().any(|arg| arg == "--allow-rewind"),
+            ..RewindConfig::default()
+        },
+        auto_forced: std::env::args().any(|arg| arg == "--auto-forced"),
+        forfeit_on_disconnect: std::env::args().any(|arg| arg == "--forfeit-on-disconnect"),
+        limits: Limits::default(),
+        // the decks are built without any randomness
+        seed: None,
+        record_views: false,
+    };
+    let game = GameDriver::new(game, config);
+    #[cfg(feature = "metrics")]
+    let game = game.with_metrics(metrics);
+    let mut game = game;
+    let mut player_one = Connection::accept(&listener, Player::One, websocket, &*validator, &config.limits, &mut game)?;
+    let mut player_two = Connection::accept(&listener, Player::Two, websocket, &*validator, &config.limits, &mut game)?;
+    let id = games.lock().unwrap().insert(game);
+    let mut seen = EventSeq::default();
+    let mut notices_seen = 0;
+    loop {
+        let mut live = games.lock().unwrap();
+        for notice in &live.notices()[notices_seen..] {
+            player_one.send(&format!("Notice: {}", notice))?;
+            player_two.send(&format!("Notice: {}", notice))?;
+        }
+        notices_seen = live.notices().len();
+        let game = live.get_mut(id).unwrap();
+        let options = game.options(cards);
+        let summary = describe_turn(game.game().events_since(seen), cards);
+        seen = game.game().event_seq();
+        if !summary.is_empty() {
+            player_one.send(&summary)?;
+            player_two.send(&summary)?;
+        }
+        if let Some(result) = game.result(cards) {
+            live.remove(id);
+            let message = match result.result.winner() {
+                Some(winner) => format!("Player {:?} wins! ({:?})", winner, result.result.reason()),
+                None => format!("Nobody wins ({:?})", result.result.reason()),
+            };
+            player_one.send(&message)?;
+            player_two.send(&message)?;
+            for player in [Player::One, Player::Two].iter() {
+                let times = result.think_times(*player);
+                let message = format!(
+                    "Player {:?} made {} decisions, taking {:?} on average and {:?} at most",
+                    player, times.decisions, times.average(), times.max
+                );
+                player_one.send(&message)?;
+                player_two.send(&message)?;
+            }
+            return Ok(());
+        }
+        let priority = game.game().priority();
+        // the game is unlocked while waiting for players so operators can act on it
+        drop(live);
+        let interrupted = || {
+            let live = games.lock().unwrap();
+            live.notices().len() > notices_seen || live.get(id).is_none_or(|game| game.result(cards).is_some())
+        };
+        let (connection, opponent) = match priority {
+            Player::One => (&mut player_one, &mut player_two),
+            Player::Two => (&mut player_two, &mut player_one),
+        };
+        for (i, option) in options.iter().enumerate() {
+            connection.send(&format!("{}: {:?}", i, option))?;
+        }
+        let line = match connection.receive(&interrupted)? {
+            Received::Line(line) => line,
+            Received::Disconnected => {
+                games.lock().unwrap().get_mut(id).unwrap().disconnected(priority).ok();
+                continue;
+            },
+            Received::Interrupted => continue,
+        };
+        if line.trim() == "rewind" {
+            if games.lock().unwrap().get_mut(id).unwrap().request_rewind(priority).is_err() {
+                connection.send("Nothing can be rewound")?;
+                continue;
+            }
+            opponent.send(&format!("Player {:?} asks to rewind one action, reply y to agree", priority))?;
+            let agree = match opponent.receive(&|| false)? {
+                Received::Line(line) => line.trim() == "y",
+                Received::Disconnected => {
+                    games.lock().unwrap().get_mut(id).unwrap().disconnected(priority.next()).ok();
+                    continue;
+                },
+                Received::Interrupted => continue,
+            };
+            if !games.lock().unwrap().get_mut(id).unwrap().respond_to_rewind(priority.next(), agree)? {
+                connection.send("Your opponent declined to rewind")?;
+            }
+            continue;
+        }
+        let option = match line.trim().parse::<usize>().ok().and_then(|i| options.get(i)) {
+            Some(option) => option.clone(),
+            None => {
+                connection.send("Choose one of the numbers listed")?;
+                continue;
+            }
+        };
+        let identity = &connection.identity;
+        if games.lock().unwrap().get_mut(id).unwrap().take_option_as(identity, cards, option).is_err() {
+            // an operator ended the game after the options were sent
+            continue;
+        }
+    }
+}
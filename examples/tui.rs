@@ -0,0 +1,57 @@
+//! Two players sharing one terminal in a full screen interface. Move between the options with
+//! the arrow keys or `j` and `k`, choose one with Enter, and quit with `q`. The board is shown
+//! from the side of whoever has priority, with cards colored by `data/theme.toml`.
+//!
+//! Run with `cargo run --example tui --features tui` from the repository root so the card data
+//! is found.
+
+use card_game::prelude::*;
+use card_game::theme::Theme;
+use card_game::tui::{Key, Tui};
+
+use ratatui::DefaultTerminal;
+
+use std::error::Error;
+
+/// Builds a left deck, center deck, right deck and starting hand by cycling through the card pool
+fn build_decks(cards: &Cards, instances: &mut CardInstances) -> (Vec<Card>, Vec<Card>, Vec<Card>, Vec<Card>) {
+    let mut pool = cards.iter().cycle();
+    let mut deck = |size| (0..size).map(|_| instances.instantiate(pool.next().unwrap())).collect();
+    (deck(5), vec![], deck(5), deck(3))
+}
+
+/// Plays until the game ends or a player quits, returning the result if the game ended
+fn play(terminal: &mut DefaultTerminal, cards: &Cards) -> Result<Option<GameResult>, Box<dyn Error>> {
+    let mut instances = CardInstances::default();
+    let mut game = GameState::start(build_decks(cards, &mut instances), build_decks(cards, &mut instances));
+    let mut tui = Tui::with_theme(Theme::load()?);
+    loop {
+        game.take_forced_options(cards);
+        tui.update(&game, cards);
+        terminal.draw(|frame| tui.render(frame, &game, game.priority(), cards))?;
+        if let Some(result) = game.result(cards) {
+            return Ok(Some(result));
+        }
+        let options = game.priority_player_options(cards);
+        match Key::read()? {
+            Key::Quit => return Ok(None),
+            key => if let Some(chosen) = tui.handle_key(key, options.len()) {
+                game.priorty_player_take_option(cards, options[chosen].clone())?;
+            },
+        }
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let cards = Cards::get();
+    let mut terminal = ratatui::init();
+    let result = play(&mut terminal, cards);
+    ratatui::restore();
+    if let Some(result) = result? {
+        match result.winner() {
+            Some(winner) => println!("{:?} wins! ({:?})", winner, result.reason()),
+            None => println!("Nobody wins ({:?})", result.reason()),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,149 @@
+//! Playing against a simple bot at the terminal. You are player one and pick your options by
+//! number, the bot is player two and always takes the first action it can. Between games you
+//! can pick new decks, rematch, or resume a game you saved part way through, and every finished
+//! game's replay is kept for the rest of the session.
+//!
+//! Run with `cargo run --example vs_bot` from the repository root so the card data is found.
+
+use card_game::archive::MemoryArchive;
+use card_game::deck::Decks;
+use card_game::driver::DriverConfig;
+use card_game::prelude::*;
+use card_game::session::Session;
+
+use std::error::Error;
+use std::io::{self, BufRead, Write};
+
+/// Takes the first action available, drawing whenever possible and otherwise skipping
+fn bot_choice(options: &[PlayerOption]) -> PlayerOption {
+    options.iter()
+        .find(|option| matches!(option, PlayerOption::Action(_)))
+        .or_else(|| options.iter().find(|option| matches!(option, PlayerOption::Draw(_))))
+        .unwrap_or(&options[0])
+        .clone()
+}
+
+/// Reads the next line typed, or None once input runs out
+fn prompt(lines: &mut impl Iterator<Item = io::Result<String>>) -> io::Result<Option<String>> {
+    print!("> ");
+    io::stdout().flush()?;
+    lines.next().transpose().map(|line| line.map(|line| line.trim().to_owned()))
+}
+
+/// Asks for one of the decks by number
+fn pick_deck<'a>(
+    names: &[&'a str], whose: &str, lines: &mut impl Iterator<Item = io::Result<String>>
+) -> io::Result<Option<&'a str>> {
+    loop {
+        println!("Pick {} deck:", whose);
+        for (i, name) in names.iter().enumerate() {
+            println!("  {}: {}", i, name);
+        }
+        let line = match prompt(lines)? {
+            Some(line) => line,
+            None => return Ok(None),
+        };
+        match line.parse::<usize>().ok().and_then(|i| names.get(i)) {
+            Some(name) => return Ok(Some(name)),
+            None => println!("Choose one of the numbers listed"),
+        }
+    }
+}
+
+/// Plays the session's game until it ends or you save it, returning false if input ran out
+fn play(
+    session: &mut Session<MemoryArchive>, cards: &Cards, lines: &mut impl Iterator<Item = io::Result<String>>
+) -> Result<bool, Box<dyn Error>> {
+    let mut seen = session.game().map(|game| game.game().event_seq()).unwrap_or_default();
+    while let Some(game) = session.game() {
+        let options = game.options(cards);
+        println!("{}", describe_turn(game.game().events_since(seen), cards));
+        seen = game.game().event_seq();
+        if let Some(result) = game.result(cards) {
+            match result.result.winner() {
+                Some(Player::One) => println!("You win! ({:?})", result.result.reason()),
+                Some(Player::Two) => println!("The bot wins! ({:?})", result.result.reason()),
+                None => println!("Nobody wins ({:?})", result.result.reason()),
+            }
+            if let Some(id) = session.finish(cards) {
+                println!("Saved the replay as game {}", id);
+            }
+            return Ok(true);
+        }
+        let option = match game.game().priority() {
+            Player::One => {
+                println!("{:?}", game.game());
+                for (i, option) in options.iter().enumerate() {
+                    println!("  {}: {:?}", i, option);
+                }
+                println!("  s: save the game to resume later");
+                let line = match prompt(lines)? {
+                    Some(line) => line,
+                    None => return Ok(false),
+                };
+                if line == "s" {
+                    println!("Saved, resume with: l {}", session.save(cards)?);
+                    return Ok(true);
+                }
+                match line.parse::<usize>().ok().and_then(|i| options.get(i)) {
+                    Some(option) => option.clone(),
+                    None => {
+                        println!("Choose one of the numbers listed");
+                        continue;
+                    }
+                }
+            },
+            Player::Two => bot_choice(&options),
+        };
+        game.take_option(cards, option)?;
+    }
+    Ok(true)
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let cards = Cards::get();
+    let driver_config = DriverConfig { auto_forced: true, ..DriverConfig::default() };
+    let players = ["you".into(), "bot".into()];
+    let mut session = Session::new(Decks::load()?, GameConfig::default(), driver_config, players, MemoryArchive::default());
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        println!("n: new game, r: rematch, l <token>: resume a saved game, q: quit");
+        let line = match prompt(&mut lines)? {
+            Some(line) => line,
+            None => break,
+        };
+        let started = match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+            ["n"] => {
+                let names = session.deck_names().into_iter().map(str::to_owned).collect::<Vec<_>>();
+                let names = names.iter().map(String::as_str).collect::<Vec<_>>();
+                let yours = match pick_deck(&names, "your", &mut lines)? {
+                    Some(name) => name,
+                    None => break,
+                };
+                let bots = match pick_deck(&names, "the bot's", &mut lines)? {
+                    Some(name) => name,
+                    None => break,
+                };
+                session.start(cards, [yours, bots]).map(|_| ())
+            },
+            ["r"] => session.rematch(cards).map(|_| ()),
+            ["l", token] => session.resume(cards, token).map(|_| ()),
+            ["q"] => break,
+            _ => {
+                println!("Choose one of the commands listed");
+                continue;
+            },
+        };
+        if let Err(error) = started {
+            println!("Couldn't start the game: {}", error);
+            continue;
+        }
+        if !play(&mut session, cards, &mut lines)? {
+            break;
+        }
+    }
+    let record = session.record();
+    println!("You won {}, lost {} and drew {}", record.wins, record.losses, record.draws);
+    Ok(())
+}
@@ -0,0 +1,239 @@
+use crate::archive::{ArchivedGame, GameStore};
+use crate::cards::{Cards, Fnv};
+use crate::driver::{GameDriver, ReplayError};
+use crate::ids::IdMap;
+use crate::net::auth_token;
+use crate::state::Player;
+use crate::summary::describe_state;
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub use crate::ids::GameId;
+
+/// A command an operator sends on a server's admin channel, one per line such as `inspect 3`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AdminCommand {
+    /// `list` every game being hosted
+    List,
+    /// `inspect <game>` to describe what both players can see of a game
+    Inspect(GameId),
+    /// `end <game>` to stop a game without a winner
+    End(GameId),
+    /// `adjudicate <game> <p1|p2|draw>` to end a stuck game with a winner or as a draw
+    Adjudicate(GameId, Option<Player>),
+    /// `broadcast <notice>` to send a notice to the players of every game
+    Broadcast(String),
+    /// `shutdown` to stop starting new games, so the server can checkpoint the games it is
+    /// hosting and exit
+    Shutdown,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AdminError {
+    UnknownCommand(String),
+    InvalidArgument(String),
+    NoSuchGame(GameId),
+    /// The game has already ended so can't be ended again
+    GameOver(GameId),
+    /// No checkpoint is stored with the resume token
+    NoSuchCheckpoint(String),
+    /// The checkpoint couldn't be resumed
+    Resume(ReplayError),
+}
+
+impl fmt::Display for AdminError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AdminError::UnknownCommand(command) => write!(f, "unknown command {:?}", command),
+            AdminError::InvalidArgument(argument) => write!(f, "invalid argument {:?}", argument),
+            AdminError::NoSuchGame(id) => write!(f, "no game {}", id),
+            AdminError::GameOver(id) => write!(f, "game {} has already ended", id),
+            AdminError::NoSuchCheckpoint(token) => write!(f, "no checkpoint with resume token {:?}", token),
+            AdminError::Resume(error) => write!(f, "the checkpoint couldn't be resumed: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for AdminError {}
+
+fn game_id(argument: Option<&str>) -> Result<GameId, AdminError> {
+    let argument = argument.unwrap_or("");
+    argument.parse().map_err(|_| AdminError::InvalidArgument(argument.to_owned()))
+}
+
+impl FromStr for AdminCommand {
+    type Err = AdminError;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let line = line.trim();
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let mut arguments = rest.split_whitespace();
+        match command {
+            "list" => Ok(AdminCommand::List),
+            "inspect" => Ok(AdminCommand::Inspect(game_id(arguments.next())?)),
+            "end" => Ok(AdminCommand::End(game_id(arguments.next())?)),
+            "adjudicate" => {
+                let id = game_id(arguments.next())?;
+                let winner = match arguments.next() {
+                    Some("p1") => Some(Player::One),
+                    Some("p2") => Some(Player::Two),
+                    Some("draw") => None,
+                    argument => return Err(AdminError::InvalidArgument(argument.unwrap_or("").to_owned())),
+                };
+                Ok(AdminCommand::Adjudicate(id, winner))
+            },
+            "broadcast" if !rest.trim().is_empty() => Ok(AdminCommand::Broadcast(rest.trim().to_owned())),
+            "broadcast" => Err(AdminError::InvalidArgument(String::new())),
+            "shutdown" => Ok(AdminCommand::Shutdown),
+            _ => Err(AdminError::UnknownCommand(command.to_owned())),
+        }
+    }
+}
+
+/// Checks the first line sent on an admin channel, which must be `auth <secret>` with the
+/// secret the server was started with. The comparison takes the same time however much of the
+/// secret is right.
+pub fn authenticate(line: &str, secret: &str) -> bool {
+    let given = match auth_token(line) {
+        Some(given) => given.as_bytes(),
+        None => return false,
+    };
+    let secret = secret.as_bytes();
+    given.len() == secret.len() && !secret.is_empty()
+        && given.iter().zip(secret).fold(0, |difference, (a, b)| difference | (a ^ b)) == 0
+}
+
+/// The games a server is hosting, which operators can manage with admin commands
+#[derive(Default)]
+pub struct LiveGames {
+    games: IdMap<GameDriver>,
+    notices: Vec<String>,
+    shutting_down: bool,
+}
+
+impl LiveGames {
+    /// Starts hosting a game, returning its id
+    pub fn insert(&mut self, game: GameDriver) -> GameId {
+        self.games.insert(game)
+    }
+
+    pub fn get(&self, id: GameId) -> Option<&GameDriver> {
+        self.games.get(id)
+    }
+
+    pub fn get_mut(&mut self, id: GameId) -> Option<&mut GameDriver> {
+        self.games.get_mut(id)
+    }
+
+    /// Stops hosting a game, such as once its players have been sent the result
+    pub fn remove(&mut self, id: GameId) -> Option<GameDriver> {
+        self.games.remove(id)
+    }
+
+    /// Stops hosting a game which has ended and keeps it in the store, returning if it had ended
+    pub fn archive(&mut self, id: GameId, card_pool: &Cards, store: &mut impl GameStore) -> bool {
+        let archived = self.get(id).and_then(|game| ArchivedGame::of(id, game, card_pool, SystemTime::now()));
+        match archived {
+            Some(archived) => {
+                self.games.remove(id);
+                store.store(archived);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// If an operator has asked the server to shut down, after which hosts should stop starting
+    /// games and checkpoint the ones they are hosting
+    pub fn shutting_down(&self) -> bool {
+        self.shutting_down
+    }
+
+    /// Stops hosting every game, archiving the ones which have ended and checkpointing the rest
+    /// so a new server process can resume them. Returns each checkpointed game's resume token
+    /// for the host to send its players. Games which weren't started from a setup can't be
+    /// checkpointed, so are left hosted.
+    pub fn checkpoint_all(&mut self, card_pool: &Cards, store: &mut impl GameStore) -> Vec<(GameId, String)> {
+        let ids: Vec<GameId> = self.games.ids().collect();
+        let mut tokens = vec![];
+        for id in ids {
+            if self.archive(id, card_pool, store) {
+                continue;
+            }
+            if let Some(checkpoint) = self.games.get(id).and_then(|game| game.checkpoint(card_pool)) {
+                let token = resume_token(id);
+                store.store_checkpoint(token.clone(), checkpoint);
+                self.games.remove(id);
+                tokens.push((id, token));
+            }
+        }
+        tokens
+    }
+
+    /// Starts hosting a checkpointed game again, returning its new id
+    pub fn resume(&mut self, resume_token: &str, card_pool: &Cards, store: &mut impl GameStore) -> Result<GameId, AdminError> {
+        let checkpoint = store.take_checkpoint(resume_token)
+            .ok_or_else(|| AdminError::NoSuchCheckpoint(resume_token.to_owned()))?;
+        match GameDriver::resume(card_pool, &checkpoint) {
+            Ok(game) => Ok(self.insert(game)),
+            Err(error) => {
+                // kept so the game can still be resumed with a fixed card pool
+                store.store_checkpoint(resume_token.to_owned(), checkpoint);
+                Err(AdminError::Resume(error))
+            },
+        }
+    }
+
+    /// Every notice broadcast so far, oldest first, so hosts can send their players any they
+    /// haven't yet
+    pub fn notices(&self) -> &[String] {
+        &self.notices
+    }
+
+    /// Carries out the command, returning the reply for the operator
+    pub fn execute(&mut self, command: AdminCommand, card_pool: &Cards) -> Result<String, AdminError> {
+        match command {
+            AdminCommand::List => Ok(self.games.iter()
+                .map(|(id, game)| match game.result(card_pool) {
+                    Some(result) => format!("{}: ended ({:?})", id, result.result.reason()),
+                    None => format!("{}: {:?} to act", id, game.game().priority()),
+                })
+                .collect::<Vec<_>>()
+                .join("\n")),
+            AdminCommand::Inspect(id) => {
+                let game = self.get(id).ok_or(AdminError::NoSuchGame(id))?;
+                Ok(describe_state(game.game(), card_pool))
+            },
+            AdminCommand::End(id) => {
+                let game = self.games.get_mut(id).ok_or(AdminError::NoSuchGame(id))?;
+                game.abort().map_err(|_| AdminError::GameOver(id))?;
+                Ok(format!("ended game {}", id))
+            },
+            AdminCommand::Adjudicate(id, winner) => {
+                let game = self.games.get_mut(id).ok_or(AdminError::NoSuchGame(id))?;
+                game.adjudicate(winner).map_err(|_| AdminError::GameOver(id))?;
+                Ok(format!("adjudicated game {}", id))
+            },
+            AdminCommand::Broadcast(notice) => {
+                self.notices.push(notice);
+                Ok(format!("sent to {} games", self.games.len()))
+            },
+            AdminCommand::Shutdown => {
+                self.shutting_down = true;
+                Ok(format!("shutting down, {} games to checkpoint", self.games.len()))
+            },
+        }
+    }
+}
+
+/// A token for resuming a checkpointed game, which only needs to tell checkpoints apart as
+/// players still authenticate as themselves to rejoin
+pub(crate) fn resume_token(id: GameId) -> String {
+    let mut hash = Fnv::new();
+    hash.write(&id.0.to_le_bytes());
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    hash.write(&now.as_nanos().to_le_bytes());
+    format!("{}-{:016x}", id, hash.finish())
+}
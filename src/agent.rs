@@ -0,0 +1,405 @@
+use crate::card_type::CardTypeIdentifier;
+use crate::cards::Cards;
+use crate::sim::Rng;
+use crate::state::{Field, GameState, Player, PlayerOption};
+use crate::transposition::{Bound, TranspositionTable};
+use crate::view::PlayerView;
+
+use std::sync::Arc;
+
+/// The score of a game the player has won, which outweighs any position
+pub const WIN: i64 = 1_000_000;
+
+/// Chooses options for whichever player has priority, such as a bot opponent. Agents see the
+/// whole game including hidden cards, which is fine for testing how strong they play but means
+/// they shouldn't be given games a real opponent is playing. Bots for those are `FairAgent`s.
+pub trait Agent: Send + Sync {
+    /// The name to report the agent by, including any settings such as its search budget
+    fn name(&self) -> String;
+
+    /// Chooses one of the priority player's options, using the generator for anything random so
+    /// games can be played again from their seed. The game always has options to choose from.
+    fn choose(&self, card_pool: &Cards, game: &GameState, random: &mut Rng) -> PlayerOption;
+}
+
+/// Chooses options from only what the priority player can see, so it can't cheat against a real
+/// opponent. It's never given the game, only the player's view of it, which leaves out the
+/// cards the player doesn't know.
+pub trait FairAgent: Send + Sync {
+    /// The name to report the agent by, including any settings
+    fn name(&self) -> String;
+
+    /// Chooses one of the options in the view, using the generator for anything random. The
+    /// view always has options to choose from.
+    fn choose(&self, card_pool: &Cards, view: &PlayerView, random: &mut Rng) -> PlayerOption;
+}
+
+impl<A: FairAgent + ?Sized> FairAgent for &A {
+    fn name(&self) -> String {
+        (**self).name()
+    }
+
+    fn choose(&self, card_pool: &Cards, view: &PlayerView, random: &mut Rng) -> PlayerOption {
+        (**self).choose(card_pool, view, random)
+    }
+}
+
+/// Plays a fair agent anywhere an agent can play, giving it the priority player's view of the
+/// game rather than the game itself
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Fair<A>(pub A);
+
+impl<A: FairAgent> Agent for Fair<A> {
+    fn name(&self) -> String {
+        self.0.name()
+    }
+
+    fn choose(&self, card_pool: &Cards, game: &GameState, random: &mut Rng) -> PlayerOption {
+        self.0.choose(card_pool, &PlayerView::of(game, game.priority(), card_pool), random)
+    }
+}
+
+/// A game the view could have been seen from, guessing each card the player doesn't know from
+/// the cards they've seen, or from the whole card pool if they haven't seen any
+fn guess_game(card_pool: &Cards, view: &PlayerView, random: &mut Rng) -> Option<GameState> {
+    let mut seen: Vec<CardTypeIdentifier> = vec![];
+    for player in Player::all() {
+        let field = view.field(player);
+        seen.extend(field.field.iter().map(|card| card.card_type));
+        seen.extend(field.hand.iter().chain(&field.left_deck).chain(&field.right_deck).filter_map(|card| card.known()));
+        seen.extend(field.center_deck.iter().chain(field.destroyed.iter().map(|(_, card)| card)).chain(&field.removed));
+    }
+    if seen.is_empty() {
+        seen = card_pool.current().map(|card| card.id).collect();
+    }
+    view.guess_game(|| seen[random.below(seen.len())])
+}
+
+/// Lets a searching agent play fairly, by searching a game guessed from the view rather than the
+/// game itself. Takes a random option if the view has no game to guess, as when it was read back
+/// from a client, or if the agent chose an option the guessed cards gave it but the real ones
+/// don't.
+fn choose_in_guessed_game(agent: &dyn Agent, card_pool: &Cards, view: &PlayerView, random: &mut Rng) -> PlayerOption {
+    if let Some(game) = guess_game(card_pool, view, random) {
+        let option = agent.choose(card_pool, &game, random);
+        if view.options.contains(&option) {
+            return option;
+        }
+    }
+    view.options[random.below(view.options.len())].clone()
+}
+
+/// How good the game looks for the player, from the cards each player has on the field, in hand
+/// and left to draw, or `WIN` once they've won
+pub fn evaluate(card_pool: &Cards, game: &GameState, player: Player) -> i64 {
+    match game.result(card_pool) {
+        Some(result) => match result.winner() {
+            Some(winner) if winner == player => WIN,
+            Some(_) => -WIN,
+            None => 0,
+        },
+        None => material(card_pool, game.field_of(player)) - material(card_pool, game.opponent_of(player)),
+    }
+}
+
+fn material(card_pool: &Cards, field: &Field) -> i64 {
+    let on_field: i64 = field.occupied_slots()
+        .map(|(slot, _)| {
+            let attack = field.attack_of(card_pool, slot).unwrap_or(0);
+            let defense = field.remaining_defense_of(card_pool, slot).unwrap_or(0);
+            i64::from(attack + defense)
+        })
+        .sum();
+    let decks = field.deck_sizes();
+    on_field * 4 + field.hand_size() as i64 * 3 + (decks.left + decks.center + decks.right) as i64
+}
+
+/// The game after the option, leaving the game as it was. Responses are skipped while there's
+/// nothing else to do, so searches look ahead to what the option did rather than spending their
+/// depth on response windows.
+fn after(card_pool: &Cards, game: &GameState, option: &PlayerOption) -> GameState {
+    let mut next = game.snapshot();
+    next.take_option_unchecked(card_pool, option.clone())
+        .expect("options the engine gives are always legal");
+    while next.result(card_pool).is_none() && next.legal_options(card_pool) == [PlayerOption::SkipResponse] {
+        next.take_option_unchecked(card_pool, PlayerOption::SkipResponse)
+            .expect("options the engine gives are always legal");
+    }
+    next
+}
+
+/// Takes a random option
+#[derive(Copy, Clone, Debug, Default)]
+pub struct RandomAgent;
+
+impl Agent for RandomAgent {
+    fn name(&self) -> String {
+        "random".to_owned()
+    }
+
+    fn choose(&self, card_pool: &Cards, game: &GameState, random: &mut Rng) -> PlayerOption {
+        let options = game.legal_options(card_pool);
+        options[random.below(options.len())].clone()
+    }
+}
+
+impl FairAgent for RandomAgent {
+    fn name(&self) -> String {
+        "random".to_owned()
+    }
+
+    fn choose(&self, _card_pool: &Cards, view: &PlayerView, random: &mut Rng) -> PlayerOption {
+        view.options[random.below(view.options.len())].clone()
+    }
+}
+
+/// Takes the option which leaves the best position, looking no further ahead
+#[derive(Copy, Clone, Debug, Default)]
+pub struct GreedyAgent;
+
+impl Agent for GreedyAgent {
+    fn name(&self) -> String {
+        "greedy".to_owned()
+    }
+
+    fn choose(&self, card_pool: &Cards, game: &GameState, _random: &mut Rng) -> PlayerOption {
+        let player = game.priority();
+        game.legal_options(card_pool)
+            .iter()
+            .max_by_key(|option| evaluate(card_pool, &after(card_pool, game, option), player))
+            .expect("the game always has options")
+            .clone()
+    }
+}
+
+impl FairAgent for GreedyAgent {
+    fn name(&self) -> String {
+        Agent::name(self)
+    }
+
+    fn choose(&self, card_pool: &Cards, view: &PlayerView, random: &mut Rng) -> PlayerOption {
+        choose_in_guessed_game(self, card_pool, view, random)
+    }
+}
+
+/// Scores the game for the player, through the table if there is one
+fn evaluate_with(table: &Option<Arc<TranspositionTable>>, card_pool: &Cards, game: &GameState, player: Player) -> i64 {
+    match table {
+        Some(table) => table.evaluate(card_pool, game, player),
+        None => evaluate(card_pool, game, player),
+    }
+}
+
+/// Searches every line of play a number of options deep with alpha-beta pruning, assuming the
+/// opponent always answers with the option that is worst for the agent
+#[derive(Clone)]
+pub struct MinimaxAgent {
+    /// How many options ahead to look, counting both players'
+    pub depth: u32,
+    /// Positions already searched, which may be shared with other agents
+    pub table: Option<Arc<TranspositionTable>>,
+}
+
+impl MinimaxAgent {
+    pub fn new(depth: u32) -> Self {
+        MinimaxAgent { depth, table: None }
+    }
+
+    pub fn with_table(self, table: Arc<TranspositionTable>) -> Self {
+        MinimaxAgent { table: Some(table), ..self }
+    }
+
+    fn search(&self, card_pool: &Cards, game: &GameState, player: Player, depth: u32, mut alpha: i64, mut beta: i64) -> i64 {
+        if depth == 0 || game.is_terminal(card_pool) {
+            return evaluate_with(&self.table, card_pool, game, player);
+        }
+        let hash = game.state_hash();
+        if let Some(score) = self.table.as_ref().and_then(|table| table.search_score(hash, player, depth, alpha, beta)) {
+            return score;
+        }
+        let window = (alpha, beta);
+        let maximizing = game.priority() == player;
+        let mut best = if maximizing { i64::MIN } else { i64::MAX };
+        for option in game.legal_options(card_pool) {
+            let score = self.search(card_pool, &after(card_pool, game, option), player, depth - 1, alpha, beta);
+            if maximizing {
+                best = best.max(score);
+                alpha = alpha.max(best);
+            } else {
+                best = best.min(score);
+                beta = beta.min(best);
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+        if let Some(table) = &self.table {
+            let bound = if best <= window.0 {
+                Bound::Upper
+            } else if best >= window.1 {
+                Bound::Lower
+            } else {
+                Bound::Exact
+            };
+            table.store_search(hash, player, depth, best, bound);
+        }
+        best
+    }
+}
+
+impl Agent for MinimaxAgent {
+    fn name(&self) -> String {
+        format!("minimax (depth {})", self.depth)
+    }
+
+    fn choose(&self, card_pool: &Cards, game: &GameState, _random: &mut Rng) -> PlayerOption {
+        let player = game.priority();
+        let mut best: Option<(i64, &PlayerOption)> = None;
+        for option in game.legal_options(card_pool) {
+            let alpha = best.map_or(i64::MIN, |(score, _)| score);
+            let score = self.search(card_pool, &after(card_pool, game, option), player, self.depth.saturating_sub(1), alpha, i64::MAX);
+            if best.is_none_or(|(best, _)| score > best) {
+                best = Some((score, option));
+            }
+        }
+        best.expect("the game always has options").1.clone()
+    }
+}
+
+impl FairAgent for MinimaxAgent {
+    fn name(&self) -> String {
+        Agent::name(self)
+    }
+
+    fn choose(&self, card_pool: &Cards, view: &PlayerView, random: &mut Rng) -> PlayerOption {
+        choose_in_guessed_game(self, card_pool, view, random)
+    }
+}
+
+/// Monte Carlo tree search, which grows a tree of the most promising lines of play by playing
+/// random games out from them
+#[derive(Clone)]
+pub struct MctsAgent {
+    /// How many random games to play out for each choice
+    pub iterations: u32,
+    /// How many options a random game is played for before the position is scored instead
+    pub rollout_limit: u32,
+    /// Scores of positions random games ended in, which may be shared with other agents
+    pub table: Option<Arc<TranspositionTable>>,
+}
+
+impl MctsAgent {
+    pub fn new(iterations: u32) -> Self {
+        MctsAgent { iterations, rollout_limit: 200, table: None }
+    }
+
+    pub fn with_table(self, table: Arc<TranspositionTable>) -> Self {
+        MctsAgent { table: Some(table), ..self }
+    }
+}
+
+struct Node {
+    // the option taken to reach this node from its parent, and who took it
+    option: Option<PlayerOption>,
+    chosen_by: Player,
+    children: Vec<usize>,
+    untried: Vec<PlayerOption>,
+    visits: u32,
+    // the total reward for the player who chose this node's option, 1 for each win
+    reward: f64,
+}
+
+impl MctsAgent {
+    /// Plays random options out from the game, returning the reward for each player
+    fn rollout(&self, card_pool: &Cards, mut game: GameState, random: &mut Rng) -> [f64 ; 2] {
+        for _ in 0..self.rollout_limit {
+            if game.is_terminal(card_pool) {
+                break;
+            }
+            let options = game.legal_options(card_pool);
+            let option = options[random.below(options.len())].clone();
+            game.take_option_unchecked(card_pool, option).expect("options the engine gives are always legal");
+        }
+        let score = evaluate_with(&self.table, card_pool, &game, Player::One);
+        let one = match score {
+            0 => 0.5,
+            score if score > 0 => 1.0,
+            _ => 0.0,
+        };
+        [one, 1.0 - one]
+    }
+}
+
+impl Agent for MctsAgent {
+    fn name(&self) -> String {
+        format!("mcts ({} iterations)", self.iterations)
+    }
+
+    fn choose(&self, card_pool: &Cards, game: &GameState, random: &mut Rng) -> PlayerOption {
+        let mut nodes = vec![Node {
+            option: None,
+            chosen_by: game.priority().next(),
+            children: vec![],
+            untried: game.priority_player_options(card_pool),
+            visits: 0,
+            reward: 0.0,
+        }];
+        for _ in 0..self.iterations.max(1) {
+            let mut state = game.snapshot();
+            let mut path = vec![0];
+            let mut node = 0;
+            // selection, by the upper confidence bound of each child for the player choosing
+            while nodes[node].untried.is_empty() && !nodes[node].children.is_empty() {
+                let parent_visits = f64::from(nodes[node].visits.max(1)).ln();
+                node = *nodes[node].children.iter()
+                    .max_by(|&&a, &&b| {
+                        let bound = |child: &Node| child.reward / f64::from(child.visits)
+                            + (2.0 * parent_visits / f64::from(child.visits)).sqrt();
+                        bound(&nodes[a]).total_cmp(&bound(&nodes[b]))
+                    })
+                    .expect("the node has children");
+                let option = nodes[node].option.clone().expect("only the root has no option");
+                state.take_option_unchecked(card_pool, option).expect("options the engine gives are always legal");
+                path.push(node);
+            }
+            // expansion
+            if state.result(card_pool).is_none() && !nodes[node].untried.is_empty() {
+                let untried = &mut nodes[node].untried;
+                let option = untried.swap_remove(random.below(untried.len()));
+                let chosen_by = state.priority();
+                state.take_option_unchecked(card_pool, option.clone()).expect("options the engine gives are always legal");
+                nodes.push(Node {
+                    option: Some(option),
+                    chosen_by,
+                    children: vec![],
+                    untried: if state.is_terminal(card_pool) { vec![] } else { state.priority_player_options(card_pool) },
+                    visits: 0,
+                    reward: 0.0,
+                });
+                let child = nodes.len() - 1;
+                nodes[node].children.push(child);
+                path.push(child);
+            }
+            let rewards = self.rollout(card_pool, state, random);
+            for &node in &path {
+                let node = &mut nodes[node];
+                node.visits += 1;
+                node.reward += rewards[node.chosen_by.index()];
+            }
+        }
+        nodes[0].children.iter()
+            .max_by_key(|&&child| nodes[child].visits)
+            .and_then(|&child| nodes[child].option.clone())
+            .unwrap_or_else(|| game.legal_options(card_pool)[0].clone())
+    }
+}
+
+impl FairAgent for MctsAgent {
+    fn name(&self) -> String {
+        Agent::name(self)
+    }
+
+    fn choose(&self, card_pool: &Cards, view: &PlayerView, random: &mut Rng) -> PlayerOption {
+        choose_in_guessed_game(self, card_pool, view, random)
+    }
+}
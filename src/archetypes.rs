@@ -0,0 +1,233 @@
+use crate::card_type::CardTypeIdentifier;
+use crate::cards::Cards;
+use crate::deck::Deck;
+use crate::sim;
+
+use std::fmt::Write;
+
+/// How often each deck beats each other deck, found by simulating games between every pair of
+/// decks from both seats with random options. Draws and games still going at the limit of
+/// options count as half a win, and each deck is given a win rate of one half against itself.
+pub fn win_rates(card_pool: &Cards, decks: &[Deck], seeds: &[u64], max_options: u32) -> Vec<Vec<f64>> {
+    let mut rates = vec![vec![0.5 ; decks.len()] ; decks.len()];
+    for a in 0..decks.len() {
+        for b in a + 1..decks.len() {
+            let first = sim::stats(&sim::simulate(card_pool, [&decks[a], &decks[b]], seeds, max_options));
+            let second = sim::stats(&sim::simulate(card_pool, [&decks[b], &decks[a]], seeds, max_options));
+            let games = first.games + second.games;
+            if games == 0 {
+                continue;
+            }
+            let wins = (first.wins[0] + second.wins[1]) as f64;
+            let halves = (first.draws + first.unfinished + second.draws + second.unfinished) as f64 / 2.0;
+            rates[a][b] = (wins + halves) / games as f64;
+            rates[b][a] = 1.0 - rates[a][b];
+        }
+    }
+    rates
+}
+
+/// A group of decks which are built from similar cards and do well and badly against the same
+/// decks
+#[derive(Clone, Debug, PartialEq)]
+pub struct Archetype {
+    /// The decks in the archetype, by their index in the decks clustered
+    pub decks: Vec<usize>,
+    /// The deck most like the others in the archetype
+    pub representative: usize,
+    /// The card types played by at least half the archetype's decks, with the fraction of its
+    /// decks playing each, most common first
+    pub core: Vec<(CardTypeIdentifier, f64)>,
+    /// How often the archetype's decks win against every other deck on average
+    pub win_rate: f64,
+    /// How often the archetype's decks win against each archetype's decks on average, by the
+    /// order of the archetypes
+    pub matchups: Vec<f64>,
+    /// How much of the metagame the archetype would take if decks were played in proportion to
+    /// how often they win
+    pub share: f64,
+}
+
+/// The archetypes simulated decks fall into
+#[derive(Clone, Debug, PartialEq)]
+pub struct Metagame {
+    /// Largest share of the metagame first
+    pub archetypes: Vec<Archetype>,
+}
+
+impl Metagame {
+    /// How many archetypes the metagame effectively has, which is the number of archetypes when
+    /// they all have an equal share and approaches 1 as one archetype takes over
+    pub fn diversity(&self) -> f64 {
+        let concentration: f64 = self.archetypes.iter().map(|archetype| archetype.share * archetype.share).sum();
+        if concentration > 0.0 { 1.0 / concentration } else { 0.0 }
+    }
+
+    /// A summary of each archetype for designers to read, naming its core cards and how its
+    /// matchups go
+    pub fn report(&self, card_pool: &Cards) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "{} archetypes, {:.1} effectively", self.archetypes.len(), self.diversity());
+        for (i, archetype) in self.archetypes.iter().enumerate() {
+            let core: Vec<String> = archetype.core.iter()
+                .map(|&(id, fraction)| {
+                    let name = card_pool.card(id).map_or("unknown card", |card_type| card_type.name.as_str());
+                    format!("{} {:.0}%", name, fraction * 100.0)
+                })
+                .collect();
+            let matchups: Vec<String> = archetype.matchups.iter()
+                .enumerate()
+                .map(|(j, rate)| format!("{}: {:.0}%", j + 1, rate * 100.0))
+                .collect();
+            let _ = writeln!(
+                text,
+                "{}. {} decks, {:.0}% of the metagame, winning {:.0}%\n   core: {}\n   against: {}",
+                i + 1,
+                archetype.decks.len(),
+                archetype.share * 100.0,
+                archetype.win_rate * 100.0,
+                core.join(", "),
+                matchups.join(", "),
+            );
+        }
+        text
+    }
+}
+
+/// Clusters decks into up to the number of archetypes, by the cards they play together and by
+/// their win rates from `win_rates`. Clustering is deterministic, picking the most central deck
+/// and then the deck least like any picked so far as the representative of each archetype, then
+/// moving representatives to the most central deck of their archetype until none move.
+pub fn cluster(decks: &[Deck], win_rates: &[Vec<f64>], archetypes: usize) -> Metagame {
+    let count = archetypes.min(decks.len());
+    if count == 0 {
+        return Metagame { archetypes: vec![] };
+    }
+    let cards: Vec<_> = decks.iter().map(card_counts).collect();
+    let distances: Vec<Vec<f64>> = (0..decks.len())
+        .map(|a| (0..decks.len()).map(|b| distance(&cards, win_rates, a, b)).collect())
+        .collect();
+    let everyone: Vec<usize> = (0..decks.len()).collect();
+    // the deck closest to the rest of the group
+    let central = |group: &[usize]| group.iter()
+        .copied()
+        .min_by(|&a, &b| {
+            let spread = |deck: usize| group.iter().map(|&other| distances[deck][other]).sum::<f64>();
+            spread(a).total_cmp(&spread(b))
+        });
+
+    let mut representatives = vec![central(&everyone).expect("there are decks")];
+    while representatives.len() < count {
+        let farthest = (0..decks.len())
+            .filter(|deck| !representatives.contains(deck))
+            .max_by(|&a, &b| {
+                let nearest = |deck: usize| representatives.iter().map(|&r| distances[deck][r]).fold(f64::INFINITY, f64::min);
+                nearest(a).total_cmp(&nearest(b))
+            })
+            .expect("there are more decks than representatives");
+        representatives.push(farthest);
+    }
+
+    let assign = |representatives: &[usize]| -> Vec<Vec<usize>> {
+        let mut members = vec![vec![] ; representatives.len()];
+        for (deck, distances) in distances.iter().enumerate() {
+            let closest = (0..representatives.len())
+                .min_by(|&a, &b| distances[representatives[a]].total_cmp(&distances[representatives[b]]))
+                .expect("there are representatives");
+            members[closest].push(deck);
+        }
+        members
+    };
+    let mut members = assign(&representatives);
+    // each update lowers the total distance to representatives, so this always settles
+    for _ in 0..100 {
+        let moved: Vec<usize> = members.iter()
+            .zip(&representatives)
+            .map(|(group, &current)| central(group).unwrap_or(current))
+            .collect();
+        if moved == representatives {
+            break;
+        }
+        representatives = moved;
+        members = assign(&representatives);
+    }
+
+    let average = |rows: &[usize], columns: &[usize]| {
+        let pairs: Vec<f64> = rows.iter()
+            .flat_map(|&a| columns.iter().filter(move |&&b| b != a).map(move |&b| win_rates[a][b]))
+            .collect();
+        if pairs.is_empty() { 0.5 } else { pairs.iter().sum::<f64>() / pairs.len() as f64 }
+    };
+    let total_wins: f64 = everyone.iter().map(|&deck| average(&[deck], &everyone)).sum();
+    let mut archetypes: Vec<Archetype> = members.iter()
+        .zip(&representatives)
+        .map(|(group, &representative)| {
+            let mut core: Vec<(CardTypeIdentifier, f64)> = vec![];
+            for &deck in group {
+                for &(id, _) in &cards[deck] {
+                    match core.iter_mut().find(|(existing, _)| *existing == id) {
+                        Some((_, decks)) => *decks += 1.0,
+                        None => core.push((id, 1.0)),
+                    }
+                }
+            }
+            core.iter_mut().for_each(|(_, decks)| *decks /= group.len() as f64);
+            core.retain(|&(_, fraction)| fraction >= 0.5);
+            core.sort_by(|a, b| b.1.total_cmp(&a.1));
+            let wins: f64 = group.iter().map(|&deck| average(&[deck], &everyone)).sum();
+            Archetype {
+                decks: group.clone(),
+                representative,
+                core,
+                win_rate: average(group, &everyone),
+                matchups: members.iter().map(|other| average(group, other)).collect(),
+                share: if total_wins > 0.0 { wins / total_wins } else { 0.0 },
+            }
+        })
+        .filter(|archetype| !archetype.decks.is_empty())
+        .collect();
+
+    // sorts the archetypes by share, keeping the matchups in the same order
+    let mut order: Vec<usize> = (0..archetypes.len()).collect();
+    order.sort_by(|&a, &b| archetypes[b].share.total_cmp(&archetypes[a].share));
+    for archetype in &mut archetypes {
+        archetype.matchups = order.iter().map(|&i| archetype.matchups[i]).collect();
+    }
+    Metagame {
+        archetypes: order.iter().map(|&i| archetypes[i].clone()).collect(),
+    }
+}
+
+/// How many copies of each card type the deck holds, in the order they first appear
+fn card_counts(deck: &Deck) -> Vec<(CardTypeIdentifier, f64)> {
+    let mut counts: Vec<(CardTypeIdentifier, f64)> = vec![];
+    for id in deck.iter() {
+        match counts.iter_mut().find(|(existing, _)| *existing == id) {
+            Some((_, copies)) => *copies += 1.0,
+            None => counts.push((id, 1.0)),
+        }
+    }
+    counts
+}
+
+/// How unalike two decks are from 0 to 1, averaging how differently they're built with how
+/// differently they do against the other decks
+fn distance(cards: &[Vec<(CardTypeIdentifier, f64)>], win_rates: &[Vec<f64>], a: usize, b: usize) -> f64 {
+    if a == b {
+        return 0.0;
+    }
+    let count = |deck: usize, id: CardTypeIdentifier| cards[deck].iter()
+        .find(|(existing, _)| *existing == id)
+        .map_or(0.0, |&(_, copies)| copies);
+    let norm = |deck: usize| cards[deck].iter().map(|(_, copies)| copies * copies).sum::<f64>().sqrt();
+    let shared: f64 = cards[a].iter().map(|&(id, copies)| copies * count(b, id)).sum();
+    let similarity = if norm(a) > 0.0 && norm(b) > 0.0 { shared / (norm(a) * norm(b)) } else { 0.0 };
+
+    let others: Vec<usize> = (0..win_rates.len()).filter(|&deck| deck != a && deck != b).collect();
+    let profile = if others.is_empty() {
+        0.0
+    } else {
+        others.iter().map(|&deck| (win_rates[a][deck] - win_rates[b][deck]).abs()).sum::<f64>() / others.len() as f64
+    };
+    ((1.0 - similarity) + profile) / 2.0
+}
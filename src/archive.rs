@@ -0,0 +1,217 @@
+use crate::cards::Cards;
+use crate::driver::{Checkpoint, GameDriver, Replay};
+use crate::ids::{GameId, PlayerId};
+use crate::state::{EndReason, GameResult, Player};
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::SystemTime;
+
+/// A finished game kept by a server, with who played it and its replay
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ArchivedGame {
+    pub id: GameId,
+    /// Who played each side, player one first, if they were bound to the driver
+    pub players: [Option<PlayerId> ; 2],
+    /// When the game was archived
+    pub finished: SystemTime,
+    pub result: GameResult,
+    /// The replay, with each player's think times
+    pub replay: Replay,
+}
+
+impl ArchivedGame {
+    /// Archives a driver's game, if it has ended
+    pub fn of(id: GameId, driver: &GameDriver, card_pool: &Cards, finished: SystemTime) -> Option<Self> {
+        let result = driver.result(card_pool)?.result;
+        Some(ArchivedGame {
+            id,
+            players: [driver.identity(Player::One).cloned(), driver.identity(Player::Two).cloned()],
+            finished,
+            result,
+            replay: driver.replay(card_pool, true),
+        })
+    }
+
+    /// Which side the identity played, if they played in the game
+    pub fn player_of(&self, identity: &PlayerId) -> Option<Player> {
+        Player::all().find(|&player| self.identity(player) == Some(identity))
+    }
+
+    pub fn identity(&self, player: Player) -> Option<&PlayerId> {
+        self.players[player.index()].as_ref()
+    }
+
+    /// Who won, if the game had a winner who was bound to the driver
+    pub fn winner(&self) -> Option<&PlayerId> {
+        self.result.winner().and_then(|winner| self.identity(winner))
+    }
+}
+
+/// How a game ended, to find games by
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Outcome {
+    WonBy(PlayerId),
+    Drawn,
+    Reason(EndReason),
+}
+
+/// Which archived games to list. Every condition given must hold, so the default lists every
+/// game.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct GameQuery {
+    /// Games the identity played in
+    pub player: Option<PlayerId>,
+    /// Games which finished at or after the time
+    pub since: Option<SystemTime>,
+    /// Games which finished before the time
+    pub until: Option<SystemTime>,
+    pub outcome: Option<Outcome>,
+}
+
+impl GameQuery {
+    pub fn matches(&self, game: &ArchivedGame) -> bool {
+        self.player.as_ref().is_none_or(|player| game.player_of(player).is_some())
+            && self.since.is_none_or(|since| game.finished >= since)
+            && self.until.is_none_or(|until| game.finished < until)
+            && self.outcome.as_ref().is_none_or(|outcome| match outcome {
+                Outcome::WonBy(identity) => game.winner() == Some(identity),
+                Outcome::Drawn => game.result.is_draw(),
+                Outcome::Reason(reason) => game.result.reason() == *reason,
+            })
+    }
+}
+
+/// How one player has done against another, from the first player's side. Aborted games and
+/// games without a winner bound to the driver aren't counted.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct HeadToHead {
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+}
+
+/// Where a server keeps finished games, and unfinished ones it checkpoints when shutting down.
+/// Queries are built on listing the games, so backends such as a database only need to store and
+/// list them. Games are only resumed by a new server process if the backend outlives the old one.
+/// `SharedArchive` answers the queries asynchronously.
+pub trait GameStore {
+    fn store(&mut self, game: ArchivedGame);
+
+    /// Keeps an unfinished game until it is resumed with the token
+    fn store_checkpoint(&mut self, resume_token: String, checkpoint: Checkpoint);
+
+    /// Removes the checkpoint with the token, so each game is only resumed once
+    fn take_checkpoint(&mut self, resume_token: &str) -> Option<Checkpoint>;
+
+    fn game(&self, id: GameId) -> Option<&ArchivedGame>;
+
+    /// Every archived game, in order of id
+    fn games(&self) -> Box<dyn Iterator<Item = &ArchivedGame> + '_>;
+
+    /// The games which match the query, in order of id
+    fn query(&self, query: &GameQuery) -> Vec<&ArchivedGame> {
+        self.games().filter(|game| query.matches(game)).collect()
+    }
+
+    fn replay(&self, id: GameId) -> Option<&Replay> {
+        self.game(id).map(|game| &game.replay)
+    }
+
+    fn head_to_head(&self, player: &PlayerId, opponent: &PlayerId) -> HeadToHead {
+        let mut record = HeadToHead::default();
+        for game in self.games() {
+            if game.player_of(player).is_none() || game.player_of(opponent).is_none() {
+                continue;
+            }
+            match game.winner() {
+                _ if game.result.is_draw() => record.draws += 1,
+                Some(winner) if winner == player => record.wins += 1,
+                Some(winner) if winner == opponent => record.losses += 1,
+                _ => (),
+            }
+        }
+        record
+    }
+}
+
+/// Keeps finished games and checkpoints in memory, for servers which don't need them to outlive
+/// the process
+#[derive(Clone, Debug, Default)]
+pub struct MemoryArchive {
+    games: BTreeMap<GameId, ArchivedGame>,
+    checkpoints: BTreeMap<String, Checkpoint>,
+}
+
+impl GameStore for MemoryArchive {
+    fn store(&mut self, game: ArchivedGame) {
+        self.games.insert(game.id, game);
+    }
+
+    fn store_checkpoint(&mut self, resume_token: String, checkpoint: Checkpoint) {
+        self.checkpoints.insert(resume_token, checkpoint);
+    }
+
+    fn take_checkpoint(&mut self, resume_token: &str) -> Option<Checkpoint> {
+        self.checkpoints.remove(resume_token)
+    }
+
+    fn game(&self, id: GameId) -> Option<&ArchivedGame> {
+        self.games.get(&id)
+    }
+
+    fn games(&self) -> Box<dyn Iterator<Item = &ArchivedGame> + '_> {
+        Box::new(self.games.values())
+    }
+}
+
+/// A store shared between the server hosting games and anything else which reads it, with the
+/// queries as async methods for lobbies, tournaments and dashboards running on an async runtime.
+/// Clones share the same store.
+///
+/// Each method holds the lock for as long as the store takes to answer, so stores which block on
+/// IO, such as a database, should be called through the runtime's blocking pool.
+pub struct SharedArchive<S> {
+    store: Arc<Mutex<S>>,
+}
+
+impl<S> Clone for SharedArchive<S> {
+    fn clone(&self) -> Self {
+        SharedArchive { store: Arc::clone(&self.store) }
+    }
+}
+
+impl<S: GameStore> SharedArchive<S> {
+    pub fn new(store: S) -> Self {
+        SharedArchive { store: Arc::new(Mutex::new(store)) }
+    }
+
+    /// Locks the store for the synchronous methods, such as for archiving games with
+    /// `LiveGames::archive`
+    pub fn lock(&self) -> MutexGuard<'_, S> {
+        // a panic while the store was locked doesn't leave a game half stored, as every store
+        // method either stores a game or doesn't
+        self.store.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub async fn store(&self, game: ArchivedGame) {
+        self.lock().store(game)
+    }
+
+    pub async fn game(&self, id: GameId) -> Option<ArchivedGame> {
+        self.lock().game(id).cloned()
+    }
+
+    /// The games which match the query, in order of id
+    pub async fn query(&self, query: &GameQuery) -> Vec<ArchivedGame> {
+        self.lock().query(query).into_iter().cloned().collect()
+    }
+
+    pub async fn replay(&self, id: GameId) -> Option<Replay> {
+        self.lock().replay(id).cloned()
+    }
+
+    pub async fn head_to_head(&self, player: &PlayerId, opponent: &PlayerId) -> HeadToHead {
+        self.lock().head_to_head(player, opponent)
+    }
+}
@@ -0,0 +1,216 @@
+use crate::agent::{Agent, Fair, FairAgent};
+use crate::cards::Cards;
+use crate::deck::Deck;
+use crate::sim::Rng;
+use crate::state::{CardInstances, GameState, Player};
+
+/// How a tournament between agents is played
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct TournamentConfig {
+    /// How many games each pair of agents plays with each pair of decks from each seat
+    pub games: usize,
+    /// Games still going after this many options are scored as draws
+    pub max_options: u32,
+    pub seed: u64,
+}
+
+impl Default for TournamentConfig {
+    fn default() -> Self {
+        TournamentConfig {
+            games: 2,
+            max_options: 500,
+            seed: 0,
+        }
+    }
+}
+
+/// How one agent did against another
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Record {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl Record {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Wins plus half the draws
+    pub fn score(&self) -> f64 {
+        f64::from(self.wins) + f64::from(self.draws) / 2.0
+    }
+}
+
+/// An agent's strength on the Elo scale, where 400 points between two agents means the stronger
+/// one is expected to score ten times as much as the weaker
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Rating {
+    /// Relative to the average agent in the tournament, which is 0
+    pub elo: f64,
+    /// How far either side of the rating the agent's true strength could be, with 95% confidence
+    pub margin: f64,
+}
+
+/// The results of a tournament
+#[derive(Clone, Debug, PartialEq)]
+pub struct Standings {
+    /// The agents' names, in the order they were given
+    pub names: Vec<String>,
+    /// How each agent did against each other agent, by the order they were given
+    pub records: Vec<Vec<Record>>,
+    pub ratings: Vec<Rating>,
+}
+
+impl Standings {
+    /// A table of the agents from strongest to weakest, one line each
+    pub fn report(&self) -> String {
+        let mut order: Vec<usize> = (0..self.names.len()).collect();
+        order.sort_by(|&a, &b| self.ratings[b].elo.total_cmp(&self.ratings[a].elo));
+        order.iter()
+            .map(|&i| {
+                let total = self.records[i].iter().fold(Record::default(), |total, record| Record {
+                    wins: total.wins + record.wins,
+                    draws: total.draws + record.draws,
+                    losses: total.losses + record.losses,
+                });
+                format!(
+                    "{}: {:.0} ± {:.0} ({}-{}-{})",
+                    self.names[i], self.ratings[i].elo, self.ratings[i].margin, total.wins, total.draws, total.losses
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Plays one game between two agents, returning the winner if the game ends within the limit
+/// of options
+pub fn play(card_pool: &Cards, agents: [&dyn Agent ; 2], decks: [&Deck ; 2], seed: u64, max_options: u32) -> Option<Player> {
+    let mut instances = CardInstances::default();
+    let mut game = GameState::start(
+        decks[0].instantiate(card_pool, &mut instances),
+        decks[1].instantiate(card_pool, &mut instances),
+    );
+    let mut random = Rng::new(seed);
+    for _ in 0..max_options {
+        if game.is_terminal(card_pool) {
+            break;
+        }
+        let agent = match game.priority() {
+            Player::One => agents[0],
+            Player::Two => agents[1],
+        };
+        let option = agent.choose(card_pool, &game, &mut random);
+        game.take_option_unchecked(card_pool, option).expect("agents only choose options the engine gave them");
+    }
+    game.result(card_pool).and_then(|result| result.winner())
+}
+
+/// Plays every pair of agents against each other with every pair of decks, from both seats, and
+/// estimates how strong each agent is from the results. Games only depend on the seed, so a
+/// change to an agent can be checked by running the same tournament before and after it. With
+/// the `parallel` feature the games are spread across every core.
+pub fn tournament(card_pool: &Cards, agents: &[&dyn Agent], decks: &[Deck], config: TournamentConfig) -> Standings {
+    // every game to play, as the agents in each seat, the decks, and the seed
+    let mut games = vec![];
+    for a in 0..agents.len() {
+        for b in a + 1..agents.len() {
+            for first in decks {
+                for second in decks {
+                    for _ in 0..config.games {
+                        // both seats play with the same seed, so neither agent gets luckier draws
+                        let seed = config.seed.wrapping_add(games.len() as u64);
+                        games.push(([a, b], [first, second], seed));
+                        games.push(([b, a], [first, second], seed));
+                    }
+                }
+            }
+        }
+    }
+    let play_game = |&(seats, decks, seed): &([usize ; 2], [&Deck ; 2], u64)| {
+        play(card_pool, [agents[seats[0]], agents[seats[1]]], decks, seed, config.max_options)
+    };
+    #[cfg(all(feature = "parallel", not(target_family = "wasm")))]
+    let winners: Vec<Option<Player>> = {
+        use rayon::prelude::*;
+        games.par_iter().map(play_game).collect()
+    };
+    #[cfg(not(all(feature = "parallel", not(target_family = "wasm"))))]
+    let winners: Vec<Option<Player>> = games.iter().map(play_game).collect();
+
+    let mut records = vec![vec![Record::default() ; agents.len()] ; agents.len()];
+    for ((seats, _, _), winner) in games.iter().zip(winners) {
+        let [one, two] = *seats;
+        match winner {
+            Some(Player::One) => {
+                records[one][two].wins += 1;
+                records[two][one].losses += 1;
+            },
+            Some(Player::Two) => {
+                records[two][one].wins += 1;
+                records[one][two].losses += 1;
+            },
+            None => {
+                records[one][two].draws += 1;
+                records[two][one].draws += 1;
+            },
+        }
+    }
+    Standings {
+        names: agents.iter().map(|agent| agent.name()).collect(),
+        ratings: ratings(&records),
+        records,
+    }
+}
+
+/// Plays a tournament between fair agents, which are only ever given their player's view of
+/// each game, such as to rate the bots real players will face
+pub fn fair_tournament(card_pool: &Cards, agents: &[&dyn FairAgent], decks: &[Deck], config: TournamentConfig) -> Standings {
+    let fair: Vec<Fair<&dyn FairAgent>> = agents.iter().map(|&agent| Fair(agent)).collect();
+    let agents: Vec<&dyn Agent> = fair.iter().map(|agent| agent as &dyn Agent).collect();
+    tournament(card_pool, &agents, decks, config)
+}
+
+/// Fits Elo ratings to the records with the Bradley-Terry model, counting draws as half a win
+/// each. Every pair of agents is also given one drawn game, so an agent which won every game
+/// still gets a finite rating.
+fn ratings(records: &[Vec<Record>]) -> Vec<Rating> {
+    let agents = records.len();
+    let games = |i: usize, j: usize| f64::from(records[i][j].games()) + 1.0;
+    let score = |i: usize| (0..agents).filter(|&j| j != i).map(|j| records[i][j].score() + 0.5).sum::<f64>();
+    let mut strengths = vec![1.0 ; agents];
+    // the minorization-maximization updates always converge for this model
+    for _ in 0..1000 {
+        let next: Vec<f64> = (0..agents)
+            .map(|i| {
+                let expected: f64 = (0..agents)
+                    .filter(|&j| j != i)
+                    .map(|j| games(i, j) / (strengths[i] + strengths[j]))
+                    .sum();
+                if expected == 0.0 { strengths[i] } else { score(i) / expected }
+            })
+            .collect();
+        // keeps the geometric mean at 1 so the average rating is 0
+        let mean = (next.iter().map(|strength| strength.ln()).sum::<f64>() / agents.max(1) as f64).exp();
+        strengths = next.iter().map(|strength| strength / mean).collect();
+    }
+    let scale = 400.0 / std::f64::consts::LN_10;
+    (0..agents)
+        .map(|i| {
+            // the standard error from the Fisher information of the agent's log strength
+            let information: f64 = (0..agents)
+                .filter(|&j| j != i)
+                .map(|j| {
+                    let expected = strengths[i] / (strengths[i] + strengths[j]);
+                    games(i, j) * expected * (1.0 - expected)
+                })
+                .sum();
+            Rating {
+                elo: scale * strengths[i].ln(),
+                margin: if information > 0.0 { 1.96 * scale / information.sqrt() } else { f64::INFINITY },
+            }
+        })
+        .collect()
+}
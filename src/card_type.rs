@@ -1,5 +1,7 @@
-// use crate::cards::Cards;
-//
+use crate::cards::Cards;
+use crate::combat::CombatModifier;
+use crate::state::{Activation, ActivationData, ActivatableType, Card, CardInstance, CardStatus, Column, DrawModifier, DrawRule, EngineAction, FaceDownDeck, Field, FieldSlot, GameState, GameStateType, Player, ResolutionContext, Row, Trigger};
+
 use std::fmt;
 use std::fmt::Debug;
 
@@ -21,150 +23,2078 @@ pub struct CardType {
     #[serde(skip_deserializing)]
     pub id: CardTypeIdentifier,
     pub name: String,
-    //#[serde(default)]
-    //pub effects: Vec<Box<dyn CardEffect>>,
+    /// Which errata of the card this is, starting from 1. Each version is its own card type, so
+    /// games using an older version keep its behavior.
+    #[serde(default = "first_version")]
+    pub version: u32,
+    #[serde(default)]
+    pub effects: Vec<Box<dyn CardEffect>>,
     pub defense: u32,
     pub attack: u32,
+    #[serde(default)]
+    pub keywords: Vec<Keyword>,
+    /// Words effects can pick cards out of the card pool by, such as `dragon`
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub auras: Vec<Aura>,
+    /// Modifiers which change how attacks by or against this card deal damage
+    #[serde(default)]
+    pub combat: Vec<Box<dyn CombatModifier>>,
+    /// The resource points its controller pays out of their resources to summon the card as an
+    /// action, whether from the hand, the center deck or a destroyed pile, in formats with
+    /// resources. Cards without a cost can't be summoned from the center deck as an action.
+    #[serde(default)]
+    pub resource_cost: u32,
+    #[serde(default)]
+    pub summon_restriction: Option<SummonRestriction>,
+}
+
+fn first_version() -> u32 {
+    1
+}
+
+impl CardType {
+    pub fn has_keyword(&self, keyword: Keyword) -> bool {
+        self.keywords.contains(&keyword)
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|existing| existing == tag)
+    }
+
+    /// Describes each of this card type's effects, in order, for displaying the card
+    pub fn effect_summaries(&self) -> Vec<EffectSummary> {
+        self.effects.iter().map(|effect| effect.summary()).collect()
+    }
+
+    /// The fewest cards which must be sacrificed to summon this card from the center deck, None
+    /// if it has no way to be summoned from there
+    pub fn summon_cost(&self) -> Option<usize> {
+        self.effects.iter().filter_map(|effect| effect.summon_cost()).min()
+    }
+
+    /// Can this card be summoned to the slot on the field, by a normal summon from the hand or
+    /// by a card effect? Every way of summoning a card checks this.
+    pub fn can_summon_to(&self, field: &Field, slot: FieldSlot, by_effect: bool) -> bool {
+        self.summon_restriction.is_none_or(|restriction| restriction.allows(field, slot, by_effect))
+    }
+}
+
+/// A limit on how a card may be summoned
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(from = "SummonRestrictionToml", into = "SummonRestrictionToml")]
+pub enum SummonRestriction {
+    /// May only be summoned to the front row
+    FrontRowOnly,
+    /// May only be summoned to the back row
+    BackRowOnly,
+    /// May only be summoned to a column with at least this many cards in its destroyed pile
+    DestroyedInColumn(u32),
+    /// May only be summoned by card effects, never by a normal summon from the hand
+    EffectOnly,
+}
+
+impl SummonRestriction {
+    fn allows(self, field: &Field, slot: FieldSlot, by_effect: bool) -> bool {
+        match self {
+            SummonRestriction::FrontRowOnly => slot.row() == Row::Front,
+            SummonRestriction::BackRowOnly => slot.row() == Row::Back,
+            SummonRestriction::DestroyedInColumn(cards) => field.destroyed(slot.column()).len() >= cards as usize,
+            SummonRestriction::EffectOnly => by_effect,
+        }
+    }
+}
+
+// The toml crate can't read or write enum variants with data as tables, so cards spell these
+// out as a name or a table with one entry
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+enum SummonRestrictionToml {
+    Named(NamedSummonRestriction),
+    DestroyedInColumn {
+        #[serde(rename = "DestroyedInColumn")]
+        cards: u32,
+    },
+}
+
+#[derive(Deserialize, Serialize)]
+enum NamedSummonRestriction {
+    #[serde(rename = "FrontRowOnly")]
+    FrontRow,
+    #[serde(rename = "BackRowOnly")]
+    BackRow,
+    #[serde(rename = "EffectOnly")]
+    Effect,
+}
+
+impl From<SummonRestrictionToml> for SummonRestriction {
+    fn from(toml: SummonRestrictionToml) -> Self {
+        match toml {
+            SummonRestrictionToml::Named(NamedSummonRestriction::FrontRow) => SummonRestriction::FrontRowOnly,
+            SummonRestrictionToml::Named(NamedSummonRestriction::BackRow) => SummonRestriction::BackRowOnly,
+            SummonRestrictionToml::Named(NamedSummonRestriction::Effect) => SummonRestriction::EffectOnly,
+            SummonRestrictionToml::DestroyedInColumn { cards } => SummonRestriction::DestroyedInColumn(cards),
+        }
+    }
+}
+
+impl From<SummonRestriction> for SummonRestrictionToml {
+    fn from(restriction: SummonRestriction) -> Self {
+        match restriction {
+            SummonRestriction::FrontRowOnly => SummonRestrictionToml::Named(NamedSummonRestriction::FrontRow),
+            SummonRestriction::BackRowOnly => SummonRestrictionToml::Named(NamedSummonRestriction::BackRow),
+            SummonRestriction::EffectOnly => SummonRestrictionToml::Named(NamedSummonRestriction::Effect),
+            SummonRestriction::DestroyedInColumn(cards) => SummonRestrictionToml::DestroyedInColumn { cards },
+        }
+    }
+}
+
+impl fmt::Display for SummonRestriction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SummonRestriction::FrontRowOnly => write!(f, "Can only be summoned to the front row."),
+            SummonRestriction::BackRowOnly => write!(f, "Can only be summoned to the back row."),
+            SummonRestriction::DestroyedInColumn(1) => write!(f, "Can only be summoned to a column with a destroyed card."),
+            SummonRestriction::DestroyedInColumn(cards) => write!(f, "Can only be summoned to a column with {} destroyed cards.", cards),
+            SummonRestriction::EffectOnly => write!(f, "Cannot be normal summoned."),
+        }
+    }
+}
+
+/// When a card effect can be activated
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TriggerKind {
+    OnSummon,
+    OnDraw,
+    OnField,
+    InCenterDeck,
+    OnDestroyed,
+    OnDeckEmptied,
+    OnTurnStart,
+    /// An effect type defined outside this crate
+    Other,
+}
+
+/// When an effect can activate. The engine only asks effects whether they can activate when
+/// their timing matches what the game is waiting on, and clients can show it on the card.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum TriggerTiming {
+    /// When the card is summoned
+    OnSummon,
+    /// When the card is drawn
+    OnDraw,
+    /// When a card is attacked
+    OnAttack,
+    /// In response to an effect being activated
+    Response,
+    /// After the chain the card was destroyed in resolves
+    OnDestroy,
+    /// After the chain resolves in which, or before which, a face down deck ran out
+    OnDeckEmptied,
+    /// At the start of a turn, before the turn player draws
+    StartOfTurn,
+    /// Whenever its controller has priority
+    Priority,
+    /// As an action during its controller's turn, rather than in response to anything
+    Action,
+    /// Whenever the engine checks for effects, for effects which don't give a timing
+    Any,
+    /// Never, for effects which apply for as long as their card is on the field rather than
+    /// going on the chain, like auras. The engine never asks these effects if they can activate.
+    Static,
+}
+
+impl TriggerTiming {
+    /// If effects with this timing only wait on something happening to their own card
+    pub fn targets_own_card(self) -> bool {
+        matches!(self, TriggerTiming::OnSummon | TriggerTiming::OnDraw | TriggerTiming::OnDestroy)
+    }
+
+    /// If an effect with this timing could activate in the context, which is false for effects
+    /// waiting on a different trigger
+    pub fn could_activate(self, context: &ResolutionContext, open: bool) -> bool {
+        let trigger = context.trigger.as_ref();
+        match self {
+            TriggerTiming::OnSummon => matches!(trigger, Some(Trigger::Summoned(_))),
+            TriggerTiming::OnDraw => matches!(trigger, Some(Trigger::Drawn(_))),
+            TriggerTiming::OnAttack => matches!(trigger, Some(Trigger::Attacked(_))),
+            TriggerTiming::Response => matches!(trigger, Some(Trigger::Activated(_))),
+            TriggerTiming::OnDestroy => matches!(trigger, Some(Trigger::Destroyed(_))),
+            TriggerTiming::OnDeckEmptied => matches!(trigger, Some(Trigger::DeckEmptied { .. })),
+            TriggerTiming::StartOfTurn => matches!(trigger, Some(Trigger::TurnStarted(_))),
+            TriggerTiming::Action => open,
+            TriggerTiming::Priority | TriggerTiming::Any => true,
+            TriggerTiming::Static => false,
+        }
+    }
+}
+
+/// A description of a card effect for clients to display, without needing to know the effect's
+/// type
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EffectSummary {
+    pub kind: TriggerKind,
+    pub timing: TriggerTiming,
+    /// If the effect must be activated whenever it can be
+    pub mandatory: bool,
+    /// What must be paid to activate the effect, if anything
+    pub cost: Option<String>,
+    /// What the effect does
+    pub text: String,
+}
+
+/// Keywords are common card abilities which are implemented by the engine, so cards don't need
+/// full effect definitions for them.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub enum Keyword {
+    /// May attack on the turn it is summoned, ignoring summoning sickness.
+    Swift,
+    /// Attacks against this card's column must target this card.
+    Guard,
+    /// Damage in excess of the attacked card's remaining defense spills onto the card behind it.
+    Piercing,
+    /// May be summoned from its destroyed pile as an action, entering the field with damage
+    /// counters equal to half its defense.
+    Undying,
+}
+
+impl Keyword {
+    /// Iterates through every keyword the engine implements
+    pub fn all() -> impl Iterator<Item = Keyword> {
+        [Keyword::Swift, Keyword::Guard, Keyword::Piercing, Keyword::Undying].iter().copied()
+    }
+}
+
+/// Which slots on the same field count as next to a slot
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub enum Adjacency {
+    /// The other slot in the same column, in front or behind
+    SameColumn,
+    /// The slots in the same row of the columns either side
+    NeighbouringColumns,
+    /// Both the same column and the neighbouring columns
+    Any,
+}
+
+/// A static effect which boosts the cards adjacent to this card for as long as it is on the field
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Aura {
+    pub adjacency: Adjacency,
+    #[serde(default)]
+    pub attack: u32,
+    #[serde(default)]
+    pub defense: u32,
+    /// If the aura only applies while this card is in the row, such as support cards which only
+    /// boost the cards around them from the back row
+    #[serde(default)]
+    pub while_in: Option<Row>,
+}
+
+#[typetag::serde(tag = "type")]
+pub trait CardEffect: Send + Sync + fmt::Debug {
+    /// How can this card type effect out of the card pool activate in this game state for this card instance in this context?
+    fn can_activate(&self, card_pool: &Cards, card_type: &CardType, game_state: &GameState, instance: CardInstance, context: &ResolutionContext) -> Vec<Activation>;
+
+    /// Try to activate this card type effect out of the card pool in this game state for this card instance in this context in a particular way.
+    fn activate(&self, card_pool: &Cards, card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: &Activation, context: &ResolutionContext);
+
+    /// Resolve this card type effect out of the card pool in this game state for this card instance in this context after it was activated in a particular way.
+    fn resolve(&self, card_pool: &Cards, card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: &Activation, context: &ResolutionContext);
+
+    /// When this card type effect can activate, so the engine doesn't need to ask it otherwise.
+    fn timing(&self) -> TriggerTiming {
+        TriggerTiming::Any
+    }
+
+    /// Describes this card type effect for displaying the card.
+    fn summary(&self) -> EffectSummary {
+        EffectSummary {
+            kind: TriggerKind::Other,
+            timing: self.timing(),
+            mandatory: false,
+            cost: None,
+            text: format!("{:?}", self),
+        }
+    }
+
+    /// How many cards must be sacrificed to summon the card from the center deck with this
+    /// effect, if it is a way to summon the card from there
+    fn summon_cost(&self) -> Option<usize> {
+        None
+    }
+
+    /// If effects may be activated in response to this card type effect
+    fn can_be_responded_to(&self) -> bool {
+        true
+    }
+}
+
+fn summary(kind: TriggerKind, timing: TriggerTiming, mandatory: bool, trigger: &dyn EffectTrigger) -> EffectSummary {
+    EffectSummary {
+        kind,
+        timing,
+        mandatory,
+        cost: trigger.cost(),
+        text: trigger.text(),
+    }
+}
+
+fn activations(trigger: &dyn EffectTrigger, mandatory: bool, card_pool: &Cards, card_type: &CardType, game_state: &GameState, instance: CardInstance, context: &ResolutionContext) -> Vec<Activation> {
+    trigger.variants(card_pool, card_type, game_state, instance, context).into_iter().map(|data| Activation {
+        status: if mandatory { ActivatableType::Mandatory } else { ActivatableType::Can },
+        data,
+    }).collect()
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct OnSummon {
+    pub mandatory: bool,
+    pub trigger: Box<dyn EffectTrigger>,
+}
+
+#[typetag::serde]
+impl CardEffect for OnSummon {
+    fn can_activate(&self, card_pool: &Cards, card_type: &CardType, game_state: &GameState, instance: CardInstance, context: &ResolutionContext) -> Vec<Activation> {
+        let summoned = context.trigger == Some(Trigger::Summoned(instance));
+        if summoned && game_state.card(instance).is_some_and(|card| card.instance_of(card_type) && card.status().on_field()) {
+            activations(self.trigger.as_ref(), self.mandatory, card_pool, card_type, game_state, instance, context)
+        } else {
+            vec![]
+        }
+    }
+
+    fn activate(&self, card_pool: &Cards, card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: &Activation, context: &ResolutionContext) {
+        self.trigger.activation(card_pool, card_type, game_state, instance, activation, context);
+    }
+
+    fn resolve(&self, card_pool: &Cards, card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: &Activation, context: &ResolutionContext) {
+        self.trigger.resolution(card_pool, card_type, game_state, instance, activation, context);
+    }
+
+    fn timing(&self) -> TriggerTiming {
+        TriggerTiming::OnSummon
+    }
+
+    fn summary(&self) -> EffectSummary {
+        summary(TriggerKind::OnSummon, self.timing(), self.mandatory, self.trigger.as_ref())
+    }
+
+    fn can_be_responded_to(&self) -> bool {
+        self.trigger.can_be_responded_to()
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct OnDraw {
+    pub mandatory: bool,
+    pub trigger: Box<dyn EffectTrigger>,
+}
+
+#[typetag::serde]
+impl CardEffect for OnDraw {
+    fn can_activate(&self, card_pool: &Cards, card_type: &CardType, game_state: &GameState, instance: CardInstance, context: &ResolutionContext) -> Vec<Activation> {
+        let drawn = context.trigger == Some(Trigger::Drawn(instance));
+        if drawn && game_state.card(instance).is_some_and(|card| card.instance_of(card_type) && card.status() == CardStatus::Hand) {
+            activations(self.trigger.as_ref(), self.mandatory, card_pool, card_type, game_state, instance, context)
+        } else {
+            vec![]
+        }
+    }
+
+    fn activate(&self, card_pool: &Cards, card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: &Activation, context: &ResolutionContext) {
+        self.trigger.activation(card_pool, card_type, game_state, instance, activation, context);
+    }
+
+    fn resolve(&self, card_pool: &Cards, card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: &Activation, context: &ResolutionContext) {
+        self.trigger.resolution(card_pool, card_type, game_state, instance, activation, context);
+    }
+
+    fn timing(&self) -> TriggerTiming {
+        TriggerTiming::OnDraw
+    }
+
+    fn summary(&self) -> EffectSummary {
+        summary(TriggerKind::OnDraw, self.timing(), self.mandatory, self.trigger.as_ref())
+    }
+
+    fn can_be_responded_to(&self) -> bool {
+        self.trigger.can_be_responded_to()
+    }
+}
+
+/// An effect which can be activated whenever its controller has priority while the card is on
+/// the field
+#[derive(Debug, Deserialize, Serialize)]
+pub struct OnField {
+    pub mandatory: bool,
+    pub trigger: Box<dyn EffectTrigger>,
+}
+
+#[typetag::serde]
+impl CardEffect for OnField {
+    fn can_activate(&self, card_pool: &Cards, card_type: &CardType, game_state: &GameState, instance: CardInstance, context: &ResolutionContext) -> Vec<Activation> {
+        if game_state.card(instance).is_some_and(|card| card.instance_of(card_type) && card.status().on_field()) {
+            activations(self.trigger.as_ref(), self.mandatory, card_pool, card_type, game_state, instance, context)
+        } else {
+            vec![]
+        }
+    }
+
+    fn activate(&self, card_pool: &Cards, card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: &Activation, context: &ResolutionContext) {
+        self.trigger.activation(card_pool, card_type, game_state, instance, activation, context);
+    }
+
+    fn resolve(&self, card_pool: &Cards, card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: &Activation, context: &ResolutionContext) {
+        self.trigger.resolution(card_pool, card_type, game_state, instance, activation, context);
+    }
+
+    fn timing(&self) -> TriggerTiming {
+        TriggerTiming::Priority
+    }
+
+    fn summary(&self) -> EffectSummary {
+        summary(TriggerKind::OnField, self.timing(), self.mandatory, self.trigger.as_ref())
+    }
+
+    fn can_be_responded_to(&self) -> bool {
+        self.trigger.can_be_responded_to()
+    }
+}
+
+/// An effect which can be activated as an action while the card is in its controller's center
+/// deck, such as the card's summoning cost
+#[derive(Debug, Deserialize, Serialize)]
+pub struct InCenterDeck {
+    pub trigger: Box<dyn EffectTrigger>,
+}
+
+#[typetag::serde]
+impl CardEffect for InCenterDeck {
+    fn can_activate(&self, card_pool: &Cards, card_type: &CardType, game_state: &GameState, instance: CardInstance, context: &ResolutionContext) -> Vec<Activation> {
+        let taking_action = matches!(game_state.open(), GameStateType::Open { .. });
+        if taking_action && game_state.card_in_center_deck(instance).is_some_and(|card| card.instance_of(card_type)) {
+            activations(self.trigger.as_ref(), false, card_pool, card_type, game_state, instance, context)
+        } else {
+            vec![]
+        }
+    }
+
+    fn activate(&self, card_pool: &Cards, card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: &Activation, context: &ResolutionContext) {
+        self.trigger.activation(card_pool, card_type, game_state, instance, activation, context);
+    }
+
+    fn resolve(&self, card_pool: &Cards, card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: &Activation, context: &ResolutionContext) {
+        self.trigger.resolution(card_pool, card_type, game_state, instance, activation, context);
+    }
+
+    fn timing(&self) -> TriggerTiming {
+        TriggerTiming::Action
+    }
+
+    fn summary(&self) -> EffectSummary {
+        summary(TriggerKind::InCenterDeck, self.timing(), false, self.trigger.as_ref())
+    }
+
+    fn summon_cost(&self) -> Option<usize> {
+        self.trigger.summon_cost()
+    }
+
+    fn can_be_responded_to(&self) -> bool {
+        self.trigger.can_be_responded_to()
+    }
+}
+
+/// An effect which can be activated after a chain resolves if this card was destroyed from the
+/// field while it resolved
+#[derive(Debug, Deserialize, Serialize)]
+pub struct OnDestroyed {
+    pub mandatory: bool,
+    pub trigger: Box<dyn EffectTrigger>,
+}
+
+#[typetag::serde]
+impl CardEffect for OnDestroyed {
+    fn can_activate(&self, card_pool: &Cards, card_type: &CardType, game_state: &GameState, instance: CardInstance, context: &ResolutionContext) -> Vec<Activation> {
+        let destroyed = matches!(&context.trigger, Some(Trigger::Destroyed(destroyed)) if destroyed.contains(&instance));
+        if destroyed && game_state.card(instance).is_some_and(|card| card.instance_of(card_type) && card.status() == CardStatus::Destroyed) {
+            activations(self.trigger.as_ref(), self.mandatory, card_pool, card_type, game_state, instance, context)
+        } else {
+            vec![]
+        }
+    }
+
+    fn activate(&self, card_pool: &Cards, card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: &Activation, context: &ResolutionContext) {
+        self.trigger.activation(card_pool, card_type, game_state, instance, activation, context);
+    }
+
+    fn resolve(&self, card_pool: &Cards, card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: &Activation, context: &ResolutionContext) {
+        self.trigger.resolution(card_pool, card_type, game_state, instance, activation, context);
+    }
+
+    fn timing(&self) -> TriggerTiming {
+        TriggerTiming::OnDestroy
+    }
+
+    fn summary(&self) -> EffectSummary {
+        summary(TriggerKind::OnDestroyed, self.timing(), self.mandatory, self.trigger.as_ref())
+    }
+
+    fn can_be_responded_to(&self) -> bool {
+        self.trigger.can_be_responded_to()
+    }
+}
+
+/// An effect which can be activated once one of a player's face down decks runs out, from
+/// wherever its controller can activate effects. The window opens after the chain the deck ran
+/// out during resolves, or after the next chain if the deck ran out by drawing.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct OnDeckEmptied {
+    pub whose: Whose,
+    pub deck: DeckChoice,
+    pub mandatory: bool,
+    pub trigger: Box<dyn EffectTrigger>,
+}
+
+#[typetag::serde]
+impl CardEffect for OnDeckEmptied {
+    fn can_activate(&self, card_pool: &Cards, card_type: &CardType, game_state: &GameState, instance: CardInstance, context: &ResolutionContext) -> Vec<Activation> {
+        let emptied = match context.trigger {
+            Some(Trigger::DeckEmptied { player, deck }) => {
+                self.whose.player(game_state, instance) == Some(player) && self.deck.decks().contains(&deck)
+            },
+            _ => false,
+        };
+        if emptied && game_state.card(instance).is_some_and(|card| card.instance_of(card_type)) {
+            activations(self.trigger.as_ref(), self.mandatory, card_pool, card_type, game_state, instance, context)
+        } else {
+            vec![]
+        }
+    }
+
+    fn activate(&self, card_pool: &Cards, card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: &Activation, context: &ResolutionContext) {
+        self.trigger.activation(card_pool, card_type, game_state, instance, activation, context);
+    }
+
+    fn resolve(&self, card_pool: &Cards, card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: &Activation, context: &ResolutionContext) {
+        self.trigger.resolution(card_pool, card_type, game_state, instance, activation, context);
+    }
+
+    fn timing(&self) -> TriggerTiming {
+        TriggerTiming::OnDeckEmptied
+    }
+
+    fn summary(&self) -> EffectSummary {
+        summary(TriggerKind::OnDeckEmptied, self.timing(), self.mandatory, self.trigger.as_ref())
+    }
+
+    fn can_be_responded_to(&self) -> bool {
+        self.trigger.can_be_responded_to()
+    }
+}
+
+/// An effect which can be activated at the start of its controller's turn while the card is on
+/// the field, before they draw. The window only opens if an effect like this can activate.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct OnTurnStart {
+    pub mandatory: bool,
+    pub trigger: Box<dyn EffectTrigger>,
+}
+
+#[typetag::serde]
+impl CardEffect for OnTurnStart {
+    fn can_activate(&self, card_pool: &Cards, card_type: &CardType, game_state: &GameState, instance: CardInstance, context: &ResolutionContext) -> Vec<Activation> {
+        let started = match context.trigger {
+            Some(Trigger::TurnStarted(player)) => game_state.controller_of(instance) == Some(player),
+            _ => false,
+        };
+        if started && game_state.card(instance).is_some_and(|card| card.instance_of(card_type) && card.status().on_field()) {
+            activations(self.trigger.as_ref(), self.mandatory, card_pool, card_type, game_state, instance, context)
+        } else {
+            vec![]
+        }
+    }
+
+    fn activate(&self, card_pool: &Cards, card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: &Activation, context: &ResolutionContext) {
+        self.trigger.activation(card_pool, card_type, game_state, instance, activation, context);
+    }
+
+    fn resolve(&self, card_pool: &Cards, card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: &Activation, context: &ResolutionContext) {
+        self.trigger.resolution(card_pool, card_type, game_state, instance, activation, context);
+    }
+
+    fn timing(&self) -> TriggerTiming {
+        TriggerTiming::StartOfTurn
+    }
+
+    fn summary(&self) -> EffectSummary {
+        summary(TriggerKind::OnTurnStart, self.timing(), self.mandatory, self.trigger.as_ref())
+    }
+
+    fn can_be_responded_to(&self) -> bool {
+        self.trigger.can_be_responded_to()
+    }
+}
+
+#[typetag::serde(tag = "type")]
+#[allow(unused_variables)]
+pub trait EffectTrigger: Send + Sync + fmt::Debug {
+    /// In what different ways can this trigger activate?
+    fn variants(&self, card_pool: &Cards, card_type: &CardType, game_state: &GameState, instance: CardInstance, context: &ResolutionContext) -> Vec<ActivationData> {
+        vec![ActivationData::default()]
+    }
+    fn activation(&self, card_pool: &Cards, card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: &Activation, context: &ResolutionContext) {}
+    fn resolution(&self, card_pool: &Cards, card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: &Activation, context: &ResolutionContext) {}
+    /// What this trigger does, in rules text
+    fn text(&self) -> String {
+        format!("{:?}", self)
+    }
+    /// What this trigger costs to activate, in rules text, if anything
+    fn cost(&self) -> Option<String> {
+        None
+    }
+    /// How many cards must be sacrificed to summon the card with this trigger, if it summons
+    /// the card at all
+    fn summon_cost(&self) -> Option<usize> {
+        None
+    }
+    /// If effects may be activated in response to this trigger
+    fn can_be_responded_to(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DestroySelfUnless {
+    pub condition: Box<dyn Condition>,
+}
+
+#[typetag::serde]
+impl EffectTrigger for DestroySelfUnless {
+    fn activation(&self, card_pool: &Cards, card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: &Activation, context: &ResolutionContext) {
+        if !self.condition.met(card_pool, card_type, game_state, instance, activation, context) {
+            // swallow error, we don't care if the instance is actually on the field, just that
+            // it gets destroyed if it is
+            let _ = game_state.take_action(card_pool, EngineAction::DestroyOnField(instance));
+        }
+    }
+
+    fn text(&self) -> String {
+        format!("Destroy this card unless {}.", self.condition.text())
+    }
+}
+
+/// Resolves another trigger only if the condition is met when it resolves
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Conditional {
+    pub condition: Box<dyn Condition>,
+    pub trigger: Box<dyn EffectTrigger>,
+}
+
+#[typetag::serde]
+impl EffectTrigger for Conditional {
+    fn variants(&self, card_pool: &Cards, card_type: &CardType, game_state: &GameState, instance: CardInstance, context: &ResolutionContext) -> Vec<ActivationData> {
+        self.trigger.variants(card_pool, card_type, game_state, instance, context)
+    }
+
+    fn activation(&self, card_pool: &Cards, card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: &Activation, context: &ResolutionContext) {
+        self.trigger.activation(card_pool, card_type, game_state, instance, activation, context);
+    }
+
+    fn resolution(&self, card_pool: &Cards, card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: &Activation, context: &ResolutionContext) {
+        if self.condition.met(card_pool, card_type, game_state, instance, activation, context) {
+            self.trigger.resolution(card_pool, card_type, game_state, instance, activation, context);
+        }
+    }
+
+    fn text(&self) -> String {
+        format!("If {}: {}", self.condition.text(), self.trigger.text())
+    }
+
+    fn cost(&self) -> Option<String> {
+        self.trigger.cost()
+    }
+
+    fn can_be_responded_to(&self) -> bool {
+        self.trigger.can_be_responded_to()
+    }
+}
+
+/// Another trigger which no effects may be activated in response to
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Unrespondable {
+    pub trigger: Box<dyn EffectTrigger>,
+}
+
+#[typetag::serde]
+impl EffectTrigger for Unrespondable {
+    fn variants(&self, card_pool: &Cards, card_type: &CardType, game_state: &GameState, instance: CardInstance, context: &ResolutionContext) -> Vec<ActivationData> {
+        self.trigger.variants(card_pool, card_type, game_state, instance, context)
+    }
+
+    fn activation(&self, card_pool: &Cards, card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: &Activation, context: &ResolutionContext) {
+        self.trigger.activation(card_pool, card_type, game_state, instance, activation, context);
+    }
+
+    fn resolution(&self, card_pool: &Cards, card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: &Activation, context: &ResolutionContext) {
+        self.trigger.resolution(card_pool, card_type, game_state, instance, activation, context);
+    }
+
+    fn text(&self) -> String {
+        format!("{} This cannot be responded to.", self.trigger.text())
+    }
+
+    fn cost(&self) -> Option<String> {
+        self.trigger.cost()
+    }
+
+    fn summon_cost(&self) -> Option<usize> {
+        self.trigger.summon_cost()
+    }
+
+    fn can_be_responded_to(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SwapHandWithField;
+
+#[typetag::serde]
+impl EffectTrigger for SwapHandWithField {
+    // We can potentially activate on any column of our field
+    fn variants(&self, _card_pool: &Cards, card_type: &CardType, game_state: &GameState, instance: CardInstance, _context: &ResolutionContext) -> Vec<ActivationData> {
+        let controller = match game_state.controller_of(instance) {
+            Some(player) => player,
+            None => return vec![],
+        };
+        let field = game_state.field_of(controller);
+        field.occupied_slots().filter(|&(slot, _)| card_type.can_summon_to(field, slot, true)).map(|(slot, _)| ActivationData {
+            slot: Some(slot),
+            ..ActivationData::default()
+        }).collect()
+    }
+
+    fn activation(&self, card_pool: &Cards, _card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: &Activation, _context: &ResolutionContext) {
+        let slot = match activation.data.slot {
+            Some(slot) => slot,
+            None => return
+        };
+        let controller = match game_state.controller_of(instance) {
+            Some(player) => player,
+            None => return
+        };
+        let target = match game_state.field_of(controller)[slot].as_ref().map(|card| card.instance) {
+            Some(card) => card,
+            None => return
+        };
+        let _ = game_state.take_action(card_pool, EngineAction::ReturnFieldToHand(target))
+            .and_then(|_| game_state.take_action(card_pool, EngineAction::SummonFromHandToSlot(instance, slot)));
+    }
+
+    fn text(&self) -> String {
+        "Return a card on your field to your hand and summon this card to its slot.".to_owned()
+    }
+}
+
+/// Exchanges the positions of two cards on the controller's field
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SwapFieldPositions {
+    /// If the cards keep their damage counters after moving
+    pub preserve_counters: bool,
+}
+
+#[typetag::serde]
+impl EffectTrigger for SwapFieldPositions {
+    // We can swap any pair of cards on our field
+    fn variants(&self, _card_pool: &Cards, _card_type: &CardType, game_state: &GameState, instance: CardInstance, _context: &ResolutionContext) -> Vec<ActivationData> {
+        let controller = match game_state.controller_of(instance) {
+            Some(player) => player,
+            None => return vec![],
+        };
+        let cards: Vec<CardInstance> = game_state.field_of(controller)
+            .occupied_slots()
+            .map(|(_, card)| card.instance)
+            .collect();
+        let mut variants = vec![];
+        for (i, &first) in cards.iter().enumerate() {
+            for &second in cards[i + 1..].iter() {
+                variants.push(ActivationData {
+                    targets: vec![first, second],
+                    ..ActivationData::default()
+                });
+            }
+        }
+        variants
+    }
+
+    fn resolution(&self, card_pool: &Cards, _card_type: &CardType, game_state: &mut GameState, _instance: CardInstance, activation: &Activation, _context: &ResolutionContext) {
+        if let [first, second] = activation.data.targets[..] {
+            // swallow error, either card may have left the field in response
+            let _ = game_state.take_action(card_pool, EngineAction::SwapOnField(first, second, self.preserve_counters));
+        }
+    }
+
+    fn text(&self) -> String {
+        if self.preserve_counters {
+            "Swap the positions of two cards on your field, keeping their damage.".to_owned()
+        } else {
+            "Swap the positions of two cards on your field.".to_owned()
+        }
+    }
+}
+
+/// Which player an effect applies to, relative to the controller of the card
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub enum Whose {
+    Controller,
+    Opponent,
+}
+
+impl Whose {
+    fn player(self, game_state: &GameState, instance: CardInstance) -> Option<Player> {
+        let controller = game_state.controller_of(instance)?;
+        match self {
+            Whose::Controller => Some(controller),
+            Whose::Opponent => Some(controller.next()),
+        }
+    }
+}
+
+/// Which of a player's face down decks an effect applies to
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub enum DeckChoice {
+    Left,
+    Right,
+    /// The activating player chooses either deck
+    Either,
+}
+
+impl DeckChoice {
+    fn text(self, whose: Whose) -> &'static str {
+        match (self, whose) {
+            (DeckChoice::Left, Whose::Controller) => "your left deck",
+            (DeckChoice::Right, Whose::Controller) => "your right deck",
+            (DeckChoice::Either, Whose::Controller) => "either of your decks",
+            (DeckChoice::Left, Whose::Opponent) => "your opponent's left deck",
+            (DeckChoice::Right, Whose::Opponent) => "your opponent's right deck",
+            (DeckChoice::Either, Whose::Opponent) => "either of your opponent's decks",
+        }
+    }
+
+    fn decks(self) -> &'static [FaceDownDeck] {
+        match self {
+            DeckChoice::Left => &[FaceDownDeck::Left],
+            DeckChoice::Right => &[FaceDownDeck::Right],
+            DeckChoice::Either => &[FaceDownDeck::Left, FaceDownDeck::Right],
+        }
+    }
+}
+
+/// "card" or "N cards", to follow "the top"
+fn cards(count: u32) -> String {
+    if count == 1 {
+        "card".to_owned()
+    } else {
+        format!("{} cards", count)
+    }
+}
+
+/// Draws cards from the top of a face down deck to the hand. If the deck runs out of cards as
+/// many as possible are drawn. Decks a draw rule stops the player drawing from can't be chosen.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DrawCards {
+    pub whose: Whose,
+    pub deck_choice: DeckChoice,
+    pub count: u32,
+}
+
+#[typetag::serde]
+impl EffectTrigger for DrawCards {
+    fn variants(&self, _card_pool: &Cards, _card_type: &CardType, game_state: &GameState, instance: CardInstance, _context: &ResolutionContext) -> Vec<ActivationData> {
+        let player = match self.whose.player(game_state, instance) {
+            Some(player) => player,
+            None => return vec![],
+        };
+        self.deck_choice.decks()
+            .iter()
+            .filter(|&&deck| game_state.can_draw_from(player, deck))
+            .map(|&deck| ActivationData { deck: Some(deck), ..ActivationData::default() })
+            .collect()
+    }
+
+    fn resolution(&self, card_pool: &Cards, _card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: &Activation, _context: &ResolutionContext) {
+        let (player, deck) = match (self.whose.player(game_state, instance), activation.data.deck) {
+            (Some(player), Some(deck)) => (player, deck),
+            _ => return,
+        };
+        for _ in 0..self.count {
+            // a draw rule made in response stops the rest of the draws too
+            if !game_state.can_draw_from(player, deck) {
+                break;
+            }
+            let _ = game_state.take_action(card_pool, EngineAction::Draw(player, deck));
+        }
+    }
+
+    fn text(&self) -> String {
+        let count = if self.count == 1 { "1 card".to_owned() } else { format!("{} cards", self.count) };
+        match self.whose {
+            Whose::Controller => format!("Draw {} from {}.", count, self.deck_choice.text(self.whose)),
+            Whose::Opponent => format!("Your opponent draws {} from {}.", count, self.deck_choice.text(self.whose)),
+        }
+    }
+}
+
+/// How many of a card's damage counters an effect converts, which is all of them unless there
+/// is a most
+fn counters_to_convert(game_state: &GameState, instance: CardInstance, max: Option<u32>) -> u32 {
+    let damage = game_state.card_on_field(instance).map_or(0, |card| card.damage);
+    max.map_or(damage, |max| damage.min(max))
+}
+
+/// "every damage counter" or "up to N damage counters", for effect text
+fn counters_text(max: Option<u32>) -> String {
+    match max {
+        None => "every damage counter".to_owned(),
+        Some(1) => "up to 1 damage counter".to_owned(),
+        Some(max) => format!("up to {} damage counters", max),
+    }
+}
+
+/// Removes damage counters from this card to draw a card for each one removed. Only as many
+/// counters are removed as there are cards to draw.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DrawForCounters {
+    pub deck_choice: DeckChoice,
+    /// The most counters to remove
+    #[serde(default)]
+    pub max: Option<u32>,
+}
+
+#[typetag::serde]
+impl EffectTrigger for DrawForCounters {
+    fn variants(&self, _card_pool: &Cards, _card_type: &CardType, game_state: &GameState, instance: CardInstance, _context: &ResolutionContext) -> Vec<ActivationData> {
+        let player = match game_state.controller_of(instance) {
+            Some(player) if counters_to_convert(game_state, instance, self.max) > 0 => player,
+            _ => return vec![],
+        };
+        self.deck_choice.decks()
+            .iter()
+            .filter(|&&deck| game_state.can_draw_from(player, deck))
+            .map(|&deck| ActivationData { deck: Some(deck), ..ActivationData::default() })
+            .collect()
+    }
+
+    fn resolution(&self, card_pool: &Cards, _card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: &Activation, _context: &ResolutionContext) {
+        let (player, deck) = match (game_state.controller_of(instance), activation.data.deck) {
+            (Some(player), Some(deck)) if game_state.can_draw_from(player, deck) => (player, deck),
+            _ => return,
+        };
+        let available = game_state.field_of(player).deck(deck).len() as u32;
+        let counters = counters_to_convert(game_state, instance, self.max).min(available);
+        let removed = -(counters as i32);
+        if game_state.take_action(card_pool, EngineAction::ChangeCounters(vec![(instance, removed)])).is_err() {
+            return;
+        }
+        for _ in 0..counters {
+            let _ = game_state.take_action(card_pool, EngineAction::Draw(player, deck));
+        }
+    }
+
+    fn text(&self) -> String {
+        format!(
+            "Remove {} from this card to draw a card for each from {}.",
+            counters_text(self.max), self.deck_choice.text(Whose::Controller)
+        )
+    }
+}
+
+/// Removes damage counters from this card to give its controller a resource point for each one
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ResourcesForCounters {
+    /// The most counters to remove
+    #[serde(default)]
+    pub max: Option<u32>,
+}
+
+#[typetag::serde]
+impl EffectTrigger for ResourcesForCounters {
+    fn variants(&self, _card_pool: &Cards, _card_type: &CardType, game_state: &GameState, instance: CardInstance, _context: &ResolutionContext) -> Vec<ActivationData> {
+        if counters_to_convert(game_state, instance, self.max) > 0 {
+            vec![ActivationData::default()]
+        } else {
+            vec![]
+        }
+    }
+
+    fn resolution(&self, card_pool: &Cards, _card_type: &CardType, game_state: &mut GameState, instance: CardInstance, _activation: &Activation, _context: &ResolutionContext) {
+        let player = match game_state.controller_of(instance) {
+            Some(player) => player,
+            None => return,
+        };
+        let counters = counters_to_convert(game_state, instance, self.max);
+        let removed = -(counters as i32);
+        if game_state.take_action(card_pool, EngineAction::ChangeCounters(vec![(instance, removed)])).is_ok() {
+            let _ = game_state.take_action(card_pool, EngineAction::GainResources(player, counters));
+        }
+    }
+
+    fn text(&self) -> String {
+        format!("Remove {} from this card to gain a resource for each.", counters_text(self.max))
+    }
+}
+
+/// Moves damage counters from this card to a targeted card on the field, which is destroyed if
+/// that leaves it with as many counters as defense. Nothing moves if the target left the field
+/// in response.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TransferCounters {
+    pub whose: Whose,
+    #[serde(default)]
+    pub filter: TargetFilter,
+    /// The most counters to move
+    #[serde(default)]
+    pub max: Option<u32>,
+}
+
+#[typetag::serde]
+impl EffectTrigger for TransferCounters {
+    fn variants(&self, card_pool: &Cards, _card_type: &CardType, game_state: &GameState, instance: CardInstance, _context: &ResolutionContext) -> Vec<ActivationData> {
+        let player = match self.whose.player(game_state, instance) {
+            Some(player) if counters_to_convert(game_state, instance, self.max) > 0 => player,
+            _ => return vec![],
+        };
+        game_state.field_of(player)
+            .occupied_slots()
+            .filter(|(_, card)| card.instance != instance && self.filter.matches(card_pool, game_state, instance, card))
+            .map(|(_, card)| ActivationData {
+                targets: vec![card.instance],
+                ..ActivationData::default()
+            })
+            .collect()
+    }
+
+    fn resolution(&self, card_pool: &Cards, _card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: &Activation, _context: &ResolutionContext) {
+        if let [target] = activation.data.targets[..] {
+            let counters = counters_to_convert(game_state, instance, self.max) as i32;
+            // swallow error, the counters stay where they are if either card left the field
+            let _ = game_state.take_action(card_pool, EngineAction::ChangeCounters(vec![(instance, -counters), (target, counters)]));
+        }
+    }
+
+    fn text(&self) -> String {
+        let field = match self.whose {
+            Whose::Controller => "your",
+            Whose::Opponent => "your opponent's",
+        };
+        format!("Move {} from this card to a card{} on {} field.", counters_text(self.max), self.filter.text(), field)
+    }
+}
+
+/// Sends cards from the top of a face down deck to a destroyed pile. If the deck runs out of
+/// cards as many as possible are sent.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MillDeck {
+    pub whose: Whose,
+    pub deck_choice: DeckChoice,
+    pub count: u32,
+    /// The column of the destroyed pile the cards go to, chosen on activation if not fixed
+    #[serde(default)]
+    pub column: Option<Column>,
+}
+
+#[typetag::serde]
+impl EffectTrigger for MillDeck {
+    // We can mill any deck with cards left into any allowed column
+    fn variants(&self, _card_pool: &Cards, _card_type: &CardType, game_state: &GameState, instance: CardInstance, _context: &ResolutionContext) -> Vec<ActivationData> {
+        let player = match self.whose.player(game_state, instance) {
+            Some(player) => player,
+            None => return vec![],
+        };
+        let columns: Vec<Column> = match self.column {
+            Some(column) => vec![column],
+            None => Column::all().collect(),
+        };
+        let field = game_state.field_of(player);
+        let mut variants = vec![];
+        for &deck in self.deck_choice.decks().iter().filter(|&&deck| !field.deck(deck).is_empty()) {
+            for &column in columns.iter() {
+                variants.push(ActivationData {
+                    deck: Some(deck),
+                    column: Some(column),
+                    ..ActivationData::default()
+                });
+            }
+        }
+        variants
+    }
+
+    fn resolution(&self, card_pool: &Cards, _card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: &Activation, _context: &ResolutionContext) {
+        let (deck, column) = match (activation.data.deck, activation.data.column) {
+            (Some(deck), Some(column)) => (deck, column),
+            _ => return,
+        };
+        let player = match self.whose.player(game_state, instance) {
+            Some(player) => player,
+            None => return,
+        };
+        for _ in 0..self.count {
+            // stop once the deck runs out, milling into an empty deck just does nothing
+            if game_state.take_action(card_pool, EngineAction::MillToDestroyed(player, deck, column)).is_err() {
+                break;
+            }
+        }
+    }
+
+    fn text(&self) -> String {
+        let column = match self.column {
+            Some(column) => format!("column {}'s", column.0),
+            None => "a".to_owned(),
+        };
+        format!(
+            "Send the top {} of {} to {} destroyed pile.",
+            cards(self.count), self.deck_choice.text(self.whose), column
+        )
+    }
+}
+
+/// Restrictions on which cards an effect may target, every restriction given must be met
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct TargetFilter {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub max_attack: Option<u32>,
+    #[serde(default)]
+    pub max_defense: Option<u32>,
+    /// If the target must be in the same column as the card with the effect, on either field
+    #[serde(default)]
+    pub same_column: bool,
+    /// If the target must be adjacent to the card with the effect on its controller's field
+    #[serde(default)]
+    pub adjacent: Option<Adjacency>,
+    /// If the target must be on the field in the row
+    #[serde(default)]
+    pub row: Option<Row>,
+}
+
+impl TargetFilter {
+    /// The restrictions, as a phrase to follow "a card", empty if there are none
+    fn text(&self) -> String {
+        let mut text = String::new();
+        if let Some(name) = &self.name {
+            text.push_str(&format!(" named {}", name));
+        }
+        if let Some(attack) = self.max_attack {
+            text.push_str(&format!(" with at most {} attack", attack));
+        }
+        if let Some(defense) = self.max_defense {
+            text.push_str(&format!(" with at most {} defense", defense));
+        }
+        if self.same_column {
+            text.push_str(" in this card's column");
+        }
+        if self.adjacent.is_some() {
+            text.push_str(" next to this card");
+        }
+        if let Some(row) = self.row {
+            text.push_str(&format!(" in the {}", row));
+        }
+        text
+    }
+
+    /// Can the card with the effect in this game state target the card?
+    pub fn matches(&self, card_pool: &Cards, game_state: &GameState, instance: CardInstance, card: &Card) -> bool {
+        let card_type = card.lookup_self(card_pool);
+        self.name.as_ref().is_none_or(|name| &card_type.name == name)
+            && self.max_attack.is_none_or(|attack| card_type.attack <= attack)
+            && self.max_defense.is_none_or(|defense| card_type.defense <= defense)
+            && (!self.same_column || game_state.column_of(instance).is_some_and(|column| {
+                game_state.column_of(card.instance) == Some(column)
+            }))
+            && self.adjacent.is_none_or(|adjacency| adjacent(game_state, instance, card.instance, adjacency))
+            && self.row.is_none_or(|row| game_state.controller_of(card.instance)
+                .and_then(|player| game_state.field_of(player).row_of(card.instance)) == Some(row))
+    }
+}
+
+/// Are both cards on the same field and next to each other?
+fn adjacent(game_state: &GameState, instance: CardInstance, other: CardInstance, adjacency: Adjacency) -> bool {
+    let field = match game_state.controller_of(instance) {
+        Some(player) => game_state.field_of(player),
+        None => return false,
+    };
+    match (field.slot_of(instance), field.slot_of(other)) {
+        (Some(slot), Some(other)) => slot.adjacent(adjacency).contains(&other),
+        _ => false,
+    }
+}
+
+/// Returns a targeted card on the field to its owner's hand when the effect resolves
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ReturnTargetToHand {
+    pub whose: Whose,
+    #[serde(default)]
+    pub filter: TargetFilter,
+}
+
+#[typetag::serde]
+impl EffectTrigger for ReturnTargetToHand {
+    // We can target any card on the chosen field that passes the filter
+    fn variants(&self, card_pool: &Cards, _card_type: &CardType, game_state: &GameState, instance: CardInstance, _context: &ResolutionContext) -> Vec<ActivationData> {
+        let player = match self.whose.player(game_state, instance) {
+            Some(player) => player,
+            None => return vec![],
+        };
+        game_state.field_of(player)
+            .occupied_slots()
+            .filter(|(_, card)| self.filter.matches(card_pool, game_state, instance, card))
+            .map(|(_, card)| ActivationData {
+                targets: vec![card.instance],
+                ..ActivationData::default()
+            })
+            .collect()
+    }
+
+    fn resolution(&self, card_pool: &Cards, _card_type: &CardType, game_state: &mut GameState, _instance: CardInstance, activation: &Activation, _context: &ResolutionContext) {
+        if let [target] = activation.data.targets[..] {
+            // swallow error, the target may have left the field in response
+            let _ = game_state.take_action(card_pool, EngineAction::ReturnFieldToHand(target));
+        }
+    }
+
+    fn text(&self) -> String {
+        let field = match self.whose {
+            Whose::Controller => "your",
+            Whose::Opponent => "your opponent's",
+        };
+        format!("Return a card{} on {} field to its owner's hand.", self.filter.text(), field)
+    }
+}
+
+/// Removes a targeted card on the field from the game when the effect resolves
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RemoveTarget {
+    pub whose: Whose,
+    #[serde(default)]
+    pub filter: TargetFilter,
+}
+
+#[typetag::serde]
+impl EffectTrigger for RemoveTarget {
+    // We can target any card on the chosen field that passes the filter
+    fn variants(&self, card_pool: &Cards, _card_type: &CardType, game_state: &GameState, instance: CardInstance, _context: &ResolutionContext) -> Vec<ActivationData> {
+        let player = match self.whose.player(game_state, instance) {
+            Some(player) => player,
+            None => return vec![],
+        };
+        game_state.field_of(player)
+            .occupied_slots()
+            .filter(|(_, card)| self.filter.matches(card_pool, game_state, instance, card))
+            .map(|(_, card)| ActivationData {
+                targets: vec![card.instance],
+                ..ActivationData::default()
+            })
+            .collect()
+    }
+
+    fn resolution(&self, card_pool: &Cards, _card_type: &CardType, game_state: &mut GameState, _instance: CardInstance, activation: &Activation, _context: &ResolutionContext) {
+        if let [target] = activation.data.targets[..] {
+            // swallow error, the target may have left the field in response
+            if game_state.card_on_field(target).is_some() {
+                let _ = game_state.take_action(card_pool, EngineAction::RemoveFromGame(target));
+            }
+        }
+    }
+
+    fn text(&self) -> String {
+        let field = match self.whose {
+            Whose::Controller => "your",
+            Whose::Opponent => "your opponent's",
+        };
+        format!("Remove a card{} on {} field from the game.", self.filter.text(), field)
+    }
+}
+
+/// Destroys every card on both fields that passes the filter when the effect resolves, all at
+/// once
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DestroyAll {
+    #[serde(default)]
+    pub filter: TargetFilter,
+}
+
+#[typetag::serde]
+impl EffectTrigger for DestroyAll {
+    fn resolution(&self, card_pool: &Cards, _card_type: &CardType, game_state: &mut GameState, instance: CardInstance, _activation: &Activation, _context: &ResolutionContext) {
+        let targets: Vec<CardInstance> = game_state.cards_on_field()
+            .filter(|card| self.filter.matches(card_pool, game_state, instance, card))
+            .map(|card| card.instance)
+            .collect();
+        // the targets are found as the effect resolves, so are all still on the field
+        let _ = game_state.take_action(card_pool, EngineAction::DestroyAllOnField(targets));
+    }
+
+    fn text(&self) -> String {
+        format!("Destroy every card{} on the field.", self.filter.text())
+    }
+}
+
+/// Destroys every card in a column when the effect resolves, all at once
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DestroyColumn {
+    /// The column to destroy, chosen on activation if not fixed
+    #[serde(default, deserialize_with = "column_on_field")]
+    pub column: Option<Column>,
+    /// The field to destroy the column of, or both fields if not given
+    #[serde(default)]
+    pub whose: Option<Whose>,
+}
+
+/// Reads a column a card names, refusing any which isn't on the field
+fn column_on_field<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<Column>, D::Error> {
+    match Option::<Column>::deserialize(deserializer)? {
+        Some(column) if !column.is_valid() => Err(serde::de::Error::custom(format!(
+            "there is no column {}, columns are numbered from 0 to 6", column.0
+        ))),
+        column => Ok(column),
+    }
+}
+
+#[typetag::serde]
+impl EffectTrigger for DestroyColumn {
+    // We can destroy any allowed column
+    fn variants(&self, _card_pool: &Cards, _card_type: &CardType, _game_state: &GameState, _instance: CardInstance, _context: &ResolutionContext) -> Vec<ActivationData> {
+        let columns: Vec<Column> = match self.column {
+            Some(column) => vec![column].into_iter().filter(|column| column.is_valid()).collect(),
+            None => Column::all().collect(),
+        };
+        columns.into_iter().map(|column| ActivationData {
+            column: Some(column),
+            ..ActivationData::default()
+        }).collect()
+    }
+
+    fn resolution(&self, card_pool: &Cards, _card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: &Activation, _context: &ResolutionContext) {
+        let column = match activation.data.column {
+            Some(column) => column,
+            None => return,
+        };
+        let players = match self.whose {
+            Some(whose) => match whose.player(game_state, instance) {
+                Some(player) => vec![player],
+                None => return,
+            },
+            None => Player::all().collect(),
+        };
+        let targets: Vec<CardInstance> = players.into_iter()
+            .flat_map(|player| game_state.field_of(player).cards_in_column(column).map(|card| card.instance))
+            .collect();
+        let _ = game_state.take_action(card_pool, EngineAction::DestroyAllOnField(targets));
+    }
+
+    fn text(&self) -> String {
+        let column = match self.column {
+            Some(column) => format!("column {}", column.0),
+            None => "a column".to_owned(),
+        };
+        let field = match self.whose {
+            Some(Whose::Controller) => "on your field",
+            Some(Whose::Opponent) => "on your opponent's field",
+            None => "on both fields",
+        };
+        format!("Destroy every card in {} {}.", column, field)
+    }
+}
+
+/// Moves a targeted card on the opponent's field to an empty slot on the controller's field when
+/// the effect resolves. The card still belongs to the opponent, so it goes back to their zones
+/// when it leaves the field.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TakeControlOfTarget {
+    #[serde(default)]
+    pub filter: TargetFilter,
+}
+
+#[typetag::serde]
+impl EffectTrigger for TakeControlOfTarget {
+    // We can target any opponent's card that passes the filter and move it to any empty slot
+    fn variants(&self, card_pool: &Cards, _card_type: &CardType, game_state: &GameState, instance: CardInstance, _context: &ResolutionContext) -> Vec<ActivationData> {
+        let controller = match game_state.controller_of(instance) {
+            Some(player) => player,
+            None => return vec![],
+        };
+        let empty = game_state.field_of(controller).empty_slots();
+        let mut variants = vec![];
+        for (_, card) in game_state.opponent_of(controller).occupied_slots() {
+            if !self.filter.matches(card_pool, game_state, instance, card) {
+                continue;
+            }
+            for &slot in empty.iter() {
+                variants.push(ActivationData {
+                    slot: Some(slot),
+                    targets: vec![card.instance],
+                    ..ActivationData::default()
+                });
+            }
+        }
+        variants
+    }
+
+    fn resolution(&self, card_pool: &Cards, _card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: &Activation, _context: &ResolutionContext) {
+        let (target, slot) = match (&activation.data.targets[..], activation.data.slot) {
+            (&[target], Some(slot)) => (target, slot),
+            _ => return,
+        };
+        let controller = match game_state.controller_of(instance) {
+            Some(player) => player,
+            None => return,
+        };
+        // swallow error, the target may have left the field or the slot been filled in response
+        let _ = game_state.take_action(card_pool, EngineAction::TakeControl(controller, target, slot));
+    }
+
+    fn text(&self) -> String {
+        format!("Take control of a card{} on your opponent's field.", self.filter.text())
+    }
+}
+
+/// Lets the controller look at cards from the top of one of their opponent's face down decks
+/// without changing their order
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PeekOpponentDeck {
+    pub deck: DeckChoice,
+    pub count: u32,
+}
+
+#[typetag::serde]
+impl EffectTrigger for PeekOpponentDeck {
+    // We can look at any allowed deck with cards left
+    fn variants(&self, _card_pool: &Cards, _card_type: &CardType, game_state: &GameState, instance: CardInstance, _context: &ResolutionContext) -> Vec<ActivationData> {
+        let opponent = match Whose::Opponent.player(game_state, instance) {
+            Some(player) => player,
+            None => return vec![],
+        };
+        let field = game_state.field_of(opponent);
+        self.deck.decks()
+            .iter()
+            .filter(|&&deck| !field.deck(deck).is_empty())
+            .map(|&deck| ActivationData {
+                deck: Some(deck),
+                ..ActivationData::default()
+            })
+            .collect()
+    }
+
+    fn resolution(&self, card_pool: &Cards, _card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: &Activation, _context: &ResolutionContext) {
+        let deck = match activation.data.deck {
+            Some(deck) => deck,
+            None => return,
+        };
+        let controller = match game_state.controller_of(instance) {
+            Some(player) => player,
+            None => return,
+        };
+        let seen: Vec<CardInstance> = game_state.opponent_of(controller)
+            .deck(deck)
+            .iter()
+            .rev()
+            .take(self.count as usize)
+            .map(|card| card.instance)
+            .collect();
+        for card in seen {
+            let _ = game_state.take_action(card_pool, EngineAction::Reveal(controller, card));
+        }
+    }
+
+    fn text(&self) -> String {
+        format!("Look at the top {} of {}.", cards(self.count), self.deck.text(Whose::Opponent))
+    }
+}
+
+/// Changes the target of the attack the chain was opened by to another card the attack could
+/// have targeted, or to this card
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RetargetAttack;
+
+#[typetag::serde]
+impl EffectTrigger for RetargetAttack {
+    // We can only redirect attacks against our own field
+    fn variants(&self, card_pool: &Cards, _card_type: &CardType, game_state: &GameState, instance: CardInstance, context: &ResolutionContext) -> Vec<ActivationData> {
+        let attack = match context.attack {
+            Some(attack) => attack,
+            None => return vec![],
+        };
+        let defender = match game_state.controller_of(attack.target) {
+            Some(player) => player,
+            None => return vec![],
+        };
+        if game_state.controller_of(instance) != Some(defender) {
+            return vec![];
+        }
+        let field = game_state.field_of(defender);
+        let mut targets: Vec<CardInstance> = field.attack_targets(card_pool)
+            .into_iter()
+            .filter_map(|slot| field[slot].as_ref().map(|card| card.instance))
+            .collect();
+        if field.occupied_slots().any(|(_, card)| card.instance == instance) && !targets.contains(&instance) {
+            targets.push(instance);
+        }
+        targets.into_iter()
+            .filter(|&target| target != attack.target)
+            .map(|target| ActivationData {
+                targets: vec![target],
+                ..ActivationData::default()
+            })
+            .collect()
+    }
+
+    fn resolution(&self, card_pool: &Cards, _card_type: &CardType, game_state: &mut GameState, _instance: CardInstance, activation: &Activation, _context: &ResolutionContext) {
+        if let [target] = activation.data.targets[..] {
+            // swallow error, the new target may have left the field in response
+            let _ = game_state.take_action(card_pool, EngineAction::RetargetAttack(target));
+        }
+    }
+
+    fn text(&self) -> String {
+        "Change the target of an attack on your field to another card it could attack, or to this card.".to_owned()
+    }
+}
+
+/// Gives a player resource points when the effect resolves
+#[derive(Debug, Deserialize, Serialize)]
+pub struct GainResources {
+    pub whose: Whose,
+    pub resources: u32,
+}
+
+#[typetag::serde]
+impl EffectTrigger for GainResources {
+    fn resolution(&self, card_pool: &Cards, _card_type: &CardType, game_state: &mut GameState, instance: CardInstance, _activation: &Activation, _context: &ResolutionContext) {
+        if let Some(player) = self.whose.player(game_state, instance) {
+            let _ = game_state.take_action(card_pool, EngineAction::GainResources(player, self.resources));
+        }
+    }
+
+    fn text(&self) -> String {
+        match self.whose {
+            Whose::Controller => format!("Gain {} resources.", self.resources),
+            Whose::Opponent => format!("Your opponent gains {} resources.", self.resources),
+        }
+    }
+}
+
+/// Restrictions on which card types in the card pool an effect may pick, every restriction given
+/// must be met
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct PoolFilter {
+    #[serde(default)]
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub keyword: Option<Keyword>,
+    #[serde(default)]
+    pub max_attack: Option<u32>,
+    #[serde(default)]
+    pub max_defense: Option<u32>,
+}
+
+impl PoolFilter {
+    /// The card, with the restrictions, for effect text
+    fn text(&self) -> String {
+        let mut text = match &self.tag {
+            Some(tag) => format!("{} card", tag),
+            None => "card".to_owned(),
+        };
+        if let Some(keyword) = self.keyword {
+            text.push_str(&format!(" with {:?}", keyword));
+        }
+        if let Some(attack) = self.max_attack {
+            text.push_str(&format!(" with at most {} attack", attack));
+        }
+        if let Some(defense) = self.max_defense {
+            text.push_str(&format!(" with at most {} defense", defense));
+        }
+        text
+    }
+
+    pub fn matches(&self, card_type: &CardType) -> bool {
+        self.tag.as_ref().is_none_or(|tag| card_type.has_tag(tag))
+            && self.keyword.is_none_or(|keyword| card_type.has_keyword(keyword))
+            && self.max_attack.is_none_or(|attack| card_type.attack <= attack)
+            && self.max_defense.is_none_or(|defense| card_type.defense <= defense)
+    }
+
+    /// The card types names look up in the card pool which meet the restrictions and are from
+    /// packs the game allows, in the order of the card pool
+    pub fn candidates(&self, card_pool: &Cards, game_state: &GameState) -> Vec<CardTypeIdentifier> {
+        let pool: Box<dyn Iterator<Item = &CardType>> = match &self.tag {
+            Some(tag) => Box::new(card_pool.tagged(tag).iter().filter_map(|&id| card_pool.card(id))),
+            None => Box::new(card_pool.iter()),
+        };
+        pool.filter(|card_type| card_pool.is_current(card_type) && self.matches(card_type))
+            .filter(|card_type| card_pool.pack_of(card_type.id).is_none_or(|pack| game_state.config().pack_enabled(pack)))
+            .map(|card_type| card_type.id)
+            .collect()
+    }
+}
+
+/// Adds a new card, chosen at random from the card types in the card pool which meet the filter,
+/// to a player's hand when the effect resolves
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AddRandomCard {
+    pub whose: Whose,
+    #[serde(default)]
+    pub filter: PoolFilter,
+}
+
+#[typetag::serde]
+impl EffectTrigger for AddRandomCard {
+    fn variants(&self, card_pool: &Cards, _card_type: &CardType, game_state: &GameState, _instance: CardInstance, _context: &ResolutionContext) -> Vec<ActivationData> {
+        if self.filter.candidates(card_pool, game_state).is_empty() {
+            vec![]
+        } else {
+            vec![ActivationData::default()]
+        }
+    }
+
+    fn resolution(&self, card_pool: &Cards, _card_type: &CardType, game_state: &mut GameState, instance: CardInstance, _activation: &Activation, _context: &ResolutionContext) {
+        if let Some(player) = self.whose.player(game_state, instance) {
+            let candidates = self.filter.candidates(card_pool, game_state);
+            let _ = game_state.take_action(card_pool, EngineAction::GenerateToHand(player, candidates));
+        }
+    }
+
+    fn text(&self) -> String {
+        match self.whose {
+            Whose::Controller => format!("Add a random {} to your hand.", self.filter.text()),
+            Whose::Opponent => format!("Add a random {} to your opponent's hand.", self.filter.text()),
+        }
+    }
+}
+
+/// The number of turns a draw rule lasts, for effect text
+fn turns_text(turns: u32) -> String {
+    match turns {
+        1 => "1 turn".to_owned(),
+        turns => format!("{} turns", turns),
+    }
+}
+
+/// Stops a player drawing from one of their face down decks, or from either if no deck is
+/// given, until the end of a number of their turns
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PreventDraws {
+    pub whose: Whose,
+    #[serde(default)]
+    pub deck: Option<FaceDownDeck>,
+    pub turns: u32,
+}
+
+#[typetag::serde]
+impl EffectTrigger for PreventDraws {
+    fn resolution(&self, card_pool: &Cards, _card_type: &CardType, game_state: &mut GameState, instance: CardInstance, _activation: &Activation, _context: &ResolutionContext) {
+        if let Some(player) = self.whose.player(game_state, instance) {
+            let modifier = DrawModifier { player, rule: DrawRule::CannotDraw(self.deck), turns: self.turns };
+            let _ = game_state.take_action(card_pool, EngineAction::ModifyDraws(modifier));
+        }
+    }
+
+    fn text(&self) -> String {
+        let (subject, whose) = match self.whose {
+            Whose::Controller => ("You", "your"),
+            Whose::Opponent => ("Your opponent", "their"),
+        };
+        let deck = match self.deck {
+            Some(FaceDownDeck::Left) => format!(" from {} left deck", whose),
+            Some(FaceDownDeck::Right) => format!(" from {} right deck", whose),
+            None => String::new(),
+        };
+        format!("{} can't draw{} for {}.", subject, deck, turns_text(self.turns))
+    }
+}
+
+/// Lets a player draw more cards in each of their turns until the end of a number of them, which
+/// they may draw in their action phase and still take an action after
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ExtraDraws {
+    pub whose: Whose,
+    pub draws: u32,
+    pub turns: u32,
+}
+
+#[typetag::serde]
+impl EffectTrigger for ExtraDraws {
+    fn resolution(&self, card_pool: &Cards, _card_type: &CardType, game_state: &mut GameState, instance: CardInstance, _activation: &Activation, _context: &ResolutionContext) {
+        if let Some(player) = self.whose.player(game_state, instance) {
+            let modifier = DrawModifier { player, rule: DrawRule::ExtraDraws(self.draws), turns: self.turns };
+            let _ = game_state.take_action(card_pool, EngineAction::ModifyDraws(modifier));
+        }
+    }
+
+    fn text(&self) -> String {
+        let subject = match self.whose {
+            Whose::Controller => "You",
+            Whose::Opponent => "Your opponent",
+        };
+        let cards = if self.draws == 1 { "card" } else { "cards" };
+        format!("{} may draw {} more {} each turn for {}.", subject, self.draws, cards, turns_text(self.turns))
+    }
+}
+
+/// Spends the controller's resource points as the cost of activating another trigger, which
+/// can't activate while they have too few
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PayResources {
+    pub resources: u32,
+    pub trigger: Box<dyn EffectTrigger>,
+}
+
+#[typetag::serde]
+impl EffectTrigger for PayResources {
+    fn variants(&self, card_pool: &Cards, card_type: &CardType, game_state: &GameState, instance: CardInstance, context: &ResolutionContext) -> Vec<ActivationData> {
+        let affordable = game_state.controller_of(instance)
+            .is_some_and(|player| game_state.field_of(player).resources() >= self.resources);
+        if affordable {
+            self.trigger.variants(card_pool, card_type, game_state, instance, context)
+        } else {
+            vec![]
+        }
+    }
+
+    // The resources are a cost, so they are paid on activation
+    fn activation(&self, card_pool: &Cards, card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: &Activation, context: &ResolutionContext) {
+        let paid = game_state.controller_of(instance)
+            .is_some_and(|player| game_state.take_action(card_pool, EngineAction::SpendResources(player, self.resources)).is_ok());
+        if paid {
+            self.trigger.activation(card_pool, card_type, game_state, instance, activation, context);
+        }
+    }
+
+    fn resolution(&self, card_pool: &Cards, card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: &Activation, context: &ResolutionContext) {
+        self.trigger.resolution(card_pool, card_type, game_state, instance, activation, context);
+    }
+
+    fn text(&self) -> String {
+        self.trigger.text()
+    }
+
+    fn cost(&self) -> Option<String> {
+        let cost = format!("Pay {} resources.", self.resources);
+        match self.trigger.cost() {
+            Some(other) => Some(format!("{} {}", cost, other)),
+            None => Some(cost),
+        }
+    }
+
+    fn summon_cost(&self) -> Option<usize> {
+        self.trigger.summon_cost()
+    }
+}
+
+/// Destroys cards the controller has on the field as the cost of summoning this card from the
+/// center deck to one of the slots left empty
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SacrificeToSummon {
+    pub sacrifices: usize,
+}
+
+/// Every way to choose `count` of the cards, keeping their order
+fn combinations(cards: &[CardInstance], count: usize) -> Vec<Vec<CardInstance>> {
+    if count == 0 {
+        return vec![vec![]];
+    }
+    let mut chosen = vec![];
+    for (i, &card) in cards.iter().enumerate() {
+        for mut rest in combinations(&cards[i + 1..], count - 1) {
+            rest.insert(0, card);
+            chosen.push(rest);
+        }
+    }
+    chosen
+}
+
+#[typetag::serde]
+impl EffectTrigger for SacrificeToSummon {
+    // We can sacrifice any selection of our cards and summon to any slot that is then empty
+    fn variants(&self, _card_pool: &Cards, card_type: &CardType, game_state: &GameState, instance: CardInstance, _context: &ResolutionContext) -> Vec<ActivationData> {
+        let controller = match game_state.controller_of(instance) {
+            Some(player) => player,
+            None => return vec![],
+        };
+        let field = game_state.field_of(controller);
+        let occupied: Vec<(FieldSlot, CardInstance)> = field.occupied_slots()
+            .map(|(slot, card)| (slot, card.instance))
+            .collect();
+        let cards: Vec<CardInstance> = occupied.iter().map(|&(_, card)| card).collect();
+        let empty = field.empty_slots();
+        let mut variants = vec![];
+        for sacrifices in combinations(&cards, self.sacrifices) {
+            let freed = occupied.iter()
+                .filter(|(_, card)| sacrifices.contains(card))
+                .map(|&(slot, _)| slot);
+            for slot in empty.iter().copied().chain(freed).filter(|&slot| card_type.can_summon_to(field, slot, true)) {
+                variants.push(ActivationData {
+                    slot: Some(slot),
+                    targets: sacrifices.clone(),
+                    ..ActivationData::default()
+                });
+            }
+        }
+        variants
+    }
+
+    // The sacrifices are a cost, so they are paid and the card summoned on activation
+    fn activation(&self, card_pool: &Cards, _card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: &Activation, _context: &ResolutionContext) {
+        let slot = match activation.data.slot {
+            Some(slot) => slot,
+            None => return,
+        };
+        let _ = activation.data.targets.iter()
+            .try_for_each(|&target| game_state.take_action(card_pool, EngineAction::DestroyOnField(target)))
+            .and_then(|_| game_state.take_action(card_pool, EngineAction::SummonFromCenterDeckToSlot(instance, slot)));
+    }
+
+    fn text(&self) -> String {
+        "Summon this card from your center deck.".to_owned()
+    }
+
+    fn summon_cost(&self) -> Option<usize> {
+        Some(self.sacrifices)
+    }
+
+    fn cost(&self) -> Option<String> {
+        match self.sacrifices {
+            0 => None,
+            1 => Some("Destroy a card on your field.".to_owned()),
+            sacrifices => Some(format!("Destroy {} cards on your field.", sacrifices)),
+        }
+    }
+}
+
+#[typetag::serde(tag = "type")]
+pub trait Condition: Send + Sync + fmt::Debug {
+    /// Is this card type out of the card pool in this game state for for this card instance in this context able to meet its condition?
+    fn met(&self, card_pool: &Cards, card_type: &CardType, game_state: &GameState, instance: CardInstance, activation: &Activation, context: &ResolutionContext) -> bool;
+
+    /// What this condition requires, as a phrase such as "a card named X is on the field"
+    fn text(&self) -> String {
+        format!("{:?}", self)
+    }
+
+    /// If the condition only looks at what both players can see, such as how many cards are in
+    /// a hidden zone rather than which cards they are, so either player's client can show if it
+    /// is met without being told anything hidden. Conditions which look at hidden cards must
+    /// leave this false.
+    fn public(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct NamedCardOnField {
+    pub name: String,
+}
+
+#[typetag::serde]
+impl Condition for NamedCardOnField {
+    fn met(&self, card_pool: &Cards, _card_type: &CardType, game_state: &GameState, _instance: CardInstance, _activation: &Activation, _context: &ResolutionContext) -> bool {
+        let named = card_pool.ids_named(&self.name);
+        game_state.cards_on_field().any(|card| named.contains(&card.card_type))
+    }
+
+    fn text(&self) -> String {
+        format!("a card named {} is on the field", self.name)
+    }
+
+    fn public(&self) -> bool {
+        true
+    }
+}
+
+/// Met if an opponent activated an effect targeting this card further down the chain
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TargetedByOpponent;
+
+#[typetag::serde]
+impl Condition for TargetedByOpponent {
+    fn met(&self, _card_pool: &Cards, _card_type: &CardType, game_state: &GameState, instance: CardInstance, _activation: &Activation, context: &ResolutionContext) -> bool {
+        let controller = game_state.controller_of(instance);
+        context.chain.iter().any(|link| {
+            Some(link.player) != controller && link.activation.data.targets.contains(&instance)
+        })
+    }
+
+    fn text(&self) -> String {
+        "this card is targeted by an opponent's effect".to_owned()
+    }
+
+    fn public(&self) -> bool {
+        true
+    }
+}
+
+/// Met if a card with the name is on the controller's field in the same column as this card
+#[derive(Debug, Deserialize, Serialize)]
+pub struct NamedCardInColumn {
+    pub name: String,
+}
+
+#[typetag::serde]
+impl Condition for NamedCardInColumn {
+    fn met(&self, card_pool: &Cards, _card_type: &CardType, game_state: &GameState, instance: CardInstance, _activation: &Activation, _context: &ResolutionContext) -> bool {
+        let field = match game_state.controller_of(instance) {
+            Some(player) => game_state.field_of(player),
+            None => return false,
+        };
+        let named = card_pool.ids_named(&self.name);
+        field.column_of(instance).is_some_and(|column| {
+            field.cards_in_column(column).any(|card| card.instance != instance && named.contains(&card.card_type))
+        })
+    }
+
+    fn text(&self) -> String {
+        format!("a card named {} is in this card's column on your field", self.name)
+    }
+
+    fn public(&self) -> bool {
+        true
+    }
+}
+
+/// Met if a card with the name has been removed from the game by either player
+#[derive(Debug, Deserialize, Serialize)]
+pub struct NamedCardRemoved {
+    pub name: String,
+}
+
+#[typetag::serde]
+impl Condition for NamedCardRemoved {
+    fn met(&self, card_pool: &Cards, _card_type: &CardType, game_state: &GameState, _instance: CardInstance, _activation: &Activation, _context: &ResolutionContext) -> bool {
+        let named = card_pool.ids_named(&self.name);
+        Player::all()
+            .flat_map(|player| game_state.field_of(player).removed())
+            .any(|card| named.contains(&card.card_type))
+    }
+
+    fn text(&self) -> String {
+        format!("a card named {} has been removed from the game", self.name)
+    }
+
+    fn public(&self) -> bool {
+        true
+    }
+}
+
+/// Met if the controller of this card has at least as many resource points as the condition
+/// asks for
+#[derive(Debug, Deserialize, Serialize)]
+pub struct HasResources {
+    pub resources: u32,
+}
+
+#[typetag::serde]
+impl Condition for HasResources {
+    fn met(&self, _card_pool: &Cards, _card_type: &CardType, game_state: &GameState, instance: CardInstance, _activation: &Activation, _context: &ResolutionContext) -> bool {
+        game_state.controller_of(instance)
+            .is_some_and(|player| game_state.field_of(player).resources() >= self.resources)
+    }
+
+    fn text(&self) -> String {
+        format!("you have at least {} resources", self.resources)
+    }
+
+    fn public(&self) -> bool {
+        true
+    }
+}
+
+/// A zone of a player's field which both players can count the cards in, even when they can't
+/// see the cards themselves
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub enum PublicZone {
+    Hand,
+    LeftDeck,
+    RightDeck,
+    /// The left and right decks together
+    Decks,
+    CenterDeck,
+    Field,
+    /// The destroyed piles of every column together
+    Destroyed,
+    Removed,
+}
+
+impl PublicZone {
+    /// How many cards are in the zone of the field
+    pub fn count(self, field: &Field) -> usize {
+        let decks = field.deck_sizes();
+        match self {
+            PublicZone::Hand => field.hand_size(),
+            PublicZone::LeftDeck => decks.left,
+            PublicZone::RightDeck => decks.right,
+            PublicZone::Decks => decks.left + decks.right,
+            PublicZone::CenterDeck => decks.center,
+            PublicZone::Field => field.occupied_slots().count(),
+            PublicZone::Destroyed => Column::all().map(|column| field.destroyed(column).len()).sum(),
+            PublicZone::Removed => field.removed().len(),
+        }
+    }
+
+    fn text(self) -> &'static str {
+        match self {
+            PublicZone::Hand => "in hand",
+            PublicZone::LeftDeck => "in their left deck",
+            PublicZone::RightDeck => "in their right deck",
+            PublicZone::Decks => "in their left and right decks",
+            PublicZone::CenterDeck => "in their center deck",
+            PublicZone::Field => "on their field",
+            PublicZone::Destroyed => "destroyed",
+            PublicZone::Removed => "removed from the game",
+        }
+    }
+}
+
+/// Met if a player has at least, and no more than, a number of cards in a zone. Only the number
+/// of cards is looked at, which both players can see, so the condition never reveals which
+/// cards are in a hidden zone.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ZoneCount {
+    pub whose: Whose,
+    pub zone: PublicZone,
+    #[serde(default)]
+    pub at_least: u32,
+    #[serde(default)]
+    pub at_most: Option<u32>,
+}
+
+#[typetag::serde]
+impl Condition for ZoneCount {
+    fn met(&self, _card_pool: &Cards, _card_type: &CardType, game_state: &GameState, instance: CardInstance, _activation: &Activation, _context: &ResolutionContext) -> bool {
+        self.whose.player(game_state, instance).is_some_and(|player| {
+            let count = self.zone.count(game_state.field_of(player));
+            count >= self.at_least as usize && self.at_most.is_none_or(|at_most| count <= at_most as usize)
+        })
+    }
+
+    fn text(&self) -> String {
+        let whose = match self.whose {
+            Whose::Controller => "you have",
+            Whose::Opponent => "your opponent has",
+        };
+        let count = match self.at_most {
+            Some(at_most) if self.at_least == 0 => format!("at most {}", at_most),
+            Some(at_most) => format!("between {} and {}", self.at_least, at_most),
+            None => format!("at least {}", self.at_least),
+        };
+        let zone = match self.whose {
+            Whose::Controller => self.zone.text().replace("their", "your"),
+            Whose::Opponent => self.zone.text().to_owned(),
+        };
+        format!("{} {} cards {}", whose, count, zone)
+    }
+
+    fn public(&self) -> bool {
+        true
+    }
 }
-//
-// #[typetag::serde(tag = "type")]
-// pub trait CardEffect: Send + Sync + fmt::Debug {
-//     /// How can this card type effect out of the card pool activate in this game state for this card instance in the game state?
-//     fn can_activate(&self, card_pool: &Cards, card_type: &CardType, game_state: &GameState, instance: CardInstance) -> Vec<Activation>;
-//
-//     /// Try to activate this card type effect out of the card pool in this game state for this card instance in the game state in a particular way.
-//     fn activate(&self, card_pool: &Cards, card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: Activation);
-// }
-//
-// #[derive(Debug, Deserialize, Serialize)]
-// pub struct OnSummon {
-//     pub mandatory: bool,
-//     pub trigger: Box<dyn EffectTrigger>,
-// }
-//
-// #[typetag::serde]
-// impl CardEffect for OnSummon {
-//     fn can_activate(&self, card_pool: &Cards, card_type: &CardType, game_state: &GameState, instance: CardInstance) -> Vec<Activation> {
-//         if game_state.field.values()
-//             .any(|card|
-//                 card.instance == instance
-//                 && card.instance_of(card_type)
-//                 && card.state == CardStatus::Summoned
-//             )
-//         {
-//             self.trigger.variants(card_pool, card_type, game_state, instance).into_iter().map(|data| Activation {
-//                 status: if self.mandatory { ActivationStatus::Mandatory } else { ActivationStatus::Can },
-//                 data,
-//             }).collect()
-//         } else {
-//             vec![]
-//         }
-//     }
-//
-//     fn activate(&self, card_pool: &Cards, card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: Activation) {
-//         self.trigger.activation(card_pool, card_type, game_state, instance, activation);
-//         self.trigger.resolution(card_pool, card_type, game_state, instance, activation);
-//     }
-// }
-//
-// #[derive(Debug, Deserialize, Serialize)]
-// pub struct OnDraw {
-//     pub mandatory: bool,
-//     pub trigger: Box<dyn EffectTrigger>,
-// }
-//
-// #[typetag::serde]
-// impl CardEffect for OnDraw {
-//     fn can_activate(&self, card_pool: &Cards, card_type: &CardType, game_state: &GameState, instance: CardInstance) -> Vec<Activation> {
-//         if game_state.hand.iter()
-//             .any(|card|
-//                 card.instance == instance
-//                 && card.instance_of(card_type)
-//                 && card.state == CardStatus::Drawn
-//             )
-//         {
-//             self.trigger.variants(card_pool, card_type, game_state, instance).into_iter().map(|data| Activation {
-//                 status: if self.mandatory { ActivationStatus::Mandatory } else { ActivationStatus::Can },
-//                 data,
-//             }).collect()
-//         } else {
-//             vec![]
-//         }
-//     }
-//
-//     fn activate(&self, card_pool: &Cards, card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: Activation) {
-//         self.trigger.activation(card_pool, card_type, game_state, instance, activation);
-//         self.trigger.resolution(card_pool, card_type, game_state, instance, activation);
-//     }
-// }
-//
-// #[typetag::serde(tag = "type")]
-// #[allow(unused_variables)]
-// pub trait EffectTrigger: Send + Sync + fmt::Debug {
-//     /// In what different ways can this trigger activate?
-//     fn variants(&self, card_pool: &Cards, card_type: &CardType, game_state: &GameState, instance: CardInstance) -> Vec<ActivationData> {
-//         vec![ActivationData::default()]
-//     }
-//     fn activation(&self, card_pool: &Cards, card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: Activation) {}
-//     fn resolution(&self, card_pool: &Cards, card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: Activation) {}
-// }
-//
-// #[derive(Debug, Deserialize, Serialize)]
-// pub struct DestroySelfUnless {
-//     pub condition: Box<dyn Condition>,
-// }
-//
-// #[typetag::serde]
-// impl EffectTrigger for DestroySelfUnless {
-//     fn activation(&self, card_pool: &Cards, card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: Activation) {
-//         if !self.condition.met(card_pool, card_type, game_state, instance, activation) {
-//             // swallow error, we don't care if the instance is actually on the field, just that
-//             // it gets destroyed if it is
-//             let _ = game_state.take_action(card_pool, Action::DestroyOnField(instance));
-//         }
-//     }
-// }
-//
-// #[derive(Debug, Deserialize, Serialize)]
-// pub struct SwapHandWithField;
-//
-// #[typetag::serde]
-// impl EffectTrigger for SwapHandWithField {
-//     // We can potentially activate on any column of our field
-//     fn variants(&self, _card_pool: &Cards, _card_type: &CardType, game_state: &GameState, _instance: CardInstance) -> Vec<ActivationData> {
-//         game_state.field.iter().map(|(i, _)| ActivationData {
-//             slot: Some(*i)
-//         }).collect()
-//     }
-//
-//     fn activation(&self, card_pool: &Cards, _card_type: &CardType, game_state: &mut GameState, instance: CardInstance, activation: Activation) {
-//         let slot = match activation.data.slot {
-//             Some(slot) => slot,
-//             None => return
-//         };
-//         let target = match game_state.field.get(&slot).map(|card| card.instance) {
-//             Some(card) => card,
-//             None => return
-//         };
-//         let _ = game_state.take_action(card_pool, Action::ReturnFieldToHand(target))
-//             .and_then(|_| game_state.take_action(card_pool, Action::SummonFromHandToSlot(instance, slot)));
-//     }
-// }
-//
-// #[typetag::serde(tag = "type")]
-// pub trait Condition: Send + Sync + fmt::Debug {
-//     /// Is this card type out of the card pool in this game state for for this card instance able to meet its condition?
-//     fn met(&self, card_pool: &Cards, card_type: &CardType, game_state: &GameState, instance: CardInstance, activation: Activation) -> bool;
-// }
-//
-// #[derive(Debug, Deserialize, Serialize)]
-// pub struct NamedCardOnField {
-//     pub name: String,
-// }
-//
-// #[typetag::serde]
-// impl Condition for NamedCardOnField {
-//     fn met(&self, card_pool: &Cards, _card_type: &CardType, game_state: &GameState, _instance: CardInstance, _activation: Activation) -> bool {
-//         game_state.field.values().any(|card| card.has_name(card_pool, &self.name))
-//     }
-// }
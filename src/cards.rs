@@ -1,60 +1,554 @@
 use crate::card_type::{CardType, CardTypeIdentifier};
 
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use serde::Deserialize;
+
+#[cfg(feature = "fs")]
 use once_cell::sync::Lazy;
+#[cfg(feature = "fs")]
 use std::fs;
+#[cfg(feature = "fs")]
+use std::path::PathBuf;
 
+#[cfg(feature = "fs")]
 static CARDS: Lazy<Cards> = Lazy::new(|| Cards::load().unwrap());
 
+/// Where the card files of a card pool are read from, so pools can be loaded without a file
+/// system, such as in sandboxes without one
+pub trait CardSource {
+    /// The contents of every card file
+    fn card_files(&self) -> Result<Vec<String>, Box<dyn std::error::Error>>;
+}
+
+/// Card files which are already in memory
+#[derive(Clone, Debug, Default)]
+pub struct MemorySource {
+    pub files: Vec<String>,
+}
+
+impl CardSource for MemorySource {
+    fn card_files(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        Ok(self.files.clone())
+    }
+}
+
+/// Card files built into the program, such as with `include_bytes!`
+#[derive(Copy, Clone, Debug)]
+pub struct EmbeddedSource(pub &'static [&'static [u8]]);
+
+impl CardSource for EmbeddedSource {
+    fn card_files(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        self.0.iter()
+            .map(|&bytes| Ok(String::from_utf8(bytes.to_vec())?))
+            .collect()
+    }
+}
+
+/// Every file in a directory, not including its subdirectories
+#[cfg(feature = "fs")]
+#[derive(Clone, Debug)]
+pub struct DirectorySource {
+    pub path: PathBuf,
+}
+
+#[cfg(feature = "fs")]
+impl CardSource for DirectorySource {
+    fn card_files(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&self.path)? {
+            let path = entry?.path();
+            if path.is_file() {
+                files.push(String::from_utf8(fs::read(path)?)?);
+            }
+        }
+        Ok(files)
+    }
+}
+
+/// Card files fetched over plain HTTP, each from a path on the same server. There's no TLS, so
+/// this is only for servers on a trusted network.
+#[cfg(feature = "http")]
+#[derive(Clone, Debug)]
+pub struct HttpSource {
+    /// The host and port of the server, such as `"localhost:8000"`
+    pub address: String,
+    pub paths: Vec<String>,
+}
+
+// How long to wait on the server to connect, accept the request or send more of the response
+#[cfg(feature = "http")]
+const HTTP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+// Card files are small, so a response any larger than this is refused rather than read into
+// memory
+#[cfg(feature = "http")]
+const MAX_RESPONSE: u64 = 1 << 20;
+
+#[cfg(feature = "http")]
+impl HttpSource {
+    pub(crate) fn fetch(&self, path: &str) -> Result<String, Box<dyn std::error::Error>> {
+        use std::io::{Read, Write};
+        use std::net::{TcpStream, ToSocketAddrs};
+        let socket = self.address.to_socket_addrs()?.next().ok_or("the address didn't resolve")?;
+        let mut stream = TcpStream::connect_timeout(&socket, HTTP_TIMEOUT)?;
+        stream.set_read_timeout(Some(HTTP_TIMEOUT))?;
+        stream.set_write_timeout(Some(HTTP_TIMEOUT))?;
+        let host = self.address.split(':').next().unwrap_or(&self.address);
+        // HTTP/1.0 so the body is never chunked and ends when the server closes the connection
+        stream.write_all(format!("GET {} HTTP/1.0\r\nHost: {}\r\n\r\n", path, host).as_bytes())?;
+        let mut response = Vec::new();
+        // one byte over the limit is enough to tell the response is too long
+        stream.take(MAX_RESPONSE + 1).read_to_end(&mut response)?;
+        if response.len() as u64 > MAX_RESPONSE {
+            return Err(format!("the response to {} is over {} bytes", path, MAX_RESPONSE).into());
+        }
+        let response = String::from_utf8(response)?;
+        let (head, body) = response.split_once("\r\n\r\n").ok_or("the response has no body")?;
+        let status = head.lines().next().unwrap_or("");
+        if status.split_whitespace().nth(1) != Some("200") {
+            return Err(format!("fetching {} failed with {}", path, status).into());
+        }
+        Ok(body.to_owned())
+    }
+}
+
+#[cfg(feature = "http")]
+impl CardSource for HttpSource {
+    fn card_files(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        self.paths.iter().map(|path| self.fetch(path)).collect()
+    }
+}
+
+/// The card files of a community card pack, whose cards are named `pack:name` in a card pool so
+/// they can't collide with cards of the same name from other packs
+pub struct PackSource {
+    pub name: String,
+    pub source: Box<dyn CardSource>,
+}
+
+impl PackSource {
+    pub fn new(name: impl Into<String>, source: impl CardSource + 'static) -> Self {
+        PackSource { name: name.into(), source: Box::new(source) }
+    }
+
+    /// A pack of every file in a directory, not including its subdirectories
+    #[cfg(feature = "fs")]
+    pub fn directory(name: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        PackSource::new(name, DirectorySource { path: path.into() })
+    }
+}
+
 pub struct Cards {
-    cards: Vec<CardType>,
+    // shared so that pools with overrides don't need to copy the card types they keep
+    cards: Vec<Arc<CardType>>,
+    warnings: Vec<LoadWarning>,
+    // the versions looked up by names which don't give one, instead of the latest
+    pins: Vec<(String, u32)>,
+    // the names of the packs loaded, in the order they were loaded
+    packs: Vec<String>,
+    index: Index,
+}
+
+/// The identifiers of every card type with each name and each tag, lowest first, so names and
+/// tags are looked up without going through the whole pool. Rebuilt whenever the card types
+/// change.
+#[derive(Clone, Default)]
+struct Index {
+    names: HashMap<String, Vec<CardTypeIdentifier>>,
+    tags: HashMap<String, Vec<CardTypeIdentifier>>,
+}
+
+impl Index {
+    fn of(cards: &[Arc<CardType>]) -> Self {
+        let mut index = Index::default();
+        for card_type in cards {
+            index.names.entry(card_type.name.clone()).or_default().push(card_type.id);
+            for tag in &card_type.tags {
+                index.tags.entry(tag.clone()).or_default().push(card_type.id);
+            }
+        }
+        index
+    }
+}
+
+/// FNV-1a, for hashes which must be the same on every machine, as the standard library's
+/// hashers may change between releases
+pub(crate) struct Fnv(u64);
+
+impl Fnv {
+    pub(crate) fn new() -> Self {
+        Fnv(0xcbf29ce484222325)
+    }
+
+    pub(crate) fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    pub(crate) fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// How much of their three letter runs a name must share with a search query to be found when
+/// it doesn't contain the query
+const MIN_SIMILARITY: f64 = 0.3;
+
+/// The distinct runs of three letters in the text, counting the start and end as spaces so short
+/// words still have some
+fn trigrams(text: &str) -> Vec<[char ; 3]> {
+    let padded: Vec<char> = format!("  {} ", text).chars().collect();
+    let mut trigrams: Vec<[char ; 3]> = padded.windows(3).map(|run| [run[0], run[1], run[2]]).collect();
+    trigrams.sort_unstable();
+    trigrams.dedup();
+    trigrams
+}
+
+/// Splits a `name@version` reference into the name and version, leaving names without a version
+/// whole
+fn split_version(reference: &str) -> (&str, Option<u32>) {
+    match reference.rsplit_once('@') {
+        Some((name, version)) => match version.parse() {
+            Ok(version) => (name, Some(version)),
+            Err(_) => (reference, None),
+        },
+        None => (reference, None),
+    }
+}
+
+/// The version of the card file format this crate writes. Files declare the version they were
+/// written in with a top level `format = N`, and files without one are format 1.
+pub const CURRENT_FORMAT: i64 = 2;
+
+/// Upgrades a card table from the format at its index plus one to the next format
+const MIGRATIONS: [fn(&mut toml::value::Table, &mut Vec<String>) ; 1] = [
+    // format 1 allowed a single effect as an `[effect]` table instead of an `[[effects]]` array
+    |table, warnings| {
+        if let Some(effect) = table.remove("effect") {
+            warnings.push("`effect` is deprecated, use an `[[effects]]` array".to_owned());
+            table.insert("effects".to_owned(), toml::Value::Array(vec![effect]));
+        }
+    },
+];
+
+/// Field names which are still accepted in any format, and the names they are aliases of
+const DEPRECATED_FIELDS: [(&str, &str) ; 2] = [
+    ("atk", "attack"),
+    ("def", "defense"),
+];
+
+/// Something in a card file which loaded but should be updated
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LoadWarning {
+    /// The name of the card, if it has one
+    pub card: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for LoadWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.card {
+            Some(card) => write!(f, "{}: {}", card, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct UnsupportedFormat(i64);
+
+impl fmt::Display for UnsupportedFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "card format {} is not supported, the newest supported format is {}", self.0, CURRENT_FORMAT)
+    }
+}
+
+impl std::error::Error for UnsupportedFormat {}
+
+/// Parses a card file of any supported format, migrating it to the current format and
+/// collecting warnings for anything deprecated
+fn parse(str: &str, warnings: &mut Vec<LoadWarning>) -> Result<CardType, Box<dyn std::error::Error>> {
+    let mut table: toml::value::Table = toml::from_str(str)?;
+    let format = match table.remove("format") {
+        Some(toml::Value::Integer(format)) => format,
+        Some(_) => return Err("`format` must be an integer".into()),
+        None => 1,
+    };
+    if !(1..=CURRENT_FORMAT).contains(&format) {
+        return Err(Box::new(UnsupportedFormat(format)));
+    }
+    let mut messages = vec![];
+    for migration in &MIGRATIONS[(format - 1) as usize..] {
+        migration(&mut table, &mut messages);
+    }
+    for &(deprecated, field) in DEPRECATED_FIELDS.iter() {
+        if let Some(value) = table.remove(deprecated) {
+            messages.push(format!("`{}` is deprecated, use `{}`", deprecated, field));
+            table.entry(field.to_owned()).or_insert(value);
+        }
+    }
+    let card = table.get("name").and_then(|name| name.as_str()).map(|name| name.to_owned());
+    warnings.extend(messages.into_iter().map(|message| LoadWarning { card: card.clone(), message }));
+    Ok(CardType::deserialize(toml::Value::Table(table))?)
 }
 
 impl Cards {
+    /// The card pool loaded from the data directory
+    #[cfg(feature = "fs")]
     pub fn get() -> &'static Self {
         &CARDS
     }
 
-    // TODO: Generic directory walking should be extracted
     // TODO: Walk subfolders
+    #[cfg(feature = "fs")]
     fn load() -> Result<Self, Box<dyn std::error::Error>> {
+        Cards::from_source(DirectorySource { path: PathBuf::from("data/cards") })
+    }
+
+    /// Loads a card pool from the card files of a source
+    pub fn from_source(source: impl CardSource) -> Result<Self, Box<dyn std::error::Error>> {
         let mut cards = Vec::new();
-        let mut id = 0;
-        for entry in fs::read_dir("data/cards")? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.is_file() {
-                let str = String::from_utf8(fs::read(path)?)?;
-                let mut parsed: CardType = toml::from_str(&str)?;
+        let mut warnings = Vec::new();
+        for file in source.card_files()? {
+            cards.push(parse(&file, &mut warnings)?);
+        }
+        // errata come after every original, so adding one doesn't change the identifiers of
+        // card types which games and replays already use
+        cards.sort_by_key(|card_type| card_type.version);
+        Ok(Cards { warnings, ..Cards::new(cards) })
+    }
+
+    /// Creates a card pool from card types which have already been parsed, for hosts which load
+    /// their card definitions some other way than from the data directory
+    pub fn new<I: IntoIterator<Item = CardType>>(card_types: I) -> Self {
+        let cards: Vec<Arc<CardType>> = card_types.into_iter()
+            .enumerate()
+            .map(|(id, mut card_type)| {
                 // Here we maintain the invariant that the position of a CardType in our cards Vec
                 // is also the CardTypeIdentifier that we assign to the CardType, which ensures
                 // we have 0(1) lookup when fetching cards by ID
-                parsed.id = CardTypeIdentifier(id);
-                id += 1;
-                cards.push(parsed);
+                card_type.id = CardTypeIdentifier(id as u32);
+                Arc::new(card_type)
+            })
+            .collect();
+        Cards { index: Index::of(&cards), cards, warnings: vec![], pins: vec![], packs: vec![] }
+    }
+
+    /// Creates a copy of this card pool with the cards of each pack added after its own. Each
+    /// card is named `pack:name` after its pack, so cards from different packs or from the pool
+    /// itself can share a name. Fails if a pack's name is empty, contains a `:` or is already
+    /// loaded, or if any of its card files fail to load.
+    pub fn load_packs(&self, packs: &[PackSource]) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut pool = self.copy();
+        for pack in packs {
+            if pack.name.is_empty() || pack.name.contains(':') {
+                return Err(format!("{:?} is not a valid pack name", pack.name).into());
             }
+            if pool.packs.contains(&pack.name) {
+                return Err(format!("the pack {} is already loaded", pack.name).into());
+            }
+            let mut cards = Vec::new();
+            let mut warnings = Vec::new();
+            for file in pack.source.card_files()? {
+                let mut card_type = parse(&file, &mut warnings)?;
+                card_type.name = format!("{}:{}", pack.name, card_type.name);
+                cards.push(card_type);
+            }
+            cards.sort_by_key(|card_type| card_type.version);
+            for mut card_type in cards {
+                card_type.id = CardTypeIdentifier(pool.cards.len() as u32);
+                pool.cards.push(Arc::new(card_type));
+            }
+            pool.warnings.extend(warnings.into_iter().map(|warning| LoadWarning {
+                card: warning.card.map(|card| format!("{}:{}", pack.name, card)),
+                ..warning
+            }));
+            pool.packs.push(pack.name.clone());
         }
-        Ok(Cards { cards })
+        pool.index = Index::of(&pool.cards);
+        Ok(pool)
+    }
+
+    /// The names of the packs loaded into the pool, in the order they were loaded
+    pub fn packs(&self) -> &[String] {
+        &self.packs
+    }
+
+    /// The pack the card type was loaded from, None for the pool's own cards
+    pub fn pack_of(&self, id: CardTypeIdentifier) -> Option<&str> {
+        let (pack, _) = self.card(id)?.name.split_once(':')?;
+        self.packs.iter().find(|loaded| loaded.as_str() == pack).map(String::as_str)
     }
 
+    /// Everything deprecated found while loading the card files, which still loaded but should
+    /// be updated to the current format
+    pub fn warnings(&self) -> &[LoadWarning] {
+        &self.warnings
+    }
+
+    /// Looks up a card type by identifier or by name. Names can give a version as `name@2`,
+    /// otherwise the version pinned for that name is used, or the latest version if it isn't
+    /// pinned, which is the version new games use.
     pub fn card<R: Into<Reference>>(&self, reference: R) -> Option<&CardType> {
         let reference: Reference = reference.into();
         match reference {
-            Reference::Identifier(id) => self.cards.iter().find(|s| s.name == id),
-            Reference::StaticIdentifier(id) => self.cards.iter().find(|s| s.name == id),
-            Reference::ID(id) => self.cards.get(id.0 as usize),
+            Reference::Identifier(id) => self.named(&id),
+            Reference::StaticIdentifier(id) => self.named(id),
+            Reference::ID(id) => self.cards.get(id.0 as usize).map(|card_type| card_type.as_ref()),
+        }
+    }
+
+    fn named(&self, reference: &str) -> Option<&CardType> {
+        let (name, version) = split_version(reference);
+        let version = version.or_else(|| {
+            self.pins.iter().find(|(pinned, _)| pinned == name).map(|&(_, version)| version)
+        });
+        let mut versions = self.ids_named(name).iter().filter_map(|&id| self.card(id));
+        match version {
+            Some(version) => versions.find(|card_type| card_type.version == version),
+            None => versions.max_by_key(|card_type| card_type.version),
+        }
+    }
+
+    /// Every version of the card types with the name, in the order of the card pool
+    pub fn ids_named(&self, name: &str) -> &[CardTypeIdentifier] {
+        self.index.names.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    /// Every version of the card types with the tag, in the order of the card pool
+    pub fn tagged(&self, tag: &str) -> &[CardTypeIdentifier] {
+        self.index.tags.get(tag).map_or(&[], Vec::as_slice)
+    }
+
+    /// Iterates through the card types names look up, leaving out versions which are only kept
+    /// for games already using them
+    pub fn current(&self) -> impl Iterator<Item = &CardType> + Clone + '_ {
+        self.iter().filter(move |card_type| self.is_current(card_type))
+    }
+
+    /// If names look up this version of the card type
+    pub fn is_current(&self, card_type: &CardType) -> bool {
+        self.named(&card_type.name).is_some_and(|current| current.id == card_type.id)
+    }
+
+    /// Finds the card types names look up whose names match the query, best match first, for
+    /// deck builders and clients to autocomplete names with. Names match exactly, then by
+    /// prefix, by the prefix of one of their words, by substring, and lastly by sharing enough
+    /// three letter runs with the query to catch typos, all ignoring case. Words of the query
+    /// written as `tag:name` only keep card types with the tag, so a query of just tags lists
+    /// every card type with them by name.
+    pub fn search(&self, query: &str) -> Vec<&CardType> {
+        let (tags, words): (Vec<&str>, Vec<&str>) = query.split_whitespace().partition(|word| word.starts_with("tag:"));
+        let tags: Vec<&str> = tags.iter().map(|tag| &tag["tag:".len()..]).collect();
+        let query = words.join(" ").to_lowercase();
+        let query_trigrams = trigrams(&query);
+        let mut found: Vec<((u8, f64), &CardType)> = self.current()
+            .filter(|card_type| tags.iter().all(|tag| self.tagged(tag).contains(&card_type.id)))
+            .filter_map(|card_type| {
+                let name = card_type.name.to_lowercase();
+                let rank = if query.is_empty() || name == query {
+                    (0, 1.0)
+                } else if name.starts_with(&query) {
+                    (1, 1.0)
+                } else if name.split(|c: char| !c.is_alphanumeric()).any(|word| word.starts_with(&query)) {
+                    (2, 1.0)
+                } else if name.contains(&query) {
+                    (3, 1.0)
+                } else {
+                    let name_trigrams = trigrams(&name);
+                    let shared = query_trigrams.iter().filter(|trigram| name_trigrams.contains(trigram)).count();
+                    let similarity = shared as f64 / (query_trigrams.len() + name_trigrams.len() - shared) as f64;
+                    if similarity < MIN_SIMILARITY {
+                        return None;
+                    }
+                    (4, similarity)
+                };
+                Some((rank, card_type))
+            })
+            .collect();
+        found.sort_by(|((tier, similarity), a), ((other_tier, other_similarity), b)| {
+            tier.cmp(other_tier)
+                .then(other_similarity.total_cmp(similarity))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        found.into_iter().map(|(_, card_type)| card_type).collect()
+    }
+
+    /// The version each name looks up, in the order of the card pool
+    pub fn versions(&self) -> Vec<(String, u32)> {
+        self.current().map(|card_type| (card_type.name.clone(), card_type.version)).collect()
+    }
+
+    /// Creates a copy of this card pool where names look up the versions given instead, such as
+    /// for loading a replay with the versions it was recorded with. Fails with the first
+    /// version which isn't in the pool.
+    pub fn with_versions(&self, versions: &[(String, u32)]) -> Result<Self, (String, u32)> {
+        if let Some((name, version)) = versions.iter().find(|(name, version)| {
+            !self.iter().any(|card_type| &card_type.name == name && card_type.version == *version)
+        }) {
+            return Err((name.clone(), *version));
+        }
+        Ok(Cards { pins: versions.to_vec(), ..self.copy() })
+    }
+
+    /// A hash of the content of every card type names look up, in order, which is the same for
+    /// the same card pool on any machine and changes whenever one of those card types does.
+    /// Replays record it so they are only played back with the card pool they were recorded
+    /// with. Adding errata doesn't change it for a pool with the previous versions pinned.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hash = Fnv::new();
+        for card_type in self.current() {
+            let content = toml::Value::try_from(card_type)
+                .ok()
+                .and_then(|value| toml::to_string(&value).ok())
+                .unwrap_or_else(|| format!("{:?}", card_type));
+            hash.write(content.as_bytes());
+            hash.write(&[0]);
+        }
+        hash.finish()
+    }
+
+    /// Iterates through every card type in the pool
+    pub fn iter(&self) -> impl Iterator<Item = &CardType> + Clone + '_ {
+        self.cards.iter().map(|card_type| card_type.as_ref())
+    }
+
+    /// Creates a copy of this card pool where each of the card types replaces the card type with
+    /// the same name and version, or is added if there isn't one. Replaced card types keep their identifier,
+    /// so games can be started with the copy to playtest changes without affecting games using
+    /// this pool.
+    pub fn with_overrides(&self, overrides: Vec<CardType>) -> Self {
+        let mut cards = self.cards.clone();
+        for mut card_type in overrides {
+            let id = cards.iter()
+                .position(|existing| existing.name == card_type.name && existing.version == card_type.version)
+                .unwrap_or(cards.len());
+            card_type.id = CardTypeIdentifier(id as u32);
+            if id == cards.len() {
+                cards.push(Arc::new(card_type));
+            } else {
+                cards[id] = Arc::new(card_type);
+            }
+        }
+        Cards { index: Index::of(&cards), cards, ..self.copy() }
+    }
+
+    fn copy(&self) -> Self {
+        Cards {
+            cards: self.cards.clone(),
+            warnings: self.warnings.clone(),
+            pins: self.pins.clone(),
+            packs: self.packs.clone(),
+            index: self.index.clone(),
         }
     }
 
     pub fn from_test(toml_cards: Vec<&str>) -> Result<Self, Box<dyn std::error::Error>> {
-        let mut cards = Vec::new();
-        let mut id = 0;
-        for str in toml_cards.iter() {
-            let mut parsed: CardType = toml::from_str(&str)?;
-            parsed.id = CardTypeIdentifier(id);
-            id += 1;
-            cards.push(parsed);
-        }
-        Ok(Cards { cards })
+        Cards::from_source(MemorySource { files: toml_cards.into_iter().map(str::to_owned).collect() })
     }
 }
 
@@ -0,0 +1,188 @@
+use crate::card_type::{Condition, Keyword};
+use crate::cards::Cards;
+use crate::state::{Activation, ActivatableType, ActivationData, Attack, CardInstance, EngineAction, EventCause, GameState, ResolutionContext};
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// The damage an attack is about to deal, which modifiers can change before it is dealt
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Strike {
+    pub attacker: CardInstance,
+    pub target: CardInstance,
+    /// The damage dealt to the target
+    pub damage: u32,
+    /// If damage in excess of the target's remaining defense spills onto the card behind it
+    pub piercing: bool,
+    /// If a modifier replaced the damage with nothing, which is only known after damage
+    pub prevented: bool,
+}
+
+/// Changes how attacks deal damage. Modifiers are listed on card types, and are asked about
+/// every attack the card makes or is the target of, so each modifier checks which side its card
+/// is on. Keywords which change combat are modifiers too.
+#[typetag::serde(tag = "type")]
+#[allow(unused_variables)]
+pub trait CombatModifier: Send + Sync + fmt::Debug {
+    /// Changes the damage before anything is dealt
+    fn before_damage(&self, card_pool: &Cards, game_state: &GameState, card: CardInstance, strike: &mut Strike, context: &ResolutionContext) {}
+    /// If the damage should be replaced with nothing
+    fn prevents_damage(&self, card_pool: &Cards, game_state: &GameState, card: CardInstance, strike: &Strike, context: &ResolutionContext) -> bool {
+        false
+    }
+    /// Reacts to the damage once it is dealt or prevented
+    fn after_damage(&self, card_pool: &Cards, game_state: &mut GameState, card: CardInstance, strike: &Strike, context: &ResolutionContext) {}
+    /// What this modifier does, in rules text
+    fn text(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+/// The modifier a keyword applies in combat, if it changes combat
+fn keyword_modifier(keyword: Keyword) -> Option<&'static dyn CombatModifier> {
+    match keyword {
+        Keyword::Piercing => Some(&Piercing),
+        Keyword::Swift | Keyword::Guard | Keyword::Undying => None,
+    }
+}
+
+/// Deals the damage of an attack, which fizzles if either card has left the field. The modifiers
+/// of the attacker then the target change the damage, may replace it, and react to it after.
+pub fn resolve(card_pool: &Cards, game_state: &mut GameState, attack: Attack, context: &ResolutionContext) {
+    let damage = match game_state.controller_of(attack.attacker).and_then(|player| {
+        let field = game_state.field_of(player);
+        field.slot_of(attack.attacker).and_then(|slot| field.attack_of(card_pool, slot))
+    }) {
+        Some(damage) => damage,
+        None => return,
+    };
+    let mut modifiers: Vec<(CardInstance, &dyn CombatModifier)> = vec![];
+    for &instance in [attack.attacker, attack.target].iter() {
+        let card_type = match game_state.card_on_field(instance) {
+            Some(card) => card.lookup_self(card_pool),
+            None => return,
+        };
+        modifiers.extend(card_type.keywords.iter().filter_map(|&keyword| keyword_modifier(keyword)).map(|modifier| (instance, modifier)));
+        modifiers.extend(card_type.combat.iter().map(|modifier| (instance, modifier.as_ref())));
+    }
+    let mut strike = Strike {
+        attacker: attack.attacker,
+        target: attack.target,
+        damage,
+        piercing: false,
+        prevented: false,
+    };
+    for &(card, modifier) in &modifiers {
+        modifier.before_damage(card_pool, game_state, card, &mut strike, context);
+    }
+    strike.prevented = modifiers.iter().any(|&(card, modifier)| modifier.prevents_damage(card_pool, game_state, card, &strike, context));
+    let attacker = game_state.card(attack.attacker).map(|card| card.card_type).unwrap_or_default();
+    let player = game_state.controller_of(attack.attacker).unwrap_or_else(|| game_state.turn());
+    let cause = game_state.caused_by(EventCause::Attack { player, attacker });
+    if !strike.prevented {
+        deal(card_pool, game_state, &strike);
+    }
+    for &(card, modifier) in &modifiers {
+        if let Some(card_type) = game_state.card(card).map(|card| card.card_type) {
+            game_state.caused_by(EventCause::Static { card: card_type });
+        }
+        modifier.after_damage(card_pool, game_state, card, &strike, context);
+    }
+    game_state.caused_by(cause);
+}
+
+/// Places the damage on the target, spilling the excess onto the card behind it if piercing
+fn deal(card_pool: &Cards, game_state: &mut GameState, strike: &Strike) {
+    let defender = match game_state.controller_of(strike.target) {
+        Some(player) => game_state.field_of(player),
+        None => return,
+    };
+    let target = match defender.slot_of(strike.target) {
+        Some(slot) => slot,
+        None => return,
+    };
+    let excess = defender.remaining_defense_of(card_pool, target)
+        .map_or(0, |remaining| strike.damage.saturating_sub(remaining));
+    let behind = target.behind()
+        .and_then(|slot| defender[slot].as_ref())
+        .map(|card| card.instance);
+    let _ = game_state.take_action(card_pool, EngineAction::DamageOnField(strike.target, strike.damage));
+    if let Some(behind) = behind {
+        if strike.piercing && excess > 0 {
+            let _ = game_state.take_action(card_pool, EngineAction::DamageOnField(behind, excess));
+        }
+    }
+}
+
+/// Damage this card deals in excess of its target's remaining defense spills onto the card
+/// behind the target, as the Piercing keyword
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Piercing;
+
+#[typetag::serde]
+impl CombatModifier for Piercing {
+    fn before_damage(&self, _card_pool: &Cards, _game_state: &GameState, card: CardInstance, strike: &mut Strike, _context: &ResolutionContext) {
+        if card == strike.attacker {
+            strike.piercing = true;
+        }
+    }
+
+    fn text(&self) -> String {
+        "Damage this card deals beyond what destroys its target is dealt to the card behind it.".to_owned()
+    }
+}
+
+/// Attacks against this card deal no damage, while the condition is met if there is one
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PreventDamage {
+    #[serde(default)]
+    pub condition: Option<Box<dyn Condition>>,
+}
+
+#[typetag::serde]
+impl CombatModifier for PreventDamage {
+    fn prevents_damage(&self, card_pool: &Cards, game_state: &GameState, card: CardInstance, strike: &Strike, context: &ResolutionContext) -> bool {
+        if card != strike.target {
+            return false;
+        }
+        match &self.condition {
+            None => true,
+            Some(condition) => {
+                let card_type = match game_state.card(card) {
+                    Some(card) => card.lookup_self(card_pool),
+                    None => return false,
+                };
+                let activation = Activation { status: ActivatableType::Can, data: ActivationData::default() };
+                condition.met(card_pool, card_type, game_state, card, &activation, context)
+            }
+        }
+    }
+
+    fn text(&self) -> String {
+        match &self.condition {
+            None => "Attacks against this card deal no damage.".to_owned(),
+            Some(condition) => format!("Attacks against this card deal no damage while {}.", condition.text()),
+        }
+    }
+}
+
+/// Deals damage back to the attacker after this card is attacked, if the attacker is still on
+/// the field
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Retaliate {
+    pub damage: u32,
+}
+
+#[typetag::serde]
+impl CombatModifier for Retaliate {
+    fn after_damage(&self, card_pool: &Cards, game_state: &mut GameState, card: CardInstance, strike: &Strike, _context: &ResolutionContext) {
+        if card == strike.target && game_state.card_on_field(strike.attacker).is_some() {
+            let _ = game_state.take_action(card_pool, EngineAction::DamageOnField(strike.attacker, self.damage));
+        }
+    }
+
+    fn text(&self) -> String {
+        format!("After this card is attacked, deal {} damage to the attacker.", self.damage)
+    }
+}
@@ -0,0 +1,565 @@
+use crate::card_type::{CardTypeIdentifier, Keyword};
+use crate::cards::Cards;
+use crate::state::{Card, CardInstances, FaceDownDeck, GameConfig};
+
+use std::fmt;
+
+use serde::Deserialize;
+
+#[cfg(feature = "fs")]
+use crate::cards::{CardSource, DirectorySource};
+#[cfg(feature = "fs")]
+use std::path::PathBuf;
+
+/// The most cards a center deck may hold
+pub const MAX_CENTER_DECK: usize = 20;
+
+/// The most cards the left or right deck may hold by default
+pub const MAX_SIDE_DECK: usize = 20;
+
+/// How large decks may be and how many copies of a card they may hold, which formats can change
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct DeckLimits {
+    /// The fewest cards the left and right decks must each hold
+    pub min_side: usize,
+    /// The most cards the left and right decks may each hold
+    pub max_side: usize,
+    pub max_center: usize,
+    /// The most copies of a card type the whole deck may hold, if there is a limit
+    pub max_copies: Option<usize>,
+}
+
+impl Default for DeckLimits {
+    fn default() -> Self {
+        DeckLimits {
+            min_side: 0,
+            max_side: MAX_SIDE_DECK,
+            max_center: MAX_CENTER_DECK,
+            max_copies: None,
+        }
+    }
+}
+
+/// The card types a player builds their decks from before a game
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Deck {
+    pub left: Vec<CardTypeIdentifier>,
+    pub center: Vec<CardTypeIdentifier>,
+    pub right: Vec<CardTypeIdentifier>,
+}
+
+/// Something about how a deck is built which will make it play badly or not at all
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DeckWarning {
+    /// The card type isn't in the card pool
+    UnknownCard(CardTypeIdentifier),
+    /// The center deck holds more cards than the limit
+    CenterDeckTooLarge { size: usize, max: usize },
+    /// The left or right deck holds fewer or more cards than the limits
+    SideDeckSize { deck: FaceDownDeck, size: usize, min: usize, max: usize },
+    /// The deck holds more copies of the card type than the limit
+    TooManyCopies { card: CardTypeIdentifier, copies: usize, max: usize },
+    /// Cards with no cost to summon may not be placed in the center deck
+    FreeCardInCenter(CardTypeIdentifier),
+    /// The card has no way to be summoned from the center deck
+    UnsummonableInCenter(CardTypeIdentifier),
+    /// The card must sacrifice more cards to be summoned than the left and right decks hold
+    NoSacrificeFodder { card: CardTypeIdentifier, sacrifices: usize },
+    /// The left and right decks are both empty, so the player can never draw
+    NothingToDraw,
+    /// The card is from a pack the game config doesn't allow
+    DisabledPack { card: CardTypeIdentifier, pack: String },
+}
+
+impl fmt::Display for DeckWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeckWarning::UnknownCard(card) => write!(f, "card {:?} is not in the card pool", card),
+            DeckWarning::CenterDeckTooLarge { size, max } => write!(
+                f, "the center deck has {} cards but may only have {}", size, max
+            ),
+            DeckWarning::SideDeckSize { deck, size, min, max } => write!(
+                f, "the {} deck has {} cards but must have from {} to {}",
+                match deck {
+                    FaceDownDeck::Left => "left",
+                    FaceDownDeck::Right => "right",
+                },
+                size, min, max
+            ),
+            DeckWarning::TooManyCopies { card, copies, max } => write!(
+                f, "the deck has {} copies of card {:?} but may only have {}", copies, card, max
+            ),
+            DeckWarning::FreeCardInCenter(card) => write!(f, "card {:?} has no cost so can't be in the center deck", card),
+            DeckWarning::UnsummonableInCenter(card) => write!(
+                f, "card {:?} can't be summoned from the center deck", card
+            ),
+            DeckWarning::NoSacrificeFodder { card, sacrifices } => write!(
+                f, "card {:?} in the center deck needs {} sacrifices but the left and right decks don't have that many cards",
+                card, sacrifices
+            ),
+            DeckWarning::NothingToDraw => write!(f, "the left and right decks are both empty"),
+            DeckWarning::DisabledPack { card, pack } => write!(f, "card {:?} is from the pack {} which is disabled", card, pack),
+        }
+    }
+}
+
+/// Statistics about a deck for deck builders to display
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeckStats {
+    pub cards: usize,
+    /// How many cards cost each number of sacrifices to summon, indexed by the number
+    pub cost_curve: Vec<usize>,
+    /// How many cards can be summoned without sacrificing anything
+    pub zero_cost: usize,
+    pub average_attack: f64,
+    pub average_defense: f64,
+    /// How many cards have each keyword, in the order the keywords first appear
+    pub keywords: Vec<(Keyword, usize)>,
+    /// The card packs the deck needs loaded to be played
+    pub packs: Vec<String>,
+    pub warnings: Vec<DeckWarning>,
+}
+
+impl Deck {
+    /// Iterates through every card type in the deck, left deck first
+    pub fn iter(&self) -> impl Iterator<Item = CardTypeIdentifier> + '_ {
+        self.left.iter().chain(self.center.iter()).chain(self.right.iter()).copied()
+    }
+
+    /// Creates the cards for a game, as the left, center and right decks and an empty hand to
+    /// start the game with. Card types which aren't in the card pool are left out.
+    pub fn instantiate(&self, card_pool: &Cards, instances: &mut CardInstances) -> (Vec<Card>, Vec<Card>, Vec<Card>, Vec<Card>) {
+        let mut cards = |ids: &[CardTypeIdentifier]| ids.iter()
+            .filter_map(|&id| card_pool.card(id))
+            .map(|card_type| instances.instantiate(card_type))
+            .collect();
+        (cards(&self.left), cards(&self.center), cards(&self.right), vec![])
+    }
+
+    /// The card packs the deck's cards come from, in the order they were loaded into the pool
+    pub fn packs(&self, card_pool: &Cards) -> Vec<String> {
+        card_pool.packs().iter()
+            .filter(|pack| self.iter().any(|id| card_pool.pack_of(id) == Some(pack.as_str())))
+            .cloned()
+            .collect()
+    }
+
+    /// Warns about each card type in the deck from a pack the game config disables
+    pub fn check_packs(&self, card_pool: &Cards, config: &GameConfig) -> Vec<DeckWarning> {
+        let mut warnings = vec![];
+        for id in self.iter() {
+            if let Some(pack) = card_pool.pack_of(id).filter(|pack| !config.pack_enabled(pack)) {
+                let warning = DeckWarning::DisabledPack { card: id, pack: pack.to_owned() };
+                if !warnings.contains(&warning) {
+                    warnings.push(warning);
+                }
+            }
+        }
+        warnings
+    }
+
+    /// Analyzes the deck against the default limits
+    pub fn analyze(&self, card_pool: &Cards) -> DeckStats {
+        self.analyze_with(card_pool, &DeckLimits::default())
+    }
+
+    /// Analyzes the deck, warning about anything outside the limits of the format
+    pub fn analyze_with(&self, card_pool: &Cards, limits: &DeckLimits) -> DeckStats {
+        let mut stats = DeckStats {
+            cards: 0,
+            cost_curve: vec![],
+            zero_cost: 0,
+            average_attack: 0.0,
+            average_defense: 0.0,
+            keywords: vec![],
+            packs: self.packs(card_pool),
+            warnings: vec![],
+        };
+        let (mut attack, mut defense) = (0, 0);
+        for id in self.iter() {
+            let card_type = match card_pool.card(id) {
+                Some(card_type) => card_type,
+                None => {
+                    stats.warnings.push(DeckWarning::UnknownCard(id));
+                    continue;
+                }
+            };
+            stats.cards += 1;
+            attack += u64::from(card_type.attack);
+            defense += u64::from(card_type.defense);
+            let cost = card_type.summon_cost().unwrap_or(0);
+            if stats.cost_curve.len() <= cost {
+                stats.cost_curve.resize(cost + 1, 0);
+            }
+            stats.cost_curve[cost] += 1;
+            if cost == 0 {
+                stats.zero_cost += 1;
+            }
+            for &keyword in &card_type.keywords {
+                match stats.keywords.iter_mut().find(|(existing, _)| *existing == keyword) {
+                    Some((_, count)) => *count += 1,
+                    None => stats.keywords.push((keyword, 1)),
+                }
+            }
+        }
+        if stats.cards > 0 {
+            stats.average_attack = attack as f64 / stats.cards as f64;
+            stats.average_defense = defense as f64 / stats.cards as f64;
+        }
+
+        if self.center.len() > limits.max_center {
+            stats.warnings.push(DeckWarning::CenterDeckTooLarge { size: self.center.len(), max: limits.max_center });
+        }
+        for &(deck, cards) in [(FaceDownDeck::Left, &self.left), (FaceDownDeck::Right, &self.right)].iter() {
+            if cards.len() < limits.min_side || cards.len() > limits.max_side {
+                stats.warnings.push(DeckWarning::SideDeckSize {
+                    deck,
+                    size: cards.len(),
+                    min: limits.min_side,
+                    max: limits.max_side,
+                });
+            }
+        }
+        if let Some(max) = limits.max_copies {
+            for id in self.iter() {
+                let copies = self.iter().filter(|&card| card == id).count();
+                let warning = DeckWarning::TooManyCopies { card: id, copies, max };
+                if copies > max && !stats.warnings.contains(&warning) {
+                    stats.warnings.push(warning);
+                }
+            }
+        }
+        let fodder = self.left.len() + self.right.len();
+        for &id in &self.center {
+            let card_type = match card_pool.card(id) {
+                Some(card_type) => card_type,
+                None => continue,
+            };
+            let warning = match card_type.summon_cost() {
+                None => DeckWarning::UnsummonableInCenter(id),
+                Some(0) => DeckWarning::FreeCardInCenter(id),
+                Some(sacrifices) if sacrifices > fodder => DeckWarning::NoSacrificeFodder { card: id, sacrifices },
+                Some(_) => continue,
+            };
+            if !stats.warnings.contains(&warning) {
+                stats.warnings.push(warning);
+            }
+        }
+        if fodder == 0 {
+            stats.warnings.push(DeckWarning::NothingToDraw);
+        }
+        stats
+    }
+}
+
+/// A problem with a preconstructed deck file
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DeckError {
+    /// The file isn't a valid deck
+    Parse(String),
+    /// The deck lists a card which isn't in the card pool
+    UnknownCard { deck: String, card: String },
+    /// More than one deck has the name
+    DuplicateName(String),
+}
+
+impl fmt::Display for DeckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeckError::Parse(error) => write!(f, "invalid deck file: {}", error),
+            DeckError::UnknownCard { deck, card } => write!(f, "{} lists {:?} which is not in the card pool", deck, card),
+            DeckError::DuplicateName(deck) => write!(f, "more than one deck is named {}", deck),
+        }
+    }
+}
+
+impl std::error::Error for DeckError {}
+
+/// A preconstructed deck as written in its file, listing cards by name with a name repeated for
+/// each copy
+#[derive(Deserialize)]
+struct DeckFile {
+    name: String,
+    #[serde(default)]
+    left: Vec<String>,
+    #[serde(default)]
+    center: Vec<String>,
+    #[serde(default)]
+    right: Vec<String>,
+}
+
+/// The preconstructed decks, such as starter decks, which clients and simulations can refer to
+/// by name
+#[derive(Clone, Debug, Default)]
+pub struct Decks {
+    decks: Vec<(String, Deck)>,
+}
+
+impl Decks {
+    /// Loads every deck in the data directory, checking each card they list is in the card pool
+    #[cfg(feature = "fs")]
+    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+        let files = DirectorySource { path: PathBuf::from("data/decks") }.card_files()?;
+        Ok(Decks::parse(files.iter().map(String::as_str), Cards::get())?)
+    }
+
+    /// Parses deck files which have already been read, checking each card they list is in the
+    /// card pool
+    pub fn parse<'a, I: IntoIterator<Item = &'a str>>(files: I, card_pool: &Cards) -> Result<Self, DeckError> {
+        let mut decks = Decks::default();
+        for file in files {
+            let file: DeckFile = toml::from_str(file).map_err(|error| DeckError::Parse(error.to_string()))?;
+            if decks.deck(&file.name).is_some() {
+                return Err(DeckError::DuplicateName(file.name));
+            }
+            let ids = |names: &[String]| names.iter()
+                .map(|name| card_pool.card(name.clone()).map(|card_type| card_type.id).ok_or_else(|| {
+                    DeckError::UnknownCard { deck: file.name.clone(), card: name.clone() }
+                }))
+                .collect::<Result<Vec<_>, _>>();
+            let deck = Deck {
+                left: ids(&file.left)?,
+                center: ids(&file.center)?,
+                right: ids(&file.right)?,
+            };
+            decks.decks.push((file.name, deck));
+        }
+        Ok(decks)
+    }
+
+    pub fn deck(&self, name: &str) -> Option<&Deck> {
+        self.decks.iter().find(|(existing, _)| existing == name).map(|(_, deck)| deck)
+    }
+
+    /// Iterates through the name and cards of every deck
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Deck)> {
+        self.decks.iter().map(|(name, deck)| (name.as_str(), deck))
+    }
+}
+
+/// Which part of a deck a decklist line puts its cards in
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Zone {
+    Left,
+    Center,
+    Right,
+}
+
+/// What was wrong with a line of a decklist
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DecklistProblem {
+    /// The count before the card name isn't a number of copies, such as `0x`
+    BadCount(String),
+    /// The line doesn't say which deck the cards go in with `@left`, `@center` or `@right`
+    MissingZone,
+    UnknownZone(String),
+    /// The position after the zone isn't `top` or `bottom`
+    UnknownPosition(String),
+    /// No card in the pool has a name close enough to the one written, with the closest name if
+    /// any was near it
+    UnknownCard { name: String, suggestion: Option<String> },
+}
+
+/// A line of a decklist which couldn't be read, numbered from 1
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DecklistError {
+    pub line: usize,
+    pub problem: DecklistProblem,
+}
+
+impl fmt::Display for DecklistError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.problem {
+            DecklistProblem::BadCount(count) => write!(f, "{:?} is not a number of copies", count),
+            DecklistProblem::MissingZone => write!(f, "no deck given, end the line with @left, @center or @right"),
+            DecklistProblem::UnknownZone(zone) => write!(f, "unknown deck {:?}, use left, center or right", zone),
+            DecklistProblem::UnknownPosition(position) => write!(f, "unknown position {:?}, use top or bottom", position),
+            DecklistProblem::UnknownCard { name, suggestion: Some(suggestion) } => write!(
+                f, "no card named {:?}, did you mean {:?}?", name, suggestion
+            ),
+            DecklistProblem::UnknownCard { name, suggestion: None } => write!(f, "no card named {:?}", name),
+        }
+    }
+}
+
+impl std::error::Error for DecklistError {}
+
+/// A card name in a decklist which didn't match any card exactly, and the card it was taken to be
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NameCorrection {
+    pub line: usize,
+    pub written: String,
+    pub card: String,
+}
+
+/// A deck read from a decklist, with the names which were corrected to read it and anything
+/// wrong with how the deck is built
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportedDeck {
+    pub deck: Deck,
+    pub corrections: Vec<NameCorrection>,
+    pub warnings: Vec<DeckWarning>,
+}
+
+impl Deck {
+    /// Reads a decklist written one line per card, such as `3x Staple Dragon @left` or
+    /// `2x Other Card @center:top`. Cards go on the bottom of their deck unless the line ends
+    /// with `:top`, so a list reads from the top of each deck down. The count is optional, blank
+    /// lines and lines starting with `#` are skipped, and names match the card pool ignoring case
+    /// or a typo or two. Every line which can't be read is reported, not just the first.
+    pub fn from_text(text: &str, card_pool: &Cards) -> Result<ImportedDeck, Vec<DecklistError>> {
+        let names = {
+            let mut names: Vec<&str> = card_pool.iter().map(|card_type| card_type.name.as_str()).collect();
+            names.sort_unstable();
+            names.dedup();
+            names
+        };
+        // each deck with the top first while reading
+        let (mut left, mut center, mut right) = (vec![], vec![], vec![]);
+        let mut corrections = vec![];
+        let mut errors = vec![];
+        for (i, line) in text.lines().enumerate() {
+            let line_number = i + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |problem| DecklistError { line: line_number, problem };
+            let (copies, rest) = split_count(line);
+            let copies = match copies {
+                Some(Ok(copies)) => copies,
+                Some(Err(count)) => {
+                    errors.push(error(DecklistProblem::BadCount(count)));
+                    continue;
+                },
+                None => 1,
+            };
+            let (name, placement) = match rest.rsplit_once('@').filter(|(_, zone)| zone.trim().parse::<u32>().is_err()) {
+                Some((name, placement)) => (name.trim(), placement.trim().to_lowercase()),
+                None => {
+                    errors.push(error(DecklistProblem::MissingZone));
+                    continue;
+                },
+            };
+            let (zone, position) = match placement.split_once(':') {
+                Some((zone, position)) => (zone.trim(), Some(position.trim())),
+                None => (placement.as_str(), None),
+            };
+            let zone = match zone {
+                "left" => Zone::Left,
+                "center" | "centre" => Zone::Center,
+                "right" => Zone::Right,
+                zone => {
+                    errors.push(error(DecklistProblem::UnknownZone(zone.to_owned())));
+                    continue;
+                },
+            };
+            let on_top = match position {
+                None | Some("bottom") => false,
+                Some("top") => true,
+                Some(position) => {
+                    errors.push(error(DecklistProblem::UnknownPosition(position.to_owned())));
+                    continue;
+                },
+            };
+            let id = match card_pool.card(name.to_owned()) {
+                Some(card_type) => card_type.id,
+                None => match closest_name(name, &names) {
+                    Ok(matched) => {
+                        corrections.push(NameCorrection { line: line_number, written: name.to_owned(), card: matched.to_owned() });
+                        card_pool.card(matched.to_owned()).map(|card_type| card_type.id).unwrap()
+                    },
+                    Err(suggestion) => {
+                        errors.push(error(DecklistProblem::UnknownCard {
+                            name: name.to_owned(),
+                            suggestion: suggestion.map(str::to_owned),
+                        }));
+                        continue;
+                    },
+                },
+            };
+            let deck: &mut Vec<CardTypeIdentifier> = match zone {
+                Zone::Left => &mut left,
+                Zone::Center => &mut center,
+                Zone::Right => &mut right,
+            };
+            if on_top {
+                deck.splice(0..0, std::iter::repeat_n(id, copies));
+            } else {
+                deck.extend(std::iter::repeat_n(id, copies));
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        // decks keep the top of the deck last
+        for deck in [&mut left, &mut center, &mut right] {
+            deck.reverse();
+        }
+        let deck = Deck { left, center, right };
+        let warnings = deck.analyze(card_pool).warnings;
+        Ok(ImportedDeck { deck, corrections, warnings })
+    }
+}
+
+/// Splits a count such as `3x`, `3 x`, `x3` or `3` off the front of a line, if it starts with
+/// one, giving back what was written if it isn't a number of copies
+fn split_count(line: &str) -> (Option<Result<usize, String>>, &str) {
+    let is_number = |text: &str| !text.is_empty() && text.chars().all(|c| c.is_ascii_digit());
+    let (first, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let rest = rest.trim_start();
+    let (count, rest) = match rest.split_once(char::is_whitespace) {
+        Some((x, after)) if x.eq_ignore_ascii_case("x") && is_number(first) => (first, after),
+        _ => match first.strip_suffix(['x', 'X']).or_else(|| first.strip_prefix(['x', 'X'])).unwrap_or(first) {
+            count if is_number(count) => (count, rest),
+            _ => return (None, line),
+        },
+    };
+    match count.parse::<usize>() {
+        Ok(copies) if copies > 0 => (Some(Ok(copies)), rest.trim_start()),
+        _ => (Some(Err(count.to_owned())), rest.trim_start()),
+    }
+}
+
+/// The card name a name written in a decklist most likely means: the only name which matches
+/// ignoring case, or else the only closest name within a typo for every four letters. When no
+/// name is close enough the closest within half the name's length is given as a suggestion.
+fn closest_name<'a>(written: &str, names: &[&'a str]) -> Result<&'a str, Option<&'a str>> {
+    let written = written.to_lowercase();
+    let mut same_case: Vec<&str> = names.iter().copied().filter(|name| name.to_lowercase() == written).collect();
+    if same_case.len() == 1 {
+        return Ok(same_case.remove(0));
+    }
+    let mut distances: Vec<(usize, &str)> = names.iter().map(|&name| (edit_distance(&written, &name.to_lowercase()), name)).collect();
+    distances.sort_unstable();
+    let length = written.chars().count();
+    match distances.as_slice() {
+        [(best, name), rest @ ..] if *best <= (length / 4).max(1) && rest.first().is_none_or(|(next, _)| next > best) => Ok(name),
+        [(best, name), ..] if *best <= length / 2 => Err(Some(name)),
+        _ => Err(None),
+    }
+}
+
+/// How many letters must be added, removed, changed or swapped with their neighbour to turn one
+/// string into the other
+fn edit_distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let mut distances = vec![vec![0 ; b.len() + 1] ; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    distances[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let mut distance = (distances[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]))
+                .min(distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
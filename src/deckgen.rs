@@ -0,0 +1,83 @@
+use crate::card_type::{CardType, CardTypeIdentifier, Keyword};
+use crate::cards::Cards;
+use crate::deck::{Deck, MAX_CENTER_DECK};
+
+/// What a completed deck should look like
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Constraints {
+    pub left: usize,
+    pub center: usize,
+    pub right: usize,
+    /// The most copies of a card type the whole deck may hold
+    pub max_copies: usize,
+    /// A keyword to build around, whose cards are picked before any others
+    pub archetype: Option<Keyword>,
+}
+
+impl Default for Constraints {
+    fn default() -> Self {
+        Constraints {
+            left: 10,
+            center: 5,
+            right: 10,
+            max_copies: 3,
+            archetype: None,
+        }
+    }
+}
+
+fn copies(deck: &Deck, id: CardTypeIdentifier) -> usize {
+    deck.iter().filter(|&card| card == id).count()
+}
+
+/// Fills the rest of a partly built deck up to the sizes in the constraints, such as for
+/// starter decks or giving bots some variety. Cards already in the deck are kept.
+///
+/// The center deck is filled first with cards that have a cost to summon from there, balancing
+/// the cost curve and never needing more sacrifices than the left and right decks will hold.
+/// The left and right decks are then filled in turn, preferring cards which aren't meant for
+/// the center deck and spreading picks across as many card types as possible. Cards of the
+/// archetype are always picked first. Decks are left short if the copy limit runs out of cards.
+pub fn complete(card_pool: &Cards, partial: Deck, constraints: &Constraints) -> Deck {
+    let mut deck = partial;
+    let archetype = |card_type: &CardType| constraints.archetype.is_some_and(|keyword| card_type.has_keyword(keyword));
+    let fodder = constraints.left.max(deck.left.len()) + constraints.right.max(deck.right.len());
+    while deck.center.len() < constraints.center.min(MAX_CENTER_DECK) {
+        let pick = card_pool.current()
+            .filter(|card_type| copies(&deck, card_type.id) < constraints.max_copies)
+            .filter_map(|card_type| card_type.summon_cost()
+                .filter(|&cost| cost > 0 && cost <= fodder)
+                .map(|cost| (card_type, cost)))
+            .min_by_key(|&(card_type, cost)| {
+                let same_cost = deck.center.iter()
+                    .filter(|&&id| card_pool.card(id).and_then(CardType::summon_cost) == Some(cost))
+                    .count();
+                (!archetype(card_type), same_cost, cost, card_type.id.0)
+            });
+        match pick {
+            Some((card_type, _)) => deck.center.push(card_type.id),
+            None => break,
+        }
+    }
+    loop {
+        let left = deck.left.len() < constraints.left
+            && (deck.left.len() <= deck.right.len() || deck.right.len() >= constraints.right);
+        if !left && deck.right.len() >= constraints.right {
+            break;
+        }
+        let pick = card_pool.current()
+            .filter(|card_type| copies(&deck, card_type.id) < constraints.max_copies)
+            .min_by_key(|card_type| (
+                !archetype(card_type),
+                card_type.summon_cost().is_some_and(|cost| cost > 0),
+                copies(&deck, card_type.id),
+                card_type.id.0,
+            ));
+        match (pick, left) {
+            (Some(card_type), true) => deck.left.push(card_type.id),
+            (Some(card_type), false) => deck.right.push(card_type.id),
+            (None, _) => break,
+        }
+    }
+    deck
+}
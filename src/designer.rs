@@ -0,0 +1,210 @@
+use crate::card_type::{Aura, CardEffect, CardType, CardTypeIdentifier, DeckChoice, EffectTrigger, InCenterDeck, Keyword, OnDeckEmptied, OnDestroyed, OnDraw, OnField, OnSummon, OnTurnStart, SummonRestriction, Whose};
+use crate::cards::CURRENT_FORMAT;
+use crate::combat::CombatModifier;
+
+use std::fmt;
+
+/// A problem with a card definition which would stop it loading or make it unplayable
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DesignError {
+    /// The card has no name to look it up by
+    EmptyName,
+    /// Names can't contain `@`, which separates a name from a version when looking cards up
+    NameContainsVersion,
+    /// Versions start from 1
+    NoVersion,
+    /// A card with no defense is destroyed by any damage, and can never be summoned usefully
+    NoDefense,
+    /// The keyword is listed more than once
+    DuplicateKeyword(Keyword),
+    /// The card doesn't survive being written to TOML and read back
+    Serialization(String),
+}
+
+impl fmt::Display for DesignError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DesignError::EmptyName => write!(f, "the card has no name"),
+            DesignError::NameContainsVersion => write!(f, "the card's name contains @"),
+            DesignError::NoVersion => write!(f, "the card's version is 0 but versions start from 1"),
+            DesignError::NoDefense => write!(f, "the card has no defense"),
+            DesignError::DuplicateKeyword(keyword) => write!(f, "{:?} is listed more than once", keyword),
+            DesignError::Serialization(error) => write!(f, "the card can't be written as TOML: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for DesignError {}
+
+/// Builds a card type in code, for editors which want to manipulate cards without templating
+/// TOML
+pub struct CardTypeBuilder {
+    card_type: CardType,
+}
+
+impl CardTypeBuilder {
+    pub fn new(name: impl Into<String>) -> Self {
+        CardTypeBuilder {
+            card_type: CardType {
+                id: CardTypeIdentifier::default(),
+                name: name.into(),
+                version: 1,
+                effects: vec![],
+                defense: 0,
+                attack: 0,
+                keywords: vec![],
+                tags: vec![],
+                auras: vec![],
+                combat: vec![],
+                resource_cost: 0,
+                summon_restriction: None,
+            },
+        }
+    }
+
+    pub fn attack(mut self, attack: u32) -> Self {
+        self.card_type.attack = attack;
+        self
+    }
+
+    pub fn version(mut self, version: u32) -> Self {
+        self.card_type.version = version;
+        self
+    }
+
+    pub fn resource_cost(mut self, resource_cost: u32) -> Self {
+        self.card_type.resource_cost = resource_cost;
+        self
+    }
+
+    pub fn defense(mut self, defense: u32) -> Self {
+        self.card_type.defense = defense;
+        self
+    }
+
+    pub fn keyword(mut self, keyword: Keyword) -> Self {
+        self.card_type.keywords.push(keyword);
+        self
+    }
+
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.card_type.tags.push(tag.into());
+        self
+    }
+
+    pub fn summon_restriction(mut self, restriction: SummonRestriction) -> Self {
+        self.card_type.summon_restriction = Some(restriction);
+        self
+    }
+
+    pub fn aura(mut self, aura: Aura) -> Self {
+        self.card_type.auras.push(aura);
+        self
+    }
+
+    /// Adds a combat modifier of any type, including those defined outside this crate
+    pub fn combat(mut self, modifier: impl CombatModifier + 'static) -> Self {
+        self.card_type.combat.push(Box::new(modifier));
+        self
+    }
+
+    /// Adds an effect of any type, including those defined outside this crate
+    pub fn effect(mut self, effect: impl CardEffect + 'static) -> Self {
+        self.card_type.effects.push(Box::new(effect));
+        self
+    }
+
+    pub fn on_summon(self, mandatory: bool, trigger: impl EffectTrigger + 'static) -> Self {
+        self.effect(OnSummon { mandatory, trigger: Box::new(trigger) })
+    }
+
+    pub fn on_draw(self, mandatory: bool, trigger: impl EffectTrigger + 'static) -> Self {
+        self.effect(OnDraw { mandatory, trigger: Box::new(trigger) })
+    }
+
+    pub fn on_field(self, mandatory: bool, trigger: impl EffectTrigger + 'static) -> Self {
+        self.effect(OnField { mandatory, trigger: Box::new(trigger) })
+    }
+
+    pub fn in_center_deck(self, trigger: impl EffectTrigger + 'static) -> Self {
+        self.effect(InCenterDeck { trigger: Box::new(trigger) })
+    }
+
+    pub fn on_destroyed(self, mandatory: bool, trigger: impl EffectTrigger + 'static) -> Self {
+        self.effect(OnDestroyed { mandatory, trigger: Box::new(trigger) })
+    }
+
+    pub fn on_deck_emptied(self, whose: Whose, deck: DeckChoice, mandatory: bool, trigger: impl EffectTrigger + 'static) -> Self {
+        self.effect(OnDeckEmptied { whose, deck, mandatory, trigger: Box::new(trigger) })
+    }
+
+    pub fn on_turn_start(self, mandatory: bool, trigger: impl EffectTrigger + 'static) -> Self {
+        self.effect(OnTurnStart { mandatory, trigger: Box::new(trigger) })
+    }
+
+    /// The card type, if it passes validation
+    pub fn build(self) -> Result<CardType, Vec<DesignError>> {
+        let errors = validate(&self.card_type);
+        if errors.is_empty() {
+            Ok(self.card_type)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Every problem with the card type, empty if it is fine to add to the data directory
+pub fn validate(card_type: &CardType) -> Vec<DesignError> {
+    let mut errors = vec![];
+    if card_type.name.trim().is_empty() {
+        errors.push(DesignError::EmptyName);
+    }
+    if card_type.name.contains('@') {
+        errors.push(DesignError::NameContainsVersion);
+    }
+    if card_type.version == 0 {
+        errors.push(DesignError::NoVersion);
+    }
+    if card_type.defense == 0 {
+        errors.push(DesignError::NoDefense);
+    }
+    for (i, keyword) in card_type.keywords.iter().enumerate() {
+        if card_type.keywords[..i].contains(keyword) && !errors.contains(&DesignError::DuplicateKeyword(*keyword)) {
+            errors.push(DesignError::DuplicateKeyword(*keyword));
+        }
+    }
+    if let Err(error) = to_toml(card_type).and_then(|toml| {
+        toml::from_str::<CardType>(&toml).map_err(|error| DesignError::Serialization(error.to_string()))
+    }) {
+        errors.push(error);
+    }
+    errors
+}
+
+/// Writes the card type in the current card file format, with keys in a fixed order so the same
+/// card always produces the same file
+pub fn to_toml(card_type: &CardType) -> Result<String, DesignError> {
+    let serialization = |error: toml::ser::Error| DesignError::Serialization(error.to_string());
+    let mut table = match toml::Value::try_from(card_type).map_err(serialization)? {
+        toml::Value::Table(table) => table,
+        _ => return Err(DesignError::Serialization("a card must be a table".to_owned())),
+    };
+    // ids are assigned when the card pool is loaded
+    table.remove("id");
+    // cards only need a version once they have errata
+    if card_type.version == 1 {
+        table.remove("version");
+    }
+    // most cards don't cost resources
+    if card_type.resource_cost == 0 {
+        table.remove("resource_cost");
+    }
+    // lists which default to empty are left out rather than written as `[]`
+    for list in ["effects", "keywords", "auras", "combat"].iter() {
+        if table.get(*list).and_then(|value| value.as_array()).is_some_and(|array| array.is_empty()) {
+            table.remove(*list);
+        }
+    }
+    table.insert("format".to_owned(), toml::Value::Integer(CURRENT_FORMAT));
+    toml::to_string(&toml::Value::Table(table)).map_err(serialization)
+}
@@ -0,0 +1,634 @@
+use crate::cards::Cards;
+use crate::deck::Deck;
+use crate::ids::{EventSeq, PlayerId};
+#[cfg(feature = "metrics")]
+use crate::metrics::Metrics;
+use crate::net::Limits;
+use crate::rewind::{RewindableGame, RewindConfig};
+use crate::state::{CardInstances, GameConfig, GameEvent, GameResult, GameState, InvalidAction, Player, PlayerOption, StateHash};
+use crate::view::PlayerView;
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{self, Read, Write};
+#[cfg(feature = "metrics")]
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+/// How a driver runs a game
+#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct DriverConfig {
+    pub rewind: RewindConfig,
+    /// If options are taken for players who only have one, rather than asking them
+    pub auto_forced: bool,
+    /// If a player who disconnects loses as if they ran out of time, rather than the game being
+    /// stopped without a winner
+    pub forfeit_on_disconnect: bool,
+    /// The budget for options submitted by connections
+    pub limits: Limits,
+    /// The seed the host used for anything random, such as shuffling decks, so the game can be
+    /// recreated from its replay
+    pub seed: Option<u64>,
+    /// If what each player could see is recorded at every decision, so replays can be played
+    /// back from either player's perspective
+    pub record_views: bool,
+}
+
+/// How long a player took to make their decisions. Options taken for a player because they were
+/// forced don't count as decisions.
+#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ThinkTimes {
+    pub decisions: u32,
+    pub total: Duration,
+    pub max: Duration,
+}
+
+impl ThinkTimes {
+    pub fn average(&self) -> Duration {
+        if self.decisions == 0 {
+            Duration::default()
+        } else {
+            self.total / self.decisions
+        }
+    }
+
+    fn record(&mut self, time: Duration) {
+        self.decisions += 1;
+        self.total += time;
+        self.max = self.max.max(time);
+    }
+}
+
+/// How a match went, for tournament software to record
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct MatchResult {
+    pub result: GameResult,
+    think_times: [ThinkTimes ; 2],
+}
+
+impl MatchResult {
+    pub fn think_times(&self, player: Player) -> &ThinkTimes {
+        &self.think_times[player.index()]
+    }
+}
+
+/// The version of this crate, which replays record as the engine they were played with, and
+/// clients and servers exchange in the handshake
+pub const ENGINE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Information about a recorded game which isn't part of its events
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ReplayHeader {
+    pub engine_version: String,
+    /// The `Cards::fingerprint` of the card pool the game was played with
+    pub card_pool: u64,
+    /// The version of each card type new games used, from `Cards::versions`
+    pub card_versions: Vec<(String, u32)>,
+    pub seed: Option<u64>,
+    pub config: DriverConfig,
+    pub loop_limit: u32,
+    /// Each player's think times, player one first, if they were included
+    pub think_times: Option<[ThinkTimes ; 2]>,
+}
+
+/// Why a replay can't be played back
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ReplayError {
+    /// The replay was recorded with a different version of the engine, whose rules may differ
+    EngineVersion { recorded: String, current: String },
+    /// The replay was recorded with a different card pool, whose cards may differ
+    CardPool { recorded: u64, current: u64 },
+    /// The card pool no longer has a version of a card the replay was recorded with
+    MissingVersion { name: String, version: u32 },
+    /// An entry of a checkpoint's journal couldn't be replayed
+    Journal { entry: usize },
+    /// The position is past the end of the replay's events
+    NoSuchPosition { seq: EventSeq, events: usize },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::EngineVersion { recorded, current } => write!(
+                f, "the replay was recorded with engine version {} but this is version {}", recorded, current
+            ),
+            ReplayError::CardPool { recorded, current } => write!(
+                f, "the replay was recorded with card pool {:016x} but this card pool is {:016x}", recorded, current
+            ),
+            ReplayError::MissingVersion { name, version } => write!(
+                f, "the replay was recorded with {}@{} which is not in this card pool", name, version
+            ),
+            ReplayError::Journal { entry } => write!(f, "entry {} of the checkpoint's journal couldn't be replayed", entry),
+            ReplayError::NoSuchPosition { seq, events } => write!(
+                f, "position {} is past the end of the replay, which has {} events", seq, events
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+/// An option a player chose, with what each player could see when they chose it
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Decision {
+    pub player: Player,
+    pub option: PlayerOption,
+    /// The position in the game's events the decision was made at
+    pub events: EventSeq,
+    views: [PlayerView ; 2],
+}
+
+impl Decision {
+    /// What the player could see when the decision was made
+    pub fn view(&self, player: Player) -> &PlayerView {
+        &self.views[player.index()]
+    }
+}
+
+/// One point in a replay played back from a player's perspective
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Moment<'a> {
+    /// What the viewer could see at this point
+    pub view: &'a PlayerView,
+    /// The events since the last moment, which both players saw
+    pub events: &'a [GameEvent],
+    /// The option the viewer chose, if they were the one deciding
+    pub chose: Option<&'a PlayerOption>,
+    /// What reviewers left at the positions since the last moment, up to and including this one
+    pub annotations: Vec<&'a Annotation>,
+}
+
+/// What kind of mark a reviewer left on a replay
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum AnnotationKind {
+    /// A comment on what happened, such as a coach's advice
+    Note,
+    /// A named position to jump back to, such as the moment a bug report is about
+    Bookmark,
+}
+
+/// A mark a reviewer left at a position in a replay
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Annotation {
+    /// The position in the replay's events the annotation is at
+    pub seq: EventSeq,
+    pub kind: AnnotationKind,
+    pub text: String,
+    /// When the annotation was added
+    pub added: SystemTime,
+}
+
+/// A record of a game which can be played back
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Replay {
+    pub header: ReplayHeader,
+    pub events: Vec<GameEvent>,
+    /// Every decision made, if views were recorded
+    pub decisions: Vec<Decision>,
+    /// The notes and bookmarks reviewers left, in order of position then of when they were added
+    pub annotations: Vec<Annotation>,
+}
+
+impl Replay {
+    /// Checks the replay was recorded with this engine and the card pool, so it can be played
+    /// back without the events going wrong partway through
+    pub fn verify(&self, card_pool: &Cards) -> Result<(), ReplayError> {
+        if self.header.engine_version != ENGINE_VERSION {
+            return Err(ReplayError::EngineVersion {
+                recorded: self.header.engine_version.clone(),
+                current: ENGINE_VERSION.to_owned(),
+            });
+        }
+        let current = card_pool.fingerprint();
+        if self.header.card_pool != current {
+            return Err(ReplayError::CardPool { recorded: self.header.card_pool, current });
+        }
+        Ok(())
+    }
+
+    /// Writes the replay as JSON along with its annotations, such as to keep it for review
+    pub fn save(&self, writer: impl Write) -> io::Result<()> {
+        serde_json::to_writer(writer, self).map_err(io::Error::from)
+    }
+
+    /// Reads a replay written by `save`. It should be checked with `verify` or `card_pool` before
+    /// it is played back.
+    pub fn load(reader: impl Read) -> io::Result<Self> {
+        serde_json::from_reader(reader).map_err(io::Error::from)
+    }
+
+    /// Loads the card pool to play the replay back with, where names look up the versions the
+    /// replay was recorded with even if cards have had errata since, checking it as `verify` does
+    pub fn card_pool(&self, card_pool: &Cards) -> Result<Cards, ReplayError> {
+        let pinned = card_pool.with_versions(&self.header.card_versions)
+            .map_err(|(name, version)| ReplayError::MissingVersion { name, version })?;
+        self.verify(&pinned)?;
+        Ok(pinned)
+    }
+
+    /// Plays back the game as the viewer saw it, with what they knew at each decision either
+    /// player made rather than everything the replay records. Empty unless views were recorded.
+    pub fn playback(&self, viewer: Player) -> Vec<Moment<'_>> {
+        let mut seen = EventSeq::default();
+        let mut annotations = self.annotations.iter().peekable();
+        self.decisions.iter()
+            .map(|decision| {
+                let events = &self.events[seen.index()..decision.events.index()];
+                seen = decision.events;
+                let mut at = vec![];
+                while let Some(annotation) = annotations.next_if(|annotation| annotation.seq <= decision.events) {
+                    at.push(annotation);
+                }
+                Moment {
+                    view: decision.view(viewer),
+                    events,
+                    chose: Some(&decision.option).filter(|_| decision.player == viewer),
+                    annotations: at,
+                }
+            })
+            .collect()
+    }
+
+    /// Leaves a note at the position in the replay's events, timestamped with the current time
+    pub fn annotate(&mut self, seq: EventSeq, text: impl Into<String>) -> Result<(), ReplayError> {
+        self.add_annotation(seq, AnnotationKind::Note, text.into())
+    }
+
+    /// Bookmarks the position in the replay's events with a name, timestamped with the current
+    /// time
+    pub fn bookmark(&mut self, seq: EventSeq, name: impl Into<String>) -> Result<(), ReplayError> {
+        self.add_annotation(seq, AnnotationKind::Bookmark, name.into())
+    }
+
+    fn add_annotation(&mut self, seq: EventSeq, kind: AnnotationKind, text: String) -> Result<(), ReplayError> {
+        if seq.index() > self.events.len() {
+            return Err(ReplayError::NoSuchPosition { seq, events: self.events.len() });
+        }
+        let index = self.annotations.partition_point(|annotation| annotation.seq <= seq);
+        self.annotations.insert(index, Annotation { seq, kind, text, added: SystemTime::now() });
+        Ok(())
+    }
+
+    /// The annotations left at the position
+    pub fn annotations_at(&self, seq: EventSeq) -> impl Iterator<Item = &Annotation> {
+        self.annotations.iter().filter(move |annotation| annotation.seq == seq)
+    }
+
+    /// The bookmarks, in order of position
+    pub fn bookmarks(&self) -> impl Iterator<Item = &Annotation> {
+        self.annotations.iter().filter(|annotation| annotation.kind == AnnotationKind::Bookmark)
+    }
+}
+
+/// The decks and rules a game started from, so the game can be started again exactly
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GameSetup {
+    /// Each player's deck, player one's first
+    pub decks: [Deck ; 2],
+    pub config: GameConfig,
+}
+
+impl GameSetup {
+    /// Starts the game, instantiating the decks the same way every time
+    pub fn start(&self, card_pool: &Cards) -> GameState {
+        let mut instances = CardInstances::default();
+        let player_one = self.decks[0].instantiate(card_pool, &mut instances);
+        let player_two = self.decks[1].instantiate(card_pool, &mut instances);
+        GameState::start_with(self.config.clone(), player_one, player_two)
+    }
+}
+
+/// Something a driver did to its game. Replaying a journal from the same setup recreates the
+/// game exactly, as the rules always play out the same way.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum JournalEntry {
+    /// The priority player took the option at this index of their legal options
+    Chose(usize),
+    /// Options were taken for players who only had one
+    Forced,
+    /// The game was rewound at the request of the player
+    Rewound(Player),
+    Disconnected(Player),
+    Aborted,
+    Adjudicated(Option<Player>),
+}
+
+/// An unfinished game saved so it can be resumed, such as by a new server process during a deploy
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Checkpoint {
+    pub setup: GameSetup,
+    pub config: DriverConfig,
+    pub journal: Vec<JournalEntry>,
+    /// Who played each side, player one first
+    pub identities: [Option<PlayerId> ; 2],
+    pub think_times: [ThinkTimes ; 2],
+    /// The `Cards::fingerprint` of the card pool the game was played with
+    pub card_pool: u64,
+}
+
+/// Runs a game for a host such as a server, which asks the priority player for their choice
+/// between the options the driver gives and passes it back to the driver
+pub struct GameDriver {
+    game: RewindableGame,
+    config: DriverConfig,
+    think_times: [ThinkTimes ; 2],
+    // the player who was last given their options and when, until they take one
+    asked: Option<(Player, Instant)>,
+    disconnected: Option<Player>,
+    decisions: Vec<Decision>,
+    identities: [Option<PlayerId> ; 2],
+    // how the game started and everything done to it since, if it can be checkpointed
+    setup: Option<GameSetup>,
+    journal: Vec<JournalEntry>,
+    #[cfg(feature = "metrics")]
+    metrics: Option<Arc<Metrics>>,
+}
+
+impl GameDriver {
+    pub fn new(game: GameState, config: DriverConfig) -> Self {
+        GameDriver {
+            game: RewindableGame::new(game, config.rewind),
+            config,
+            think_times: [ThinkTimes::default() ; 2],
+            asked: None,
+            disconnected: None,
+            decisions: vec![],
+            identities: [None, None],
+            setup: None,
+            journal: vec![],
+            #[cfg(feature = "metrics")]
+            metrics: None,
+        }
+    }
+
+    /// Runs a game started from the setup, which unlike other games can be checkpointed
+    pub fn from_setup(card_pool: &Cards, setup: GameSetup, config: DriverConfig) -> Self {
+        let mut driver = GameDriver::new(setup.start(card_pool), config);
+        driver.setup = Some(setup);
+        driver
+    }
+
+    /// Saves the game so it can be resumed later, if it hasn't ended and was started from a
+    /// setup
+    pub fn checkpoint(&self, card_pool: &Cards) -> Option<Checkpoint> {
+        if self.result(card_pool).is_some() {
+            return None;
+        }
+        Some(Checkpoint {
+            setup: self.setup.clone()?,
+            config: self.config,
+            journal: self.journal.clone(),
+            identities: self.identities.clone(),
+            think_times: self.think_times,
+            card_pool: card_pool.fingerprint(),
+        })
+    }
+
+    /// Resumes a checkpointed game by replaying its journal, which needs the card pool the game
+    /// was played with
+    pub fn resume(card_pool: &Cards, checkpoint: &Checkpoint) -> Result<Self, ReplayError> {
+        let current = card_pool.fingerprint();
+        if checkpoint.card_pool != current {
+            return Err(ReplayError::CardPool { recorded: checkpoint.card_pool, current });
+        }
+        let mut driver = GameDriver::from_setup(card_pool, checkpoint.setup.clone(), checkpoint.config);
+        for (i, entry) in checkpoint.journal.iter().enumerate() {
+            let replayed = match *entry {
+                JournalEntry::Chose(index) => match driver.game().legal_options(card_pool).get(index) {
+                    Some(option) => driver.take_option(card_pool, option.clone()),
+                    None => Err(InvalidAction),
+                },
+                JournalEntry::Forced => match driver.take_forced_options(card_pool) {
+                    0 => Err(InvalidAction),
+                    _ => Ok(()),
+                },
+                JournalEntry::Rewound(player) => driver.request_rewind(player)
+                    .and_then(|()| driver.respond_to_rewind(player.next(), true))
+                    .and_then(|rewound| if rewound { Ok(()) } else { Err(InvalidAction) }),
+                JournalEntry::Disconnected(player) => driver.disconnected(player),
+                JournalEntry::Aborted => driver.abort(),
+                JournalEntry::Adjudicated(winner) => driver.adjudicate(winner),
+            };
+            replayed.map_err(|_| ReplayError::Journal { entry: i })?;
+        }
+        driver.identities = checkpoint.identities.clone();
+        driver.think_times = checkpoint.think_times;
+        driver.asked = None;
+        Ok(driver)
+    }
+
+    /// Counts the game and what happens in it in the metrics, which are usually shared by every
+    /// game a server hosts
+    #[cfg(feature = "metrics")]
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        metrics.game_started();
+        if let Some(previous) = self.metrics.replace(metrics) {
+            previous.game_dropped();
+        }
+        self
+    }
+
+    pub fn game(&self) -> &GameState {
+        self.game.game()
+    }
+
+    /// The options the priority player must now choose between, taking any forced options for
+    /// them first if configured to. The player's think time starts when they are first given
+    /// their options.
+    pub fn options(&mut self, card_pool: &Cards) -> Vec<PlayerOption> {
+        if self.config.auto_forced {
+            self.take_forced_options(card_pool);
+        }
+        let priority = self.game.game().priority();
+        if self.asked.is_none_or(|(player, _)| player != priority) {
+            self.asked = Some((priority, Instant::now()));
+        }
+        self.game.game().priority_player_options(card_pool)
+    }
+
+    fn take_forced_options(&mut self, card_pool: &Cards) -> usize {
+        let forced = self.game.take_forced_options(card_pool);
+        if forced > 0 {
+            self.asked = None;
+            self.journal.push(JournalEntry::Forced);
+            #[cfg(feature = "metrics")]
+            if let Some(metrics) = &self.metrics {
+                metrics.actions_taken(forced as u64);
+            }
+        }
+        forced
+    }
+
+    /// Takes one of the options the priority player was given, recording how long they took to
+    /// choose it
+    pub fn take_option(&mut self, card_pool: &Cards, option: PlayerOption) -> Result<(), InvalidAction> {
+        let priority = self.game.game().priority();
+        let chose = self.game.game().legal_options(card_pool).iter().position(|legal| *legal == option).ok_or(InvalidAction)?;
+        let decision = if self.config.record_views {
+            let game = self.game.game();
+            Some(Decision {
+                player: priority,
+                option: option.clone(),
+                events: game.event_seq(),
+                views: [PlayerView::of(game, Player::One, card_pool), PlayerView::of(game, Player::Two, card_pool)],
+            })
+        } else {
+            None
+        };
+        self.game.take_option(card_pool, option)?;
+        self.journal.push(JournalEntry::Chose(chose));
+        self.decisions.extend(decision);
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &self.metrics {
+            metrics.actions_taken(1);
+        }
+        if let Some((player, asked)) = self.asked.take() {
+            if player == priority {
+                let time = asked.elapsed();
+                self.think_times[player.index()].record(time);
+                #[cfg(feature = "metrics")]
+                if let Some(metrics) = &self.metrics {
+                    metrics.decided(time);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Binds the identity a connection authenticated as to one of the players, failing if the
+    /// player is already bound or the identity is already playing as the other player
+    pub fn bind(&mut self, player: Player, identity: PlayerId) -> Result<(), InvalidAction> {
+        if self.identities[player.index()].is_some() || self.player_of(&identity).is_some() {
+            return Err(InvalidAction);
+        }
+        self.identities[player.index()] = Some(identity);
+        Ok(())
+    }
+
+    pub fn identity(&self, player: Player) -> Option<&PlayerId> {
+        self.identities[player.index()].as_ref()
+    }
+
+    /// The player the identity is bound to, if any
+    pub fn player_of(&self, identity: &PlayerId) -> Option<Player> {
+        Player::all()
+            .find(|&player| self.identity(player) == Some(identity))
+    }
+
+    /// Takes an option submitted by a connection, rejecting it unless the connection's identity
+    /// is bound to the priority player and the option is within the budget of the limits
+    pub fn take_option_as(
+        &mut self, identity: &PlayerId, card_pool: &Cards, option: PlayerOption
+    ) -> Result<(), InvalidAction> {
+        if !self.config.limits.allows(&option) || self.player_of(identity) != Some(self.game.game().priority()) {
+            return Err(InvalidAction);
+        }
+        self.take_option(card_pool, option)
+    }
+
+    /// Asks the player's opponent to agree to undo the last option taken
+    pub fn request_rewind(&mut self, player: Player) -> Result<(), InvalidAction> {
+        self.game.request_rewind(player)
+    }
+
+    /// Answers the opponent's request to rewind, returning if the game was rewound
+    pub fn respond_to_rewind(&mut self, player: Player, agree: bool) -> Result<bool, InvalidAction> {
+        let rewound = self.game.respond_to_rewind(player, agree)?;
+        if rewound {
+            self.asked = None;
+            self.journal.push(JournalEntry::Rewound(player.next()));
+        }
+        Ok(rewound)
+    }
+
+    /// Tells the driver the transport considers the player disconnected, which ends the game by
+    /// timing them out or aborting depending on the config
+    pub fn disconnected(&mut self, player: Player) -> Result<(), InvalidAction> {
+        if self.config.forfeit_on_disconnect {
+            self.game.time_out(player)?;
+        } else {
+            self.game.abort()?;
+        }
+        self.disconnected = Some(player);
+        self.asked = None;
+        self.journal.push(JournalEntry::Disconnected(player));
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &self.metrics {
+            metrics.disconnected();
+        }
+        Ok(())
+    }
+
+    /// Checks the state hash a client reports against the game, such as after every option, so
+    /// a client whose game has drifted from the server's can be told to resync. Mismatches are
+    /// counted as desyncs in the metrics.
+    pub fn confirm_hash(&self, hash: StateHash) -> bool {
+        let matches = self.game.game().state_hash() == hash;
+        #[cfg(feature = "metrics")]
+        if let (false, Some(metrics)) = (matches, &self.metrics) {
+            metrics.desynced();
+        }
+        matches
+    }
+
+    /// Stops the game without a winner, such as when an operator force ends it
+    pub fn abort(&mut self) -> Result<(), InvalidAction> {
+        self.game.abort()?;
+        self.asked = None;
+        self.journal.push(JournalEntry::Aborted);
+        Ok(())
+    }
+
+    /// Ends a stuck game with the winner an operator decided on, or as a draw
+    pub fn adjudicate(&mut self, winner: Option<Player>) -> Result<(), InvalidAction> {
+        self.game.adjudicate(winner)?;
+        self.asked = None;
+        self.journal.push(JournalEntry::Adjudicated(winner));
+        Ok(())
+    }
+
+    /// The player who disconnected, if either did
+    pub fn disconnected_player(&self) -> Option<Player> {
+        self.disconnected
+    }
+
+    pub fn think_times(&self, player: Player) -> &ThinkTimes {
+        &self.think_times[player.index()]
+    }
+
+    /// How the match went, if the game is over
+    pub fn result(&self, card_pool: &Cards) -> Option<MatchResult> {
+        self.game.game().result(card_pool).map(|result| MatchResult {
+            result,
+            think_times: self.think_times,
+        })
+    }
+
+    /// A record of the game so far, played with the card pool, optionally with each player's
+    /// think times in the header
+    pub fn replay(&self, card_pool: &Cards, include_think_times: bool) -> Replay {
+        Replay {
+            header: ReplayHeader {
+                engine_version: ENGINE_VERSION.to_owned(),
+                card_pool: card_pool.fingerprint(),
+                card_versions: card_pool.versions(),
+                seed: self.config.seed,
+                config: self.config,
+                loop_limit: self.game.game().loop_limit(),
+                think_times: Some(self.think_times).filter(|_| include_think_times),
+            },
+            events: self.game.game().events().to_vec(),
+            decisions: self.decisions.clone(),
+            annotations: vec![],
+        }
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl Drop for GameDriver {
+    fn drop(&mut self) {
+        if let Some(metrics) = &self.metrics {
+            metrics.game_dropped();
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use crate::card_type::{CardEffect, Condition, EffectTrigger};
+use crate::combat::CombatModifier;
+
+use std::fmt;
+
+use serde::de::{self, Deserialize};
+use serde::de::value::MapDeserializer;
+
+/// Which trait a registered type implements
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TypeKind {
+    Effect,
+    Trigger,
+    Condition,
+    CombatModifier,
+}
+
+/// A field a registered type expects in its table
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct FieldInfo {
+    pub name: &'static str,
+    /// What the field holds, such as "bool", "u32" or "trigger" for a nested table
+    pub value: &'static str,
+    /// If the field may be left out
+    pub optional: bool,
+}
+
+/// A `type = "..."` value which can be used in card files
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TypeInfo {
+    pub name: &'static str,
+    pub kind: TypeKind,
+    /// The fields the type expects. Types registered outside this crate have no known fields.
+    pub fields: &'static [FieldInfo],
+}
+
+const fn required(name: &'static str, value: &'static str) -> FieldInfo {
+    FieldInfo { name, value, optional: false }
+}
+
+const fn optional(name: &'static str, value: &'static str) -> FieldInfo {
+    FieldInfo { name, value, optional: true }
+}
+
+/// The fields of the types this crate registers
+const KNOWN_FIELDS: &[(&str, &[FieldInfo])] = &[
+    ("OnSummon", &[required("mandatory", "bool"), required("trigger", "trigger")]),
+    ("OnDraw", &[required("mandatory", "bool"), required("trigger", "trigger")]),
+    ("OnField", &[required("mandatory", "bool"), required("trigger", "trigger")]),
+    ("InCenterDeck", &[required("trigger", "trigger")]),
+    ("OnDestroyed", &[required("mandatory", "bool"), required("trigger", "trigger")]),
+    ("OnDeckEmptied", &[
+        required("whose", "Whose"),
+        required("deck", "DeckChoice"),
+        required("mandatory", "bool"),
+        required("trigger", "trigger"),
+    ]),
+    ("OnTurnStart", &[required("mandatory", "bool"), required("trigger", "trigger")]),
+    ("DestroySelfUnless", &[required("condition", "condition")]),
+    ("Conditional", &[required("condition", "condition"), required("trigger", "trigger")]),
+    ("Unrespondable", &[required("trigger", "trigger")]),
+    ("SwapHandWithField", &[]),
+    ("SwapFieldPositions", &[required("preserve_counters", "bool")]),
+    ("DrawCards", &[required("whose", "Whose"), required("deck_choice", "DeckChoice"), required("count", "u32")]),
+    ("DrawForCounters", &[required("deck_choice", "DeckChoice"), optional("max", "u32")]),
+    ("ResourcesForCounters", &[optional("max", "u32")]),
+    ("TransferCounters", &[required("whose", "Whose"), optional("filter", "TargetFilter"), optional("max", "u32")]),
+    ("MillDeck", &[
+        required("whose", "Whose"),
+        required("deck_choice", "DeckChoice"),
+        required("count", "u32"),
+        optional("column", "Column"),
+    ]),
+    ("ReturnTargetToHand", &[required("whose", "Whose"), optional("filter", "TargetFilter")]),
+    ("RemoveTarget", &[required("whose", "Whose"), optional("filter", "TargetFilter")]),
+    ("DestroyAll", &[optional("filter", "TargetFilter")]),
+    ("DestroyColumn", &[optional("column", "Column"), optional("whose", "Whose")]),
+    ("TakeControlOfTarget", &[optional("filter", "TargetFilter")]),
+    ("PeekOpponentDeck", &[required("deck", "DeckChoice"), required("count", "u32")]),
+    ("RetargetAttack", &[]),
+    ("GainResources", &[required("whose", "Whose"), required("resources", "u32")]),
+    ("AddRandomCard", &[required("whose", "Whose"), optional("filter", "PoolFilter")]),
+    ("PayResources", &[required("resources", "u32"), required("trigger", "trigger")]),
+    ("PreventDraws", &[required("whose", "Whose"), optional("deck", "FaceDownDeck"), required("turns", "u32")]),
+    ("ExtraDraws", &[required("whose", "Whose"), required("draws", "u32"), required("turns", "u32")]),
+    ("SacrificeToSummon", &[required("sacrifices", "usize")]),
+    ("NamedCardOnField", &[required("name", "string")]),
+    ("TargetedByOpponent", &[]),
+    ("NamedCardInColumn", &[required("name", "string")]),
+    ("NamedCardRemoved", &[required("name", "string")]),
+    ("HasResources", &[required("resources", "u32")]),
+    ("ZoneCount", &[
+        required("whose", "Whose"),
+        required("zone", "PublicZone"),
+        optional("at_least", "u32"),
+        optional("at_most", "u32"),
+    ]),
+    ("Piercing", &[]),
+    ("PreventDamage", &[optional("condition", "condition")]),
+    ("Retaliate", &[required("damage", "u32")]),
+];
+
+/// Every type tag registered for effects, triggers, conditions and combat modifiers, including
+/// those registered by other crates, sorted by name within each kind
+pub fn registered_types() -> Vec<TypeInfo> {
+    let kinds = [
+        (TypeKind::Effect, tags::<dyn CardEffect>()),
+        (TypeKind::Trigger, tags::<dyn EffectTrigger>()),
+        (TypeKind::Condition, tags::<dyn Condition>()),
+        (TypeKind::CombatModifier, tags::<dyn CombatModifier>()),
+    ];
+    kinds.iter()
+        .flat_map(|&(kind, names)| names.iter().map(move |&name| TypeInfo {
+            name,
+            kind,
+            fields: KNOWN_FIELDS.iter()
+                .find(|&&(known, _)| known == name)
+                .map_or(&[], |&(_, fields)| fields),
+        }))
+        .collect()
+}
+
+/// The error from deserializing a tag which isn't registered, which typetag reports with the
+/// list of tags that are
+#[derive(Debug)]
+struct Probe(&'static [&'static str]);
+
+impl fmt::Display for Probe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected one of {:?}", self.0)
+    }
+}
+
+impl std::error::Error for Probe {}
+
+impl de::Error for Probe {
+    fn custom<T: fmt::Display>(_msg: T) -> Self {
+        Probe(&[])
+    }
+
+    fn unknown_variant(_variant: &str, expected: &'static [&'static str]) -> Self {
+        Probe(expected)
+    }
+}
+
+/// The tags registered for a typetag trait object
+fn tags<T: ?Sized>() -> &'static [&'static str]
+where
+    Box<T>: for<'de> Deserialize<'de>,
+{
+    let probe = MapDeserializer::<_, Probe>::new(std::iter::once(("type", "")));
+    match Box::<T>::deserialize(probe) {
+        Err(Probe(names)) => names,
+        Ok(_) => &[],
+    }
+}
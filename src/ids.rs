@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+
+use std::collections::btree_map::{self, BTreeMap};
+use std::fmt;
+use std::num::ParseIntError;
+use std::str::FromStr;
+
+/// Identifies a game hosted by a server
+#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(transparent)]
+pub struct GameId(pub u64);
+
+impl GameId {
+    /// The id given to the game hosted after this one
+    pub fn next(self) -> GameId {
+        GameId(self.0 + 1)
+    }
+}
+
+impl fmt::Display for GameId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for GameId {
+    type Err = ParseIntError;
+
+    fn from_str(id: &str) -> Result<Self, Self::Err> {
+        id.parse().map(GameId)
+    }
+}
+
+impl From<u64> for GameId {
+    fn from(id: u64) -> Self {
+        GameId(id)
+    }
+}
+
+impl From<GameId> for u64 {
+    fn from(id: GameId) -> Self {
+        id.0
+    }
+}
+
+/// Who is behind a connection, as established by a `TokenValidator` when they connect
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(transparent)]
+pub struct PlayerId(pub String);
+
+impl PlayerId {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for PlayerId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<&str> for PlayerId {
+    fn from(name: &str) -> Self {
+        PlayerId(name.to_owned())
+    }
+}
+
+impl From<String> for PlayerId {
+    fn from(name: String) -> Self {
+        PlayerId(name)
+    }
+}
+
+impl From<PlayerId> for String {
+    fn from(id: PlayerId) -> Self {
+        id.0
+    }
+}
+
+/// A link of the chain by its position, counting from 0 for the first link activated, which
+/// resolves last
+#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(transparent)]
+pub struct ChainLinkId(pub u32);
+
+impl ChainLinkId {
+    /// The index of the link in `GameState::pending_chain`
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl fmt::Display for ChainLinkId {
+    /// Numbered from 1 as players count links
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "link {}", self.0 + 1)
+    }
+}
+
+impl From<usize> for ChainLinkId {
+    fn from(index: usize) -> Self {
+        ChainLinkId(index as u32)
+    }
+}
+
+/// A position in a game's events, counting how many events happened before it. Events from a
+/// position on are `GameState::events_since`.
+#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(transparent)]
+pub struct EventSeq(pub u64);
+
+impl EventSeq {
+    /// The index of the first event from this position on in `GameState::events`
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl fmt::Display for EventSeq {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<usize> for EventSeq {
+    fn from(index: usize) -> Self {
+        EventSeq(index as u64)
+    }
+}
+
+/// Values by game id, giving each value inserted the next unused id so ids are never reused
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IdMap<V> {
+    values: BTreeMap<GameId, V>,
+    next: GameId,
+}
+
+impl<V> Default for IdMap<V> {
+    fn default() -> Self {
+        IdMap { values: BTreeMap::new(), next: GameId::default() }
+    }
+}
+
+impl<V> IdMap<V> {
+    pub fn new() -> Self {
+        IdMap::default()
+    }
+
+    /// Inserts the value with the next unused id, returning the id
+    pub fn insert(&mut self, value: V) -> GameId {
+        let id = self.next;
+        self.next = id.next();
+        self.values.insert(id, value);
+        id
+    }
+
+    pub fn get(&self, id: GameId) -> Option<&V> {
+        self.values.get(&id)
+    }
+
+    pub fn get_mut(&mut self, id: GameId) -> Option<&mut V> {
+        self.values.get_mut(&id)
+    }
+
+    pub fn remove(&mut self, id: GameId) -> Option<V> {
+        self.values.remove(&id)
+    }
+
+    pub fn contains(&self, id: GameId) -> bool {
+        self.values.contains_key(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// The ids in use, lowest first
+    pub fn ids(&self) -> impl Iterator<Item = GameId> + '_ {
+        self.values.keys().copied()
+    }
+
+    /// The values with their ids, lowest id first
+    pub fn iter(&self) -> btree_map::Iter<'_, GameId, V> {
+        self.values.iter()
+    }
+}
@@ -1,16 +1,124 @@
-mod card_type;
-mod cards;
-mod state;
+// The rules only use integer arithmetic, so games play out the same on every platform and
+// lockstep clients never drift apart. The modules the rules are made of deny floats below, while
+// agents, statistics and metrics around them are free to use them.
+
+/// Commands for operators to manage the games a server is hosting
+pub mod admin;
+/// Bots which choose options for a player, from taking random options to searching ahead
+pub mod agent;
+/// Grouping simulated decks into archetypes by the cards they play and how their matchups go
+pub mod archetypes;
+/// Keeping finished games and finding them again by player, time or result
+pub mod archive;
+/// Playing agents against each other across decks to estimate how strong each one is
+pub mod arena;
+/// Card type definitions and the effects, triggers and conditions they are built from
+#[deny(clippy::float_arithmetic)]
+pub mod card_type;
+/// Loading and looking up the pool of card types
+pub mod cards;
+/// How attacks deal damage, and the modifiers cards change it with
+#[deny(clippy::float_arithmetic)]
+pub mod combat;
+/// Decks and statistics about them for deck builders
+pub mod deck;
+/// Filling in decks automatically
+pub mod deckgen;
+/// Building, validating and writing card types for card editors
+pub mod designer;
+/// Running games for hosts, with timing and records of each match
+pub mod driver;
+/// Listing the effect, trigger, condition and combat modifier types card files can use
+pub mod effects;
+/// Typed identifiers for games, players, chain links and positions in a game's events
+pub mod ids;
+/// Counters for monitoring the games a server hosts, in a format metrics scrapers read
+#[cfg(feature = "metrics")]
+pub mod metrics;
+/// Messages and liveness checks shared by the transports players connect with
+pub mod net;
+/// The types most clients need, re-exported in one place
+pub mod prelude;
+/// Positions written outside the engine for games to start from
+pub mod position;
+/// Letting players agree to undo options in casual games
+pub mod rewind;
+/// A rules reference generated from the engine for clients' in-game rulebooks
+pub mod rules_doc;
+/// A run of games at a client, from picking decks to saving replays and resuming saved games
+pub mod session;
+/// Playing many games between decks with random options, such as for balance testing
+pub mod sim;
+/// The rules engine, game state and the options players can take
+#[deny(clippy::float_arithmetic)]
+pub mod state;
+/// Plain text descriptions of what happened in a game
+pub mod summary;
+/// Colors and glyphs for drawing cards in terminal clients, loaded from a theme file
+pub mod theme;
+/// Keeping card pools in step with a server, checked against signed manifests
+#[cfg(feature = "sync")]
+pub mod sync;
+/// Remembering what agents worked out about positions, so a position reached again isn't worked
+/// out twice
+pub mod transposition;
+/// A full screen terminal interface for playtesting
+#[cfg(feature = "tui")]
+pub mod tui;
+/// Scripted tutorials which guide a player through a real game one move at a time
+pub mod tutorial;
+/// What each player can see of a game at any point
+pub mod view;
 
 #[cfg(test)]
 mod tests {
-    use crate::cards::Cards;
-    use crate::state::{Action, ActionType, Card, CardInstance, GameState, PlayerOption, FaceDownDeck, FieldSlot, InvalidAction};
+    // some helpers are only used by the tests which load the card pool from the data directory
+    #![cfg_attr(not(feature = "fs"), allow(dead_code, unused_imports))]
+
+    use crate::card_type::{self, CardType, CardTypeIdentifier, Condition, DeckChoice, DestroyAll, DestroyColumn, DrawForCounters, EffectSummary, EffectTrigger, HasResources, Keyword, NamedCardInColumn, NamedCardRemoved, PoolFilter, PublicZone, RemoveTarget, SummonRestriction, TargetFilter, TargetedByOpponent, TransferCounters, TriggerKind, TriggerTiming, Whose, ZoneCount};
+    use crate::admin::{authenticate, AdminCommand, AdminError, LiveGames};
+    use crate::agent::{Agent, FairAgent, GreedyAgent, MctsAgent, MinimaxAgent, RandomAgent};
+    use crate::archetypes;
+    use crate::arena::{self, TournamentConfig};
+    use crate::archive::{GameQuery, GameStore, HeadToHead, MemoryArchive, Outcome, SharedArchive};
+    use crate::cards::{Cards, EmbeddedSource, MemorySource, PackSource};
+    #[cfg(feature = "http")]
+    use crate::cards::HttpSource;
+    use crate::driver::{AnnotationKind, DriverConfig, GameDriver, GameSetup, Moment, Replay, ReplayError, ThinkTimes, ENGINE_VERSION};
+    use crate::net::{
+        auth_token, read_limited_line, rules_fingerprint, AnyToken, Limits, Liveness, LivenessConfig, RateLimiter,
+        RulesVersion, StaticTokens, TokenValidator,
+    };
+    use crate::position::{ImportError, PositionSpec};
+    use crate::rewind::{RewindableGame, RewindConfig};
+    use crate::rules_doc::RulesDoc;
+    use crate::session::{Session, SessionError};
+    use crate::sim;
+    use crate::deck::{Deck, DeckError, DeckLimits, DecklistProblem, Decks, DeckWarning, NameCorrection};
+    use crate::deckgen::{self, Constraints};
+    use crate::designer::{self, CardTypeBuilder, DesignError};
+    use crate::effects::{self, TypeKind};
+    use crate::ids::{ChainLinkId, EventSeq, GameId, IdMap, PlayerId};
+    use crate::summary::{describe_cause, describe_state, describe_turn};
+    use crate::theme::{CardRenderer, CardStyle, Color, Theme, ThemeError};
+    #[cfg(feature = "sync")]
+    use crate::sync::{sign_manifest, CardSync, Manifest, Remote, SigningKey, SyncError};
+    use crate::transposition::TranspositionTable;
+    use crate::tutorial::{Step, TutorialError, TutorialGame};
+    use crate::view::{OptionAnnotation, PlayerView, VisibleCard};
+
+    use serde::{Deserialize, Serialize};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use crate::state::{Action, Activation, ActivationData, ActivatableType, AttackTarget, Card, CardEffect, CardInstance, CardInstances, CardStatus, ChainLink, Column, DeckSizes, DrawModifier, DrawRule, EffectBudget, EndReason, EngineAction, EventCause, GameConfig, GameEvent, GameState, GameStateType, Phase, Player, PlayerOption, FaceDownDeck, FieldSlot, Row, SoftLockPolicy, StateHash, InvalidAction, ResolutionContext, TooManyCards, Trigger, DEFAULT_LOOP_LIMIT, MAX_CARDS};
 
     fn same_set(one: Vec<PlayerOption>, two: Vec<PlayerOption>) -> bool {
         one.iter().all(|option| two.contains(option)) && one.len() == two.len()
     }
 
+    #[cfg(feature = "fs")]
     #[test]
     fn reading_cards() {
         let cards = Cards::get();
@@ -18,31 +126,47 @@ mod tests {
         assert_eq!(card.name, "Staple Dragon");
     }
 
+    #[cfg(feature = "fs")]
+    #[test]
+    fn reading_decks() {
+        let decks = Decks::load().unwrap();
+        assert!(decks.deck("Staple Starter").unwrap().analyze(Cards::get()).warnings.is_empty());
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn reading_theme() {
+        let theme = Theme::load().unwrap();
+        assert_eq!(theme.style(Cards::get().card("Staple Mirage").unwrap()).color, Some(Color::Cyan));
+    }
+
+    #[cfg(feature = "fs")]
     #[test]
     fn starting_game_state() {
+        let mut instances = CardInstances::default();
         let cards = Cards::get();
         let card = cards.card("Staple Dragon").unwrap();
         let player_one = (
-            vec![Card::instantiate(card), Card::instantiate(card), Card::instantiate(card)],
+            vec![instances.instantiate(card), instances.instantiate(card), instances.instantiate(card)],
             vec![],
-            vec![Card::instantiate(card), Card::instantiate(card)],
+            vec![instances.instantiate(card), instances.instantiate(card)],
             vec![
-                Card::instantiate(card), Card::instantiate(card), Card::instantiate(card),
-                Card::instantiate(card), Card::instantiate(card)
+                instances.instantiate(card), instances.instantiate(card), instances.instantiate(card),
+                instances.instantiate(card), instances.instantiate(card)
             ]
         );
         let player_two = (
-            vec![Card::instantiate(card), Card::instantiate(card), Card::instantiate(card)],
+            vec![instances.instantiate(card), instances.instantiate(card), instances.instantiate(card)],
             vec![],
-            vec![Card::instantiate(card), Card::instantiate(card)],
+            vec![instances.instantiate(card), instances.instantiate(card)],
             vec![
-                Card::instantiate(card), Card::instantiate(card), Card::instantiate(card),
-                Card::instantiate(card), Card::instantiate(card)
+                instances.instantiate(card), instances.instantiate(card), instances.instantiate(card),
+                instances.instantiate(card), instances.instantiate(card)
             ]
         );
         let game = GameState::start(player_one, player_two);
         println!("{:?}", game);
-        let actions = game.priority_player_options();
+        let actions = game.priority_player_options(cards);
         assert!(
             same_set(actions, vec![
                 PlayerOption::Draw(FaceDownDeck::Left),
@@ -52,25 +176,4129 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "fs")]
     #[test]
     fn test_summoning() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
         let cards = Cards::get();
-        let card = cards.card("Staple Dragon").unwrap();
+        let card = instances.instantiate(cards.card("Staple Dragon").unwrap());
+        let instance = card.instance;
         let player_one = (
             vec![],
             vec![],
             vec![],
-            vec![Card::instantiate(card)]
+            vec![card]
         );
         let player_two = (vec![], vec![], vec![], vec![]);
         let mut game = GameState::start(player_one, player_two);
-        game.priorty_player_take_option(PlayerOption::SkipDraw)?;
-        game.priorty_player_take_option(PlayerOption::Action(Action {
-            action_type: ActionType::Summon,
-            instance: CardInstance(0),
-            slot: Some(FieldSlot::F4),
+        game.priorty_player_take_option(cards, PlayerOption::SkipDraw)?;
+        game.priorty_player_take_option(cards, PlayerOption::Action(Action::Summon {
+            instance,
+            slot: FieldSlot::F4,
         }))?;
         println!("{:?}", game);
         Ok(())
     }
+
+    fn keyword_cards() -> Cards {
+        Cards::from_test(vec![
+            r#"
+            name = "Soldier"
+            defense = 3
+            attack = 2
+            "#,
+            r#"
+            name = "Shieldbearer"
+            defense = 4
+            attack = 1
+            keywords = ["Guard"]
+            "#,
+            r#"
+            name = "Lancer"
+            defense = 2
+            attack = 5
+            keywords = ["Piercing"]
+            "#,
+        ]).unwrap()
+    }
+
+    fn summon(game: &mut GameState, cards: &Cards, instance: CardInstance, slot: FieldSlot) -> Result<(), InvalidAction> {
+        game.priorty_player_take_option(cards, PlayerOption::SkipDraw)?;
+        game.priorty_player_take_option(cards, PlayerOption::Action(Action::Summon { instance, slot }))?;
+        game.priorty_player_take_option(cards, PlayerOption::SkipResponse)?;
+        game.priorty_player_take_option(cards, PlayerOption::SkipResponse)
+    }
+
+    fn attack_options(options: Vec<PlayerOption>) -> Vec<(CardInstance, FieldSlot)> {
+        options.into_iter().filter_map(|option| match option {
+            PlayerOption::Action(Action::Attack { instance, target }) => Some((instance, target)),
+            _ => None,
+        }).collect()
+    }
+
+    #[test]
+    fn card_instances_run_out_rather_than_repeat() {
+        let cards = keyword_cards();
+        let soldier = cards.card("Soldier").unwrap();
+        let mut instances = CardInstances::default();
+        for i in 0..MAX_CARDS {
+            assert_eq!(instances.try_instantiate(soldier).unwrap().instance, CardInstance(i as u16));
+        }
+        assert_eq!(instances.try_instantiate(soldier).err(), Some(TooManyCards));
+    }
+
+    #[test]
+    fn guard_must_be_attacked_first_in_its_column() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = keyword_cards();
+        let soldier = instances.instantiate(cards.card("Soldier").unwrap());
+        let lancer = instances.instantiate(cards.card("Lancer").unwrap());
+        let shieldbearer = instances.instantiate(cards.card("Shieldbearer").unwrap());
+        let (soldier_id, lancer_id, shieldbearer_id) = (soldier.instance, lancer.instance, shieldbearer.instance);
+        let mut game = GameState::start(
+            (vec![], vec![], vec![], vec![lancer]),
+            (vec![], vec![], vec![], vec![soldier, shieldbearer]),
+        );
+        summon(&mut game, &cards, lancer_id, FieldSlot::F0)?;
+        summon(&mut game, &cards, soldier_id, FieldSlot::F2)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        assert_eq!(attack_options(game.priority_player_options(&cards)), vec![(lancer_id, FieldSlot::F2)]);
+        game.priorty_player_take_option(&cards, PlayerOption::SkipAction)?;
+        summon(&mut game, &cards, shieldbearer_id, FieldSlot::B2)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        // the soldier in front no longer protects the column from being attacked through the guard
+        assert_eq!(attack_options(game.priority_player_options(&cards)), vec![(lancer_id, FieldSlot::B2)]);
+        Ok(())
+    }
+
+    #[test]
+    fn combat_modifiers_can_prevent_and_react_to_damage() -> Result<(), InvalidAction> {
+        let cards = Cards::from_test(vec![
+            r#"
+            name = "Lancer"
+            defense = 2
+            attack = 5
+            keywords = ["Piercing"]
+            "#,
+            r#"
+            name = "Bulwark"
+            defense = 2
+            attack = 1
+            [[combat]]
+                type = "PreventDamage"
+            [[combat]]
+                type = "Retaliate"
+                damage = 2
+            "#,
+        ]).unwrap();
+        let mut instances = CardInstances::default();
+        let lancer = instances.instantiate(cards.card("Lancer").unwrap());
+        let bulwark = instances.instantiate(cards.card("Bulwark").unwrap());
+        let (lancer_id, bulwark_id) = (lancer.instance, bulwark.instance);
+        let mut game = GameState::start(
+            (vec![], vec![], vec![], vec![lancer]),
+            (vec![], vec![], vec![], vec![bulwark]),
+        );
+        summon(&mut game, &cards, lancer_id, FieldSlot::F0)?;
+        summon(&mut game, &cards, bulwark_id, FieldSlot::F3)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        game.priorty_player_take_option(&cards, PlayerOption::Action(Action::Attack {
+            instance: lancer_id,
+            target: FieldSlot::F3,
+        }))?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipResponse)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipResponse)?;
+        // the bulwark takes none of the 5 damage and deals 2 back, destroying the lancer
+        assert_eq!(game.card(bulwark_id).map(|card| card.damage), Some(0));
+        assert_eq!(game.card(lancer_id).map(Card::status), Some(CardStatus::Destroyed));
+        let bulwark = cards.card("Bulwark").unwrap();
+        assert_eq!(bulwark.combat[1].text(), "After this card is attacked, deal 2 damage to the attacker.");
+        Ok(())
+    }
+
+    #[test]
+    fn links_fizzle_when_their_target_leaves_the_field_in_response() -> Result<(), InvalidAction> {
+        let cards = Cards::from_test(vec![
+            r#"
+            name = "Bouncer"
+            defense = 2
+            attack = 1
+            [[effects]]
+                type = "OnField"
+                mandatory = false
+                [effects.trigger]
+                    type = "ReturnTargetToHand"
+                    whose = "Opponent"
+            "#,
+            r#"
+            name = "Retreat"
+            defense = 2
+            attack = 1
+            [[effects]]
+                type = "OnField"
+                mandatory = false
+                [effects.trigger]
+                    type = "ReturnTargetToHand"
+                    whose = "Controller"
+            "#,
+        ]).unwrap();
+        let mut instances = CardInstances::default();
+        let bouncer = instances.instantiate(cards.card("Bouncer").unwrap());
+        let retreat = instances.instantiate(cards.card("Retreat").unwrap());
+        let (bouncer_id, retreat_id) = (bouncer.instance, retreat.instance);
+        let mut game = GameState::start((vec![], vec![], vec![], vec![bouncer]), (vec![], vec![], vec![], vec![retreat]));
+        summon(&mut game, &cards, bouncer_id, FieldSlot::F0)?;
+        summon(&mut game, &cards, retreat_id, FieldSlot::F0)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        let activate = |game: &GameState, instance| game.legal_options(&cards)
+            .iter()
+            .find(|option| matches!(option, PlayerOption::Action(Action::Effect { instance: activated, .. }) if *activated == instance))
+            .cloned()
+            .unwrap();
+        let option = activate(&game, bouncer_id);
+        game.priorty_player_take_option(&cards, option)?;
+        let option = activate(&game, retreat_id);
+        game.priorty_player_take_option(&cards, option)?;
+        let chain: Vec<CardInstance> = game.pending_chain().iter().map(|link| link.instance).collect();
+        assert_eq!(chain, vec![bouncer_id, retreat_id]);
+        game.priorty_player_take_option(&cards, PlayerOption::SkipResponse)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipResponse)?;
+        assert!(game.pending_chain().is_empty());
+        let (bouncer, retreat) = (cards.card("Bouncer").unwrap().id, cards.card("Retreat").unwrap().id);
+        let resolution: Vec<GameEvent> = game.events().iter()
+            .skip_while(|event| !matches!(event, GameEvent::ChainResolutionStarted { .. }))
+            .take(4)
+            .cloned()
+            .collect();
+        assert_eq!(resolution, vec![
+            GameEvent::ChainResolutionStarted { links: 2 },
+            GameEvent::LinkResolved { link: ChainLinkId(1), player: Player::Two, card: retreat },
+            GameEvent::LinkFizzled { link: ChainLinkId(0), player: Player::One, card: bouncer },
+            GameEvent::Resolved,
+        ]);
+        Ok(())
+    }
+
+    static TARGETED_RESOLUTIONS: AtomicUsize = AtomicUsize::new(0);
+
+    /// An effect targeting another card on the field, which counts how many times it resolves
+    /// whether or not its target is still there
+    #[derive(Debug, Deserialize, Serialize)]
+    struct CountedTargetedResolve;
+
+    #[typetag::serde]
+    impl card_type::CardEffect for CountedTargetedResolve {
+        fn can_activate(&self, _: &Cards, _: &CardType, game: &GameState, instance: CardInstance, _: &ResolutionContext) -> Vec<Activation> {
+            game.cards_on_field()
+                .map(|card| card.instance)
+                .filter(|&target| target != instance)
+                .map(|target| Activation {
+                    status: ActivatableType::Can,
+                    data: ActivationData { targets: vec![target], ..ActivationData::default() },
+                })
+                .collect()
+        }
+
+        fn activate(&self, _: &Cards, _: &CardType, _: &mut GameState, _: CardInstance, _: &Activation, _: &ResolutionContext) {}
+
+        fn resolve(&self, _: &Cards, _: &CardType, _: &mut GameState, _: CardInstance, _: &Activation, _: &ResolutionContext) {
+            TARGETED_RESOLUTIONS.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn fizzled_links_do_not_resolve() -> Result<(), InvalidAction> {
+        let cards = Cards::from_test(vec![
+            r#"
+            name = "Counter"
+            defense = 2
+            attack = 1
+            [[effects]]
+                type = "CountedTargetedResolve"
+            "#,
+            r#"
+            name = "Retreat"
+            defense = 2
+            attack = 1
+            [[effects]]
+                type = "OnField"
+                mandatory = false
+                [effects.trigger]
+                    type = "ReturnTargetToHand"
+                    whose = "Controller"
+            "#,
+        ]).unwrap();
+        let play = |respond: bool| -> Result<GameState, InvalidAction> {
+            let mut instances = CardInstances::default();
+            let counter = instances.instantiate(cards.card("Counter").unwrap());
+            let retreat = instances.instantiate(cards.card("Retreat").unwrap());
+            let (counter_id, retreat_id) = (counter.instance, retreat.instance);
+            let mut game = GameState::start((vec![], vec![], vec![], vec![counter]), (vec![], vec![], vec![], vec![retreat]));
+            summon(&mut game, &cards, counter_id, FieldSlot::F0)?;
+            summon(&mut game, &cards, retreat_id, FieldSlot::F0)?;
+            game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+            let activate = |game: &GameState, instance| game.legal_options(&cards)
+                .iter()
+                .find(|option| matches!(option, PlayerOption::Action(Action::Effect { instance: activated, .. }) if *activated == instance))
+                .cloned()
+                .unwrap();
+            game.priorty_player_take_option(&cards, activate(&game, counter_id))?;
+            if respond {
+                game.priorty_player_take_option(&cards, activate(&game, retreat_id))?;
+            }
+            while !game.pending_chain().is_empty() {
+                game.priorty_player_take_option(&cards, PlayerOption::SkipResponse)?;
+            }
+            Ok(game)
+        };
+        let counter = cards.card("Counter").unwrap().id;
+        let game = play(false)?;
+        assert_eq!(TARGETED_RESOLUTIONS.load(Ordering::SeqCst), 1);
+        assert!(game.events().contains(&GameEvent::LinkResolved { link: ChainLinkId(0), player: Player::One, card: counter }));
+        // the retreat leaves the field in response, so the counter's link doesn't resolve at all
+        let game = play(true)?;
+        assert_eq!(TARGETED_RESOLUTIONS.load(Ordering::SeqCst), 1);
+        assert!(game.events().contains(&GameEvent::LinkFizzled { link: ChainLinkId(0), player: Player::One, card: counter }));
+        assert!(!game.events().contains(&GameEvent::LinkResolved { link: ChainLinkId(0), player: Player::One, card: counter }));
+        Ok(())
+    }
+
+    #[test]
+    fn attacks_can_be_chosen_attacker_first() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = keyword_cards();
+        let soldier = instances.instantiate(cards.card("Soldier").unwrap());
+        let lancer = instances.instantiate(cards.card("Lancer").unwrap());
+        let shieldbearer = instances.instantiate(cards.card("Shieldbearer").unwrap());
+        let (soldier_id, lancer_id, shieldbearer_id) = (soldier.instance, lancer.instance, shieldbearer.instance);
+        let mut game = GameState::start(
+            (vec![], vec![], vec![], vec![lancer]),
+            (vec![], vec![], vec![], vec![soldier, shieldbearer]),
+        );
+        summon(&mut game, &cards, lancer_id, FieldSlot::F0)?;
+        summon(&mut game, &cards, soldier_id, FieldSlot::F2)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipAction)?;
+        summon(&mut game, &cards, shieldbearer_id, FieldSlot::B4)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        assert_eq!(game.attackers(&cards), vec![lancer_id]);
+        let targets = game.attack_targets(&cards, lancer_id);
+        assert_eq!(targets, vec![
+            AttackTarget { slot: FieldSlot::F2, instance: soldier_id, guard: false },
+            AttackTarget { slot: FieldSlot::B4, instance: shieldbearer_id, guard: true },
+        ]);
+        assert!(game.attack_targets(&cards, soldier_id).is_empty());
+        game.priorty_player_take_option(&cards, PlayerOption::Action(Action::Attack { instance: lancer_id, target: targets[1].slot }))?;
+        assert_eq!(game.events().last(), Some(&GameEvent::Attacked {
+            player: Player::One,
+            attacker: cards.card("Lancer").unwrap().id,
+            target: cards.card("Shieldbearer").unwrap().id,
+        }));
+        Ok(())
+    }
+
+    #[test]
+    fn piercing_damage_spills_to_the_back_row() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = keyword_cards();
+        let soldier = instances.instantiate(cards.card("Soldier").unwrap());
+        let other_soldier = instances.instantiate(cards.card("Soldier").unwrap());
+        let lancer = instances.instantiate(cards.card("Lancer").unwrap());
+        let (soldier_id, other_soldier_id, lancer_id) = (soldier.instance, other_soldier.instance, lancer.instance);
+        let mut game = GameState::start(
+            (vec![], vec![], vec![], vec![lancer]),
+            (vec![], vec![], vec![], vec![soldier, other_soldier]),
+        );
+        summon(&mut game, &cards, lancer_id, FieldSlot::F0)?;
+        summon(&mut game, &cards, soldier_id, FieldSlot::B3)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipAction)?;
+        summon(&mut game, &cards, other_soldier_id, FieldSlot::F3)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        game.priorty_player_take_option(&cards, PlayerOption::Action(Action::Attack {
+            instance: lancer_id,
+            target: FieldSlot::F3,
+        }))?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipResponse)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipResponse)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        // 5 attack against 3 defense destroys the front soldier and leaves 2 damage on the back
+        // soldier, which is not enough to destroy it so it can still attack
+        assert_eq!(attack_options(game.priority_player_options(&cards)), vec![(soldier_id, FieldSlot::F0)]);
+        Ok(())
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn on_summon_effects_only_activate_when_triggered_by_the_summon() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = Cards::get();
+        let card_type = cards.card("Staple Dragon").unwrap();
+        let card = instances.instantiate(card_type);
+        let instance = card.instance;
+        let mut game = GameState::start((vec![], vec![], vec![], vec![card]), (vec![], vec![], vec![], vec![]));
+        game.priorty_player_take_option(cards, PlayerOption::SkipDraw)?;
+        game.priorty_player_take_option(cards, PlayerOption::Action(Action::Summon {
+            instance,
+            slot: FieldSlot::F4,
+        }))?;
+        let effect = &card_type.effects[0];
+        assert!(effect.can_activate(cards, card_type, &game, instance, &ResolutionContext::default()).is_empty());
+        let context = ResolutionContext {
+            trigger: Some(Trigger::Summoned(instance)),
+            ..ResolutionContext::default()
+        };
+        let activations = effect.can_activate(cards, card_type, &game, instance, &context);
+        assert_eq!(activations, vec![Activation {
+            status: ActivatableType::Mandatory,
+            data: ActivationData::default(),
+        }]);
+        // there's no Dragonification on the field so the dragon destroys itself
+        effect.activate(cards, card_type, &mut game, instance, &activations[0], &context);
+        assert!(game.card_on_field(instance).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn chains_stop_at_the_depth_limit_and_at_unrespondable_links() -> Result<(), InvalidAction> {
+        let cards = Cards::from_test(vec![
+            r#"
+            name = "Bouncer"
+            defense = 2
+            attack = 1
+            [[effects]]
+                type = "OnField"
+                mandatory = false
+                [effects.trigger]
+                    type = "ReturnTargetToHand"
+                    whose = "Opponent"
+            "#,
+            r#"
+            name = "Ambusher"
+            defense = 2
+            attack = 1
+            [[effects]]
+                type = "OnField"
+                mandatory = false
+                [effects.trigger]
+                    type = "Unrespondable"
+                    [effects.trigger.trigger]
+                        type = "ReturnTargetToHand"
+                        whose = "Opponent"
+            "#,
+            r#"
+            name = "Retreat"
+            defense = 2
+            attack = 1
+            [[effects]]
+                type = "OnField"
+                mandatory = false
+                [effects.trigger]
+                    type = "ReturnTargetToHand"
+                    whose = "Controller"
+            "#,
+        ]).unwrap();
+        // player one activates their card's effect, returning the options player two has
+        let responses = |name: &'static str, max_chain: Option<usize>| -> Result<Vec<PlayerOption>, InvalidAction> {
+            let mut instances = CardInstances::default();
+            let card = instances.instantiate(cards.card(name).unwrap());
+            let retreat = instances.instantiate(cards.card("Retreat").unwrap());
+            let (card_id, retreat_id) = (card.instance, retreat.instance);
+            let mut game = GameState::start((vec![], vec![], vec![], vec![card]), (vec![], vec![], vec![], vec![retreat]));
+            game.configure(GameConfig { max_chain, ..GameConfig::default() });
+            summon(&mut game, &cards, card_id, FieldSlot::F0)?;
+            summon(&mut game, &cards, retreat_id, FieldSlot::F0)?;
+            game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+            let option = game.legal_options(&cards)
+                .iter()
+                .find(|option| matches!(option, PlayerOption::Action(Action::Effect { instance, .. }) if *instance == card_id))
+                .cloned()
+                .unwrap();
+            game.priorty_player_take_option(&cards, option)?;
+            assert_eq!(game.priority(), Player::Two);
+            Ok(game.priority_player_options(&cards))
+        };
+        assert!(responses("Bouncer", None)?.len() > 1);
+        assert!(responses("Bouncer", Some(2))?.len() > 1);
+        assert_eq!(responses("Bouncer", Some(1))?, vec![PlayerOption::SkipResponse]);
+        assert_eq!(responses("Ambusher", None)?, vec![PlayerOption::SkipResponse]);
+        assert!(!cards.card("Ambusher").unwrap().effects[0].can_be_responded_to());
+        assert_eq!(
+            cards.card("Ambusher").unwrap().effect_summaries()[0].text,
+            "Return a card on your opponent's field to its owner's hand. This cannot be responded to.",
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn targeted_by_opponent_checks_the_chain_below() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = keyword_cards();
+        let card_type = cards.card("Soldier").unwrap();
+        let soldier = instances.instantiate(card_type);
+        let lancer = instances.instantiate(cards.card("Lancer").unwrap());
+        let (soldier_id, lancer_id) = (soldier.instance, lancer.instance);
+        let mut game = GameState::start((vec![], vec![], vec![], vec![soldier]), (vec![], vec![], vec![], vec![lancer]));
+        summon(&mut game, &cards, soldier_id, FieldSlot::F0)?;
+        summon(&mut game, &cards, lancer_id, FieldSlot::F0)?;
+        let activation = Activation {
+            status: ActivatableType::Can,
+            data: ActivationData::default(),
+        };
+        let targeting = |player| ResolutionContext {
+            chain: vec![ChainLink {
+                player,
+                instance: lancer_id,
+                card: cards.card("Lancer").unwrap().id,
+                effect: CardEffect(0),
+                activation: Activation {
+                    status: ActivatableType::Can,
+                    data: ActivationData {
+                        slot: Some(FieldSlot::F0),
+                        targets: vec![soldier_id],
+                        ..ActivationData::default()
+                    },
+                },
+                on_field: vec![soldier_id],
+            }],
+            ..ResolutionContext::default()
+        };
+        let condition = TargetedByOpponent;
+        assert!(condition.met(&cards, card_type, &game, soldier_id, &activation, &targeting(Player::Two)));
+        assert!(!condition.met(&cards, card_type, &game, soldier_id, &activation, &targeting(Player::One)));
+        assert!(!condition.met(&cards, card_type, &game, soldier_id, &activation, &ResolutionContext::default()));
+        Ok(())
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn mandatory_effects_must_respond_before_passing() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = Cards::get();
+        let card = instances.instantiate(cards.card("Staple Dragon").unwrap());
+        let instance = card.instance;
+        let mut game = GameState::start((vec![], vec![], vec![], vec![card]), (vec![], vec![], vec![], vec![]));
+        game.priorty_player_take_option(cards, PlayerOption::SkipDraw)?;
+        game.priorty_player_take_option(cards, PlayerOption::Action(Action::Summon {
+            instance,
+            slot: FieldSlot::F4,
+        }))?;
+        assert_eq!(game.priority(), Player::Two);
+        game.priorty_player_take_option(cards, PlayerOption::SkipResponse)?;
+        // the dragon's on summon effect is mandatory, so player one cannot pass
+        let effect = Action::Effect {
+            instance,
+            effect_index: CardEffect(0),
+            activation: Activation {
+                status: ActivatableType::Mandatory,
+                data: ActivationData::default(),
+            },
+        };
+        assert_eq!(game.priority_player_options(cards), vec![PlayerOption::Action(effect.clone())]);
+        game.priorty_player_take_option(cards, PlayerOption::Action(effect))?;
+        assert!(game.card_on_field(instance).is_none());
+        game.priorty_player_take_option(cards, PlayerOption::SkipResponse)?;
+        game.priorty_player_take_option(cards, PlayerOption::SkipResponse)?;
+        // after resolution player two gets priority in an open game state
+        assert_eq!(game.priority(), Player::Two);
+        assert_eq!(game.priority_player_options(cards), vec![PlayerOption::SkipDraw]);
+        Ok(())
+    }
+
+    #[test]
+    fn on_field_effects_can_be_activated_as_an_action() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = Cards::from_test(vec![
+            r#"
+            name = "Unstable Golem"
+            defense = 3
+            attack = 3
+            [[effects]]
+                type = "OnField"
+                mandatory = false
+                [effects.trigger]
+                    type = "DestroySelfUnless"
+                    [effects.trigger.condition]
+                        type = "NamedCardOnField"
+                        name = "Golem Forge"
+            "#,
+        ]).unwrap();
+        let card = instances.instantiate(cards.card("Unstable Golem").unwrap());
+        let instance = card.instance;
+        let mut game = GameState::start((vec![], vec![], vec![], vec![card]), (vec![], vec![], vec![], vec![]));
+        summon(&mut game, &cards, instance, FieldSlot::F0)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipAction)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        let activation = Activation {
+            status: ActivatableType::Can,
+            data: ActivationData::default(),
+        };
+        assert!(game.priorty_player_take_option(&cards, PlayerOption::Action(Action::Effect {
+            instance,
+            effect_index: CardEffect(1),
+            activation: activation.clone(),
+        })).is_err());
+        game.priorty_player_take_option(&cards, PlayerOption::Action(Action::Effect {
+            instance,
+            effect_index: CardEffect(0),
+            activation,
+        }))?;
+        assert!(game.card_on_field(instance).is_none());
+        assert_eq!(game.priority_player_options(&cards), vec![PlayerOption::SkipResponse]);
+        Ok(())
+    }
+
+    #[test]
+    fn engine_actions_require_cards_to_be_in_place() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = keyword_cards();
+        let card = instances.instantiate(cards.card("Soldier").unwrap());
+        let instance = card.instance;
+        let mut game = GameState::start((vec![], vec![], vec![], vec![card]), (vec![], vec![], vec![], vec![]));
+        assert!(game.take_action(&cards, EngineAction::ReturnFieldToHand(instance)).is_err());
+        game.take_action(&cards, EngineAction::SummonFromHandToSlot(instance, FieldSlot::B1))?;
+        assert!(game.take_action(&cards, EngineAction::SummonFromHandToSlot(instance, FieldSlot::B2)).is_err());
+        game.take_action(&cards, EngineAction::DamageOnField(instance, 2))?;
+        assert_eq!(game.card_on_field(instance).unwrap().damage, 2);
+        game.take_action(&cards, EngineAction::ReturnFieldToHand(instance))?;
+        // counters are lost when leaving the field
+        assert_eq!(game.card_in_hand(instance).unwrap().damage, 0);
+        Ok(())
+    }
+
+    fn activate_effect_as_action(game: &mut GameState, cards: &Cards, instance: CardInstance, data: ActivationData) -> Result<(), InvalidAction> {
+        game.priorty_player_take_option(cards, PlayerOption::Action(Action::Effect {
+            instance,
+            effect_index: CardEffect(0),
+            activation: Activation {
+                status: ActivatableType::Can,
+                data,
+            },
+        }))?;
+        game.priorty_player_take_option(cards, PlayerOption::SkipResponse)?;
+        game.priorty_player_take_option(cards, PlayerOption::SkipResponse)
+    }
+
+    #[test]
+    fn swapping_field_positions() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = Cards::from_test(vec![
+            r#"
+            name = "Tactician"
+            defense = 3
+            attack = 1
+            [[effects]]
+                type = "OnField"
+                mandatory = false
+                [effects.trigger]
+                    type = "SwapFieldPositions"
+                    preserve_counters = false
+            "#,
+            r#"
+            name = "Soldier"
+            defense = 3
+            attack = 2
+            "#,
+        ]).unwrap();
+        let tactician = instances.instantiate(cards.card("Tactician").unwrap());
+        let soldier = instances.instantiate(cards.card("Soldier").unwrap());
+        let (tactician_id, soldier_id) = (tactician.instance, soldier.instance);
+        let mut game = GameState::start((vec![], vec![], vec![], vec![tactician, soldier]), (vec![], vec![], vec![], vec![]));
+        summon(&mut game, &cards, tactician_id, FieldSlot::B3)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipAction)?;
+        summon(&mut game, &cards, soldier_id, FieldSlot::F3)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipAction)?;
+        game.take_action(&cards, EngineAction::DamageOnField(soldier_id, 2))?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        activate_effect_as_action(&mut game, &cards, tactician_id, ActivationData {
+            targets: vec![soldier_id, tactician_id],
+            ..ActivationData::default()
+        })?;
+        let field = game.field_of(Player::One);
+        assert_eq!(field[FieldSlot::F3].as_ref().map(|card| card.instance), Some(tactician_id));
+        assert_eq!(field[FieldSlot::B3].as_ref().map(|card| (card.instance, card.damage)), Some((soldier_id, 0)));
+        Ok(())
+    }
+
+    #[test]
+    fn milling_stops_when_the_deck_runs_out() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = Cards::from_test(vec![
+            r#"
+            name = "Grinder"
+            defense = 2
+            attack = 0
+            [[effects]]
+                type = "OnField"
+                mandatory = false
+                [effects.trigger]
+                    type = "MillDeck"
+                    whose = "Opponent"
+                    deck_choice = "Either"
+                    count = 3
+                    column = 2
+            "#,
+            r#"
+            name = "Soldier"
+            defense = 3
+            attack = 2
+            "#,
+        ]).unwrap();
+        let grinder = instances.instantiate(cards.card("Grinder").unwrap());
+        let first = instances.instantiate(cards.card("Soldier").unwrap());
+        let second = instances.instantiate(cards.card("Soldier").unwrap());
+        let kept = instances.instantiate(cards.card("Soldier").unwrap());
+        let (grinder_id, first_id, second_id) = (grinder.instance, first.instance, second.instance);
+        let mut game = GameState::start(
+            (vec![], vec![], vec![], vec![grinder]),
+            (vec![first, second], vec![], vec![kept], vec![]),
+        );
+        summon(&mut game, &cards, grinder_id, FieldSlot::B0)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipAction)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        activate_effect_as_action(&mut game, &cards, grinder_id, ActivationData {
+            deck: Some(FaceDownDeck::Left),
+            column: Some(Column(2)),
+            ..ActivationData::default()
+        })?;
+        let field = game.field_of(Player::Two);
+        assert!(field.deck(FaceDownDeck::Left).is_empty());
+        assert_eq!(field.deck(FaceDownDeck::Right).len(), 1);
+        let milled: Vec<CardInstance> = field.destroyed(Column(2)).iter().map(|card| card.instance).collect();
+        assert_eq!(milled, vec![second_id, first_id]);
+        Ok(())
+    }
+
+    #[test]
+    fn bounced_cards_stay_known_to_the_opponent() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = Cards::from_test(vec![
+            r#"
+            name = "Gust"
+            defense = 1
+            attack = 0
+            [[effects]]
+                type = "OnField"
+                mandatory = false
+                [effects.trigger]
+                    type = "ReturnTargetToHand"
+                    whose = "Opponent"
+                    [effects.trigger.filter]
+                        max_attack = 3
+            "#,
+            r#"
+            name = "Soldier"
+            defense = 3
+            attack = 2
+            "#,
+            r#"
+            name = "Lancer"
+            defense = 2
+            attack = 5
+            "#,
+        ]).unwrap();
+        let gust = instances.instantiate(cards.card("Gust").unwrap());
+        let soldier = instances.instantiate(cards.card("Soldier").unwrap());
+        let lancer = instances.instantiate(cards.card("Lancer").unwrap());
+        let hidden = instances.instantiate(cards.card("Soldier").unwrap());
+        let (gust_id, soldier_id, lancer_id, hidden_id) = (gust.instance, soldier.instance, lancer.instance, hidden.instance);
+        let mut game = GameState::start(
+            (vec![], vec![], vec![], vec![gust]),
+            (vec![], vec![], vec![], vec![soldier, lancer, hidden]),
+        );
+        summon(&mut game, &cards, gust_id, FieldSlot::B0)?;
+        summon(&mut game, &cards, soldier_id, FieldSlot::F1)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipAction)?;
+        summon(&mut game, &cards, lancer_id, FieldSlot::F2)?;
+        game.take_action(&cards, EngineAction::DamageOnField(soldier_id, 2))?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        let targets: Vec<Vec<CardInstance>> = game.priority_player_options(&cards).into_iter().filter_map(|option| match option {
+            PlayerOption::Action(Action::Effect { activation, .. }) => Some(activation.data.targets),
+            _ => None,
+        }).collect();
+        // the lancer's attack is too high to pass the filter
+        assert_eq!(targets, vec![vec![soldier_id]]);
+        assert!(!game.knows(Player::One, hidden_id));
+        activate_effect_as_action(&mut game, &cards, gust_id, ActivationData {
+            targets: vec![soldier_id],
+            ..ActivationData::default()
+        })?;
+        assert_eq!(game.card_in_hand(soldier_id).unwrap().damage, 0);
+        assert!(game.knows(Player::One, soldier_id));
+        assert!(!game.knows(Player::One, hidden_id));
+        Ok(())
+    }
+
+    #[test]
+    fn peeking_reveals_the_top_of_the_opponents_deck() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = Cards::from_test(vec![
+            r#"
+            name = "Seer"
+            defense = 1
+            attack = 0
+            [[effects]]
+                type = "OnField"
+                mandatory = false
+                [effects.trigger]
+                    type = "PeekOpponentDeck"
+                    deck = "Right"
+                    count = 2
+            "#,
+            r#"
+            name = "Soldier"
+            defense = 3
+            attack = 2
+            "#,
+        ]).unwrap();
+        let seer = instances.instantiate(cards.card("Seer").unwrap());
+        let seer_id = seer.instance;
+        let deck: Vec<Card> = (0..3).map(|_| instances.instantiate(cards.card("Soldier").unwrap())).collect();
+        let deck_ids: Vec<CardInstance> = deck.iter().map(|card| card.instance).collect();
+        let mut game = GameState::start((vec![], vec![], vec![], vec![seer]), (vec![], vec![], deck, vec![]));
+        summon(&mut game, &cards, seer_id, FieldSlot::B0)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipAction)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        let seen = |game: &GameState| -> Vec<Option<CardInstance>> {
+            game.deck_view(Player::One, Player::Two, FaceDownDeck::Right)
+                .into_iter()
+                .map(|card| card.map(|card| card.instance))
+                .collect()
+        };
+        assert_eq!(seen(&game), vec![None, None, None]);
+        activate_effect_as_action(&mut game, &cards, seer_id, ActivationData {
+            deck: Some(FaceDownDeck::Right),
+            ..ActivationData::default()
+        })?;
+        assert_eq!(seen(&game), vec![None, Some(deck_ids[1]), Some(deck_ids[2])]);
+        // the owner always knows their own deck
+        assert_eq!(game.deck_view(Player::Two, Player::Two, FaceDownDeck::Right).iter().filter(|card| card.is_some()).count(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn retargeting_an_attack_before_it_resolves() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = Cards::from_test(vec![
+            r#"
+            name = "Decoy"
+            defense = 6
+            attack = 0
+            [[effects]]
+                type = "OnField"
+                mandatory = false
+                [effects.trigger]
+                    type = "RetargetAttack"
+            "#,
+            r#"
+            name = "Soldier"
+            defense = 3
+            attack = 2
+            "#,
+        ]).unwrap();
+        let decoy = instances.instantiate(cards.card("Decoy").unwrap());
+        let soldier = instances.instantiate(cards.card("Soldier").unwrap());
+        let attacker = instances.instantiate(cards.card("Soldier").unwrap());
+        let (decoy_id, soldier_id, attacker_id) = (decoy.instance, soldier.instance, attacker.instance);
+        let mut game = GameState::start(
+            (vec![], vec![], vec![], vec![attacker]),
+            (vec![], vec![], vec![], vec![decoy, soldier]),
+        );
+        summon(&mut game, &cards, attacker_id, FieldSlot::F0)?;
+        summon(&mut game, &cards, decoy_id, FieldSlot::B5)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipAction)?;
+        summon(&mut game, &cards, soldier_id, FieldSlot::F1)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        game.priorty_player_take_option(&cards, PlayerOption::Action(Action::Attack {
+            instance: attacker_id,
+            target: FieldSlot::F1,
+        }))?;
+        // the attack is pending until the chain resolves
+        assert_eq!(game.card_on_field(soldier_id).unwrap().damage, 0);
+        // the decoy is the only other card the attack can be redirected to
+        let redirect = Action::Effect {
+            instance: decoy_id,
+            effect_index: CardEffect(0),
+            activation: Activation {
+                status: ActivatableType::Can,
+                data: ActivationData {
+                    targets: vec![decoy_id],
+                    ..ActivationData::default()
+                },
+            },
+        };
+        assert_eq!(game.priority_player_options(&cards), vec![PlayerOption::SkipResponse, PlayerOption::Action(redirect.clone())]);
+        game.priorty_player_take_option(&cards, PlayerOption::Action(redirect))?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipResponse)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipResponse)?;
+        assert_eq!(game.card_on_field(soldier_id).unwrap().damage, 0);
+        assert_eq!(game.card_on_field(decoy_id).unwrap().damage, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn forced_options_are_taken_automatically() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = keyword_cards();
+        let soldier = instances.instantiate(cards.card("Soldier").unwrap());
+        let soldier_id = soldier.instance;
+        let mut game = GameState::start((vec![], vec![], vec![], vec![soldier]), (vec![], vec![], vec![], vec![]));
+        // with nothing to draw the only option is to skip drawing
+        assert_eq!(game.take_forced_options(&cards), 1);
+        assert_eq!(game.open(), GameStateType::Open { phase: Phase::MayTakeAction });
+        game.priorty_player_take_option(&cards, PlayerOption::Action(Action::Summon { instance: soldier_id, slot: FieldSlot::F0 }))?;
+        // neither player has anything to respond with, and then player two can't draw or act
+        assert_eq!(game.take_forced_options(&cards), 2);
+        assert_eq!(game.winner(&cards), Some(Player::One));
+        assert_eq!(game.take_forced_options(&cards), 0);
+        assert_eq!(
+            describe_turn(game.events(), &cards),
+            "P1 had only one option and summoned Soldier to F0; P2 had only one option and passed; \
+            P1 had only one option and passed; the chain resolved"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn drivers_record_how_long_players_think() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = keyword_cards();
+        let soldier = instances.instantiate(cards.card("Soldier").unwrap());
+        let soldier_id = soldier.instance;
+        let game = GameState::start((vec![], vec![], vec![], vec![soldier]), (vec![], vec![], vec![], vec![]));
+        let mut driver = GameDriver::new(game, DriverConfig { auto_forced: true, ..DriverConfig::default() });
+        // skipping the draw is forced, so player one's first decision is what to summon
+        let options = driver.options(&cards);
+        assert!(options.len() > 1);
+        thread::sleep(Duration::from_millis(20));
+        driver.take_option(&cards, PlayerOption::Action(Action::Summon { instance: soldier_id, slot: FieldSlot::F0 }))?;
+        driver.options(&cards);
+        let result = driver.result(&cards).unwrap();
+        assert_eq!(result.result.winner(), Some(Player::One));
+        let times = *result.think_times(Player::One);
+        assert_eq!(times.decisions, 1);
+        assert!(times.max >= Duration::from_millis(20));
+        assert_eq!((times.average(), times.total), (times.max, times.max));
+        assert_eq!(result.think_times(Player::Two).decisions, 0);
+        assert_eq!(driver.replay(&cards, true).header.think_times, Some([times, ThinkTimes::default()]));
+        assert_eq!(driver.replay(&cards, false).header.think_times, None);
+        assert_eq!(driver.replay(&cards, false).events, driver.game().events());
+        Ok(())
+    }
+
+    #[cfg(feature = "tui")]
+    #[test]
+    fn the_terminal_interface_draws_both_boards_and_navigates_options() -> Result<(), InvalidAction> {
+        use crate::tui::{Key, Tui};
+        use ratatui::backend::TestBackend;
+        use crossterm::event::{KeyCode, KeyEvent};
+        use ratatui::Terminal;
+        let cards = keyword_cards();
+        let mut instances = CardInstances::default();
+        let soldier = instances.instantiate(cards.card("Soldier").unwrap());
+        let soldier_id = soldier.instance;
+        let shieldbearer = instances.instantiate(cards.card("Shieldbearer").unwrap());
+        let mut game = GameState::start((vec![], vec![], vec![], vec![soldier]), (vec![], vec![], vec![], vec![shieldbearer]));
+        summon(&mut game, &cards, soldier_id, FieldSlot::F2)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        let mut tui = Tui::new();
+        tui.update(&game, &cards);
+        assert_eq!(tui.log().len(), 1);
+
+        let draw = |tui: &Tui, viewer| {
+            let mut terminal = Terminal::new(TestBackend::new(72, 30)).unwrap();
+            terminal.draw(|frame| tui.render(frame, &game, viewer, &cards)).unwrap();
+            let buffer = terminal.backend().buffer();
+            (0..buffer.area.height)
+                .map(|y| (0..buffer.area.width).map(|x| buffer[(x, y)].symbol()).collect::<String>())
+                .collect::<Vec<_>>()
+        };
+        let screen = draw(&tui, Player::Two);
+        let opponent_front = screen.iter().position(|line| line.contains("Soldier")).unwrap();
+        let hand = screen.iter().position(|line| line.contains("Shieldbe")).unwrap();
+        assert!(opponent_front < hand, "the opponent's board is drawn above the viewer's hand");
+        assert!(screen.iter().any(|line| line.contains("> end turn")));
+        assert!(screen.iter().any(|line| line.contains("  summon Shieldbearer Two:F0")));
+        assert!(screen.iter().any(|line| line.contains("b0       b1")), "both boards show both rows");
+        assert_eq!(screen.iter().filter(|line| line.contains("f0       f1")).count(), 2);
+        // the other player sees their hand as hidden and has no options
+        let other = draw(&tui, Player::One);
+        assert!(other.iter().any(|line| line.contains("waiting for the other player")));
+        assert!(!other.iter().any(|line| line.contains("Shieldbe")));
+
+        let options = game.priority_player_options(&cards).len();
+        assert_eq!(tui.handle_key(Key::Up, options), None);
+        assert_eq!(tui.selected(), options - 1);
+        // the options scroll to keep the selected one on screen
+        assert!(draw(&tui, Player::Two).iter().any(|line| line.contains("> summon Shieldbearer Two:B6")));
+        assert_eq!(tui.handle_key(Key::Down, options), None);
+        assert_eq!(tui.selected(), 0);
+        assert_eq!(tui.handle_key(Key::Down, options), None);
+        assert_eq!(tui.handle_key(Key::Enter, options), Some(1));
+        assert_eq!(tui.handle_key(Key::Enter, 0), None);
+        assert_eq!(Key::from(KeyEvent::from(KeyCode::Down)), Key::Down);
+        assert_eq!(Key::from(KeyEvent::from(KeyCode::Char('q'))), Key::Quit);
+        Ok(())
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn hosted_games_are_counted_in_the_metrics() -> Result<(), InvalidAction> {
+        use crate::metrics::Metrics;
+        let cards = keyword_cards();
+        let metrics = std::sync::Arc::new(Metrics::default());
+        let mut instances = CardInstances::default();
+        let soldier = instances.instantiate(cards.card("Soldier").unwrap());
+        let soldier_id = soldier.instance;
+        let game = GameState::start((vec![], vec![], vec![], vec![soldier]), (vec![], vec![], vec![], vec![]));
+        let mut driver = GameDriver::new(game, DriverConfig::default()).with_metrics(metrics.clone());
+        assert_eq!(metrics.games_active(), 1);
+        driver.options(&cards);
+        driver.take_option(&cards, PlayerOption::SkipDraw)?;
+        driver.options(&cards);
+        driver.take_option(&cards, PlayerOption::Action(Action::Summon { instance: soldier_id, slot: FieldSlot::F0 }))?;
+        assert!(driver.confirm_hash(driver.game().state_hash()));
+        assert!(!driver.confirm_hash(StateHash(0)));
+        driver.disconnected(Player::Two)?;
+        let text = metrics.render();
+        for line in ["card_game_games_active 1", "card_game_actions_total 2", "card_game_decision_seconds_count 2", "card_game_desyncs_total 1", "card_game_disconnects_total 1"].iter() {
+            assert!(text.lines().any(|sample| sample == *line), "{} missing from {}", line, text);
+        }
+        assert!(text.contains("# TYPE card_game_decision_seconds summary"));
+        drop(driver);
+        assert_eq!(metrics.games_active(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn quiet_players_are_pinged_then_disconnected() -> Result<(), InvalidAction> {
+        let start = Instant::now();
+        let config = LivenessConfig { ping_interval: Duration::from_secs(10), timeout: Duration::from_secs(30) };
+        let mut liveness = Liveness::new(config, start);
+        assert!(!liveness.ping_due(start + Duration::from_secs(5)));
+        assert!(liveness.ping_due(start + Duration::from_secs(10)));
+        // only one ping is sent per interval
+        assert!(!liveness.ping_due(start + Duration::from_secs(15)));
+        assert!(liveness.ping_due(start + Duration::from_secs(20)));
+        liveness.heard(start + Duration::from_secs(25));
+        assert!(liveness.is_alive(start + Duration::from_secs(50)));
+        assert!(!liveness.is_alive(start + Duration::from_secs(55)));
+
+        let cards = keyword_cards();
+        let empty = || (vec![], vec![], vec![], vec![]);
+        let config = DriverConfig { forfeit_on_disconnect: true, ..DriverConfig::default() };
+        let mut driver = GameDriver::new(GameState::start(empty(), empty()), config);
+        driver.disconnected(Player::One)?;
+        assert_eq!(driver.disconnected_player(), Some(Player::One));
+        let result = driver.result(&cards).unwrap().result;
+        assert_eq!((result.winner(), result.reason()), (Some(Player::Two), EndReason::Timeout));
+        assert!(driver.disconnected(Player::Two).is_err());
+
+        let mut driver = GameDriver::new(GameState::start(empty(), empty()), DriverConfig::default());
+        driver.disconnected(Player::Two)?;
+        let result = driver.result(&cards).unwrap().result;
+        assert_eq!((result.winner(), result.reason()), (None, EndReason::Aborted));
+        Ok(())
+    }
+
+    #[cfg(feature = "ws")]
+    #[test]
+    fn websockets_carry_text_messages() -> std::io::Result<()> {
+        use crate::net::ws::WebSocket;
+        use std::io::{Read, Write};
+
+        // each read takes one of the chunks, as the client won't send frames until the
+        // handshake is answered
+        struct Stream {
+            input: Vec<Vec<u8>>,
+            output: Vec<u8>,
+        }
+        impl Read for Stream {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                match self.input.first_mut() {
+                    Some(chunk) => {
+                        let read = chunk.as_slice().read(buf)?;
+                        chunk.drain(..read);
+                        if chunk.is_empty() {
+                            self.input.remove(0);
+                        }
+                        Ok(read)
+                    },
+                    None => Ok(0),
+                }
+            }
+        }
+        impl Write for Stream {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.output.write(buf)
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let handshake = b"GET /chat HTTP/1.1\r\nHost: server.example.com\r\nUpgrade: websocket\r\n\
+            Connection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n".to_vec();
+        // a masked "Hello" split across two fragments, with a ping between them
+        let mut frames = vec![0x01, 0x83, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d];
+        frames.extend_from_slice(&[0x89, 0x80, 0, 0, 0, 0]);
+        frames.extend_from_slice(&[0x80, 0x82, 0x37, 0xfa, 0x21, 0x3d, 0x5b, 0x95]);
+        frames.extend_from_slice(&[0x88, 0x80, 0, 0, 0, 0]);
+        let stream = Stream { input: vec![handshake, frames], output: vec![] };
+        let mut socket = WebSocket::accept(stream)?;
+        let response = String::from_utf8_lossy(&socket.get_ref().output).into_owned();
+        assert!(response.lines().any(|line| line.split_once(':').is_some_and(|(name, value)| {
+            name.eq_ignore_ascii_case("Sec-WebSocket-Accept") && value.trim() == "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        })));
+        assert_eq!(socket.receive()?, Some("Hello".to_owned()));
+        socket.send("Hi")?;
+        assert_eq!(socket.receive()?, None);
+        let output = &socket.get_ref().output;
+        // a pong, the text message and the reply to closing
+        assert_eq!(&output[output.len() - 8..], &[0x8A, 0x00, 0x81, 0x02, b'H', b'i', 0x88, 0x00]);
+        Ok(())
+    }
+
+    #[test]
+    fn operators_can_manage_live_games() -> Result<(), AdminError> {
+        let cards = keyword_cards();
+        let mut instances = CardInstances::default();
+        let soldier = instances.instantiate(cards.card("Soldier").unwrap());
+        let mut games = LiveGames::default();
+        let start = |hand| GameDriver::new(
+            GameState::start((vec![], vec![], vec![], hand), (vec![], vec![], vec![], vec![])),
+            DriverConfig::default(),
+        );
+        let first = games.insert(start(vec![soldier]));
+        let second = games.insert(start(vec![]));
+        assert_eq!(games.execute("list".parse()?, &cards)?, "0: One to act\n1: ended (SimultaneousLoss)");
+        let inspected = games.execute("inspect 0".parse()?, &cards)?;
+        assert!(inspected.starts_with("P1's turn, P1 has priority\nP1: 1 in hand"));
+        assert!(!inspected.contains("Soldier"));
+        assert_eq!("adjudicate 0 p2".parse(), Ok(AdminCommand::Adjudicate(first, Some(Player::Two))));
+        games.execute(AdminCommand::Adjudicate(first, None), &cards)?;
+        let result = games.get(first).unwrap().result(&cards).unwrap().result;
+        assert_eq!((result.reason(), result.is_draw()), (EndReason::Adjudicated, true));
+        assert_eq!(games.execute(AdminCommand::End(first), &cards), Err(AdminError::GameOver(first)));
+        assert_eq!(games.execute(AdminCommand::End(GameId(7)), &cards), Err(AdminError::NoSuchGame(GameId(7))));
+        assert!(games.remove(second).is_some());
+        games.execute("broadcast Restarting in 5 minutes".parse()?, &cards)?;
+        assert_eq!(games.notices(), ["Restarting in 5 minutes"]);
+        assert_eq!("reboot".parse::<AdminCommand>(), Err(AdminError::UnknownCommand("reboot".to_owned())));
+        assert!("adjudicate 0 p3".parse::<AdminCommand>().is_err());
+        assert!(authenticate("auth hunter2\n", "hunter2"));
+        assert!(!authenticate("auth hunter3", "hunter2"));
+        assert!(!authenticate("hunter2", "hunter2"));
+        Ok(())
+    }
+
+    #[test]
+    fn rules_docs_follow_the_game_config() {
+        let default = RulesDoc::new(&GameConfig::default());
+        let names = |doc: &RulesDoc, title| doc.section(title).unwrap().rules.iter().map(|rule| rule.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&default, "Keywords"), ["Swift", "Guard", "Piercing", "Undying"]);
+        assert_eq!(names(&default, "Turns"), ["Draw", "Action", "Responses"]);
+        assert!(!names(&default, "Chains").contains(&"Chain limit".to_owned()));
+
+        let config = GameConfig { resources_per_turn: 2, max_chain: Some(3), removal: false, ..GameConfig::default() };
+        let doc = RulesDoc::new(&config);
+        assert_eq!(names(&doc, "Turns")[0], "Resources");
+        let markdown = doc.to_markdown();
+        assert!(markdown.contains("\n## Chains\n\n"));
+        assert!(markdown.contains("- **Chain limit**: A chain may have at most 3 links"));
+        assert!(markdown.contains("gains 2 resource points"));
+        assert!(markdown.contains("Cards can't be removed from the game."));
+        assert!(toml::to_string(&doc).unwrap().contains("title = \"Ending the game\""));
+    }
+
+    #[test]
+    fn typed_ids_convert_and_map_without_reuse() {
+        let mut ids = IdMap::new();
+        let (first, second) = (ids.insert("first"), ids.insert("second"));
+        assert_eq!((first, second), (GameId(0), GameId(1)));
+        assert_eq!(ids.remove(second), Some("second"));
+        assert_eq!(ids.insert("third"), GameId(2));
+        assert_eq!(ids.ids().collect::<Vec<_>>(), vec![GameId(0), GameId(2)]);
+        assert_eq!("2".parse::<GameId>(), Ok(GameId(2)));
+        assert_eq!((GameId(2).to_string(), u64::from(GameId(2))), ("2".to_owned(), 2));
+        assert_eq!(PlayerId::from("Alice"), PlayerId("Alice".to_owned()));
+        assert_eq!(ChainLinkId::from(0).to_string(), "link 1");
+
+        let cards = keyword_cards();
+        let mut instances = CardInstances::default();
+        let soldier = instances.instantiate(cards.card("Soldier").unwrap());
+        let mut game = GameState::start((vec![], vec![], vec![], vec![soldier]), (vec![], vec![], vec![], vec![]));
+        let start = game.event_seq();
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw).unwrap();
+        assert_eq!(game.events_since(start), game.events());
+        assert!(game.events_since(game.event_seq()).is_empty());
+        assert!(game.events_since(EventSeq(100)).is_empty());
+        assert_eq!(game.chain_link(ChainLinkId(0)), None);
+    }
+
+    #[test]
+    fn finished_games_can_be_archived_and_queried() -> Result<(), InvalidAction> {
+        let cards = keyword_cards();
+        let alice = PlayerId("Alice".to_owned());
+        let bob = PlayerId("Bob".to_owned());
+        let carol = PlayerId("Carol".to_owned());
+        let mut games = LiveGames::default();
+        let mut start = |one: &PlayerId, two: &PlayerId, winner| -> Result<_, InvalidAction> {
+            let empty = || (vec![], vec![], vec![], vec![]);
+            let mut driver = GameDriver::new(GameState::start(empty(), empty()), DriverConfig::default());
+            driver.bind(Player::One, one.clone())?;
+            driver.bind(Player::Two, two.clone())?;
+            let id = games.insert(driver);
+            games.execute(AdminCommand::Adjudicate(id, winner), &cards).unwrap();
+            Ok(id)
+        };
+        let first = start(&alice, &bob, Some(Player::One))?;
+        let second = start(&bob, &alice, Some(Player::One))?;
+        let third = start(&alice, &bob, None)?;
+        let other = start(&alice, &carol, Some(Player::Two))?;
+        let soldier = CardInstances::default().instantiate(cards.card("Soldier").unwrap());
+        let unfinished = games.insert(GameDriver::new(
+            GameState::start((vec![], vec![], vec![], vec![soldier]), (vec![], vec![], vec![], vec![])),
+            DriverConfig::default(),
+        ));
+        let mut archive = MemoryArchive::default();
+        for id in [first, second, third, other].iter() {
+            assert!(games.archive(*id, &cards, &mut archive));
+        }
+        assert!(games.get(first).is_none());
+        assert!(!games.archive(unfinished, &cards, &mut archive));
+
+        let ids = |query: &GameQuery| archive.query(query).iter().map(|game| game.id).collect::<Vec<_>>();
+        assert_eq!(ids(&GameQuery { player: Some(carol.clone()), ..GameQuery::default() }), vec![other]);
+        assert_eq!(ids(&GameQuery { outcome: Some(Outcome::WonBy(bob.clone())), ..GameQuery::default() }), vec![second]);
+        assert_eq!(ids(&GameQuery { outcome: Some(Outcome::Drawn), ..GameQuery::default() }), vec![third]);
+        let later = std::time::SystemTime::now() + Duration::from_secs(60);
+        assert!(ids(&GameQuery { since: Some(later), ..GameQuery::default() }).is_empty());
+        assert_eq!(archive.head_to_head(&alice, &bob), HeadToHead { wins: 1, losses: 1, draws: 1 });
+        assert_eq!(archive.head_to_head(&carol, &alice), HeadToHead { wins: 1, losses: 0, draws: 0 });
+        assert_eq!(archive.replay(second).map(|replay| replay.header.think_times.is_some()), Some(true));
+
+        // the same queries answered asynchronously, by a store shared with the server
+        let shared = SharedArchive::new(archive);
+        let dashboard = shared.clone();
+        let query = GameQuery { player: Some(carol.clone()), ..GameQuery::default() };
+        let found = ready(dashboard.query(&query));
+        assert_eq!(found.iter().map(|game| game.id).collect::<Vec<_>>(), vec![other]);
+        assert_eq!(ready(dashboard.head_to_head(&alice, &bob)), HeadToHead { wins: 1, losses: 1, draws: 1 });
+        assert_eq!(ready(dashboard.replay(second)), shared.lock().replay(second).cloned());
+        let mut archived = found[0].clone();
+        archived.id = GameId(99);
+        ready(shared.store(archived));
+        assert!(ready(dashboard.game(GameId(99))).is_some());
+        Ok(())
+    }
+
+    /// The output of a future which never has to wait, without needing an async runtime
+    fn ready<F: std::future::Future>(future: F) -> F::Output {
+        use std::task::{Context, Poll, Waker};
+        match std::pin::pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("the future had to wait"),
+        }
+    }
+
+    #[test]
+    fn unfinished_games_are_checkpointed_on_shutdown_and_resumed_by_a_new_server() -> Result<(), AdminError> {
+        let cards = keyword_cards();
+        let id = |name| cards.card(name).unwrap().id;
+        let deck = Deck { left: vec![id("Soldier"), id("Lancer"), id("Soldier")], center: vec![], right: vec![id("Lancer"), id("Soldier")] };
+        let setup = GameSetup { decks: [deck.clone(), deck], config: GameConfig { starting_hand: 2, ..GameConfig::default() } };
+        let config = DriverConfig { auto_forced: true, rewind: RewindConfig { allow_rewind: true, history: 4 }, ..DriverConfig::default() };
+        let mut driver = GameDriver::from_setup(&cards, setup, config);
+        driver.bind(Player::One, PlayerId("Alice".to_owned())).unwrap();
+        let mut random = sim::Rng::new(7);
+        for _ in 0..6 {
+            let mut options = driver.options(&cards);
+            driver.take_option(&cards, options.swap_remove(random.below(options.len()))).unwrap();
+        }
+        let priority = driver.game().priority();
+        driver.request_rewind(priority).unwrap();
+        assert!(driver.respond_to_rewind(priority.next(), true).unwrap());
+        driver.options(&cards);
+        let (hash, events) = (driver.game().state_hash(), driver.game().events().to_vec());
+
+        let mut games = LiveGames::default();
+        let hosted = games.insert(driver);
+        let unmigratable = games.insert(GameDriver::new(
+            GameState::start((vec![], vec![], vec![], vec![]), (vec![], vec![], vec![], vec![])),
+            DriverConfig::default(),
+        ));
+        games.execute("shutdown".parse()?, &cards)?;
+        assert!(games.shutting_down());
+        let mut store = MemoryArchive::default();
+        let tokens = games.checkpoint_all(&cards, &mut store);
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].0, hosted);
+        assert!(games.get(hosted).is_none());
+        // the game without a setup had already ended, so was archived instead
+        assert!(store.game(unmigratable).is_some());
+
+        let mut restarted = LiveGames::default();
+        let resumed = restarted.resume(&tokens[0].1, &cards, &mut store)?;
+        let game = restarted.get(resumed).unwrap();
+        assert_eq!(game.game().state_hash(), hash);
+        assert_eq!(game.game().events(), &events[..]);
+        assert_eq!(game.identity(Player::One), Some(&PlayerId("Alice".to_owned())));
+        assert!(matches!(restarted.resume(&tokens[0].1, &cards, &mut store), Err(AdminError::NoSuchCheckpoint(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn sessions_save_replays_rematch_and_resume_saved_games() {
+        let cards = keyword_cards();
+        let starter = "name = \"Starter\"\nleft = [\"Soldier\", \"Soldier\"]\nright = [\"Shieldbearer\"]";
+        let decks = Decks::parse(vec![starter], &cards).unwrap();
+        let players = [PlayerId::from("Alice"), PlayerId::from("Bob")];
+        let driver_config = DriverConfig { auto_forced: true, ..DriverConfig::default() };
+        let mut session = Session::new(decks, GameConfig::default(), driver_config, players, MemoryArchive::default());
+        assert_eq!(session.deck_names(), vec!["Starter"]);
+        assert_eq!(session.rematch(&cards).err(), Some(SessionError::NoPreviousGame));
+        assert_eq!(session.start(&cards, ["Starter", "Missing"]).err(), Some(SessionError::UnknownDeck("Missing".to_owned())));
+
+        session.start(&cards, ["Starter", "Starter"]).unwrap();
+        assert_eq!(session.finish(&cards), None);
+        assert_eq!(session.start(&cards, ["Starter", "Starter"]).err(), Some(SessionError::GameInProgress));
+        session.game().unwrap().adjudicate(Some(Player::One)).unwrap();
+        assert_eq!(session.finish(&cards), Some(GameId(0)));
+        assert!(session.store().replay(GameId(0)).is_some());
+
+        let game = session.rematch(&cards).unwrap();
+        let option = game.options(&cards).remove(0);
+        game.take_option(&cards, option).unwrap();
+        let hash = game.game().state_hash();
+        let token = session.save(&cards).unwrap();
+        assert!(session.game().is_none());
+        assert_eq!(session.save(&cards).err(), Some(SessionError::NoGame));
+        assert_eq!(session.resume(&cards, &token).unwrap().game().state_hash(), hash);
+        session.game().unwrap().adjudicate(Some(Player::Two)).unwrap();
+        assert_eq!(session.finish(&cards), Some(GameId(1)));
+        assert_eq!(session.resume(&cards, &token).err(), Some(SessionError::NoSuchCheckpoint(token)));
+
+        assert_eq!(session.record(), HeadToHead { wins: 1, losses: 1, draws: 0 });
+        assert_eq!(session.into_store().games().count(), 2);
+    }
+
+    #[test]
+    fn mismatched_rules_are_reported_in_the_handshake() {
+        let ours = RulesVersion::current();
+        assert_eq!(ours.engine, ENGINE_VERSION);
+        assert_eq!(ours.fingerprint, rules_fingerprint());
+        // every rule of the config is part of it, but not the seed a game is played with
+        let default = GameConfig::default();
+        let formats = [
+            GameConfig { loop_limit: 3, ..GameConfig::default() },
+            GameConfig { removal: false, ..GameConfig::default() },
+            GameConfig { resources_per_turn: 1, ..GameConfig::default() },
+            GameConfig { starting_hand: 5, ..GameConfig::default() },
+            GameConfig { max_chain: Some(2), ..GameConfig::default() },
+            GameConfig { soft_lock: SoftLockPolicy::Skip, ..GameConfig::default() },
+            GameConfig { effect_budget: Some(EffectBudget::default()), ..GameConfig::default() },
+            GameConfig { disabled_packs: vec!["promo".to_owned()], ..GameConfig::default() },
+            GameConfig { mandatory_draw: true, ..GameConfig::default() },
+        ];
+        for format in &formats {
+            assert_ne!(format.fingerprint(), default.fingerprint(), "{:?}", format);
+        }
+        assert_eq!(GameConfig { seed: 7, ..GameConfig::default() }.fingerprint(), default.fingerprint());
+        assert_eq!(RulesVersion::parse(&format!("{}\n", ours)), Some(ours.clone()));
+        assert_eq!(ours.check(&RulesVersion::current()), Ok(()));
+        assert_eq!(RulesVersion::parse("auth abc"), None);
+        assert_eq!(RulesVersion::parse("rules 0.1.0 not-hex"), None);
+        assert_eq!(RulesVersion::parse("rules 0.1.0 ff extra"), None);
+
+        let registered_more = RulesVersion { fingerprint: ours.fingerprint ^ 1, ..ours.clone() };
+        let mismatch = ours.check(&registered_more).unwrap_err();
+        assert!(!mismatch.engine_differs());
+        assert!(mismatch.to_string().contains("rules fingerprint"));
+        let older = RulesVersion::parse(&format!("rules 0.0.1 {:x}", ours.fingerprint)).unwrap();
+        let mismatch = ours.check(&older).unwrap_err();
+        assert!(mismatch.engine_differs());
+        assert_eq!(mismatch.theirs, older);
+        assert!(mismatch.to_string().contains("0.0.1"));
+    }
+
+    #[test]
+    fn only_the_priority_players_identity_can_take_options() -> Result<(), InvalidAction> {
+        let cards = keyword_cards();
+        let mut tokens = StaticTokens::default();
+        tokens.insert("abc", PlayerId("Alice".to_owned()));
+        tokens.insert("xyz", PlayerId("Bob".to_owned()));
+        let alice = tokens.validate(auth_token("auth abc\n").unwrap()).unwrap();
+        let bob = tokens.validate("xyz").unwrap();
+        assert_eq!(tokens.validate("Alice"), None);
+        assert_eq!(AnyToken.validate(""), None);
+
+        let mut instances = CardInstances::default();
+        let soldier = instances.instantiate(cards.card("Soldier").unwrap());
+        let game = GameState::start((vec![soldier], vec![], vec![], vec![]), (vec![], vec![], vec![], vec![]));
+        let mut driver = GameDriver::new(game, DriverConfig::default());
+        driver.bind(Player::One, alice.clone())?;
+        assert!(driver.bind(Player::Two, alice.clone()).is_err());
+        let option = PlayerOption::SkipDraw;
+        assert!(driver.take_option_as(&bob, &cards, option.clone()).is_err());
+        driver.bind(Player::Two, bob.clone())?;
+        assert_eq!(driver.player_of(&bob), Some(Player::Two));
+        assert!(driver.take_option_as(&bob, &cards, option.clone()).is_err());
+        driver.take_option_as(&alice, &cards, option)?;
+        Ok(())
+    }
+
+    #[test]
+    fn connections_are_limited_in_what_they_can_send() {
+        let limits = Limits { max_message: 8, max_targets: 2, burst: 3, per_second: 2 };
+        let start = Instant::now();
+        let mut rate = RateLimiter::new(&limits, start);
+        assert!((0..3).all(|_| rate.allow(start)));
+        assert!(!rate.allow(start));
+        assert!(rate.allow(start + Duration::from_millis(500)));
+        assert!(!rate.allow(start + Duration::from_millis(600)));
+        // idling refills the burst but no further
+        let later = start + Duration::from_secs(60);
+        assert_eq!((0..5).filter(|_| rate.allow(later)).count(), 3);
+
+        let mut reader = std::io::Cursor::new("short\nmuch too long\n");
+        let mut line = String::new();
+        assert_eq!(read_limited_line(&mut reader, &mut line, limits.max_message).unwrap(), 6);
+        assert_eq!(line, "short\n");
+        line.clear();
+        let error = read_limited_line(&mut reader, &mut line, limits.max_message).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+
+        let targets = |count| PlayerOption::Action(Action::Effect {
+            instance: CardInstance(0),
+            effect_index: CardEffect(0),
+            activation: Activation {
+                status: ActivatableType::Can,
+                data: ActivationData { targets: vec![CardInstance(0) ; count], ..ActivationData::default() },
+            },
+        });
+        assert!(limits.allows(&targets(2)));
+        assert!(!limits.allows(&targets(3)));
+        assert!(limits.allows(&PlayerOption::SkipDraw));
+    }
+
+    #[test]
+    fn replays_only_verify_with_the_card_pool_they_were_recorded_with() {
+        let cards = keyword_cards();
+        let empty = || (vec![], vec![], vec![], vec![]);
+        let config = DriverConfig { seed: Some(42), ..DriverConfig::default() };
+        let driver = GameDriver::new(GameState::start(empty(), empty()), config);
+        let replay = driver.replay(&cards, false);
+        assert_eq!((replay.header.seed, replay.header.loop_limit), (Some(42), DEFAULT_LOOP_LIMIT));
+        assert_eq!(replay.verify(&cards), Ok(()));
+        assert_eq!(keyword_cards().fingerprint(), cards.fingerprint());
+
+        let changed = cards.with_overrides(vec![CardTypeBuilder::new("Soldier").attack(2).defense(1).build().unwrap()]);
+        assert!(matches!(replay.verify(&changed), Err(ReplayError::CardPool { .. })));
+
+        let mut old = replay;
+        old.header.engine_version = "0.0.0".to_owned();
+        assert!(matches!(old.verify(&cards), Err(ReplayError::EngineVersion { .. })));
+    }
+
+    #[test]
+    fn errata_keep_old_versions_for_replays() {
+        let cards = keyword_cards();
+        let original = cards.card("Soldier").unwrap().id;
+        let empty = || (vec![], vec![], vec![], vec![]);
+        let replay = GameDriver::new(GameState::start(empty(), empty()), DriverConfig::default()).replay(&cards, false);
+
+        let errata = cards.with_overrides(vec![CardTypeBuilder::new("Soldier").version(2).attack(2).defense(1).build().unwrap()]);
+        assert_eq!(errata.card("Soldier").unwrap().version, 2);
+        assert_eq!(errata.card("Soldier@1").unwrap().id, original);
+        assert_eq!(errata.card(original).unwrap().version, 1);
+        assert_eq!(errata.current().count(), cards.iter().count());
+        assert!(matches!(replay.verify(&errata), Err(ReplayError::CardPool { .. })));
+
+        let pinned = replay.card_pool(&errata).unwrap();
+        assert_eq!(pinned.card("Soldier").unwrap().id, original);
+        assert_eq!(pinned.card("Soldier@2").unwrap().attack, 2);
+
+        let mut missing = replay;
+        missing.header.card_versions.push(("Soldier".to_owned(), 3));
+        assert_eq!(missing.card_pool(&errata).err(), Some(ReplayError::MissingVersion { name: "Soldier".to_owned(), version: 3 }));
+    }
+
+    #[test]
+    fn replays_play_back_what_each_player_knew() -> Result<(), InvalidAction> {
+        let cards = keyword_cards();
+        let mut instances = CardInstances::default();
+        let soldier_type = cards.card("Soldier").unwrap().id;
+        let shieldbearer_type = cards.card("Shieldbearer").unwrap().id;
+        let soldier = instances.instantiate(cards.card("Soldier").unwrap());
+        let soldier_id = soldier.instance;
+        let lancer = instances.instantiate(cards.card("Lancer").unwrap());
+        let shieldbearer = instances.instantiate(cards.card("Shieldbearer").unwrap());
+        let game = GameState::start(
+            (vec![lancer], vec![], vec![], vec![soldier]),
+            (vec![], vec![], vec![], vec![shieldbearer]),
+        );
+        let config = DriverConfig { record_views: true, ..DriverConfig::default() };
+        let mut driver = GameDriver::new(game, config);
+        driver.take_option(&cards, PlayerOption::SkipDraw)?;
+        driver.take_option(&cards, PlayerOption::Action(Action::Summon { instance: soldier_id, slot: FieldSlot::F0 }))?;
+        let replay = driver.replay(&cards, false);
+
+        let two = replay.playback(Player::Two);
+        assert_eq!(two.len(), 2);
+        assert_eq!(two[0].view.field(Player::One).hand, vec![VisibleCard::Hidden]);
+        assert_eq!(two[0].view.field(Player::One).left_deck, vec![VisibleCard::Hidden]);
+        assert_eq!(two[0].view.field(Player::Two).hand, vec![VisibleCard::Known(shieldbearer_type)]);
+        assert!(two[0].view.options.is_empty());
+        assert_eq!(two[1].chose, None);
+        assert!(two[1].events.is_empty());
+
+        let one = replay.playback(Player::One);
+        assert_eq!(one[0].chose, Some(&PlayerOption::SkipDraw));
+        assert!(one[0].view.options.contains(&PlayerOption::SkipDraw));
+        assert_eq!(one[1].view.field(Player::One).hand, vec![VisibleCard::Known(soldier_type)]);
+        assert_eq!(one[1].view.field(Player::Two).hand, vec![VisibleCard::Hidden]);
+        assert_eq!(one[1].view.field(Player::One).hand[0].known(), Some(soldier_type));
+        assert!(VisibleCard::from(None).is_hidden());
+        assert!(driver.game().card_on_field(soldier_id).is_some());
+
+        let unrecorded = GameDriver::new(GameState::start((vec![], vec![], vec![], vec![]), (vec![], vec![], vec![], vec![])), DriverConfig::default());
+        assert!(unrecorded.replay(&cards, false).playback(Player::One).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn replays_can_be_annotated_and_bookmarked() -> Result<(), InvalidAction> {
+        let cards = keyword_cards();
+        let mut instances = CardInstances::default();
+        let soldier = instances.instantiate(cards.card("Soldier").unwrap());
+        let soldier_id = soldier.instance;
+        let game = GameState::start((vec![], vec![], vec![], vec![soldier]), (vec![], vec![], vec![], vec![]));
+        let mut driver = GameDriver::new(game, DriverConfig { record_views: true, ..DriverConfig::default() });
+        driver.take_option(&cards, PlayerOption::SkipDraw)?;
+        driver.take_option(&cards, PlayerOption::Action(Action::Summon { instance: soldier_id, slot: FieldSlot::F0 }))?;
+        driver.take_option(&cards, PlayerOption::SkipResponse)?;
+        let mut replay = driver.replay(&cards, false);
+        let end = EventSeq::from(replay.events.len());
+        let summoned = replay.decisions[2].events;
+        assert!(summoned > EventSeq(0));
+
+        replay.annotate(summoned, "should have held the Soldier back").unwrap();
+        replay.bookmark(EventSeq(0), "start").unwrap();
+        replay.annotate(EventSeq(0), "opening").unwrap();
+        assert_eq!(
+            replay.annotate(EventSeq(end.0 + 1), "too late").unwrap_err(),
+            ReplayError::NoSuchPosition { seq: EventSeq(end.0 + 1), events: replay.events.len() },
+        );
+        // kept in order of position, then of when they were added
+        let texts = replay.annotations.iter().map(|annotation| annotation.text.as_str()).collect::<Vec<_>>();
+        assert_eq!(texts, ["start", "opening", "should have held the Soldier back"]);
+        assert_eq!(replay.bookmarks().map(|bookmark| bookmark.seq).collect::<Vec<_>>(), [EventSeq(0)]);
+        assert_eq!(replay.annotations_at(summoned).map(|annotation| annotation.kind).collect::<Vec<_>>(), [AnnotationKind::Note]);
+
+        // saved replays keep their annotations
+        let path = std::env::temp_dir().join(format!("card-game-replay-{}.json", std::process::id()));
+        replay.save(std::fs::File::create(&path).unwrap()).unwrap();
+        let loaded = Replay::load(std::fs::File::open(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, replay);
+        let texts = loaded.annotations.iter().map(|annotation| annotation.text.as_str()).collect::<Vec<_>>();
+        assert_eq!(texts, ["start", "opening", "should have held the Soldier back"]);
+        assert_eq!(loaded.bookmarks().map(|bookmark| bookmark.seq).collect::<Vec<_>>(), [EventSeq(0)]);
+        assert!(Replay::load(&b"{}"[..]).is_err());
+
+        // playback shows each annotation at the first moment at or after its position
+        let moments = replay.playback(Player::One);
+        let texts = |moment: &Moment<'_>| moment.annotations.iter().map(|annotation| annotation.text.clone()).collect::<Vec<_>>();
+        assert_eq!(texts(&moments[0]), ["start", "opening"]);
+        assert!(moments[1].annotations.is_empty());
+        assert_eq!(texts(&moments[2]), ["should have held the Soldier back"]);
+        Ok(())
+    }
+
+    #[test]
+    fn options_are_annotated_with_what_they_touch() -> Result<(), InvalidAction> {
+        let cards = keyword_cards();
+        let mut instances = CardInstances::default();
+        let soldier = instances.instantiate(cards.card("Soldier").unwrap());
+        let soldier_id = soldier.instance;
+        let mut game = GameState::start((vec![], vec![], vec![], vec![soldier]), (vec![], vec![], vec![], vec![]));
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        let view = PlayerView::of(&game, Player::One, &cards);
+        assert_eq!(view.options.len(), view.annotations.len());
+        let summon = PlayerOption::Action(Action::Summon { instance: soldier_id, slot: FieldSlot::B3 });
+        let index = view.options.iter().position(|option| *option == summon).unwrap();
+        assert_eq!(view.annotations[index], OptionAnnotation {
+            source: Some(soldier_id),
+            slots: vec![(Player::One, FieldSlot::B3)],
+            verb: "summon",
+        });
+        assert_eq!(OptionAnnotation::of(&game, &PlayerOption::SkipAction).verb, "end turn");
+
+        game.take_action(&cards, EngineAction::SummonFromHandToSlot(soldier_id, FieldSlot::F1))?;
+        let attack = OptionAnnotation::of(&game, &PlayerOption::Action(Action::Attack { instance: soldier_id, target: FieldSlot::F5 }));
+        assert_eq!(attack.slots, vec![(Player::One, FieldSlot::F1), (Player::Two, FieldSlot::F5)]);
+        Ok(())
+    }
+
+    #[test]
+    fn both_players_can_agree_to_rewind() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = keyword_cards();
+        let soldier = instances.instantiate(cards.card("Soldier").unwrap());
+        let soldier_id = soldier.instance;
+        let game = GameState::start((vec![], vec![], vec![], vec![soldier]), (vec![], vec![], vec![], vec![]));
+        let mut casual = RewindableGame::new(game, RewindConfig { allow_rewind: true, history: 2 });
+        assert!(casual.request_rewind(Player::One).is_err());
+        casual.take_option(&cards, PlayerOption::SkipDraw)?;
+        casual.take_option(&cards, PlayerOption::Action(Action::Summon { instance: soldier_id, slot: FieldSlot::F0 }))?;
+        assert!(casual.game().card_on_field(soldier_id).is_some());
+        casual.request_rewind(Player::One)?;
+        // only the opponent can agree
+        assert!(casual.respond_to_rewind(Player::One, true).is_err());
+        assert!(!casual.respond_to_rewind(Player::Two, false)?);
+        casual.request_rewind(Player::One)?;
+        assert!(casual.respond_to_rewind(Player::Two, true)?);
+        assert!(casual.game().card_in_hand(soldier_id).is_some());
+        assert_eq!(
+            describe_turn(casual.game().events(), &cards),
+            "P1 summoned Soldier to F0 and rewound one action"
+        );
+        casual.take_option(&cards, PlayerOption::Action(Action::Summon { instance: soldier_id, slot: FieldSlot::B0 }))?;
+        assert!(casual.game().card_on_field(soldier_id).is_some());
+
+        // rewinding is opt in
+        let soldier = instances.instantiate(cards.card("Soldier").unwrap());
+        let game = GameState::start((vec![], vec![], vec![], vec![soldier]), (vec![], vec![], vec![], vec![]));
+        let mut ranked = RewindableGame::new(game, RewindConfig::default());
+        ranked.take_option(&cards, PlayerOption::SkipDraw)?;
+        assert!(ranked.request_rewind(Player::One).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn card_pools_can_be_overridden_for_playtesting() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = keyword_cards();
+        let nerfed = cards.with_overrides(vec![
+            CardTypeBuilder::new("Lancer").attack(3).defense(2).build().unwrap(),
+            CardTypeBuilder::new("Archer").attack(1).defense(1).build().unwrap(),
+        ]);
+        let lancer = cards.card("Lancer").unwrap();
+        assert_eq!((lancer.attack, nerfed.card("Lancer").unwrap().attack), (5, 3));
+        assert_eq!(nerfed.card("Lancer").unwrap().id, lancer.id);
+        assert_eq!(nerfed.card("Archer").unwrap().id, CardTypeIdentifier(3));
+        assert!(cards.card("Archer").is_none());
+        // a game using the overridden pool sees the nerf, and games using the original don't
+        let lancer = instances.instantiate(lancer);
+        let lancer_id = lancer.instance;
+        let mut game = GameState::start((vec![], vec![], vec![], vec![lancer]), (vec![], vec![], vec![], vec![]));
+        summon(&mut game, &nerfed, lancer_id, FieldSlot::F0)?;
+        assert_eq!(game.field_of(Player::One).attack_of(&nerfed, FieldSlot::F0), Some(3));
+        assert_eq!(game.field_of(Player::One).attack_of(&cards, FieldSlot::F0), Some(5));
+        Ok(())
+    }
+
+    #[test]
+    fn designed_cards_round_trip_through_toml() {
+        let titan = CardTypeBuilder::new("Titan")
+            .attack(8)
+            .defense(8)
+            .keyword(Keyword::Guard)
+            .in_center_deck(card_type::SacrificeToSummon { sacrifices: 2 })
+            .on_summon(true, card_type::MillDeck {
+                whose: card_type::Whose::Opponent,
+                deck_choice: card_type::DeckChoice::Either,
+                count: 3,
+                column: None,
+            })
+            .build()
+            .unwrap();
+        let toml = designer::to_toml(&titan).unwrap();
+        assert!(toml.starts_with("attack = 8\ndefense = 8\nformat = 2\nkeywords = [\"Guard\"]\nname = \"Titan\"\n"));
+        let cards = Cards::from_test(vec![&toml]).unwrap();
+        let loaded = cards.card("Titan").unwrap();
+        assert!(cards.warnings().is_empty());
+        assert_eq!(loaded.effect_summaries(), titan.effect_summaries());
+        assert_eq!(designer::to_toml(loaded).unwrap(), toml);
+
+        let errors = CardTypeBuilder::new(" ")
+            .keyword(Keyword::Swift)
+            .keyword(Keyword::Swift)
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(errors, vec![DesignError::EmptyName, DesignError::NoDefense, DesignError::DuplicateKeyword(Keyword::Swift)]);
+    }
+
+    #[test]
+    fn registered_effect_types_can_be_listed() {
+        let types = effects::registered_types();
+        let mill = types.iter().find(|info| info.name == "MillDeck").unwrap();
+        assert_eq!(mill.kind, TypeKind::Trigger);
+        assert_eq!(mill.fields.iter().filter(|field| !field.optional).count(), 3);
+        // types registered outside the engine are listed too, without their fields
+        let counted = types.iter().find(|info| info.name == "CountedCheck").unwrap();
+        assert_eq!((counted.kind, counted.fields.len()), (TypeKind::Effect, 0));
+        let conditions: Vec<&str> = types.iter()
+            .filter(|info| info.kind == TypeKind::Condition)
+            .map(|info| info.name)
+            .collect();
+        assert_eq!(conditions, vec!["HasResources", "NamedCardInColumn", "NamedCardOnField", "NamedCardRemoved", "TargetedByOpponent", "ZoneCount"]);
+    }
+
+    #[test]
+    fn old_card_formats_load_with_warnings() {
+        let cards = Cards::from_test(vec![
+            r#"
+            name = "Old Dragon"
+            atk = 6
+            def = 5
+            [effect]
+                type = "OnField"
+                mandatory = false
+                [effect.trigger]
+                    type = "RetargetAttack"
+            "#,
+            r#"
+            format = 2
+            name = "New Dragon"
+            attack = 6
+            defense = 5
+            "#,
+        ]).unwrap();
+        let old = cards.card("Old Dragon").unwrap();
+        assert_eq!((old.attack, old.defense, old.effects.len()), (6, 5, 1));
+        let warnings: Vec<String> = cards.warnings().iter().map(|warning| warning.to_string()).collect();
+        assert_eq!(warnings, vec![
+            "Old Dragon: `effect` is deprecated, use an `[[effects]]` array",
+            "Old Dragon: `atk` is deprecated, use `attack`",
+            "Old Dragon: `def` is deprecated, use `defense`",
+        ]);
+        assert!(Cards::from_test(vec![r#"
+            format = 3
+            name = "Future Dragon"
+            attack = 6
+            defense = 5
+        "#]).is_err());
+    }
+
+    #[test]
+    fn card_effects_can_be_summarised_for_display() {
+        let cards = Cards::from_test(vec![
+            r#"
+            name = "Titan"
+            defense = 8
+            attack = 8
+            [[effects]]
+                type = "InCenterDeck"
+                [effects.trigger]
+                    type = "SacrificeToSummon"
+                    sacrifices = 2
+            [[effects]]
+                type = "OnSummon"
+                mandatory = true
+                [effects.trigger]
+                    type = "MillDeck"
+                    whose = "Opponent"
+                    deck_choice = "Either"
+                    count = 3
+            [[effects]]
+                type = "OnField"
+                mandatory = false
+                [effects.trigger]
+                    type = "ReturnTargetToHand"
+                    whose = "Opponent"
+                    filter = { max_attack = 2 }
+            "#,
+        ]).unwrap();
+        let summaries = cards.card("Titan").unwrap().effect_summaries();
+        assert_eq!(summaries, vec![
+            EffectSummary {
+                kind: TriggerKind::InCenterDeck,
+                timing: TriggerTiming::Action,
+                mandatory: false,
+                cost: Some("Destroy 2 cards on your field.".to_owned()),
+                text: "Summon this card from your center deck.".to_owned(),
+            },
+            EffectSummary {
+                kind: TriggerKind::OnSummon,
+                timing: TriggerTiming::OnSummon,
+                mandatory: true,
+                cost: None,
+                text: "Send the top 3 cards of either of your opponent's decks to a destroyed pile.".to_owned(),
+            },
+            EffectSummary {
+                kind: TriggerKind::OnField,
+                timing: TriggerTiming::Priority,
+                mandatory: false,
+                cost: None,
+                text: "Return a card with at most 2 attack on your opponent's field to its owner's hand.".to_owned(),
+            },
+        ]);
+    }
+
+    #[test]
+    fn decks_can_be_analyzed_for_deck_builders() {
+        let cards = keyword_cards().with_overrides(vec![
+            CardTypeBuilder::new("Titan").attack(8).defense(8).in_center_deck(card_type::SacrificeToSummon { sacrifices: 2 }).build().unwrap(),
+            CardTypeBuilder::new("Imp").attack(1).defense(1).in_center_deck(card_type::SacrificeToSummon { sacrifices: 0 }).build().unwrap(),
+        ]);
+        let id = |name| cards.card(name).unwrap().id;
+        assert_eq!(cards.card("Titan").unwrap().summon_cost(), Some(2));
+        assert_eq!(cards.card("Soldier").unwrap().summon_cost(), None);
+        let deck = Deck {
+            left: vec![id("Shieldbearer")],
+            center: vec![id("Titan"), id("Soldier"), id("Titan"), id("Imp")],
+            right: vec![],
+        };
+        let stats = deck.analyze(&cards);
+        assert_eq!((stats.cards, stats.zero_cost), (5, 3));
+        assert_eq!(stats.cost_curve, vec![3, 0, 2]);
+        assert_eq!((stats.average_attack, stats.average_defense), (4.0, 4.8));
+        assert_eq!(stats.keywords, vec![(Keyword::Guard, 1)]);
+        assert_eq!(stats.warnings, vec![
+            DeckWarning::NoSacrificeFodder { card: id("Titan"), sacrifices: 2 },
+            DeckWarning::UnsummonableInCenter(id("Soldier")),
+            DeckWarning::FreeCardInCenter(id("Imp")),
+        ]);
+
+        let unknown = Deck { center: vec![CardTypeIdentifier(99)], ..Deck::default() };
+        assert_eq!(unknown.analyze(&cards).warnings, vec![
+            DeckWarning::UnknownCard(CardTypeIdentifier(99)),
+            DeckWarning::NothingToDraw,
+        ]);
+    }
+
+    #[test]
+    fn formats_can_change_starting_hands_and_deck_limits() {
+        let cards = keyword_cards();
+        let id = |name| cards.card(name).unwrap().id;
+        let deck = Deck {
+            left: vec![id("Soldier"), id("Soldier"), id("Lancer")],
+            center: vec![],
+            right: vec![id("Soldier")],
+        };
+        assert!(deck.analyze(&cards).warnings.is_empty());
+        let casual = DeckLimits { min_side: 2, max_side: 30, max_center: 0, max_copies: Some(2) };
+        assert_eq!(deck.analyze_with(&cards, &casual).warnings, vec![
+            DeckWarning::SideDeckSize { deck: FaceDownDeck::Right, size: 1, min: 2, max: 30 },
+            DeckWarning::TooManyCopies { card: id("Soldier"), copies: 3, max: 2 },
+        ]);
+
+        let mut instances = CardInstances::default();
+        let config = GameConfig { starting_hand: 3, ..GameConfig::default() };
+        let game = GameState::start_with(
+            config.clone(),
+            deck.instantiate(&cards, &mut instances),
+            deck.instantiate(&cards, &mut instances),
+        );
+        for player in Player::all() {
+            let field = game.field_of(player);
+            // the right deck runs out after one card, so the rest comes from the left
+            assert_eq!(field.hand_size(), 3);
+            assert_eq!(field.deck_sizes(), DeckSizes { left: 1, center: 0, right: 0 });
+        }
+        assert_eq!(game.config(), &config);
+    }
+
+    #[test]
+    fn tutorials_only_offer_the_scripted_moves() -> Result<(), TutorialError> {
+        let cards = keyword_cards();
+        let start = || {
+            let soldier = CardInstances::default().instantiate(cards.card("Soldier").unwrap());
+            let instance = soldier.instance;
+            (GameState::start((vec![], vec![], vec![], vec![soldier]), (vec![], vec![], vec![], vec![])), instance)
+        };
+        let (game, soldier) = start();
+        let mut tutorial = TutorialGame::new(game, vec![
+            Step::only(PlayerOption::SkipDraw).message("You have nothing to draw, so skip drawing."),
+            Step::allowing(|option| matches!(option, PlayerOption::Action(Action::Summon { slot: FieldSlot::F3, .. })))
+                .message("Summon your soldier to the middle of the front row.")
+                .expect(move |game| game.field_of(Player::One).slot_of(soldier) == Some(FieldSlot::F3)),
+        ]);
+        assert_eq!(tutorial.options(&cards), vec![PlayerOption::SkipDraw]);
+        tutorial.take_option(&cards, PlayerOption::SkipDraw)?;
+        let summon = |slot| PlayerOption::Action(Action::Summon { instance: soldier, slot });
+        assert_eq!(tutorial.options(&cards), vec![summon(FieldSlot::F3)]);
+        assert!(matches!(tutorial.take_option(&cards, summon(FieldSlot::F0)), Err(TutorialError::NotAllowed)));
+        tutorial.take_option(&cards, summon(FieldSlot::F3))?;
+        assert!(tutorial.finished());
+        assert_eq!(
+            describe_turn(tutorial.game().events(), &cards),
+            "You have nothing to draw, so skip drawing.; Summon your soldier to the middle of the front row.; P1 summoned Soldier to F3"
+        );
+
+        let (game, _) = start();
+        let mut broken = TutorialGame::new(game, vec![Step::any().expect(|_| false)]);
+        assert!(matches!(broken.take_option(&cards, PlayerOption::SkipDraw), Err(TutorialError::Unexpected { step: 0 })));
+        assert_eq!(broken.game().open(), GameStateType::Open { phase: Phase::MayDraw });
+        assert_eq!(broken.step_index(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn card_pools_can_be_loaded_without_a_file_system() {
+        let source = MemorySource {
+            files: vec![
+                "name = \"Soldier\"\nversion = 2\ndefense = 4\nattack = 2".to_owned(),
+                "name = \"Soldier\"\ndefense = 3\nattack = 2".to_owned(),
+            ],
+        };
+        let cards = Cards::from_source(source).unwrap();
+        // the original still comes first, so it keeps its identifier
+        assert_eq!(cards.card(CardTypeIdentifier(0)).map(|card_type| card_type.version), Some(1));
+        assert_eq!(cards.card("Soldier").map(|card_type| card_type.defense), Some(4));
+        let broken = MemorySource { files: vec!["name = ".to_owned()] };
+        assert!(Cards::from_source(broken).is_err());
+        let embedded = EmbeddedSource(&[b"name = \"Soldier\"\ndefense = 3\nattack = 2"]);
+        assert_eq!(Cards::from_source(embedded).unwrap().card("Soldier").map(|card_type| card_type.defense), Some(3));
+    }
+
+    #[test]
+    #[cfg(feature = "http")]
+    fn card_pools_can_be_fetched_over_http() {
+        use std::io::{Read, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = std::thread::spawn(move || {
+            let huge = "#".repeat(1 << 20);
+            for body in ["name = \"Soldier\"\ndefense = 3\nattack = 2", "missing", &huge].iter() {
+                let (mut stream, _) = listener.accept().unwrap();
+                // closing with some of the request unread would reset the connection
+                let mut request = vec![];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let mut byte = [0];
+                    stream.read_exact(&mut byte).unwrap();
+                    request.push(byte[0]);
+                }
+                let status = if *body == "missing" { "404 Not Found" } else { "200 OK" };
+                // the client stops reading responses which are too long, so this may fail
+                let _ = write!(stream, "HTTP/1.0 {}\r\nContent-Type: text/plain\r\n\r\n{}", status, body);
+            }
+        });
+        let cards = Cards::from_source(HttpSource { address: address.clone(), paths: vec!["/soldier.toml".to_owned()] }).unwrap();
+        assert_eq!(cards.card("Soldier").map(|card_type| card_type.defense), Some(3));
+        assert!(Cards::from_source(HttpSource { address: address.clone(), paths: vec!["/missing.toml".to_owned()] }).is_err());
+        let error = Cards::from_source(HttpSource { address, paths: vec!["/huge.toml".to_owned()] }).err().unwrap();
+        assert!(error.to_string().contains("is over"));
+        server.join().unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "sync")]
+    fn card_pools_sync_from_signed_manifests() {
+        struct Server(std::sync::Mutex<(std::collections::HashMap<String, String>, Vec<String>)>);
+        impl Remote for Server {
+            fn fetch(&self, path: &str) -> Result<String, Box<dyn std::error::Error>> {
+                let mut server = self.0.lock().unwrap();
+                server.1.push(path.to_owned());
+                server.0.get(path).cloned().ok_or_else(|| "not found".into())
+            }
+        }
+        let secret_key = SigningKey::from_bytes(&[9; 32]);
+        let public_key = secret_key.verifying_key();
+        let soldier = "name = \"Soldier\"\ndefense = 3\nattack = 2";
+        let archer = "name = \"Archer\"\ndefense = 1\nattack = 2";
+        let publish = |server: &Server, files: &[(&str, &str)], secret_key: &SigningKey| {
+            let manifest = Manifest::of(files.iter().cloned()).to_toml();
+            let mut server = server.0.lock().unwrap();
+            server.0.insert("/manifest.toml.sig".to_owned(), sign_manifest(secret_key, &manifest));
+            server.0.insert("/manifest.toml".to_owned(), manifest);
+            for (path, contents) in files {
+                server.0.insert(path.to_string(), contents.to_string());
+            }
+            server.1.clear();
+        };
+        let server = Server(Default::default());
+        publish(&server, &[("/soldier.toml", soldier), ("/archer.toml", archer)], &secret_key);
+        let mut sync = CardSync::new("/manifest.toml", public_key);
+        let cards = sync.sync(&server).unwrap();
+        assert_eq!(cards.card("Soldier").map(|card_type| card_type.defense), Some(3));
+        assert_eq!(sync.files().len(), 2);
+
+        // a balance patch only fetches the card which changed
+        publish(&server, &[("/soldier.toml", &soldier.replace("defense = 3", "defense = 4")), ("/archer.toml", archer)], &secret_key);
+        let cards = sync.sync(&server).unwrap();
+        assert_eq!(cards.card("Soldier").map(|card_type| card_type.defense), Some(4));
+        assert_eq!(server.0.lock().unwrap().1, vec!["/manifest.toml", "/manifest.toml.sig", "/soldier.toml"]);
+
+        // files which don't match the signed manifest are refused, keeping the last sync
+        publish(&server, &[("/soldier.toml", soldier), ("/archer.toml", archer)], &secret_key);
+        server.0.lock().unwrap().0.insert("/soldier.toml".to_owned(), soldier.replace("attack = 2", "attack = 9"));
+        assert!(matches!(sync.sync(&server), Err(SyncError::HashMismatch { ref path }) if path == "/soldier.toml"));
+        publish(&server, &[("/soldier.toml", soldier)], &SigningKey::from_bytes(&[7; 32]));
+        assert!(matches!(sync.sync(&server), Err(SyncError::BadSignature)));
+        assert!(sync.files()[0].contents.contains("defense = 4"));
+    }
+
+    #[test]
+    fn card_names_are_searched_by_prefix_substring_typo_and_tag() {
+        let cards = Cards::from_test(vec![
+            "name = \"Staple Dragon\"\ndefense = 5\nattack = 6\ntags = [\"dragon\"]",
+            "name = \"Dragon Whelp\"\ndefense = 1\nattack = 1\ntags = [\"dragon\"]",
+            "name = \"Elder Dragon\"\ndefense = 8\nattack = 8\ntags = [\"dragon\", \"legend\"]",
+            "name = \"Dragonfly\"\ndefense = 1\nattack = 1",
+            "name = \"Staple Mirage\"\ndefense = 2\nattack = 2",
+        ]).unwrap();
+        let names = |query| cards.search(query).iter().map(|card_type| card_type.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names("dragon whelp")[0], "Dragon Whelp");
+        assert_eq!(names("drag"), vec!["Dragon Whelp", "Dragonfly", "Elder Dragon", "Staple Dragon"]);
+        assert_eq!(names("RAGON"), vec!["Dragon Whelp", "Dragonfly", "Elder Dragon", "Staple Dragon"]);
+        assert_eq!(names("stapel dragon")[0], "Staple Dragon");
+        assert_eq!(names("drag tag:dragon"), vec!["Dragon Whelp", "Elder Dragon", "Staple Dragon"]);
+        assert_eq!(names("tag:dragon tag:legend"), vec!["Elder Dragon"]);
+        assert_eq!(names("tag:dragon").len(), 3);
+        assert!(names("xyzzy").is_empty());
+    }
+
+    #[test]
+    fn card_pools_index_names_and_tags() {
+        let cards = keyword_cards();
+        let soldier = cards.card("Soldier").unwrap().id;
+        assert_eq!(cards.ids_named("Soldier"), [soldier]);
+        assert!(cards.ids_named("Archer").is_empty());
+
+        let changed = cards.with_overrides(vec![
+            CardTypeBuilder::new("Soldier").version(2).attack(2).defense(4).build().unwrap(),
+            CardTypeBuilder::new("Archer").attack(2).defense(1).tag("ranged").build().unwrap(),
+        ]);
+        let errata = changed.card("Soldier").unwrap().id;
+        let archer = changed.card("Archer").unwrap().id;
+        assert_eq!(changed.ids_named("Soldier"), [soldier, errata]);
+        assert_eq!(changed.tagged("ranged"), [archer]);
+        assert!(cards.tagged("ranged").is_empty());
+        // a card of either version has the name
+        let mut instances = CardInstances::default();
+        for id in [soldier, errata].iter() {
+            assert!(instances.instantiate(changed.card(*id).unwrap()).has_name(&changed, "Soldier"));
+        }
+        let pinned = changed.with_versions(&[("Soldier".to_owned(), 1)]).unwrap();
+        assert_eq!(pinned.card("Soldier").unwrap().id, soldier);
+
+        let packed = changed.load_packs(&[PackSource::new("scouts", MemorySource {
+            files: vec!["name = \"Archer\"\ndefense = 1\nattack = 1\ntags = [\"ranged\"]".to_owned()],
+        })]).unwrap();
+        let scout = packed.card("scouts:Archer").unwrap().id;
+        assert_eq!(packed.ids_named("scouts:Archer"), [scout]);
+        assert_eq!(packed.tagged("ranged"), [archer, scout]);
+    }
+
+    #[test]
+    fn decks_are_imported_from_plain_text_lists() {
+        let cards = keyword_cards();
+        let id = |name| cards.card(name).unwrap().id;
+        let (soldier, shieldbearer) = (id("Soldier"), id("Shieldbearer"));
+        let imported = Deck::from_text("
+            # read from the top of each deck down
+            3x Soldier @left
+            shieldbearer @Right
+            2 x Soldeir @right:top
+            1x Shieldbearer @left:top
+        ", &cards).unwrap();
+        assert_eq!(imported.deck, Deck {
+            left: vec![soldier, soldier, soldier, shieldbearer],
+            center: vec![],
+            right: vec![shieldbearer, soldier, soldier],
+        });
+        assert_eq!(imported.corrections, vec![
+            NameCorrection { line: 4, written: "shieldbearer".to_owned(), card: "Shieldbearer".to_owned() },
+            NameCorrection { line: 5, written: "Soldeir".to_owned(), card: "Soldier".to_owned() },
+        ]);
+        assert!(imported.warnings.is_empty());
+
+        let errors = Deck::from_text("0x Soldier @left\nSoldier\nSoldier @middle\nSoldier @left:side\n2x Sholdbarer @right\nDragon @left", &cards).unwrap_err();
+        let problems: Vec<_> = errors.iter().map(|error| (error.line, error.problem.clone())).collect();
+        assert_eq!(problems, vec![
+            (1, DecklistProblem::BadCount("0".to_owned())),
+            (2, DecklistProblem::MissingZone),
+            (3, DecklistProblem::UnknownZone("middle".to_owned())),
+            (4, DecklistProblem::UnknownPosition("side".to_owned())),
+            (5, DecklistProblem::UnknownCard { name: "Sholdbarer".to_owned(), suggestion: Some("Shieldbearer".to_owned()) }),
+            (6, DecklistProblem::UnknownCard { name: "Dragon".to_owned(), suggestion: None }),
+        ]);
+        assert_eq!(errors[4].to_string(), "line 5: no card named \"Sholdbarer\", did you mean \"Shieldbearer\"?");
+    }
+
+    #[test]
+    fn themes_style_cards_by_name_then_tag() {
+        let cards = Cards::from_test(vec![
+            "name = \"Knight\"\ndefense = 3\nattack = 2\ntags = [\"human\", \"armored\"]",
+            "name = \"Squire\"\ndefense = 1\nattack = 1\ntags = [\"human\"]",
+            "name = \"Slime\"\ndefense = 1\nattack = 1",
+        ]).unwrap();
+        let theme = Theme::parse(r##"
+            [default]
+            glyph = "."
+
+            [tags.human]
+            color = "yellow"
+
+            [tags.armored]
+            color = "white"
+            glyph = "#"
+
+            [cards.Knight]
+            color = "blue"
+        "##, &cards).unwrap();
+        let style = |name| theme.style(cards.card(name).unwrap());
+        assert_eq!(style("Knight"), CardStyle { color: Some(Color::Blue), glyph: Some('#') });
+        assert_eq!(style("Squire"), CardStyle { color: Some(Color::Yellow), glyph: Some('.') });
+        assert_eq!(style("Slime"), CardStyle { color: None, glyph: Some('.') });
+        let knight = cards.card("Knight").unwrap();
+        assert_eq!(theme.label(knight, 4), "#Kni");
+        assert_eq!(theme.paint(knight, "#Kni"), "\x1b[34m#Kni\x1b[0m");
+        assert_eq!(Theme::default().label(knight, 10), "Knight");
+        assert_eq!(Theme::default().paint(knight, "Knight"), "Knight");
+
+        assert_eq!(Theme::parse("[cards.Dragon]\ncolor = \"red\"", &cards), Err(ThemeError::UnknownCard("Dragon".to_owned())));
+        assert!(matches!(Theme::parse("[default]\ncolor = \"mauve\"", &cards), Err(ThemeError::Parse(_))));
+    }
+
+    #[test]
+    fn preconstructed_decks_are_checked_against_the_card_pool() {
+        let cards = keyword_cards();
+        let id = |name| cards.card(name).unwrap().id;
+        let starter = "name = \"Starter\"\nleft = [\"Soldier\", \"Soldier\"]\nright = [\"Shieldbearer\"]";
+        let decks = Decks::parse(vec![starter], &cards).unwrap();
+        let deck = decks.deck("Starter").unwrap();
+        assert_eq!(*deck, Deck { left: vec![id("Soldier"), id("Soldier")], center: vec![], right: vec![id("Shieldbearer")] });
+        let (left, center, right, hand) = deck.instantiate(&cards, &mut CardInstances::default());
+        assert_eq!((left.len(), center.len(), right.len(), hand.len()), (2, 0, 1, 0));
+
+        let unknown = "name = \"Typo\"\ncenter = [\"Soldeir\"]";
+        assert_eq!(Decks::parse(vec![unknown], &cards).unwrap_err(), DeckError::UnknownCard {
+            deck: "Typo".to_owned(),
+            card: "Soldeir".to_owned(),
+        });
+        assert_eq!(Decks::parse(vec![starter, starter], &cards).unwrap_err(), DeckError::DuplicateName("Starter".to_owned()));
+        assert!(matches!(Decks::parse(vec!["left = []"], &cards), Err(DeckError::Parse(_))));
+    }
+
+    #[test]
+    fn card_packs_are_namespaced_and_can_be_disabled() {
+        let pack = |name: &str, files: &[&str]| PackSource::new(name, MemorySource { files: files.iter().map(|&file| file.to_owned()).collect() });
+        let soldier = "name = \"Soldier\"\ndefense = 5\nattack = 5";
+        let cards = keyword_cards().load_packs(&[
+            pack("veterans", &[soldier]),
+            pack("recruits", &[soldier, "name = \"Squire\"\ndefense = 1\natk = 1"]),
+        ]).unwrap();
+        assert_eq!(cards.packs(), ["veterans", "recruits"]);
+        // the pool's own Soldier keeps its name, and each pack's is named after the pack
+        let base = cards.card("Soldier").unwrap();
+        let veteran = cards.card("veterans:Soldier").unwrap();
+        let recruit = cards.card("recruits:Soldier").unwrap();
+        assert_eq!((base.defense, veteran.defense, recruit.defense), (3, 5, 5));
+        assert_eq!((cards.pack_of(base.id), cards.pack_of(veteran.id), cards.pack_of(recruit.id)), (None, Some("veterans"), Some("recruits")));
+        assert_eq!(cards.warnings()[0].card.as_deref(), Some("recruits:Squire"));
+        assert!(cards.load_packs(&[pack("veterans", &[])]).is_err());
+        assert!(cards.load_packs(&[pack("bad:name", &[])]).is_err());
+
+        let deck = Decks::parse(vec!["name = \"Mixed\"\nleft = [\"Soldier\", \"recruits:Soldier\"]\nright = [\"recruits:Squire\"]"], &cards).unwrap();
+        let deck = deck.deck("Mixed").unwrap();
+        assert_eq!(deck.packs(&cards), ["recruits"]);
+        assert_eq!(deck.analyze(&cards).packs, ["recruits"]);
+        assert_eq!(deck.check_packs(&cards, &GameConfig::default()), vec![]);
+        let config = GameConfig { disabled_packs: vec!["recruits".to_owned()], ..GameConfig::default() };
+        assert!(config.pack_enabled("veterans") && !config.pack_enabled("recruits"));
+        assert_eq!(deck.check_packs(&cards, &config), vec![
+            DeckWarning::DisabledPack { card: recruit.id, pack: "recruits".to_owned() },
+            DeckWarning::DisabledPack { card: cards.card("recruits:Squire").unwrap().id, pack: "recruits".to_owned() },
+        ]);
+        assert!(RulesDoc::new(&config).section("Setup").unwrap().rules.iter().any(|rule| rule.name == "Card packs"));
+    }
+
+    #[test]
+    fn partial_decks_can_be_completed() {
+        let cards = keyword_cards().with_overrides(vec![
+            CardTypeBuilder::new("Titan").attack(8).defense(8).in_center_deck(card_type::SacrificeToSummon { sacrifices: 2 }).build().unwrap(),
+            CardTypeBuilder::new("Imp").attack(1).defense(1).in_center_deck(card_type::SacrificeToSummon { sacrifices: 0 }).build().unwrap(),
+        ]);
+        let id = |name| cards.card(name).unwrap().id;
+        let partial = Deck { left: vec![id("Soldier")], ..Deck::default() };
+        let constraints = Constraints { left: 3, center: 3, right: 3, max_copies: 2, archetype: Some(Keyword::Guard) };
+        let deck = deckgen::complete(&cards, partial, &constraints);
+        // only two copies of the only card with a cost fit in the center deck
+        assert_eq!(deck.center, vec![id("Titan"), id("Titan")]);
+        assert_eq!(deck.left, vec![id("Soldier"), id("Shieldbearer"), id("Imp")]);
+        assert_eq!(deck.right, vec![id("Shieldbearer"), id("Lancer"), id("Soldier")]);
+        assert!(deck.analyze(&cards).warnings.is_empty());
+    }
+
+    #[test]
+    fn sacrificing_to_summon_from_the_center_deck() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = Cards::from_test(vec![
+            r#"
+            name = "Titan"
+            defense = 8
+            attack = 8
+            [[effects]]
+                type = "InCenterDeck"
+                [effects.trigger]
+                    type = "SacrificeToSummon"
+                    sacrifices = 2
+            "#,
+            r#"
+            name = "Soldier"
+            defense = 3
+            attack = 2
+            "#,
+        ]).unwrap();
+        let titan = instances.instantiate(cards.card("Titan").unwrap());
+        let first = instances.instantiate(cards.card("Soldier").unwrap());
+        let second = instances.instantiate(cards.card("Soldier").unwrap());
+        let (titan_id, first_id, second_id) = (titan.instance, first.instance, second.instance);
+        let mut game = GameState::start((vec![], vec![titan], vec![], vec![first, second]), (vec![], vec![], vec![], vec![]));
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        // with nothing to sacrifice the titan can't be summoned
+        assert!(game.priority_player_options(&cards).iter().all(|option| !matches!(option, PlayerOption::Action(Action::Effect { .. }))));
+        game.priorty_player_take_option(&cards, PlayerOption::Action(Action::Summon { instance: first_id, slot: FieldSlot::F0 }))?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipResponse)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipResponse)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipAction)?;
+        summon(&mut game, &cards, second_id, FieldSlot::B3)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipAction)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        let slots: Vec<FieldSlot> = game.priority_player_options(&cards).into_iter().filter_map(|option| match option {
+            PlayerOption::Action(Action::Effect { activation, .. }) => {
+                assert_eq!(activation.data.targets, vec![first_id, second_id]);
+                activation.data.slot
+            },
+            _ => None,
+        }).collect();
+        // every empty slot plus the two slots freed by the sacrifices
+        assert_eq!(slots.len(), 14);
+        activate_effect_as_action(&mut game, &cards, titan_id, ActivationData {
+            slot: Some(FieldSlot::B3),
+            targets: vec![first_id, second_id],
+            ..ActivationData::default()
+        })?;
+        assert!(game.card_on_field(first_id).is_none());
+        assert!(game.card_on_field(second_id).is_none());
+        assert_eq!(game.field_of(Player::One)[FieldSlot::B3].as_ref().map(|card| card.instance), Some(titan_id));
+        Ok(())
+    }
+
+    #[test]
+    fn cards_keep_their_column_when_destroyed() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = keyword_cards();
+        let card_type = cards.card("Soldier").unwrap();
+        let soldier = instances.instantiate(card_type);
+        let shieldbearer = instances.instantiate(cards.card("Shieldbearer").unwrap());
+        let (soldier_id, shieldbearer_id) = (soldier.instance, shieldbearer.instance);
+        let mut game = GameState::start((vec![], vec![], vec![], vec![soldier, shieldbearer]), (vec![], vec![], vec![], vec![]));
+        game.take_action(&cards, EngineAction::SummonFromHandToSlot(soldier_id, FieldSlot::F4))?;
+        let activation = Activation {
+            status: ActivatableType::Can,
+            data: ActivationData::default(),
+        };
+        let condition = NamedCardInColumn { name: "Shieldbearer".to_owned() };
+        assert!(!condition.met(&cards, card_type, &game, soldier_id, &activation, &ResolutionContext::default()));
+        game.take_action(&cards, EngineAction::SummonFromHandToSlot(shieldbearer_id, FieldSlot::B4))?;
+        assert!(condition.met(&cards, card_type, &game, soldier_id, &activation, &ResolutionContext::default()));
+        let field = game.field_of(Player::One);
+        assert_eq!(FieldSlot::B4.column(), Column(4));
+        assert_eq!(field.cards_in_column(Column(4)).map(|card| card.instance).collect::<Vec<_>>(), vec![soldier_id, shieldbearer_id]);
+        game.take_action(&cards, EngineAction::DestroyOnField(soldier_id))?;
+        assert_eq!(game.column_of(soldier_id), Some(Column(4)));
+        // revival effects in the destroyed pile still look at the column they were destroyed in
+        assert!(condition.met(&cards, card_type, &game, soldier_id, &activation, &ResolutionContext::default()));
+        Ok(())
+    }
+
+    #[test]
+    fn auras_boost_adjacent_cards() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = Cards::from_test(vec![
+            r#"
+            name = "Banner"
+            defense = 1
+            attack = 0
+            [[auras]]
+                adjacency = "NeighbouringColumns"
+                defense = 2
+            "#,
+            r#"
+            name = "Soldier"
+            defense = 3
+            attack = 2
+            "#,
+        ]).unwrap();
+        let banner = instances.instantiate(cards.card("Banner").unwrap());
+        let soldier = instances.instantiate(cards.card("Soldier").unwrap());
+        let behind = instances.instantiate(cards.card("Soldier").unwrap());
+        let (banner_id, soldier_id, behind_id) = (banner.instance, soldier.instance, behind.instance);
+        let mut game = GameState::start((vec![], vec![], vec![], vec![banner, soldier, behind]), (vec![], vec![], vec![], vec![]));
+        game.take_action(&cards, EngineAction::SummonFromHandToSlot(banner_id, FieldSlot::F3))?;
+        game.take_action(&cards, EngineAction::SummonFromHandToSlot(soldier_id, FieldSlot::F2))?;
+        game.take_action(&cards, EngineAction::SummonFromHandToSlot(behind_id, FieldSlot::B3))?;
+        let field = game.field_of(Player::One);
+        assert_eq!(field.adjacent_slots(FieldSlot::F3), vec![FieldSlot::B3, FieldSlot::F2, FieldSlot::F4]);
+        assert_eq!(field.defense_of(&cards, FieldSlot::F2), Some(5));
+        // the card behind is adjacent but not in a neighbouring column
+        assert_eq!(field.defense_of(&cards, FieldSlot::B3), Some(3));
+        game.take_action(&cards, EngineAction::DamageOnField(soldier_id, 4))?;
+        assert!(game.card_on_field(soldier_id).is_some());
+        game.take_action(&cards, EngineAction::DamageOnField(behind_id, 3))?;
+        assert!(game.card_on_field(behind_id).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn rows_restrict_summons_auras_and_targets() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = Cards::from_test(vec![
+            r#"
+            name = "Medic"
+            defense = 1
+            attack = 0
+            summon_restriction = "BackRowOnly"
+            [[auras]]
+                adjacency = "SameColumn"
+                defense = 2
+                while_in = "Back"
+            "#,
+            r#"
+            name = "Vanguard"
+            defense = 3
+            attack = 2
+            summon_restriction = "FrontRowOnly"
+            "#,
+        ]).unwrap();
+        let medic = instances.instantiate(cards.card("Medic").unwrap());
+        let vanguard = instances.instantiate(cards.card("Vanguard").unwrap());
+        let front_medic = instances.instantiate(cards.card("Medic").unwrap());
+        let back_vanguard = instances.instantiate(cards.card("Vanguard").unwrap());
+        let (medic_id, vanguard_id) = (medic.instance, vanguard.instance);
+        let (front_medic_id, back_vanguard_id) = (front_medic.instance, back_vanguard.instance);
+        let drawn = instances.instantiate(cards.card("Vanguard").unwrap());
+        let hand = vec![medic, vanguard, front_medic, back_vanguard];
+        let mut game = GameState::start((vec![drawn], vec![], vec![], hand), (vec![], vec![], vec![], vec![]));
+        game.priorty_player_take_option(&cards, PlayerOption::Draw(FaceDownDeck::Left))?;
+        let summons: Vec<(CardInstance, FieldSlot)> = game.priority_player_options(&cards).into_iter()
+            .filter_map(|option| match option {
+                PlayerOption::Action(Action::Summon { instance, slot }) => Some((instance, slot)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(summons.len(), 35);
+        let medics = [medic_id, front_medic_id];
+        assert!(summons.iter().all(|&(instance, slot)| slot.row() == if medics.contains(&instance) { Row::Back } else { Row::Front }));
+
+        game.take_action(&cards, EngineAction::SummonFromHandToSlot(vanguard_id, FieldSlot::F3))?;
+        game.take_action(&cards, EngineAction::SummonFromHandToSlot(medic_id, FieldSlot::B3))?;
+        let field = game.field_of(Player::One);
+        assert_eq!(field.row_of(medic_id), Some(Row::Back));
+        assert_eq!(field.cards_in_row(Row::Front).map(|card| card.instance).collect::<Vec<_>>(), vec![vanguard_id]);
+        assert_eq!(field.defense_of(&cards, FieldSlot::F3), Some(5));
+
+        let filter = TargetFilter { row: Some(Row::Back), ..TargetFilter::default() };
+        let medic_card = game.card_on_field(medic_id).unwrap();
+        let vanguard_card = game.card_on_field(vanguard_id).unwrap();
+        assert!(filter.matches(&cards, &game, vanguard_id, medic_card));
+        assert!(!filter.matches(&cards, &game, medic_id, vanguard_card));
+
+        // placed in the front row by an effect, the aura no longer applies
+        game.take_action(&cards, EngineAction::SummonFromHandToSlot(front_medic_id, FieldSlot::F5))?;
+        game.take_action(&cards, EngineAction::SummonFromHandToSlot(back_vanguard_id, FieldSlot::B5))?;
+        assert_eq!(game.field_of(Player::One).defense_of(&cards, FieldSlot::B5), Some(3));
+        Ok(())
+    }
+
+    #[test]
+    fn summon_restrictions_apply_to_normal_and_effect_summons() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = keyword_cards().with_overrides(vec![
+            CardTypeBuilder::new("Golem").attack(4).defense(4).summon_restriction(SummonRestriction::EffectOnly).build().unwrap(),
+            CardTypeBuilder::new("Phoenix").attack(3).defense(1)
+                .summon_restriction(SummonRestriction::DestroyedInColumn(1))
+                .in_center_deck(card_type::SacrificeToSummon { sacrifices: 0 })
+                .build().unwrap(),
+        ]);
+        let restricted = Cards::from_test(vec![
+            r#"
+            name = "Golem"
+            defense = 4
+            attack = 4
+            summon_restriction = "EffectOnly"
+            "#,
+            r#"
+            name = "Phoenix"
+            defense = 1
+            attack = 3
+            summon_restriction = { DestroyedInColumn = 1 }
+            "#,
+        ]).unwrap();
+        assert_eq!(restricted.card("Golem").unwrap().summon_restriction, Some(SummonRestriction::EffectOnly));
+        assert_eq!(restricted.card("Phoenix").unwrap().summon_restriction, Some(SummonRestriction::DestroyedInColumn(1)));
+
+        let golem = instances.instantiate(cards.card("Golem").unwrap());
+        let soldier = instances.instantiate(cards.card("Soldier").unwrap());
+        let phoenix = instances.instantiate(cards.card("Phoenix").unwrap());
+        let (golem_id, soldier_id, phoenix_id) = (golem.instance, soldier.instance, phoenix.instance);
+        let mut game = GameState::start((vec![], vec![phoenix], vec![], vec![golem, soldier]), (vec![], vec![], vec![], vec![]));
+        game.take_action(&cards, EngineAction::SummonFromHandToSlot(soldier_id, FieldSlot::F2))?;
+        game.take_action(&cards, EngineAction::DestroyOnField(soldier_id))?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        let options = game.priority_player_options(&cards);
+        // the golem can't be normal summoned at all
+        assert!(!options.iter().any(|option| matches!(option, PlayerOption::Action(Action::Summon { instance, .. }) if *instance == golem_id)));
+        // the phoenix can only be summoned from the center deck to the column with a destroyed card
+        let slots: Vec<FieldSlot> = options.iter()
+            .filter_map(|option| match option {
+                PlayerOption::Action(Action::Effect { instance, activation, .. }) if *instance == phoenix_id => activation.data.slot,
+                _ => None,
+            })
+            .collect();
+        assert_eq!(slots, vec![FieldSlot::F2, FieldSlot::B2]);
+        let field = game.field_of(Player::One);
+        assert!(cards.card("Golem").unwrap().can_summon_to(field, FieldSlot::F0, true));
+        assert!(!cards.card("Golem").unwrap().can_summon_to(field, FieldSlot::F0, false));
+        assert_eq!(SummonRestriction::DestroyedInColumn(2).to_string(), "Can only be summoned to a column with 2 destroyed cards.");
+        Ok(())
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn describing_a_turn() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = Cards::get();
+        let card = instances.instantiate(cards.card("Staple Dragon").unwrap());
+        let instance = card.instance;
+        let mut game = GameState::start((vec![card], vec![], vec![], vec![]), (vec![], vec![], vec![], vec![]));
+        game.priorty_player_take_option(cards, PlayerOption::Draw(FaceDownDeck::Left))?;
+        game.priorty_player_take_option(cards, PlayerOption::Action(Action::Summon {
+            instance,
+            slot: FieldSlot::F4,
+        }))?;
+        game.priorty_player_take_option(cards, PlayerOption::SkipResponse)?;
+        game.priorty_player_take_option(cards, PlayerOption::Action(Action::Effect {
+            instance,
+            effect_index: CardEffect(0),
+            activation: Activation {
+                status: ActivatableType::Mandatory,
+                data: ActivationData::default(),
+            },
+        }))?;
+        game.priorty_player_take_option(cards, PlayerOption::SkipResponse)?;
+        game.priorty_player_take_option(cards, PlayerOption::SkipResponse)?;
+        assert_eq!(
+            describe_turn(game.events(), cards),
+            "P1 drew from the left deck and ran out of cards in the left deck and summoned Staple Dragon to F4; P2 passed; \
+            P1 responded with Staple Dragon; Staple Dragon was destroyed; P2 passed; P1 passed; the chain of 1 link started resolving; \
+            Staple Dragon resolved; the chain resolved"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn events_record_what_caused_them() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = keyword_cards();
+        let lancer = instances.instantiate(cards.card("Lancer").unwrap());
+        let soldier = instances.instantiate(cards.card("Soldier").unwrap());
+        let (lancer_id, lancer_type) = (lancer.instance, lancer.card_type);
+        let (soldier_id, soldier_type) = (soldier.instance, soldier.card_type);
+        let mut game = GameState::start((vec![], vec![], vec![], vec![lancer]), (vec![], vec![], vec![], vec![soldier]));
+        summon(&mut game, &cards, lancer_id, FieldSlot::F0)?;
+        summon(&mut game, &cards, soldier_id, FieldSlot::F0)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        let attack = game.event_seq();
+        game.priorty_player_take_option(&cards, PlayerOption::Action(Action::Attack { instance: lancer_id, target: FieldSlot::F0 }))?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipResponse)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipResponse)?;
+
+        assert_eq!(game.event_causes().len(), game.events().len());
+        assert_eq!(game.cause_of(attack), Some(EventCause::Option(Player::One)));
+        let destroyed = game.events().iter()
+            .position(|event| matches!(event, GameEvent::Destroyed { .. }))
+            .unwrap();
+        assert_eq!(game.events()[destroyed], GameEvent::Destroyed { player: Player::Two, card: soldier_type, column: Column(0) });
+        let cause = game.cause_of(EventSeq::from(destroyed)).unwrap();
+        assert_eq!(cause, EventCause::Attack { player: Player::One, attacker: lancer_type });
+        assert_eq!(describe_cause(cause, &cards), "Lancer's attack");
+        assert_eq!(game.cause_of(game.event_seq()), None);
+        Ok(())
+    }
+
+    #[test]
+    fn players_lose_when_they_can_neither_draw_nor_act() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = keyword_cards();
+        let soldier = instances.instantiate(cards.card("Soldier").unwrap());
+        let mut game = GameState::start((vec![], vec![], vec![], vec![]), (vec![soldier], vec![], vec![], vec![]));
+        assert_eq!(game.winner(&cards), Some(Player::Two));
+        let soldier = instances.instantiate(cards.card("Soldier").unwrap());
+        game = GameState::start((vec![], vec![], vec![], vec![soldier]), (vec![], vec![], vec![], vec![]));
+        assert_eq!(game.winner(&cards), None);
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipAction)?;
+        assert_eq!(game.winner(&cards), Some(Player::One));
+        Ok(())
+    }
+
+    #[test]
+    fn destroyed_cards_can_trigger_after_resolution() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = Cards::from_test(vec![
+            r#"
+            name = "Soldier"
+            defense = 3
+            attack = 2
+            "#,
+            r#"
+            name = "Martyr"
+            defense = 1
+            attack = 0
+            [[effects]]
+                type = "OnDestroyed"
+                mandatory = false
+                [effects.trigger]
+                    type = "ReturnTargetToHand"
+                    whose = "Opponent"
+            "#,
+        ]).unwrap();
+        let soldier = instances.instantiate(cards.card("Soldier").unwrap());
+        let martyr = instances.instantiate(cards.card("Martyr").unwrap());
+        let (soldier_id, martyr_id) = (soldier.instance, martyr.instance);
+        let mut game = GameState::start((vec![], vec![], vec![], vec![soldier]), (vec![], vec![], vec![], vec![martyr]));
+        summon(&mut game, &cards, soldier_id, FieldSlot::F0)?;
+        summon(&mut game, &cards, martyr_id, FieldSlot::F0)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        game.priorty_player_take_option(&cards, PlayerOption::Action(Action::Attack { instance: soldier_id, target: FieldSlot::F0 }))?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipResponse)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipResponse)?;
+        // the martyr was destroyed, so the game stays closed for its trigger, turn player first
+        assert!(game.card_in_destroyed_pile(martyr_id).is_some());
+        assert_eq!((game.open(), game.priority()), (GameStateType::Closed, Player::One));
+        assert_eq!(game.priority_player_options(&cards), vec![PlayerOption::SkipResponse]);
+        game.priorty_player_take_option(&cards, PlayerOption::SkipResponse)?;
+        let trigger = game.priority_player_options(&cards).into_iter().find(|option| matches!(option, PlayerOption::Action(Action::Effect { .. }))).unwrap();
+        game.priorty_player_take_option(&cards, trigger)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipResponse)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipResponse)?;
+        assert!(game.card_in_hand(soldier_id).is_some());
+        assert_eq!(game.open(), GameStateType::Open { phase: Phase::MayDraw });
+        assert_eq!(game.priority(), Player::Two);
+
+        // with nothing to trigger the game reopens straight away as before
+        let soldier = instances.instantiate(cards.card("Soldier").unwrap());
+        let other = instances.instantiate(cards.card("Soldier").unwrap());
+        let (soldier_id, other_id) = (soldier.instance, other.instance);
+        let mut game = GameState::start((vec![], vec![], vec![], vec![soldier]), (vec![], vec![], vec![], vec![other]));
+        summon(&mut game, &cards, soldier_id, FieldSlot::F0)?;
+        summon(&mut game, &cards, other_id, FieldSlot::F0)?;
+        game.take_action(&cards, EngineAction::DamageOnField(other_id, 1))?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        game.priorty_player_take_option(&cards, PlayerOption::Action(Action::Attack { instance: soldier_id, target: FieldSlot::F0 }))?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipResponse)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipResponse)?;
+        assert!(game.card_in_destroyed_pile(other_id).is_some());
+        assert_eq!(game.open(), GameStateType::Open { phase: Phase::MayDraw });
+        Ok(())
+    }
+
+    #[test]
+    fn effects_over_their_budget_fizzle_without_changing_the_game() -> Result<(), InvalidAction> {
+        let cards = Cards::from_test(vec![
+            r#"
+            name = "Cataclysm"
+            defense = 3
+            attack = 0
+            [[effects]]
+                type = "OnField"
+                mandatory = false
+                [effects.trigger]
+                    type = "DestroyAll"
+                    filter = { name = "Relic" }
+            "#,
+            "name = \"Relic\"\ndefense = 1\nattack = 0",
+        ]).unwrap();
+        for &movements in [1, 2].iter() {
+            let mut instances = CardInstances::default();
+            let cataclysm = instances.instantiate(cards.card("Cataclysm").unwrap());
+            let relics: Vec<Card> = (0..2).map(|_| instances.instantiate(cards.card("Relic").unwrap())).collect();
+            let cataclysm_id = cataclysm.instance;
+            let ids: Vec<CardInstance> = relics.iter().map(|card| card.instance).collect();
+            let mut game = GameState::start((vec![], vec![], vec![], vec![cataclysm]), (vec![], vec![], vec![], relics));
+            game.configure(GameConfig { effect_budget: Some(EffectBudget { movements, ..EffectBudget::default() }), ..GameConfig::default() });
+            game.take_action(&cards, EngineAction::SummonFromHandToSlot(cataclysm_id, FieldSlot::F0))?;
+            game.take_action(&cards, EngineAction::SummonFromHandToSlot(ids[0], FieldSlot::F0))?;
+            game.take_action(&cards, EngineAction::SummonFromHandToSlot(ids[1], FieldSlot::F1))?;
+            game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+            let option = game.legal_options(&cards).iter()
+                .find(|option| matches!(option, PlayerOption::Action(Action::Effect { .. })))
+                .cloned()
+                .unwrap();
+            game.priorty_player_take_option(&cards, option)?;
+            game.priorty_player_take_option(&cards, PlayerOption::SkipResponse)?;
+            game.priorty_player_take_option(&cards, PlayerOption::SkipResponse)?;
+
+            let card = cards.card("Cataclysm").unwrap().id;
+            let exceeded = GameEvent::EffectBudgetExceeded { player: Player::One, card };
+            let destroyed = ids.iter().filter(|&&id| game.card_in_destroyed_pile(id).is_some()).count();
+            if movements == 1 {
+                assert_eq!(destroyed, 0);
+                assert!(game.events().contains(&exceeded));
+                assert!(game.events().contains(&GameEvent::LinkFizzled { link: ChainLinkId(0), player: Player::One, card }));
+                assert!(!game.events().iter().any(|event| matches!(event, GameEvent::Destroyed { .. })));
+            } else {
+                assert_eq!(destroyed, 2);
+                assert!(!game.events().contains(&exceeded));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn the_turn_player_puts_their_triggers_on_the_chain_first() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = Cards::from_test(vec![
+            r#"
+            name = "Cataclysm"
+            defense = 3
+            attack = 0
+            [[effects]]
+                type = "OnField"
+                mandatory = false
+                [effects.trigger]
+                    type = "DestroyAll"
+                    filter = { name = "Relic" }
+            "#,
+            r#"
+            name = "Relic"
+            defense = 1
+            attack = 0
+            [[effects]]
+                type = "OnDestroyed"
+                mandatory = false
+                [effects.trigger]
+                    type = "DrawCards"
+                    whose = "Controller"
+                    deck_choice = "Left"
+                    count = 1
+            "#,
+        ]).unwrap();
+        let cataclysm = instances.instantiate(cards.card("Cataclysm").unwrap());
+        let relics: Vec<Card> = (0..3).map(|_| instances.instantiate(cards.card("Relic").unwrap())).collect();
+        let cataclysm_id = cataclysm.instance;
+        let ids: Vec<CardInstance> = relics.iter().map(|card| card.instance).collect();
+        let mut relics = relics.into_iter();
+        let ours = vec![cataclysm, relics.next().unwrap(), relics.next().unwrap()];
+        // the relics draw when destroyed, so each player needs something to draw
+        let mut deck = |size| (0..size).map(|_| instances.instantiate(cards.card("Cataclysm").unwrap())).collect::<Vec<_>>();
+        let (our_deck, their_deck) = (deck(2), deck(1));
+        let mut game = GameState::start((our_deck, vec![], vec![], ours), (their_deck, vec![], vec![], relics.collect()));
+        for (&instance, slot) in [cataclysm_id, ids[0], ids[1], ids[2]].iter().zip([FieldSlot::F0, FieldSlot::F1, FieldSlot::F2, FieldSlot::F0]) {
+            game.take_action(&cards, EngineAction::SummonFromHandToSlot(instance, slot))?;
+        }
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        let activate = |game: &GameState, instance| game.legal_options(&cards)
+            .iter()
+            .find(|option| matches!(option, PlayerOption::Action(Action::Effect { instance: activated, .. }) if *activated == instance))
+            .cloned()
+            .unwrap();
+        let option = activate(&game, cataclysm_id);
+        game.priorty_player_take_option(&cards, option)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipResponse)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipResponse)?;
+        assert!(ids.iter().all(|&id| game.card_in_destroyed_pile(id).is_some()));
+
+        // player one picks the order of their own triggers and keeps priority until they're done
+        assert_eq!(game.priority(), Player::One);
+        let option = activate(&game, ids[1]);
+        game.priorty_player_take_option(&cards, option)?;
+        assert_eq!(game.priority(), Player::One);
+        let option = activate(&game, ids[0]);
+        game.priorty_player_take_option(&cards, option)?;
+        assert_eq!(game.priority(), Player::Two);
+        let option = activate(&game, ids[2]);
+        game.priorty_player_take_option(&cards, option)?;
+        assert_eq!(game.priority(), Player::One);
+        let chain: Vec<CardInstance> = game.pending_chain().iter().map(|link| link.instance).collect();
+        assert_eq!(chain, vec![ids[1], ids[0], ids[2]]);
+        Ok(())
+    }
+
+    #[test]
+    fn triggers_that_keep_chaining_are_stopped_at_the_loop_limit() -> Result<(), InvalidAction> {
+        let domino = |name: &str, next: &str| format!(r#"
+            name = "{}"
+            defense = 1
+            attack = 0
+            [[effects]]
+                type = "OnDestroyed"
+                mandatory = true
+                [effects.trigger]
+                    type = "DestroyAll"
+                    filter = {{ name = "{}" }}
+            "#, name, next);
+        let (a, b, c) = (domino("A", "B"), domino("B", "C"), domino("C", "D"));
+        let cards = Cards::from_test(vec![&a, &b, &c, "name = \"Soldier\"\ndefense = 3\nattack = 2"]).unwrap();
+        for &(limit, loop_broken) in [(1, true), (DEFAULT_LOOP_LIMIT, false)].iter() {
+            let mut instances = CardInstances::default();
+            let mut card = |name| instances.instantiate(cards.card(name).unwrap());
+            let soldier = card("Soldier");
+            let soldier_id = soldier.instance;
+            let dominoes = vec![card("A"), card("B"), card("C")];
+            let ids: Vec<CardInstance> = dominoes.iter().map(|card| card.instance).collect();
+            let mut game = GameState::start((vec![], vec![], vec![], vec![soldier]), (vec![], vec![], vec![], dominoes));
+            game.set_loop_limit(limit);
+            for (&instance, &slot) in ids.iter().zip([FieldSlot::F0, FieldSlot::F1, FieldSlot::F2].iter()) {
+                game.take_action(&cards, EngineAction::SummonFromHandToSlot(instance, slot))?;
+            }
+            summon(&mut game, &cards, soldier_id, FieldSlot::F0)?;
+            game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+            game.priorty_player_take_option(&cards, PlayerOption::SkipAction)?;
+            game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+            game.priorty_player_take_option(&cards, PlayerOption::Action(Action::Attack { instance: soldier_id, target: FieldSlot::F0 }))?;
+            game.take_forced_options(&cards);
+            assert_eq!(game.events().contains(&GameEvent::LoopBroken), loop_broken);
+            if loop_broken {
+                // A's trigger destroyed B, but B's trigger would have opened a second window
+                assert!(game.card_in_destroyed_pile(ids[1]).is_some());
+                assert!(game.card_on_field(ids[2]).is_some());
+                let result = game.result(&cards).unwrap();
+                assert!(result.is_draw());
+                assert_eq!(result.reason(), EndReason::Loop);
+            } else {
+                assert!(ids.iter().all(|&id| game.card_in_destroyed_pile(id).is_some()));
+                assert_eq!(game.open(), GameStateType::Open { phase: Phase::MayDraw });
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn board_wipes_destroy_every_card_at_once() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = keyword_cards();
+        let mut card = |name| instances.instantiate(cards.card(name).unwrap());
+        let (soldier, lancer, shieldbearer) = (card("Soldier"), card("Lancer"), card("Shieldbearer"));
+        let (their_soldier, their_lancer) = (card("Soldier"), card("Lancer"));
+        let ids = [soldier.instance, lancer.instance, shieldbearer.instance, their_soldier.instance, their_lancer.instance];
+        let mut game = GameState::start(
+            (vec![], vec![], vec![], vec![soldier, lancer, shieldbearer]),
+            (vec![], vec![], vec![], vec![their_soldier, their_lancer]),
+        );
+        for (&instance, &slot) in ids.iter().zip([FieldSlot::F0, FieldSlot::F1, FieldSlot::B0, FieldSlot::F0, FieldSlot::F2].iter()) {
+            game.take_action(&cards, EngineAction::SummonFromHandToSlot(instance, slot))?;
+        }
+        let lancer_type = cards.card("Lancer").unwrap();
+        let (no_activation, context) = (Activation { status: ActivatableType::Can, data: ActivationData::default() }, ResolutionContext::default());
+
+        // a card which left the field can't be destroyed, so nothing is
+        assert!(game.take_action(&cards, EngineAction::DestroyAllOnField(vec![ids[0], CardInstance(99)])).is_err());
+        assert!(game.card_on_field(ids[0]).is_some());
+
+        let wipe = DestroyAll { filter: TargetFilter { max_attack: Some(2), ..TargetFilter::default() } };
+        wipe.resolution(&cards, lancer_type, &mut game, ids[1], &no_activation, &context);
+        assert_eq!(game.cards_on_field().count(), 2);
+        // the turn player's front row reaches the pile before their back row
+        let pile: Vec<CardInstance> = game.field_of(Player::One).destroyed(Column(0)).iter().map(|card| card.instance).collect();
+        assert_eq!(pile, vec![ids[0], ids[2]]);
+        assert_eq!(game.field_of(Player::Two).destroyed(Column(0))[0].instance, ids[3]);
+
+        let column = DestroyColumn { column: None, whose: Some(Whose::Opponent) };
+        let activations = column.variants(&cards, lancer_type, &game, ids[1], &context);
+        assert_eq!(activations.len(), 7);
+        let activation = Activation { status: ActivatableType::Can, data: ActivationData { column: Some(Column(2)), ..ActivationData::default() } };
+        column.resolution(&cards, lancer_type, &mut game, ids[1], &activation, &context);
+        assert_eq!(game.cards_on_field().map(|card| card.instance).collect::<Vec<_>>(), vec![ids[1]]);
+        Ok(())
+    }
+
+    #[test]
+    fn cards_may_only_destroy_columns_on_the_field() {
+        let card = |column| format!(r#"
+            name = "Quake"
+            defense = 2
+            attack = 1
+            [[effects]]
+                type = "OnField"
+                mandatory = false
+                [effects.trigger]
+                    type = "DestroyColumn"
+                    column = {}
+            "#, column);
+        assert!(Cards::from_test(vec![&card(6)]).is_ok());
+        let error = Cards::from_test(vec![&card(7)]).err().unwrap();
+        assert!(error.to_string().contains("there is no column 7"), "{}", error);
+        // columns made in code rather than read from a file aren't checked, so are skipped instead
+        let cards = keyword_cards();
+        let mut instances = CardInstances::default();
+        let soldier = instances.instantiate(cards.card("Soldier").unwrap());
+        let soldier_id = soldier.instance;
+        let game = GameState::start((vec![], vec![], vec![], vec![soldier]), (vec![], vec![], vec![], vec![]));
+        let column = DestroyColumn { column: Some(Column(7)), whose: None };
+        let context = ResolutionContext::default();
+        assert!(column.variants(&cards, cards.card("Soldier").unwrap(), &game, soldier_id, &context).is_empty());
+        assert!(game.field_of(Player::One).destroyed(Column(9)).is_empty());
+        assert_eq!(game.field_of(Player::One).cards_in_column(Column(7)).count(), 0);
+    }
+
+    #[test]
+    fn stolen_cards_leave_the_field_to_their_owner() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = Cards::from_test(vec![
+            r#"
+            name = "Thief"
+            defense = 1
+            attack = 1
+            [[effects]]
+                type = "OnSummon"
+                mandatory = false
+                [effects.trigger]
+                    type = "TakeControlOfTarget"
+                    filter = { max_attack = 2 }
+            "#,
+            r#"
+            name = "Soldier"
+            defense = 3
+            attack = 2
+            "#,
+            r#"
+            name = "Lancer"
+            defense = 2
+            attack = 5
+            "#,
+        ]).unwrap();
+        let thief = instances.instantiate(cards.card("Thief").unwrap());
+        let soldier = instances.instantiate(cards.card("Soldier").unwrap());
+        let lancer = instances.instantiate(cards.card("Lancer").unwrap());
+        let (thief_id, soldier_id, lancer_id) = (thief.instance, soldier.instance, lancer.instance);
+        let mut game = GameState::start((vec![], vec![], vec![], vec![thief]), (vec![], vec![], vec![], vec![soldier, lancer]));
+        game.take_action(&cards, EngineAction::SummonFromHandToSlot(soldier_id, FieldSlot::F0))?;
+        game.take_action(&cards, EngineAction::SummonFromHandToSlot(lancer_id, FieldSlot::F1))?;
+        game.take_action(&cards, EngineAction::SummonFromHandToSlot(thief_id, FieldSlot::F0))?;
+        // only the soldier is weak enough to steal, and it can go to any of the 13 empty slots
+        let thief_type = cards.card("Thief").unwrap();
+        let context = ResolutionContext { trigger: Some(Trigger::Summoned(thief_id)), ..ResolutionContext::default() };
+        let activations = thief_type.effects[0].can_activate(&cards, thief_type, &game, thief_id, &context);
+        assert_eq!(activations.len(), 13);
+        assert!(activations.iter().all(|activation| activation.data.targets == vec![soldier_id]));
+
+        game.take_action(&cards, EngineAction::TakeControl(Player::One, soldier_id, FieldSlot::F2))?;
+        assert_eq!(game.controller_of(soldier_id), Some(Player::One));
+        assert_eq!(game.card(soldier_id).unwrap().owner(), Player::Two);
+        assert!(game.take_action(&cards, EngineAction::TakeControl(Player::One, soldier_id, FieldSlot::F3)).is_err());
+        game.take_action(&cards, EngineAction::ReturnFieldToHand(soldier_id))?;
+        assert!(game.card_in_hand(soldier_id).is_some());
+        assert_eq!(game.controller_of(soldier_id), Some(Player::Two));
+
+        game.take_action(&cards, EngineAction::TakeControl(Player::One, lancer_id, FieldSlot::B4))?;
+        game.take_action(&cards, EngineAction::DamageOnField(lancer_id, 2))?;
+        assert_eq!(game.controller_of(lancer_id), Some(Player::Two));
+        assert_eq!(game.field_of(Player::Two).destroyed(Column(4))[0].instance, lancer_id);
+        Ok(())
+    }
+
+    #[test]
+    fn both_players_running_out_at_once_is_a_draw() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = keyword_cards();
+        let game = GameState::start((vec![], vec![], vec![], vec![]), (vec![], vec![], vec![], vec![]));
+        assert!(game.result(&cards).unwrap().is_draw());
+        assert_eq!(game.winner(&cards), None);
+
+        // whichever card is destroyed first, once both are gone neither player wins
+        for first in [Player::One, Player::Two].iter().copied() {
+            let one = instances.instantiate(cards.card("Soldier").unwrap());
+            let two = instances.instantiate(cards.card("Soldier").unwrap());
+            let (one_id, two_id) = (one.instance, two.instance);
+            let mut game = GameState::start((vec![], vec![], vec![], vec![one]), (vec![], vec![], vec![], vec![two]));
+            summon(&mut game, &cards, one_id, FieldSlot::F0)?;
+            summon(&mut game, &cards, two_id, FieldSlot::F0)?;
+            assert_eq!(game.result(&cards), None);
+            let (first_id, second_id) = match first {
+                Player::One => (one_id, two_id),
+                Player::Two => (two_id, one_id),
+            };
+            game.take_action(&cards, EngineAction::DestroyOnField(first_id))?;
+            assert!(!game.result(&cards).is_some_and(|result| result.is_draw()));
+            game.take_action(&cards, EngineAction::DestroyOnField(second_id))?;
+            let result = game.result(&cards).unwrap();
+            assert_eq!((result.winner(), result.reason()), (None, EndReason::SimultaneousLoss));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn games_can_end_early_with_a_reason() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = keyword_cards();
+        let soldier = instances.instantiate(cards.card("Soldier").unwrap());
+        let mut game = GameState::start((vec![soldier], vec![], vec![], vec![]), (vec![], vec![], vec![], vec![]));
+        assert_eq!(game.result(&cards), None);
+        game.concede(Player::One)?;
+        let result = game.result(&cards).unwrap();
+        assert_eq!(result.winner(), Some(Player::Two));
+        assert_eq!(result.reason(), EndReason::Concession);
+        assert!(game.priority_player_options(&cards).is_empty());
+        assert!(game.priorty_player_take_option(&cards, PlayerOption::SkipDraw).is_err());
+        assert!(game.abort().is_err());
+        assert_eq!(describe_turn(game.events(), &cards), "P1 conceded");
+
+        let soldier = instances.instantiate(cards.card("Soldier").unwrap());
+        game = GameState::start((vec![soldier], vec![], vec![], vec![]), (vec![], vec![], vec![], vec![]));
+        game.take_action(&cards, EngineAction::WinGame(Player::Two))?;
+        assert_eq!(game.winner(&cards), Some(Player::Two));
+        assert_eq!(game.result(&cards).unwrap().reason(), EndReason::AlternativeWin);
+
+        let soldier = instances.instantiate(cards.card("Soldier").unwrap());
+        game = GameState::start((vec![soldier], vec![], vec![], vec![]), (vec![], vec![], vec![], vec![]));
+        game.abort()?;
+        assert_eq!(game.winner(&cards), None);
+        assert_eq!(game.result(&cards).unwrap().reason(), EndReason::Aborted);
+        Ok(())
+    }
+
+    /// A tiny xorshift generator, so random games can be replayed from their seed
+    struct Random(u64);
+
+    impl Random {
+        fn below(&mut self, bound: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % bound as u64) as usize
+        }
+    }
+
+    fn random_game(cards: &Cards, seed: u64) -> (GameState, Random) {
+        let mut instances = CardInstances::default();
+        let pool: Vec<_> = cards.iter().collect();
+        let mut deck = |size| -> Vec<Card> {
+            (0..size).map(|i| instances.instantiate(pool[i % pool.len()])).collect()
+        };
+        let game = GameState::start((deck(4), vec![], deck(4), deck(3)), (deck(4), vec![], deck(4), deck(3)));
+        (game, Random(seed + 1))
+    }
+
+    /// Takes a random option, returning false once the game is over
+    fn step_random_game(cards: &Cards, game: &mut GameState, random: &mut Random) -> bool {
+        if game.winner(cards).is_some() {
+            return false;
+        }
+        let mut options = game.priority_player_options(cards);
+        let option = options.swap_remove(random.below(options.len()));
+        game.take_option_unchecked(cards, option).unwrap();
+        true
+    }
+
+    fn play_random_game(cards: &Cards, seed: u64) -> Vec<GameEvent> {
+        let (mut game, mut random) = random_game(cards, seed);
+        for _ in 0..50 {
+            if !step_random_game(cards, &mut game, &mut random) {
+                break;
+            }
+        }
+        game.events().to_vec()
+    }
+
+    #[test]
+    fn option_counts_and_terminal_checks_agree_with_the_options() {
+        // with an Undying card so destroyed cards can be revived
+        let cards = Cards::from_test(vec![
+            "name = \"Soldier\"\ndefense = 3\nattack = 2",
+            "name = \"Lancer\"\ndefense = 2\nattack = 5\nkeywords = [\"Piercing\"]",
+            "name = \"Phoenix\"\ndefense = 1\nattack = 2\nkeywords = [\"Undying\"]",
+        ]).unwrap();
+        let mut revivals = 0;
+        for seed in 0..20 {
+            let mut instances = CardInstances::default();
+            let mut random = Random(seed + 1);
+            let pool: Vec<_> = cards.iter().collect();
+            let mut deck = |size| -> Vec<Card> {
+                (0..size).map(|i| instances.instantiate(pool[i % pool.len()])).collect()
+            };
+            let mut game = GameState::start((deck(4), vec![], deck(4), deck(3)), (deck(4), vec![], deck(4), deck(3)));
+            for _ in 0..100 {
+                // counted before the options are worked out and cached
+                let count = game.legal_option_count(&cards);
+                let any = game.has_any_option(&cards);
+                let terminal = game.is_terminal(&cards);
+                let mut options = game.priority_player_options(&cards);
+                assert_eq!(count, options.len());
+                assert_eq!(terminal, game.result(&cards).is_some());
+                if game.open() != (GameStateType::Open { phase: Phase::MayDraw }) {
+                    let skip = |option: &PlayerOption| matches!(option, PlayerOption::SkipAction | PlayerOption::SkipResponse);
+                    assert_eq!(any, options.iter().any(|option| !skip(option)));
+                }
+                if terminal {
+                    break;
+                }
+                revivals += options.iter().filter(|option| matches!(option, PlayerOption::Action(Action::Revive { .. }))).count();
+                let option = options.swap_remove(random.below(options.len()));
+                game.take_option_unchecked(&cards, option).unwrap();
+            }
+        }
+        assert!(revivals > 0, "the games should have had cards to revive");
+    }
+
+    #[test]
+    fn transposition_tables_share_work_without_changing_choices() {
+        let cards = keyword_cards();
+        let mut instances = CardInstances::default();
+        let pool: Vec<_> = cards.iter().collect();
+        let mut deck = |size| -> Vec<Card> {
+            (0..size).map(|i| instances.instantiate(pool[i % pool.len()])).collect()
+        };
+        let mut game = GameState::start((deck(4), vec![], deck(4), deck(3)), (deck(4), vec![], deck(4), deck(3)));
+        let table = Arc::new(TranspositionTable::new(1 << 20));
+        // a table with room for one position still gives the same choices
+        let tiny = Arc::new(TranspositionTable::new(0));
+        assert_eq!(tiny.capacity(), 1);
+        let plain = MinimaxAgent::new(2);
+        let cached = MinimaxAgent::new(2).with_table(table.clone());
+        let bounded = MinimaxAgent::new(2).with_table(tiny.clone());
+        let mut random = sim::Rng::new(7);
+        for _ in 0..12 {
+            if game.is_terminal(&cards) {
+                break;
+            }
+            let option = Agent::choose(&plain, &cards, &game, &mut random);
+            assert_eq!(Agent::choose(&cached, &cards, &game, &mut random), option);
+            assert_eq!(Agent::choose(&bounded, &cards, &game, &mut random), option);
+            assert_eq!(table.option_count(&cards, &game), game.legal_option_count(&cards));
+            game.take_option_unchecked(&cards, option).unwrap();
+        }
+        let stats = table.stats();
+        assert!(stats.hits > 0 && stats.stores > 0);
+        assert!(stats.hit_rate() > 0.0 && stats.hit_rate() <= 1.0);
+        assert!(tiny.stats().overwrites > 0);
+        table.clear();
+        assert_eq!(table.stats(), stats);
+    }
+
+    #[test]
+    fn concurrent_games_do_not_interfere() {
+        let cards = keyword_cards();
+        let games = 10_000;
+        let threads = 8;
+        // every thread keeps its share of the games in progress at once, taking one option in
+        // each in turn, so every game is interleaved with thousands of others across threads
+        let played: Vec<(u64, Vec<GameEvent>)> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads).map(|thread| {
+                let cards = &cards;
+                scope.spawn(move || {
+                    let mut running: Vec<(u64, GameState, Random)> = (thread..games).step_by(threads as usize)
+                        .map(|seed| {
+                            let (game, random) = random_game(cards, seed);
+                            (seed, game, random)
+                        })
+                        .collect();
+                    for _ in 0..50 {
+                        for (_, game, random) in running.iter_mut() {
+                            step_random_game(cards, game, random);
+                        }
+                    }
+                    running.into_iter().map(|(seed, game, _)| (seed, game.events().to_vec())).collect::<Vec<_>>()
+                })
+            }).collect();
+            workers.into_iter().flat_map(|worker| worker.join().unwrap()).collect()
+        });
+        assert_eq!(played.len(), games as usize);
+        // games played alongside thousands of others must play out exactly as they do alone
+        for (seed, events) in played.iter().step_by(101) {
+            assert_eq!(&play_random_game(&cards, *seed), events);
+        }
+
+        // one game's options and caches can also be read from many threads at once
+        let options = random_game(&cards, 0).0.legal_options(&cards).to_vec();
+        let (game, _) = random_game(&cards, 0);
+        thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(|| assert_eq!(game.legal_options(&cards), options.as_slice()));
+            }
+        });
+    }
+
+    #[test]
+    fn simulations_are_deterministic_for_the_same_seeds() {
+        let cards = keyword_cards();
+        let id = |name| cards.card(name).unwrap().id;
+        let deck = Deck { left: vec![id("Soldier"), id("Lancer")], center: vec![], right: vec![id("Shieldbearer"), id("Soldier")] };
+        let seeds: Vec<u64> = (0..64).collect();
+        let outcomes = sim::simulate(&cards, [&deck, &deck], &seeds, 200);
+        assert_eq!(outcomes.iter().map(|outcome| outcome.seed).collect::<Vec<_>>(), seeds);
+        assert_eq!(outcomes, sim::simulate(&cards, [&deck, &deck], &seeds, 200));
+        assert_eq!(outcomes[7], sim::play(&cards, [&deck, &deck], 7, 200));
+
+        let stats = sim::stats(&outcomes);
+        assert_eq!(stats.games, 64);
+        assert_eq!(stats.wins[0] + stats.wins[1] + stats.draws + stats.unfinished, 64);
+        let mut merged = sim::stats(&outcomes[..20]);
+        merged.merge(&sim::stats(&outcomes[20..]));
+        assert_eq!(merged, stats);
+    }
+
+    #[test]
+    fn simulated_decks_cluster_into_archetypes() {
+        let cards = keyword_cards();
+        let id = |name| cards.card(name).unwrap().id;
+        let (soldier, shieldbearer, lancer) = (id("Soldier"), id("Shieldbearer"), id("Lancer"));
+        let decks = [
+            Deck { left: vec![soldier, soldier], center: vec![], right: vec![shieldbearer, soldier] },
+            Deck { left: vec![soldier, shieldbearer], center: vec![], right: vec![shieldbearer, soldier] },
+            Deck { left: vec![lancer, lancer], center: vec![], right: vec![lancer, soldier] },
+            Deck { left: vec![lancer, lancer], center: vec![], right: vec![lancer, lancer] },
+        ];
+        let seeds: Vec<u64> = (0..16).collect();
+        let win_rates = archetypes::win_rates(&cards, &decks, &seeds, 200);
+        assert!((0..4).all(|a| (0..4).all(|b| (win_rates[a][b] + win_rates[b][a] - 1.0).abs() < 1e-9)));
+
+        let metagame = archetypes::cluster(&decks, &win_rates, 2);
+        let mut groups: Vec<Vec<usize>> = metagame.archetypes.iter().map(|archetype| archetype.decks.clone()).collect();
+        groups.sort();
+        assert_eq!(groups, vec![vec![0, 1], vec![2, 3]]);
+        let lancers = metagame.archetypes.iter().find(|archetype| archetype.decks == [2, 3]).unwrap();
+        assert_eq!(lancers.core[0], (lancer, 1.0));
+        assert!((metagame.archetypes.iter().map(|archetype| archetype.share).sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!(metagame.diversity() > 1.0 && metagame.diversity() <= 2.0);
+        assert!(metagame.archetypes.windows(2).all(|pair| pair[0].share >= pair[1].share));
+        assert!(metagame.report(&cards).starts_with("2 archetypes"));
+
+        assert_eq!(archetypes::cluster(&decks, &win_rates, 10).archetypes.len(), 4);
+        assert!(archetypes::cluster(&[], &[], 3).archetypes.is_empty());
+    }
+
+    #[test]
+    fn tournaments_rate_stronger_agents_higher() {
+        let cards = keyword_cards();
+        let id = |name| cards.card(name).unwrap().id;
+        let decks = [
+            Deck { left: vec![id("Soldier"), id("Lancer"), id("Soldier")], center: vec![], right: vec![id("Shieldbearer"), id("Lancer")] },
+            Deck { left: vec![id("Lancer"), id("Lancer")], center: vec![], right: vec![id("Soldier"), id("Shieldbearer"), id("Soldier")] },
+        ];
+        let agents: [&dyn Agent ; 4] = [&RandomAgent, &GreedyAgent, &MinimaxAgent::new(2), &MctsAgent { rollout_limit: 20, ..MctsAgent::new(10) }];
+        let config = TournamentConfig { games: 1, max_options: 200, seed: 1 };
+        let standings = arena::tournament(&cards, &agents, &decks, config);
+        assert_eq!(standings.names, vec!["random", "greedy", "minimax (depth 2)", "mcts (10 iterations)"]);
+        // each pair plays both seats with each of the four pairs of decks
+        assert!(standings.records.iter().enumerate().all(|(i, records)| records.iter().enumerate().all(|(j, record)| record.games() == if i == j { 0 } else { 8 })));
+        assert!(standings.ratings[1].elo > standings.ratings[0].elo, "{}", standings.report());
+        assert!(standings.ratings.iter().all(|rating| rating.margin > 0.0 && rating.margin.is_finite()));
+        assert!(standings.ratings.iter().map(|rating| rating.elo).sum::<f64>().abs() < 1e-6);
+        assert_eq!(standings.report().lines().count(), 4);
+        assert_eq!(arena::tournament(&cards, &agents, &decks, config), standings);
+    }
+
+    /// A fair agent which checks it never learns the cards in its opponent's hand
+    struct Blindfolded;
+
+    impl FairAgent for Blindfolded {
+        fn name(&self) -> String {
+            "blindfolded".to_owned()
+        }
+
+        fn choose(&self, _card_pool: &Cards, view: &PlayerView, _random: &mut sim::Rng) -> PlayerOption {
+            assert!(view.field(view.viewer.next()).hand.iter().all(|card| card.is_hidden()));
+            view.options[0].clone()
+        }
+    }
+
+    #[test]
+    fn fair_agents_only_see_their_players_view() {
+        let cards = keyword_cards();
+        let id = |name| cards.card(name).unwrap().id;
+        let deck = Deck { left: vec![id("Soldier"), id("Lancer")], center: vec![], right: vec![id("Shieldbearer"), id("Lancer")] };
+        let (minimax, mcts) = (MinimaxAgent::new(2), MctsAgent::new(10));
+        let agents: [&dyn FairAgent ; 5] = [&RandomAgent, &Blindfolded, &GreedyAgent, &minimax, &mcts];
+        let config = TournamentConfig { games: 1, max_options: 100, ..TournamentConfig::default() };
+        let standings = arena::fair_tournament(&cards, &agents, &[deck], config);
+        assert_eq!(standings.names, vec!["random", "blindfolded", "greedy", "minimax (depth 2)", "mcts (10 iterations)"]);
+        assert_eq!(standings.records[0][1].games(), 2);
+        assert_eq!(arena::fair_tournament(&cards, &agents, &[Deck::default()], config).records[2][4].games(), 2);
+    }
+
+    #[test]
+    fn guessed_games_only_keep_the_cards_the_viewer_knows() -> Result<(), InvalidAction> {
+        let cards = keyword_cards();
+        let mut instances = CardInstances::default();
+        let soldier = instances.instantiate(cards.card("Soldier").unwrap());
+        let lancer = instances.instantiate(cards.card("Lancer").unwrap());
+        let hidden = instances.instantiate(cards.card("Lancer").unwrap());
+        let (soldier_id, lancer_id, hidden_id) = (soldier.instance, lancer.instance, hidden.instance);
+        let mut game = GameState::start((vec![], vec![], vec![], vec![soldier]), (vec![hidden], vec![], vec![], vec![lancer]));
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        let shieldbearer = cards.card("Shieldbearer").unwrap().id;
+        let view = PlayerView::of(&game, Player::One, &cards);
+        let guessed = view.guess_game(|| shieldbearer).unwrap();
+        assert_eq!(guessed.card(soldier_id).unwrap().card_type, cards.card("Soldier").unwrap().id);
+        assert_eq!(guessed.card(lancer_id).unwrap().card_type, shieldbearer);
+        assert_eq!(guessed.card(hidden_id).unwrap().card_type, shieldbearer);
+        assert_eq!(guessed.legal_options(&cards), view.options.as_slice());
+        // only the player with priority has a game to search
+        assert!(PlayerView::of(&game, Player::Two, &cards).guess_game(|| shieldbearer).is_none());
+        let read_back: PlayerView = serde_json::from_str(&serde_json::to_string(&view).unwrap()).unwrap();
+        assert_eq!(read_back, view);
+        assert!(read_back.guess_game(|| shieldbearer).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn recorded_games_replay_to_the_same_state_hash() {
+        let cards = keyword_cards();
+        let id = |name| cards.card(name).unwrap().id;
+        let deck = Deck {
+            left: vec![id("Soldier"), id("Lancer"), id("Shieldbearer"), id("Soldier"), id("Lancer")],
+            center: vec![],
+            right: vec![id("Shieldbearer"), id("Soldier"), id("Lancer"), id("Shieldbearer"), id("Soldier")],
+        };
+        let start = || {
+            let mut instances = CardInstances::default();
+            GameState::start(deck.instantiate(&cards, &mut instances), deck.instantiate(&cards, &mut instances))
+        };
+        let mut game = start();
+        let mut random = sim::Rng::new(2024);
+        let mut recorded = vec![];
+        while game.result(&cards).is_none() && recorded.len() < 500 {
+            let mut options = game.priority_player_options(&cards);
+            let option = options.swap_remove(random.below(options.len()));
+            game.take_option_unchecked(&cards, option.clone()).unwrap();
+            recorded.push((option, game.state_hash()));
+        }
+        assert!(recorded.len() > 20);
+
+        let mut replayed = start();
+        for (option, hash) in recorded.iter().cloned() {
+            replayed.priorty_player_take_option(&cards, option).unwrap();
+            assert_eq!(replayed.state_hash(), hash);
+        }
+        // Recorded on one platform, so CI on any other platform fails here if the engine plays
+        // differently there. Changing what the hash covers or how the game plays needs this
+        // updating.
+        assert_eq!(game.state_hash(), StateHash(9597124660767662462));
+    }
+
+    #[test]
+    fn legal_options_are_cached_until_the_game_changes() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = keyword_cards();
+        let soldier = instances.instantiate(cards.card("Soldier").unwrap());
+        let instance = soldier.instance;
+        let mut game = GameState::start((vec![], vec![], vec![], vec![soldier]), (vec![], vec![], vec![], vec![]));
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        let first = game.legal_options(&cards).as_ptr();
+        assert_eq!(first, game.legal_options(&cards).as_ptr());
+        assert_eq!(game.legal_options(&cards).len(), 15);
+        game.take_action(&cards, EngineAction::SummonFromHandToSlot(instance, FieldSlot::F0))?;
+        // with the card summoned by an engine action there is nothing left to summon
+        assert_eq!(game.legal_options(&cards), &[PlayerOption::SkipAction]);
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic]
+    #[cfg(debug_assertions)]
+    fn unchecked_options_are_still_checked_in_debug_builds() {
+        let cards = keyword_cards();
+        let mut game = GameState::start((vec![], vec![], vec![], vec![]), (vec![], vec![], vec![], vec![]));
+        let _ = game.take_option_unchecked(&cards, PlayerOption::SkipResponse);
+    }
+
+    static ACTIVATION_CHECKS: AtomicUsize = AtomicUsize::new(0);
+
+    /// An effect that can never activate, which counts how many times it is asked
+    #[derive(Debug, Deserialize, Serialize)]
+    struct CountedCheck;
+
+    #[typetag::serde]
+    impl card_type::CardEffect for CountedCheck {
+        fn can_activate(&self, _: &Cards, _: &CardType, _: &GameState, _: CardInstance, _: &ResolutionContext) -> Vec<Activation> {
+            ACTIVATION_CHECKS.fetch_add(1, Ordering::SeqCst);
+            vec![]
+        }
+
+        fn activate(&self, _: &Cards, _: &CardType, _: &mut GameState, _: CardInstance, _: &Activation, _: &ResolutionContext) {}
+
+        fn resolve(&self, _: &Cards, _: &CardType, _: &mut GameState, _: CardInstance, _: &Activation, _: &ResolutionContext) {}
+    }
+
+    #[test]
+    fn cards_keep_their_status_as_they_move() -> Result<(), InvalidAction> {
+        let cards = keyword_cards();
+        let mut instances = CardInstances::default();
+        let lancer = instances.instantiate(cards.card("Lancer").unwrap());
+        let drawn = lancer.instance;
+        let mut game = GameState::start((vec![lancer], vec![], vec![], vec![]), (vec![], vec![], vec![], vec![]));
+        let status = |game: &GameState, instance| game.card(instance).map(Card::status);
+        assert_eq!(status(&game, drawn), Some(CardStatus::Deck));
+        game.take_action(&cards, EngineAction::Draw(Player::One, FaceDownDeck::Left))?;
+        assert_eq!(status(&game, drawn), Some(CardStatus::Hand));
+        game.take_action(&cards, EngineAction::SummonFromHandToSlot(drawn, FieldSlot::F0))?;
+        assert_eq!(status(&game, drawn), Some(CardStatus::Field { just_summoned: true }));
+        game.take_action(&cards, EngineAction::DestroyOnField(drawn))?;
+        assert_eq!(status(&game, drawn), Some(CardStatus::Destroyed));
+        game.take_action(&cards, EngineAction::RemoveFromGame(drawn))?;
+        assert_eq!(status(&game, drawn), Some(CardStatus::Removed));
+        assert_eq!(game.field_of(Player::One).removed().len(), 1);
+        assert!(game.take_action(&cards, EngineAction::RemoveFromGame(drawn)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn the_field_can_be_inspected_without_changing_it() -> Result<(), InvalidAction> {
+        let cards = keyword_cards();
+        let mut instances = CardInstances::default();
+        let lancer = instances.instantiate(cards.card("Lancer").unwrap());
+        let summoned = lancer.instance;
+        let spare = instances.instantiate(cards.card("Lancer").unwrap());
+        let mut game = GameState::start((vec![spare], vec![], vec![], vec![lancer]), (vec![], vec![], vec![], vec![]));
+        game.take_action(&cards, EngineAction::SummonFromHandToSlot(summoned, FieldSlot::F1))?;
+        let field = game.field_of(Player::One);
+        assert_eq!(field.slot(FieldSlot::F1).map(|card| card.instance), Some(summoned));
+        assert!(field.slot(FieldSlot::F0).is_none());
+        assert_eq!(field.slot_of(summoned), Some(FieldSlot::F1));
+        assert_eq!(
+            field.occupied_slots().map(|(slot, card)| (slot, card.instance)).collect::<Vec<_>>(),
+            vec![(FieldSlot::F1, summoned)]
+        );
+        assert!(!field.empty_slots().contains(&FieldSlot::F1));
+        assert_eq!(field.deck_sizes(), DeckSizes { left: 1, center: 0, right: 0 });
+        assert!(field.hand().is_empty());
+        assert!(Column::all().all(|column| field.destroyed(column).is_empty()));
+        Ok(())
+    }
+
+    #[test]
+    fn both_sides_can_be_inspected_from_either_player() {
+        let cards = keyword_cards();
+        let lancer = CardInstances::default().instantiate(cards.card("Lancer").unwrap());
+        let game = GameState::start((vec![lancer], vec![], vec![], vec![]), (vec![], vec![], vec![], vec![]));
+        assert_eq!(Player::all().collect::<Vec<_>>(), vec![Player::One, Player::Two]);
+        assert_eq!(game.opponent_of(Player::Two).deck_sizes().left, 1);
+        assert_eq!(game.opponent_of(Player::One).deck_sizes().left, 0);
+        assert!(game.non_priority_player() == game.field_of(Player::Two));
+        assert_eq!(game.options_of(Player::One, &cards), game.legal_options(&cards));
+        assert!(game.options_of(Player::Two, &cards).is_empty());
+    }
+
+    #[test]
+    fn formats_with_resources_charge_them_to_summon() -> Result<(), InvalidAction> {
+        let cards = Cards::from_test(vec![
+            r#"
+            name = "Ogre"
+            defense = 4
+            attack = 4
+            resource_cost = 2
+            [[effects]]
+                type = "OnField"
+                mandatory = false
+                [effects.trigger]
+                    type = "PayResources"
+                    resources = 1
+                    [effects.trigger.trigger]
+                        type = "GainResources"
+                        whose = "Opponent"
+                        resources = 3
+            "#,
+        ]).unwrap();
+        let card_type = cards.card("Ogre").unwrap();
+        assert_eq!(card_type.effect_summaries()[0].cost, Some("Pay 1 resources.".to_owned()));
+        let game = |resources_per_turn| {
+            let ogre = CardInstances::default().instantiate(card_type);
+            let instance = ogre.instance;
+            let mut game = GameState::start((vec![], vec![], vec![], vec![ogre]), (vec![], vec![], vec![], vec![]));
+            game.configure(GameConfig { resources_per_turn, ..GameConfig::default() });
+            (game, instance)
+        };
+        let summon = |instance| PlayerOption::Action(Action::Summon { instance, slot: FieldSlot::F0 });
+
+        // without resources every card is free to summon
+        let (mut free, instance) = game(0);
+        free.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        free.priorty_player_take_option(&cards, summon(instance))?;
+        assert_eq!(free.field_of(Player::One).resources(), 0);
+
+        let (mut poor, instance) = game(1);
+        poor.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        assert!(!poor.legal_options(&cards).contains(&summon(instance)));
+
+        let (mut rich, instance) = game(3);
+        let condition = HasResources { resources: 3 };
+        let activation = Activation { status: ActivatableType::Can, data: ActivationData::default() };
+        assert!(condition.met(&cards, card_type, &rich, instance, &activation, &ResolutionContext::default()));
+        rich.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        rich.priorty_player_take_option(&cards, summon(instance))?;
+        assert_eq!(rich.field_of(Player::One).resources(), 1);
+        assert!(!condition.met(&cards, card_type, &rich, instance, &activation, &ResolutionContext::default()));
+        assert!(rich.take_action(&cards, EngineAction::SpendResources(Player::One, 2)).is_err());
+        rich.take_action(&cards, EngineAction::GainResources(Player::Two, 5))?;
+        assert_eq!(rich.field_of(Player::Two).resources(), 5);
+        Ok(())
+    }
+
+    #[test]
+    fn effects_can_add_random_cards_from_the_enabled_card_pool() -> Result<(), InvalidAction> {
+        let dragon = |name: &str| format!("name = \"{}\"\ndefense = 2\nattack = 2\ntags = [\"dragon\"]", name);
+        let cards = Cards::from_test(vec![
+            r#"
+            name = "Hatchery"
+            defense = 1
+            attack = 0
+            [[effects]]
+                type = "OnSummon"
+                mandatory = true
+                [effects.trigger]
+                    type = "AddRandomCard"
+                    whose = "Controller"
+                    filter = { tag = "dragon" }
+            "#,
+            &dragon("Wyrm"),
+            &dragon("Drake"),
+            "name = \"Soldier\"\ndefense = 3\nattack = 2",
+        ]).unwrap().load_packs(&[PackSource::new("wilds", MemorySource { files: vec![dragon("Wyvern")] })]).unwrap();
+        let hatchery = cards.card("Hatchery").unwrap();
+        assert_eq!(hatchery.effect_summaries()[0].text, "Add a random dragon card to your hand.");
+        let dragons = ["Wyrm", "Drake", "wilds:Wyvern"].iter().map(|&name| cards.card(name).unwrap().id).collect::<Vec<_>>();
+
+        let play = |seed, disabled_packs: Vec<String>| -> Result<GameState, InvalidAction> {
+            let mut instances = CardInstances::default();
+            let hatchery = instances.instantiate(hatchery);
+            let instance = hatchery.instance;
+            let filler = instances.instantiate(cards.card("Soldier").unwrap());
+            let mut game = GameState::start((vec![], vec![], vec![], vec![hatchery]), (vec![filler], vec![], vec![], vec![]));
+            game.configure(GameConfig { seed, disabled_packs, ..GameConfig::default() });
+            game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+            game.priorty_player_take_option(&cards, PlayerOption::Action(Action::Summon { instance, slot: FieldSlot::F0 }))?;
+            while !game.legal_options(&cards).contains(&PlayerOption::SkipAction) {
+                let option = game.legal_options(&cards)[0].clone();
+                game.priorty_player_take_option(&cards, option)?;
+            }
+            Ok(game)
+        };
+        let game = play(7, vec![])?;
+        let generated = game.events().iter().filter_map(|event| match *event {
+            GameEvent::Generated { player: Player::One, card } => Some(card),
+            _ => None,
+        }).collect::<Vec<_>>();
+        assert_eq!(generated.len(), 1);
+        assert!(dragons.contains(&generated[0]));
+        let hand = game.field_of(Player::One).hand();
+        assert_eq!(hand.len(), 1);
+        assert_eq!((hand[0].card_type, hand[0].owner(), hand[0].status()), (generated[0], Player::One, CardStatus::Hand));
+        // the same seed generates the same card, and the random numbers are part of the state
+        assert_eq!(play(7, vec![])?.state_hash(), game.state_hash());
+        assert_eq!(play(7, vec![])?.events(), game.events());
+
+        // disabled packs are never chosen from
+        let filter = PoolFilter { tag: Some("dragon".to_owned()), ..PoolFilter::default() };
+        assert_eq!(filter.candidates(&cards, &game), dragons);
+        let game = play(7, vec!["wilds".to_owned()])?;
+        assert_eq!(filter.candidates(&cards, &game), dragons[..2]);
+        let mut empty = GameState::start((vec![], vec![], vec![], vec![]), (vec![], vec![], vec![], vec![]));
+        assert!(empty.take_action(&cards, EngineAction::GenerateToHand(Player::Two, vec![])).is_err());
+        empty.take_action(&cards, EngineAction::GenerateToHand(Player::Two, vec![dragons[0]]))?;
+        assert_eq!(empty.field_of(Player::Two).hand()[0].card_type, dragons[0]);
+        Ok(())
+    }
+
+    #[test]
+    fn undying_cards_can_be_summoned_from_their_destroyed_pile_damaged() -> Result<(), InvalidAction> {
+        let cards = Cards::from_test(vec![
+            "name = \"Phoenix\"\ndefense = 5\nattack = 2\nkeywords = [\"Undying\"]",
+            "name = \"Soldier\"\ndefense = 3\nattack = 2",
+        ]).unwrap();
+        let mut instances = CardInstances::default();
+        let phoenix = instances.instantiate(cards.card("Phoenix").unwrap());
+        let soldier = instances.instantiate(cards.card("Soldier").unwrap());
+        let filler = instances.instantiate(cards.card("Soldier").unwrap());
+        let (phoenix_instance, soldier_instance) = (phoenix.instance, soldier.instance);
+        let mut game = GameState::start((vec![], vec![], vec![], vec![phoenix, soldier]), (vec![filler], vec![], vec![], vec![]));
+        for &(instance, slot) in [(phoenix_instance, FieldSlot::F2), (soldier_instance, FieldSlot::F3)].iter() {
+            game.take_action(&cards, EngineAction::SummonFromHandToSlot(instance, slot))?;
+            game.take_action(&cards, EngineAction::DestroyOnField(instance))?;
+        }
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        let revivals = game.legal_options(&cards).iter()
+            .filter_map(|option| match *option {
+                PlayerOption::Action(Action::Revive { instance, slot }) => Some((instance, slot)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        // only the Undying card may be revived, to any empty slot
+        assert_eq!(revivals.len(), 14);
+        assert!(revivals.iter().all(|&(instance, _)| instance == phoenix_instance));
+
+        let revive = PlayerOption::Action(Action::Revive { instance: phoenix_instance, slot: FieldSlot::B0 });
+        assert_eq!(OptionAnnotation::of(&game, &revive).verb, "revive");
+        game.priorty_player_take_option(&cards, revive)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipResponse)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipResponse)?;
+        let field = game.field_of(Player::One);
+        assert_eq!(field.slot_of(phoenix_instance), Some(FieldSlot::B0));
+        assert_eq!(game.card_on_field(phoenix_instance).map(|card| card.damage), Some(2));
+        assert!(Column::all().all(|column| field.destroyed(column).iter().all(|card| card.instance != phoenix_instance)));
+        let phoenix = cards.card("Phoenix").unwrap().id;
+        assert!(game.events().contains(&GameEvent::Summoned { player: Player::One, card: phoenix, slot: FieldSlot::B0 }));
+        Ok(())
+    }
+
+    #[test]
+    fn undying_cards_left_to_revive_keep_the_game_going() -> Result<(), InvalidAction> {
+        let cards = Cards::from_test(vec!["name = \"Phoenix\"\ndefense = 5\nattack = 2\nkeywords = [\"Undying\"]"]).unwrap();
+        let phoenix = CardInstances::default().instantiate(cards.card("Phoenix").unwrap());
+        let instance = phoenix.instance;
+        let mut game = GameState::start((vec![], vec![], vec![], vec![phoenix]), (vec![], vec![], vec![], vec![]));
+        game.take_action(&cards, EngineAction::SummonFromHandToSlot(instance, FieldSlot::F0))?;
+        game.take_action(&cards, EngineAction::DestroyOnField(instance))?;
+        // both players' decks, hands and fields are empty, but the phoenix can still come back
+        assert_eq!(game.result(&cards), None);
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        let revive = PlayerOption::Action(Action::Revive { instance, slot: FieldSlot::F0 });
+        assert!(game.legal_options(&cards).contains(&revive));
+        game.priorty_player_take_option(&cards, revive)?;
+        assert!(game.card_on_field(instance).is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn draw_rules_change_which_decks_can_be_drawn_from_and_how_often() -> Result<(), InvalidAction> {
+        let cards = Cards::from_test(vec![
+            r#"
+            name = "Jailer"
+            defense = 2
+            attack = 1
+            [[effects]]
+                type = "OnSummon"
+                mandatory = true
+                [effects.trigger]
+                    type = "PreventDraws"
+                    whose = "Opponent"
+                    deck = "Left"
+                    turns = 1
+            [[effects]]
+                type = "OnSummon"
+                mandatory = true
+                [effects.trigger]
+                    type = "ExtraDraws"
+                    whose = "Controller"
+                    draws = 1
+                    turns = 2
+            "#,
+        ]).unwrap();
+        let jailer = cards.card("Jailer").unwrap();
+        let summaries = jailer.effect_summaries();
+        assert_eq!(summaries[0].text, "Your opponent can't draw from their left deck for 1 turn.");
+        assert_eq!(summaries[1].text, "You may draw 1 more card each turn for 2 turns.");
+
+        let mut instances = CardInstances::default();
+        let mut deck = |size| (0..size).map(|_| instances.instantiate(jailer)).collect::<Vec<_>>();
+        let mut game = GameState::start((deck(3), vec![], deck(3), vec![]), (deck(2), vec![], deck(1), vec![]));
+        game.take_action(&cards, EngineAction::ModifyDraws(DrawModifier { player: Player::Two, rule: DrawRule::CannotDraw(Some(FaceDownDeck::Left)), turns: 1 }))?;
+        game.take_action(&cards, EngineAction::ModifyDraws(DrawModifier { player: Player::One, rule: DrawRule::ExtraDraws(1), turns: 1 }))?;
+        let draws = |game: &GameState| game.legal_options(&cards).iter()
+            .filter_map(|option| match option {
+                PlayerOption::Draw(deck) => Some(*deck),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        // the extra draw is offered alongside the actions, only once, and doesn't end the turn
+        assert_eq!(draws(&game), vec![FaceDownDeck::Left, FaceDownDeck::Right]);
+        game.priorty_player_take_option(&cards, PlayerOption::Draw(FaceDownDeck::Left))?;
+        assert_eq!(draws(&game), vec![FaceDownDeck::Left, FaceDownDeck::Right]);
+        game.priorty_player_take_option(&cards, PlayerOption::Draw(FaceDownDeck::Right))?;
+        assert_eq!(draws(&game), vec![]);
+        assert_eq!(game.field_of(Player::One).hand_size(), 2);
+        assert_eq!(game.open(), GameStateType::Open { phase: Phase::MayTakeAction });
+        assert_eq!(game.priority(), Player::One);
+        assert!(game.legal_options(&cards).iter().any(|option| matches!(option, PlayerOption::Action(Action::Summon { .. }))));
+
+        let view = PlayerView::of(&game, Player::One, &cards);
+        assert_eq!(view.field(Player::Two).draw_rules, vec![(DrawRule::CannotDraw(Some(FaceDownDeck::Left)), 1)]);
+        assert_eq!(DrawRule::CannotDraw(Some(FaceDownDeck::Left)).to_string(), "can't draw from their left deck");
+        game.priorty_player_take_option(&cards, PlayerOption::SkipAction)?;
+        // player one's rule ended with their turn, and player two's lasts through theirs
+        assert_eq!(game.draw_rules().len(), 1);
+        assert_eq!(draws(&game), vec![FaceDownDeck::Right]);
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipAction)?;
+        assert!(game.draw_rules().is_empty());
+        assert_eq!(draws(&game), vec![FaceDownDeck::Left, FaceDownDeck::Right]);
+
+        // a player who can only draw from a deck they're stopped drawing from can't draw at all
+        let mut stopped = GameState::start((deck(1), vec![], vec![], vec![]), (deck(1), vec![], vec![], vec![]));
+        stopped.take_action(&cards, EngineAction::ModifyDraws(DrawModifier { player: Player::One, rule: DrawRule::CannotDraw(None), turns: 1 }))?;
+        assert_eq!(stopped.result(&cards).map(|result| result.reason()), Some(EndReason::NoDrawOrAction));
+        Ok(())
+    }
+
+    #[test]
+    fn conditions_can_count_the_cards_in_an_opponents_hidden_zones() -> Result<(), InvalidAction> {
+        let cards = Cards::from_test(vec![
+            r#"
+            name = "Spy"
+            defense = 1
+            attack = 1
+            [[effects]]
+                type = "OnSummon"
+                mandatory = true
+                [effects.trigger]
+                    type = "Conditional"
+                    [effects.trigger.condition]
+                        type = "ZoneCount"
+                        whose = "Opponent"
+                        zone = "Hand"
+                        at_least = 2
+                    [effects.trigger.trigger]
+                        type = "DrawCards"
+                        whose = "Controller"
+                        deck_choice = "Left"
+                        count = 1
+            "#,
+        ]).unwrap();
+        let spy = cards.card("Spy").unwrap();
+        assert_eq!(
+            spy.effect_summaries()[0].text,
+            "If your opponent has at least 2 cards in hand: Draw 1 card from your left deck."
+        );
+        let condition = ZoneCount { whose: Whose::Controller, zone: PublicZone::Decks, at_least: 1, at_most: Some(3) };
+        assert_eq!(condition.text(), "you have between 1 and 3 cards in your left and right decks");
+        assert!(condition.public());
+
+        let play = |opponent_hand: usize| -> Result<usize, InvalidAction> {
+            let mut instances = CardInstances::default();
+            let summoned = instances.instantiate(spy);
+            let instance = summoned.instance;
+            let left = vec![instances.instantiate(spy), instances.instantiate(spy)];
+            let hand = (0..opponent_hand).map(|_| instances.instantiate(spy)).collect();
+            let mut game = GameState::start((left, vec![], vec![], vec![summoned]), (vec![], vec![], vec![], hand));
+            game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+            game.priorty_player_take_option(&cards, PlayerOption::Action(Action::Summon { instance, slot: FieldSlot::F0 }))?;
+            while game.turn() == Player::One && game.result(&cards).is_none() {
+                let option = game.legal_options(&cards).last().cloned().ok_or(InvalidAction)?;
+                game.priorty_player_take_option(&cards, option)?;
+            }
+            Ok(game.field_of(Player::One).hand_size())
+        };
+        assert_eq!(play(1)?, 0);
+        assert_eq!(play(2)?, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn damage_counters_can_be_converted_and_moved_between_cards_together() -> Result<(), InvalidAction> {
+        let cards = keyword_cards();
+        let soldier_type = cards.card("Soldier").unwrap();
+        let mut instances = CardInstances::default();
+        let soldier = instances.instantiate(soldier_type);
+        let lancer = instances.instantiate(cards.card("Lancer").unwrap());
+        let in_deck = instances.instantiate(cards.card("Lancer").unwrap());
+        let instances = (soldier.instance, lancer.instance, in_deck.instance);
+        let mut game = GameState::start((vec![in_deck], vec![], vec![], vec![soldier]), (vec![], vec![], vec![], vec![lancer]));
+        let (soldier, lancer, in_deck) = instances;
+        game.take_action(&cards, EngineAction::SummonFromHandToSlot(soldier, FieldSlot::F0))?;
+        game.take_action(&cards, EngineAction::SummonFromHandToSlot(lancer, FieldSlot::F0))?;
+        game.take_action(&cards, EngineAction::DamageOnField(soldier, 2))?;
+        let damage = |game: &GameState, instance| game.card_on_field(instance).map(|card| card.damage);
+
+        // changes which can't all be made leave every card as it was
+        assert!(game.take_action(&cards, EngineAction::ChangeCounters(vec![(soldier, -1), (in_deck, 1)])).is_err());
+        assert!(game.take_action(&cards, EngineAction::ChangeCounters(vec![(soldier, -2), (soldier, -1)])).is_err());
+        assert_eq!(damage(&game, soldier), Some(2));
+
+        // only as many counters are removed as there are cards to draw
+        let context = ResolutionContext::default();
+        let draw = DrawForCounters { deck_choice: DeckChoice::Either, max: None };
+        assert_eq!(draw.text(), "Remove every damage counter from this card to draw a card for each from either of your decks.");
+        let variants = draw.variants(&cards, soldier_type, &game, soldier, &context);
+        assert_eq!(variants.len(), 1);
+        let activation = Activation { status: ActivatableType::Can, data: variants[0].clone() };
+        draw.resolution(&cards, soldier_type, &mut game, soldier, &activation, &context);
+        assert_eq!(damage(&game, soldier), Some(1));
+        assert_eq!(game.field_of(Player::One).hand_size(), 1);
+
+        // moving counters onto a card can destroy it
+        game.take_action(&cards, EngineAction::DamageOnField(soldier, 1))?;
+        let transfer = TransferCounters { whose: Whose::Opponent, filter: TargetFilter::default(), max: None };
+        let variants = transfer.variants(&cards, soldier_type, &game, soldier, &context);
+        assert_eq!(variants.iter().map(|data| data.targets.clone()).collect::<Vec<_>>(), vec![vec![lancer]]);
+        let activation = Activation { status: ActivatableType::Can, data: variants[0].clone() };
+        transfer.resolution(&cards, soldier_type, &mut game, soldier, &activation, &context);
+        assert_eq!(damage(&game, soldier), Some(0));
+        assert_eq!(damage(&game, lancer), None);
+        assert_eq!(PublicZone::Destroyed.count(game.field_of(Player::Two)), 1);
+        assert!(transfer.variants(&cards, soldier_type, &game, soldier, &context).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn cards_can_be_removed_from_the_game_unless_the_format_forbids_it() -> Result<(), InvalidAction> {
+        let cards = keyword_cards();
+        let card_type = cards.card("Lancer").unwrap();
+        let summoned = || -> Result<(GameState, CardInstance), InvalidAction> {
+            let lancer = CardInstances::default().instantiate(card_type);
+            let instance = lancer.instance;
+            let mut game = GameState::start((vec![], vec![], vec![], vec![lancer]), (vec![], vec![], vec![], vec![]));
+            game.take_action(&cards, EngineAction::SummonFromHandToSlot(instance, FieldSlot::F2))?;
+            Ok((game, instance))
+        };
+        let (mut game, removed) = summoned()?;
+        let (mut without_removal, _) = summoned()?;
+        let activation = Activation { status: ActivatableType::Can, data: ActivationData::default() };
+        let condition = NamedCardRemoved { name: "Lancer".to_owned() };
+        assert!(!condition.met(&cards, card_type, &game, removed, &activation, &ResolutionContext::default()));
+        game.take_action(&cards, EngineAction::RemoveFromGame(removed))?;
+        assert!(condition.met(&cards, card_type, &game, removed, &activation, &ResolutionContext::default()));
+        assert!(game.knows(Player::Two, removed));
+        assert!(describe_state(&game, &cards).contains("removed [Lancer]"));
+
+        without_removal.configure(GameConfig { removal: false, ..GameConfig::default() });
+        without_removal.take_action(&cards, EngineAction::RemoveFromGame(removed))?;
+        assert_eq!(without_removal.card(removed).map(Card::status), Some(CardStatus::Destroyed));
+        without_removal.take_action(&cards, EngineAction::RemoveFromGame(removed))?;
+        assert!(without_removal.field_of(Player::One).removed().is_empty());
+
+        let trigger = RemoveTarget { whose: Whose::Opponent, filter: TargetFilter::default() };
+        assert_eq!(trigger.text(), "Remove a card on your opponent's field from the game.");
+        Ok(())
+    }
+
+    #[test]
+    fn effect_activations_are_cached_until_the_game_changes() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = Cards::from_test(vec![
+            r#"
+            name = "Counter"
+            defense = 1
+            attack = 1
+            [[effects]]
+                type = "CountedCheck"
+            "#,
+        ]).unwrap();
+        let counter = instances.instantiate(cards.card("Counter").unwrap());
+        let instance = counter.instance;
+        let mut game = GameState::start((vec![], vec![], vec![], vec![counter]), (vec![], vec![], vec![], vec![]));
+        let checks = || ACTIVATION_CHECKS.load(Ordering::SeqCst);
+        // the card can be summoned, so checking for a winner stops before asking its effect
+        assert_eq!(game.winner(&cards), None);
+        assert_eq!(checks(), 0);
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        game.priority_player_options(&cards);
+        assert_eq!(checks(), 1);
+        game.priority_player_options(&cards);
+        game.has_any_option(&cards);
+        assert_eq!(checks(), 1);
+        game.take_action(&cards, EngineAction::SummonFromHandToSlot(instance, FieldSlot::F0))?;
+        game.priority_player_options(&cards);
+        assert_eq!(checks(), 2);
+        Ok(())
+    }
+
+    static DESTROY_CHECKS: AtomicUsize = AtomicUsize::new(0);
+
+    /// An effect that can never activate and only waits on its card being destroyed, which counts
+    /// how many times it is asked
+    #[derive(Debug, Deserialize, Serialize)]
+    struct CountedDestroyCheck;
+
+    #[typetag::serde]
+    impl card_type::CardEffect for CountedDestroyCheck {
+        fn can_activate(&self, _: &Cards, _: &CardType, _: &GameState, _: CardInstance, _: &ResolutionContext) -> Vec<Activation> {
+            DESTROY_CHECKS.fetch_add(1, Ordering::SeqCst);
+            vec![]
+        }
+
+        fn activate(&self, _: &Cards, _: &CardType, _: &mut GameState, _: CardInstance, _: &Activation, _: &ResolutionContext) {}
+
+        fn resolve(&self, _: &Cards, _: &CardType, _: &mut GameState, _: CardInstance, _: &Activation, _: &ResolutionContext) {}
+
+        fn timing(&self) -> TriggerTiming {
+            TriggerTiming::OnDestroy
+        }
+    }
+
+    #[test]
+    fn effects_are_only_asked_when_their_timing_matches() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = Cards::from_test(vec![
+            r#"
+            name = "Watcher"
+            defense = 1
+            attack = 1
+            [[effects]]
+                type = "CountedDestroyCheck"
+            "#,
+        ]).unwrap();
+        assert_eq!(cards.card("Watcher").unwrap().effect_summaries()[0].timing, TriggerTiming::OnDestroy);
+        let watcher = instances.instantiate(cards.card("Watcher").unwrap());
+        let instance = watcher.instance;
+        let mut game = GameState::start((vec![], vec![], vec![], vec![watcher]), (vec![], vec![], vec![], vec![]));
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        game.take_action(&cards, EngineAction::SummonFromHandToSlot(instance, FieldSlot::F0))?;
+        game.priority_player_options(&cards);
+        assert_eq!(DESTROY_CHECKS.load(Ordering::SeqCst), 0);
+
+        let context = ResolutionContext { trigger: Some(Trigger::Destroyed(vec![instance])), ..ResolutionContext::default() };
+        assert!(TriggerTiming::OnDestroy.could_activate(&context, false));
+        assert!(!TriggerTiming::OnSummon.could_activate(&context, false));
+        assert!(TriggerTiming::Action.could_activate(&ResolutionContext::default(), true));
+        Ok(())
+    }
+
+    #[test]
+    fn effects_waiting_on_the_turn_starting_activate_before_the_draw() -> Result<(), InvalidAction> {
+        let cards = Cards::from_test(vec![
+            r#"
+            name = "Sundial"
+            defense = 1
+            attack = 0
+            [[effects]]
+                type = "OnTurnStart"
+                mandatory = true
+                [effects.trigger]
+                    type = "GainResources"
+                    whose = "Controller"
+                    resources = 2
+            "#,
+        ]).unwrap();
+        assert_eq!(cards.card("Sundial").unwrap().effect_summaries()[0].timing, TriggerTiming::StartOfTurn);
+        let sundial = CardInstances::default().instantiate(cards.card("Sundial").unwrap());
+        let instance = sundial.instance;
+        let mut game = GameState::start((vec![], vec![], vec![], vec![sundial]), (vec![], vec![], vec![], vec![]));
+        game.take_action(&cards, EngineAction::SummonFromHandToSlot(instance, FieldSlot::F0))?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipAction)?;
+
+        // only the start of its controller's turns opens a window for it
+        assert_eq!((game.open(), game.priority()), (GameStateType::Open { phase: Phase::MayDraw }, Player::Two));
+        let resources = game.field_of(Player::One).resources();
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipAction)?;
+        assert_eq!((game.open(), game.priority(), game.turn()), (GameStateType::Closed, Player::One, Player::One));
+        let activate = game.legal_options(&cards).iter()
+            .find(|option| matches!(option, PlayerOption::Action(Action::Effect { instance: activated, .. }) if *activated == instance))
+            .cloned()
+            .unwrap();
+        game.priorty_player_take_option(&cards, activate)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipResponse)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipResponse)?;
+
+        // the same player then draws, rather than the turn passing
+        assert_eq!((game.open(), game.priority(), game.turn()), (GameStateType::Open { phase: Phase::MayDraw }, Player::One, Player::One));
+        assert_eq!(game.field_of(Player::One).resources(), resources + GameConfig::default().resources_per_turn + 2);
+
+        let context = ResolutionContext { trigger: Some(Trigger::TurnStarted(Player::One)), ..ResolutionContext::default() };
+        assert!(TriggerTiming::StartOfTurn.could_activate(&context, false));
+        assert!(!TriggerTiming::Static.could_activate(&context, true));
+        assert!(!TriggerTiming::Static.could_activate(&ResolutionContext::default(), true));
+        Ok(())
+    }
+
+    /// A mandatory effect which is offered differently every time it is asked, so the way it was
+    /// offered is never one it can activate in when taken, as a buggy effect might
+    #[derive(Debug, Default, Deserialize, Serialize)]
+    struct Flaky {
+        #[serde(skip)]
+        asked: AtomicUsize,
+    }
+
+    #[typetag::serde]
+    impl card_type::CardEffect for Flaky {
+        fn can_activate(&self, _: &Cards, _: &CardType, _: &GameState, _: CardInstance, _: &ResolutionContext) -> Vec<Activation> {
+            let column = Column::all().nth(self.asked.fetch_add(1, Ordering::SeqCst) % 7);
+            vec![Activation { status: ActivatableType::Mandatory, data: ActivationData { column, ..ActivationData::default() } }]
+        }
+
+        fn activate(&self, _: &Cards, _: &CardType, _: &mut GameState, _: CardInstance, _: &Activation, _: &ResolutionContext) {}
+
+        fn resolve(&self, _: &Cards, _: &CardType, _: &mut GameState, _: CardInstance, _: &Activation, _: &ResolutionContext) {}
+    }
+
+    #[test]
+    fn soft_locked_games_are_adjudicated_by_the_policy() -> Result<(), InvalidAction> {
+        let cards = Cards::from_test(vec![
+            r#"
+            name = "Glitch"
+            defense = 1
+            attack = 1
+            [[effects]]
+                type = "Flaky"
+            "#,
+            r#"
+            name = "Filler"
+            defense = 1
+            attack = 1
+            "#,
+        ]).unwrap();
+        let play = |policy| -> Result<GameState, InvalidAction> {
+            let mut instances = CardInstances::default();
+            let glitch = instances.instantiate(cards.card("Glitch").unwrap());
+            let instance = glitch.instance;
+            let other = instances.instantiate(cards.card("Filler").unwrap());
+            let mut game = GameState::start((vec![], vec![], vec![], vec![glitch]), (vec![], vec![], vec![], vec![other]));
+            game.configure(GameConfig { soft_lock: policy, ..GameConfig::default() });
+            game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+            game.priorty_player_take_option(&cards, PlayerOption::Action(Action::Summon { instance, slot: FieldSlot::F0 }))?;
+            game.priorty_player_take_option(&cards, PlayerOption::SkipResponse)?;
+            let option = game.legal_options(&cards)[0].clone();
+            assert!(matches!(option, PlayerOption::Action(Action::Effect { .. })));
+            game.priorty_player_take_option(&cards, option)?;
+            assert!(game.events().contains(&GameEvent::SoftLocked { player: Player::One }));
+            Ok(game)
+        };
+
+        let aborted = play(SoftLockPolicy::Abort)?;
+        assert_eq!(aborted.result(&cards).map(|result| result.reason()), Some(EndReason::Aborted));
+        assert!(aborted.pending_chain().is_empty());
+
+        let skipped = play(SoftLockPolicy::Skip)?;
+        assert_eq!(skipped.result(&cards), None);
+        assert_eq!(skipped.events().last(), Some(&GameEvent::Resolved));
+        assert_eq!(skipped.priority(), Player::Two);
+        assert!(!skipped.is_soft_locked(&cards));
+        Ok(())
+    }
+
+    #[test]
+    fn external_positions_are_imported_and_checked() {
+        let cards = keyword_cards();
+        let spec: PositionSpec = toml::from_str(r#"
+            turn = "Two"
+            phase = "MayTakeAction"
+
+            [player_one]
+            hand = ["Soldier"]
+            left_deck = ["Lancer", "Soldier"]
+            removed = ["Lancer"]
+            field = [{ card = "Shieldbearer", slot = "F3", damage = 3 }]
+
+            [player_two]
+            resources = 2
+            destroyed = [{ card = "Soldier", column = 3 }]
+            field = [
+                { card = "Lancer", slot = "F3" },
+                { card = "Soldier", slot = "F4", just_summoned = true },
+            ]
+        "#).unwrap();
+        let game = GameState::import(spec.clone(), &cards).unwrap();
+        assert_eq!((game.turn(), game.priority()), (Player::Two, Player::Two));
+        assert_eq!(game.open(), GameStateType::Open { phase: Phase::MayTakeAction });
+        let one = game.field_of(Player::One);
+        let shieldbearer = one.slot(FieldSlot::F3).unwrap();
+        assert_eq!((shieldbearer.damage, shieldbearer.owner()), (3, Player::One));
+        assert_eq!(one.remaining_defense_of(&cards, FieldSlot::F3), Some(1));
+        assert_eq!((one.hand().len(), one.removed().len()), (1, 1));
+        // the top of the deck is last, so the soldier is drawn first
+        assert_eq!(one.deck(FaceDownDeck::Left).last().unwrap().lookup_self(&cards).name, "Soldier");
+        let two = game.field_of(Player::Two);
+        assert_eq!((two.resources(), two.destroyed(Column(3)).len()), (2, 1));
+        let lancer = two.slot(FieldSlot::F3).unwrap().instance;
+        let attackers = attack_options(game.legal_options(&cards).to_vec());
+        assert!(!attackers.is_empty());
+        assert!(attackers.iter().all(|&(attacker, _)| attacker == lancer));
+
+        let mut unknown = spec.clone();
+        unknown.player_one.hand.push("Wizard".to_owned());
+        assert_eq!(GameState::import(unknown, &cards).unwrap_err(), ImportError::UnknownCard("Wizard".to_owned()));
+        let mut lethal = spec.clone();
+        lethal.player_one.field[0].damage = 4;
+        assert!(matches!(GameState::import(lethal, &cards), Err(ImportError::LethalDamage { damage: 4, defense: 4, .. })));
+        let mut taken = spec.clone();
+        taken.player_two.field[1].slot = FieldSlot::F3;
+        assert_eq!(
+            GameState::import(taken, &cards).unwrap_err(),
+            ImportError::SlotTaken { player: Player::Two, slot: FieldSlot::F3 },
+        );
+        let mut column = spec;
+        column.player_two.destroyed[0].column = Column(7);
+        assert_eq!(GameState::import(column, &cards).unwrap_err(), ImportError::NoSuchColumn(7));
+    }
+
+    #[test]
+    fn imported_positions_keep_to_summon_restrictions() {
+        let cards = keyword_cards().with_overrides(vec![
+            CardTypeBuilder::new("Golem").attack(4).defense(4).summon_restriction(SummonRestriction::EffectOnly).build().unwrap(),
+            CardTypeBuilder::new("Shade").attack(2).defense(2)
+                .summon_restriction(SummonRestriction::EffectOnly)
+                .in_center_deck(card_type::SacrificeToSummon { sacrifices: 1 })
+                .build().unwrap(),
+            CardTypeBuilder::new("Wraith").attack(3).defense(1).summon_restriction(SummonRestriction::DestroyedInColumn(2)).build().unwrap(),
+            CardTypeBuilder::new("Imp").attack(1).defense(1).in_center_deck(card_type::SacrificeToSummon { sacrifices: 0 }).build().unwrap(),
+        ]);
+        let spec: PositionSpec = toml::from_str(r#"
+            turn = "One"
+
+            [player_one]
+            center_deck = ["Shade"]
+            destroyed = [{ card = "Soldier", column = 2 }, { card = "Lancer", column = 2 }]
+            field = [{ card = "Wraith", slot = "F2" }, { card = "Shade", slot = "F5" }]
+        "#).unwrap();
+        assert!(GameState::import(spec.clone(), &cards).is_ok());
+
+        let mut golem = spec.clone();
+        golem.player_one.field[1].card = "Golem".to_owned();
+        assert_eq!(
+            GameState::import(golem, &cards).unwrap_err(),
+            ImportError::Unsummonable { card: "Golem".to_owned(), slot: FieldSlot::F5 },
+        );
+        let mut wraith = spec.clone();
+        wraith.player_one.destroyed.pop();
+        assert_eq!(
+            GameState::import(wraith, &cards).unwrap_err(),
+            ImportError::Unsummonable { card: "Wraith".to_owned(), slot: FieldSlot::F2 },
+        );
+        let mut imp = spec;
+        imp.player_one.center_deck.push("Imp".to_owned());
+        assert_eq!(
+            GameState::import(imp, &cards).unwrap_err(),
+            ImportError::FreeCardInCenter { player: Player::One, card: "Imp".to_owned() },
+        );
+    }
+
+    #[test]
+    fn decks_running_out_open_a_trigger_window() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = Cards::from_test(vec![
+            r#"
+            name = "Soldier"
+            defense = 3
+            attack = 2
+            "#,
+            r#"
+            name = "Last Stand"
+            defense = 4
+            attack = 4
+            [[effects]]
+                type = "OnDeckEmptied"
+                whose = "Controller"
+                deck = "Left"
+                mandatory = false
+                [effects.trigger]
+                    type = "SacrificeToSummon"
+                    sacrifices = 0
+            "#,
+        ]).unwrap();
+        let soldier = |instances: &mut CardInstances| instances.instantiate(cards.card("Soldier").unwrap());
+        let last = soldier(&mut instances);
+        let summoned = soldier(&mut instances);
+        let summoned_id = summoned.instance;
+        let last_stand = instances.instantiate(cards.card("Last Stand").unwrap());
+        let last_stand_id = last_stand.instance;
+        let mut game = GameState::start(
+            (vec![last], vec![last_stand], vec![], vec![summoned]),
+            (vec![], vec![], vec![soldier(&mut instances)], vec![]),
+        );
+        game.priorty_player_take_option(&cards, PlayerOption::Draw(FaceDownDeck::Left))?;
+        assert!(game.events().contains(&GameEvent::DeckEmptied { player: Player::One, deck: FaceDownDeck::Left }));
+        assert!(describe_turn(game.events(), &cards).contains("P1 drew from the left deck and ran out of cards in the left deck"));
+        game.priorty_player_take_option(&cards, PlayerOption::Action(Action::Summon { instance: summoned_id, slot: FieldSlot::F0 }))?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipResponse)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipResponse)?;
+
+        // the window for the deck running out opens once the summon resolves
+        assert_eq!((game.open(), game.priority(), game.turn()), (GameStateType::Closed, Player::One, Player::One));
+        let summon = game.legal_options(&cards).iter()
+            .find(|option| matches!(
+                option,
+                PlayerOption::Action(Action::Effect { instance, activation, .. })
+                    if *instance == last_stand_id && activation.data.slot == Some(FieldSlot::F3)
+            ))
+            .cloned()
+            .unwrap();
+        game.priorty_player_take_option(&cards, summon)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipResponse)?;
+        game.priorty_player_take_option(&cards, PlayerOption::SkipResponse)?;
+        assert_eq!(game.field_of(Player::One).slot(FieldSlot::F3).map(|card| card.instance), Some(last_stand_id));
+        assert_eq!((game.turn(), game.open()), (Player::Two, GameStateType::Open { phase: Phase::MayDraw }));
+
+        // running out is recorded for either player, whether or not any effect waits on it
+        game.priorty_player_take_option(&cards, PlayerOption::Draw(FaceDownDeck::Right))?;
+        assert!(game.events().contains(&GameEvent::DeckEmptied { player: Player::Two, deck: FaceDownDeck::Right }));
+        Ok(())
+    }
+
+    #[test]
+    fn mandatory_draws_leave_no_skipping_and_lose_on_empty_decks() -> Result<(), InvalidAction> {
+        let cards = keyword_cards();
+        let play = |mandatory_draw| -> Result<GameState, InvalidAction> {
+            let mut instances = CardInstances::default();
+            let mut soldier = || instances.instantiate(cards.card("Soldier").unwrap());
+            let mut game = GameState::start(
+                (vec![soldier()], vec![], vec![], vec![soldier()]),
+                (vec![], vec![], vec![], vec![soldier()]),
+            );
+            game.configure(GameConfig { mandatory_draw, ..GameConfig::default() });
+            let draws = game.legal_options(&cards).to_vec();
+            assert_eq!(draws.contains(&PlayerOption::SkipDraw), !mandatory_draw);
+            game.priorty_player_take_option(&cards, PlayerOption::Draw(FaceDownDeck::Left))?;
+            game.priorty_player_take_option(&cards, PlayerOption::SkipAction)?;
+            Ok(game)
+        };
+
+        // the second player could still summon from their hand, but has no deck left to draw from
+        let optional = play(false)?;
+        assert_eq!(optional.result(&cards), None);
+        let mandatory = play(true)?;
+        let result = mandatory.result(&cards).unwrap();
+        assert_eq!((result.winner(), result.reason()), (Some(Player::One), EndReason::NoDrawOrAction));
+
+        let rules = RulesDoc::new(&GameConfig { mandatory_draw: true, ..GameConfig::default() });
+        assert!(rules.section("Turns").unwrap().rules.iter().any(|rule| rule.text.contains("must draw")));
+        Ok(())
+    }
+
+    #[test]
+    fn center_decks_are_public_and_summoned_from_for_their_cost() -> Result<(), InvalidAction> {
+        let mut instances = CardInstances::default();
+        let cards = Cards::from_test(vec![
+            r#"
+            name = "Knight"
+            defense = 4
+            attack = 3
+            resource_cost = 2
+            "#,
+            r#"
+            name = "Squire"
+            defense = 1
+            attack = 1
+            "#,
+        ]).unwrap();
+        let knight = instances.instantiate(cards.card("Knight").unwrap());
+        let knight_id = knight.instance;
+        let squire = instances.instantiate(cards.card("Squire").unwrap());
+        let hidden = instances.instantiate(cards.card("Squire").unwrap());
+        let zones = (vec![hidden], vec![knight, squire], vec![], vec![]);
+        let mut game = GameState::start(zones, (vec![], vec![], vec![], vec![]));
+
+        // both players see the center deck, but only how many cards the face down decks hold
+        let opponent = PlayerView::of(&game, Player::Two, &cards);
+        let knight_type = cards.card("Knight").unwrap().id;
+        assert!(opponent.field(Player::One).center_deck.contains(&knight_type));
+        assert_eq!(opponent.field(Player::One).left_deck, vec![VisibleCard::from(None)]);
+        assert_eq!(game.field_of(Player::One).center_deck().len(), 2);
+
+        let summons = |game: &GameState| game.legal_options(&cards).iter()
+            .filter(|option| matches!(option, PlayerOption::Action(Action::Summon { .. })))
+            .count();
+        let mut free = game.snapshot();
+        free.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        assert_eq!(summons(&free), 0);
+
+        // with resources, costed cards can be summoned from the center deck, but the free squire
+        // still needs an effect of its own
+        game.configure(GameConfig { resources_per_turn: 2, ..GameConfig::default() });
+        game.priorty_player_take_option(&cards, PlayerOption::SkipDraw)?;
+        assert_eq!(summons(&game), 14);
+        let summon = PlayerOption::Action(Action::Summon { instance: knight_id, slot: FieldSlot::F2 });
+        game.priorty_player_take_option(&cards, summon)?;
+        let field = game.field_of(Player::One);
+        assert_eq!(field.slot(FieldSlot::F2).map(|card| card.instance), Some(knight_id));
+        assert_eq!((field.center_deck().len(), field.resources()), (1, 0));
+        Ok(())
+    }
 }
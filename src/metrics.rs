@@ -0,0 +1,82 @@
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Counts what the games a server hosts are doing, shared between every `GameDriver` given it
+/// with `GameDriver::with_metrics`, so operators can monitor a deployment by scraping `render`
+#[derive(Debug, Default)]
+pub struct Metrics {
+    games_active: AtomicU64,
+    games_started: AtomicU64,
+    actions: AtomicU64,
+    decisions: AtomicU64,
+    decision_nanos: AtomicU64,
+    desyncs: AtomicU64,
+    disconnects: AtomicU64,
+}
+
+impl Metrics {
+    pub(crate) fn game_started(&self) {
+        self.games_active.fetch_add(1, Ordering::Relaxed);
+        self.games_started.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn game_dropped(&self) {
+        self.games_active.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn actions_taken(&self, actions: u64) {
+        self.actions.fetch_add(actions, Ordering::Relaxed);
+    }
+
+    pub(crate) fn decided(&self, time: Duration) {
+        self.decisions.fetch_add(1, Ordering::Relaxed);
+        self.decision_nanos.fetch_add(time.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn desynced(&self) {
+        self.desyncs.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn disconnected(&self) {
+        self.disconnects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// How many drivers with these metrics are hosting a game
+    pub fn games_active(&self) -> u64 {
+        self.games_active.load(Ordering::Relaxed)
+    }
+
+    /// How long players took to choose their options on average
+    pub fn average_decision(&self) -> Duration {
+        match self.decisions.load(Ordering::Relaxed) {
+            0 => Duration::default(),
+            decisions => Duration::from_nanos(self.decision_nanos.load(Ordering::Relaxed) / decisions),
+        }
+    }
+
+    /// The metrics in the Prometheus text format, for a scrape endpoint to serve. Rates such as
+    /// actions per second are left to whatever scrapes the counters.
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, samples: &[(&str, String)]| {
+            let _ = writeln!(text, "# HELP card_game_{} {}", name, help);
+            let _ = writeln!(text, "# TYPE card_game_{} {}", name, kind);
+            for (suffix, value) in samples {
+                let _ = writeln!(text, "card_game_{}{} {}", name, suffix, value);
+            }
+        };
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed).to_string();
+        metric("games_active", "gauge", "Games being hosted.", &[("", load(&self.games_active))]);
+        metric("games_started_total", "counter", "Games hosted since the server started.", &[("", load(&self.games_started))]);
+        metric("actions_total", "counter", "Options taken in hosted games.", &[("", load(&self.actions))]);
+        let total = Duration::from_nanos(self.decision_nanos.load(Ordering::Relaxed));
+        metric("decision_seconds", "summary", "How long players took to choose their options.", &[
+            ("_sum", total.as_secs_f64().to_string()),
+            ("_count", load(&self.decisions)),
+        ]);
+        metric("desyncs_total", "counter", "Clients whose game state didn't match the server's.", &[("", load(&self.desyncs))]);
+        metric("disconnects_total", "counter", "Players who disconnected during a game.", &[("", load(&self.disconnects))]);
+        text
+    }
+}
@@ -0,0 +1,318 @@
+use crate::cards::Fnv;
+use crate::deck::DeckLimits;
+use crate::effects;
+use crate::ids::PlayerId;
+use crate::state::{Action, GameConfig, PlayerOption};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufRead, ErrorKind, Read};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// Sending the protocol's messages over WebSockets
+#[cfg(feature = "ws")]
+pub mod ws;
+
+/// Sent to a player to check they are still connected, who must reply with `PONG`
+pub const PING: &str = "ping";
+/// The reply to a `PING`
+pub const PONG: &str = "pong";
+
+/// Checks the token a connection sends when it connects, returning who it belongs to if it is
+/// valid
+pub trait TokenValidator {
+    fn validate(&self, token: &str) -> Option<PlayerId>;
+}
+
+/// Accepts any non empty token, taking the token as the player's name, for casual games where
+/// players only need telling apart
+#[derive(Copy, Clone, Debug, Default)]
+pub struct AnyToken;
+
+impl TokenValidator for AnyToken {
+    fn validate(&self, token: &str) -> Option<PlayerId> {
+        Some(PlayerId(token.to_owned())).filter(|_| !token.is_empty())
+    }
+}
+
+/// Accepts only the tokens it was given, such as ones issued to each player by a tournament
+#[derive(Clone, Debug, Default)]
+pub struct StaticTokens {
+    tokens: HashMap<String, PlayerId>,
+}
+
+impl StaticTokens {
+    pub fn insert(&mut self, token: impl Into<String>, identity: PlayerId) {
+        self.tokens.insert(token.into(), identity);
+    }
+}
+
+impl TokenValidator for StaticTokens {
+    fn validate(&self, token: &str) -> Option<PlayerId> {
+        self.tokens.get(token).cloned()
+    }
+}
+
+/// The token in the line a connection authenticates with, which must be `auth <token>`
+pub fn auth_token(line: &str) -> Option<&str> {
+    line.trim().strip_prefix("auth ").map(str::trim)
+}
+
+/// Clients and servers exchange the engine version to check they play by the same rules
+pub use crate::driver::ENGINE_VERSION;
+
+/// A hash of the effect, trigger, condition and combat modifier types registered, the default
+/// game config and the default deck limits, which is the same on any machine with the same
+/// rules. Registering another type or changing a default changes it even when the engine version
+/// stays the same.
+pub fn rules_fingerprint() -> u64 {
+    let mut hash = Fnv::new();
+    for info in effects::registered_types() {
+        hash.write(format!("{:?} {}", info.kind, info.name).as_bytes());
+        for field in info.fields {
+            hash.write(format!(" {}: {} {}", field.name, field.value, field.optional).as_bytes());
+        }
+        hash.write(&[0]);
+    }
+    hash.write(&GameConfig::default().fingerprint().to_le_bytes());
+    let DeckLimits { min_side, max_side, max_center, max_copies } = DeckLimits::default();
+    for limit in [min_side, max_side, max_center, max_copies.unwrap_or(usize::MAX)] {
+        hash.write(&(limit as u64).to_le_bytes());
+    }
+    hash.finish()
+}
+
+/// The rules a client or server plays by, which each side sends in the handshake as a line of
+/// `rules <engine version> <fingerprint>` so a client simulating the game itself can be turned
+/// away rather than quietly drifting out of step with the server
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RulesVersion {
+    pub engine: String,
+    pub fingerprint: u64,
+}
+
+impl RulesVersion {
+    /// The rules this build plays by
+    pub fn current() -> Self {
+        RulesVersion {
+            engine: ENGINE_VERSION.to_owned(),
+            fingerprint: rules_fingerprint(),
+        }
+    }
+
+    /// Reads a `rules` line, if the line is one
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut words = line.trim().strip_prefix("rules ")?.split_whitespace();
+        let engine = words.next()?.to_owned();
+        let fingerprint = u64::from_str_radix(words.next()?, 16).ok()?;
+        match words.next() {
+            Some(_) => None,
+            None => Some(RulesVersion { engine, fingerprint }),
+        }
+    }
+
+    /// Checks the other side's rules against these
+    pub fn check(&self, theirs: &RulesVersion) -> Result<(), RulesMismatch> {
+        if self == theirs {
+            Ok(())
+        } else {
+            Err(RulesMismatch { ours: self.clone(), theirs: theirs.clone() })
+        }
+    }
+}
+
+impl fmt::Display for RulesVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "rules {} {:016x}", self.engine, self.fingerprint)
+    }
+}
+
+/// The two sides of a connection play by different rules
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RulesMismatch {
+    pub ours: RulesVersion,
+    pub theirs: RulesVersion,
+}
+
+impl RulesMismatch {
+    /// If the sides run different versions of the engine, rather than the same version with
+    /// different types registered or different defaults
+    pub fn engine_differs(&self) -> bool {
+        self.ours.engine != self.theirs.engine
+    }
+}
+
+impl fmt::Display for RulesMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.engine_differs() {
+            write!(f, "engine version {} doesn't match version {}", self.theirs.engine, self.ours.engine)
+        } else {
+            write!(
+                f,
+                "rules fingerprint {:016x} doesn't match {:016x} for engine version {}",
+                self.theirs.fingerprint, self.ours.fingerprint, self.ours.engine
+            )
+        }
+    }
+}
+
+impl std::error::Error for RulesMismatch {}
+
+/// How often a quiet player is pinged, and how long they can stay quiet before they are
+/// considered disconnected
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct LivenessConfig {
+    pub ping_interval: Duration,
+    pub timeout: Duration,
+}
+
+impl Default for LivenessConfig {
+    fn default() -> Self {
+        LivenessConfig {
+            ping_interval: Duration::from_secs(10),
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Tracks when a player was last heard from, for any transport to decide when to ping them and
+/// when to give up on them
+#[derive(Copy, Clone, Debug)]
+pub struct Liveness {
+    config: LivenessConfig,
+    heard: Instant,
+    pinged: Option<Instant>,
+}
+
+impl Liveness {
+    pub fn new(config: LivenessConfig, now: Instant) -> Self {
+        Liveness {
+            config,
+            heard: now,
+            pinged: None,
+        }
+    }
+
+    pub fn config(&self) -> LivenessConfig {
+        self.config
+    }
+
+    /// Records any message from the player, including a `PONG`
+    pub fn heard(&mut self, now: Instant) {
+        self.heard = now;
+        self.pinged = None;
+    }
+
+    /// If the player should be sent a `PING` now, which is recorded as sent
+    pub fn ping_due(&mut self, now: Instant) -> bool {
+        let last = self.pinged.unwrap_or(self.heard);
+        if now.saturating_duration_since(last) >= self.config.ping_interval {
+            self.pinged = Some(now);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// If the player has been heard from recently enough to still be considered connected
+    pub fn is_alive(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.heard) < self.config.timeout
+    }
+}
+
+/// Limits on what a connection may send, so a hostile client can't exhaust the server with
+/// floods of messages or oversized ones
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Limits {
+    /// The longest message accepted, in bytes
+    pub max_message: usize,
+    /// The most targets an option submitted by a connection may carry
+    pub max_targets: usize,
+    /// How many messages may be sent at once before the rate limit applies
+    pub burst: u32,
+    /// How many messages may be sent each second after a burst
+    pub per_second: u32,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_message: 4096,
+            max_targets: 16,
+            burst: 20,
+            per_second: 5,
+        }
+    }
+}
+
+impl Limits {
+    /// If the option is small enough to be worth checking against the legal options. No legal
+    /// option comes close to the budget, so anything over it can be rejected without looking
+    /// any further.
+    pub fn allows(&self, option: &PlayerOption) -> bool {
+        match option {
+            PlayerOption::Action(Action::Effect { activation, .. }) => {
+                activation.data.targets.len() <= self.max_targets
+            },
+            _ => true,
+        }
+    }
+}
+
+/// Limits how often a connection may send messages, allowing a burst of them and then a steady
+/// rate
+#[derive(Copy, Clone, Debug)]
+pub struct RateLimiter {
+    burst: u32,
+    per_second: u32,
+    // how many messages can be sent right now
+    allowance: u32,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(limits: &Limits, now: Instant) -> Self {
+        RateLimiter {
+            burst: limits.burst,
+            per_second: limits.per_second,
+            allowance: limits.burst,
+            updated: now,
+        }
+    }
+
+    /// Records a message, returning false if the connection is sending too many and the message
+    /// should be dropped
+    pub fn allow(&mut self, now: Instant) -> bool {
+        if self.per_second > 0 {
+            let interval = Duration::from_secs(1) / self.per_second;
+            let elapsed = now.saturating_duration_since(self.updated);
+            let earned = (elapsed.as_nanos() / interval.as_nanos().max(1)).min(u128::from(self.burst)) as u32;
+            if earned > 0 {
+                self.allowance = (self.allowance + earned).min(self.burst);
+                self.updated += interval * earned;
+            }
+            if self.allowance == self.burst {
+                self.updated = now;
+            }
+        }
+        if self.allowance == 0 {
+            return false;
+        }
+        self.allowance -= 1;
+        true
+    }
+}
+
+/// Reads a line as with `BufRead::read_line`, failing with `ErrorKind::InvalidData` rather than
+/// buffering more than `max` bytes of it. A line cut short by a timeout is left in `line` to be
+/// continued by the next call.
+pub fn read_limited_line<R: BufRead>(reader: &mut R, line: &mut String, max: usize) -> io::Result<usize> {
+    let remaining = max.saturating_sub(line.len()) as u64;
+    let read = reader.take(remaining + 1).read_line(line)?;
+    if line.len() > max {
+        return Err(io::Error::new(ErrorKind::InvalidData, "message too long"));
+    }
+    Ok(read)
+}
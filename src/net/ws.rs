@@ -0,0 +1,79 @@
+use std::io::{self, ErrorKind, Read, Write};
+
+use tungstenite::error::ProtocolError;
+use tungstenite::protocol::WebSocketConfig;
+use tungstenite::{Error, HandshakeError, Message};
+
+// No message in the protocol comes close to this, so anything larger is refused unless a
+// different limit is set
+const MAX_MESSAGE: usize = 1 << 20;
+
+/// The server side of a WebSocket connection, sending each protocol message as a text message
+/// so browser clients can connect without a bridge to raw TCP.
+///
+/// The handshake and framing are left to tungstenite, which keeps partly read frames between
+/// calls, so the stream can have a read timeout set and `receive` called again after it times
+/// out.
+pub struct WebSocket<S> {
+    socket: tungstenite::WebSocket<S>,
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message.to_owned())
+}
+
+fn io_error(error: Error) -> io::Error {
+    match error {
+        Error::Io(error) => error,
+        error => io::Error::new(ErrorKind::InvalidData, error.to_string()),
+    }
+}
+
+impl<S: Read + Write> WebSocket<S> {
+    /// Reads the client's opening handshake from the stream and accepts it
+    pub fn accept(stream: S) -> io::Result<Self> {
+        let config = WebSocketConfig::default()
+            .max_message_size(Some(MAX_MESSAGE))
+            .max_frame_size(Some(MAX_MESSAGE));
+        match tungstenite::accept_with_config(stream, Some(config)) {
+            Ok(socket) => Ok(WebSocket { socket }),
+            Err(HandshakeError::Failure(error)) => Err(io_error(error)),
+            Err(HandshakeError::Interrupted(_)) => Err(io::Error::new(ErrorKind::WouldBlock, "handshake interrupted")),
+        }
+    }
+
+    /// Sets the longest message accepted in bytes, such as from `Limits::max_message`, after
+    /// which `receive` fails with `ErrorKind::InvalidData`
+    pub fn set_max_message(&mut self, max: usize) {
+        self.socket.set_config(|config| {
+            config.max_message_size = Some(max);
+            config.max_frame_size = Some(max);
+        });
+    }
+
+    pub fn get_ref(&self) -> &S {
+        self.socket.get_ref()
+    }
+
+    /// Sends a text message
+    pub fn send(&mut self, message: &str) -> io::Result<()> {
+        self.socket.send(Message::text(message)).map_err(io_error)
+    }
+
+    /// Receives the next text message, answering any pings on the way. Returns None once the
+    /// client closes the connection.
+    pub fn receive(&mut self) -> io::Result<Option<String>> {
+        loop {
+            match self.socket.read() {
+                Ok(Message::Text(text)) => return Ok(Some(text.as_str().to_owned())),
+                Ok(Message::Binary(_)) => return Err(invalid("binary messages are not part of the protocol")),
+                // pongs and the reply to closing are queued by tungstenite and written by the
+                // next read, which also reports once the connection has finished closing
+                Ok(_) => (),
+                Err(Error::ConnectionClosed) | Err(Error::AlreadyClosed) => return Ok(None),
+                Err(Error::Protocol(ProtocolError::ResetWithoutClosingHandshake)) => return Ok(None),
+                Err(error) => return Err(io_error(error)),
+            }
+        }
+    }
+}
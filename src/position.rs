@@ -0,0 +1,115 @@
+use crate::state::{Column, FieldSlot, Phase, Player, MAX_CARDS};
+
+use serde::{Deserialize, Serialize};
+
+use std::fmt;
+
+/// A position written outside the engine, such as by a puzzle editor or in a bug report, for
+/// `GameState::import` to start a game from. Cards are given by name, and the game state is open
+/// at the start of the turn player's draw or action phase.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PositionSpec {
+    pub turn: Player,
+    #[serde(default = "draw_phase")]
+    pub phase: Phase,
+    #[serde(default)]
+    pub player_one: SideSpec,
+    #[serde(default)]
+    pub player_two: SideSpec,
+}
+
+fn draw_phase() -> Phase {
+    Phase::MayDraw
+}
+
+/// One player's zones in a position, with the top of each face down deck last
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SideSpec {
+    #[serde(default)]
+    pub hand: Vec<String>,
+    #[serde(default)]
+    pub left_deck: Vec<String>,
+    #[serde(default)]
+    pub center_deck: Vec<String>,
+    #[serde(default)]
+    pub right_deck: Vec<String>,
+    #[serde(default)]
+    pub field: Vec<FieldCardSpec>,
+    #[serde(default)]
+    pub destroyed: Vec<DestroyedCardSpec>,
+    #[serde(default)]
+    pub removed: Vec<String>,
+    #[serde(default)]
+    pub resources: u32,
+}
+
+/// A card on the field in a position
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct FieldCardSpec {
+    pub card: String,
+    pub slot: FieldSlot,
+    #[serde(default)]
+    pub damage: u32,
+    /// If the card can't attack yet because it was summoned since its controller's turn started
+    #[serde(default)]
+    pub just_summoned: bool,
+}
+
+/// A card in one of the destroyed piles in a position
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct DestroyedCardSpec {
+    pub card: String,
+    pub column: Column,
+}
+
+/// Why a position couldn't be imported, as it isn't one the rules could reach
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ImportError {
+    /// No card in the card pool has the name
+    UnknownCard(String),
+    /// There is no column with the number, as columns are numbered from 0 to 6
+    NoSuchColumn(u8),
+    /// More than one card is in the slot
+    SlotTaken { player: Player, slot: FieldSlot },
+    /// The card has as much damage as its defense, so would already have been destroyed
+    LethalDamage { card: String, damage: u32, defense: u32 },
+    /// The card may not be summoned to the row of the slot it is in
+    WrongRow { card: String, slot: FieldSlot },
+    /// Nothing could have summoned the card to the slot it is in, as it may only be summoned by
+    /// effects and has none, or its column has too few destroyed cards
+    Unsummonable { card: String, slot: FieldSlot },
+    /// The card has no cost to summon, so may not be in the center deck
+    FreeCardInCenter { player: Player, card: String },
+    /// The center deck holds more cards than any center deck may
+    CenterDeckTooLarge { player: Player, size: usize },
+    /// The position has more cards than a game can, which is `MAX_CARDS`
+    TooManyCards,
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::UnknownCard(card) => write!(f, "no card named {:?} is in the card pool", card),
+            ImportError::NoSuchColumn(column) => write!(f, "there is no column {}, columns are numbered from 0 to 6", column),
+            ImportError::SlotTaken { player, slot } => write!(f, "more than one card is in {:?}'s slot {:?}", player, slot),
+            ImportError::LethalDamage { card, damage, defense } => write!(
+                f, "{} has {} damage but only {} defense, so would have been destroyed", card, damage, defense
+            ),
+            ImportError::WrongRow { card, slot } => write!(f, "{} can't be in {:?} as it may not be summoned to that row", card, slot),
+            ImportError::Unsummonable { card, slot } => write!(f, "{} can't be in {:?} as nothing could have summoned it there", card, slot),
+            ImportError::FreeCardInCenter { player, card } => write!(
+                f, "{} is in {:?}'s center deck but has no cost so can't be in the center deck", card, player
+            ),
+            ImportError::CenterDeckTooLarge { player, size } => write!(
+                f, "{:?}'s center deck has {} cards, more than any center deck may have", player, size
+            ),
+            ImportError::TooManyCards => write!(f, "the position has more than {} cards", MAX_CARDS),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
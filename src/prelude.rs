@@ -0,0 +1,13 @@
+//! The types needed to set up and drive a game, for `use card_game::prelude::*;`
+//!
+//! Implementing new card effects also needs the traits in the `card_type` module.
+
+pub use crate::card_type::{CardType, CardTypeIdentifier, EffectSummary, Keyword, TriggerKind, TriggerTiming};
+pub use crate::cards::{Cards, LoadWarning, PackSource, Reference};
+pub use crate::ids::{ChainLinkId, EventSeq};
+pub use crate::state::{
+    Action, Activation, ActivationData, ActivatableType, AttackTarget, Card, CardEffect, CardInstance,
+    CardInstances, CardStatus, Column, DeckSizes, DrawModifier, DrawRule, EffectBudget, EndReason, EventCause, FaceDownDeck, Field, FieldSlot, GameConfig, GameEvent, GameResult,
+    GameState, GameStateType, InvalidAction, Phase, Player, PlayerOption, Row, SoftLockPolicy, StateHash, TooManyCards, MAX_CARDS,
+};
+pub use crate::summary::describe_turn;
@@ -0,0 +1,147 @@
+use crate::cards::Cards;
+use crate::state::{GameState, InvalidAction, Player, PlayerOption};
+
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+/// Whether players may undo options they took, for casual and teaching games
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RewindConfig {
+    pub allow_rewind: bool,
+    /// How many options back the game can be rewound, one at a time
+    pub history: usize,
+}
+
+impl Default for RewindConfig {
+    fn default() -> Self {
+        RewindConfig {
+            allow_rewind: false,
+            history: 8,
+        }
+    }
+}
+
+/// A game which keeps snapshots of itself before every option taken, so both players can agree
+/// to undo the last one. Rewinding is recorded as a `GameEvent::Rewound` after the events of the
+/// option undone.
+pub struct RewindableGame {
+    game: GameState,
+    config: RewindConfig,
+    snapshots: VecDeque<GameState>,
+    // the player waiting for their opponent to agree to rewind
+    requested: Option<Player>,
+}
+
+impl RewindableGame {
+    pub fn new(game: GameState, config: RewindConfig) -> Self {
+        RewindableGame {
+            game,
+            config,
+            snapshots: VecDeque::new(),
+            requested: None,
+        }
+    }
+
+    pub fn game(&self) -> &GameState {
+        &self.game
+    }
+
+    /// Gives up the ability to rewind and returns the game
+    pub fn into_game(self) -> GameState {
+        self.game
+    }
+
+    /// Takes an option for the priority player, as with `GameState::priorty_player_take_option`.
+    /// Any request to rewind which hasn't been agreed to is dropped.
+    pub fn take_option(&mut self, card_pool: &Cards, option: PlayerOption) -> Result<(), InvalidAction> {
+        let snapshot = self.snapshot();
+        self.game.priorty_player_take_option(card_pool, option)?;
+        self.requested = None;
+        if let Some(snapshot) = snapshot {
+            self.keep(snapshot);
+        }
+        Ok(())
+    }
+
+    /// Takes the priority player's options for them while they only have one, as with
+    /// `GameState::take_forced_options`. Rewinding afterwards undoes all of them at once.
+    pub fn take_forced_options(&mut self, card_pool: &Cards) -> usize {
+        let snapshot = self.snapshot();
+        let taken = self.game.take_forced_options(card_pool);
+        if taken > 0 {
+            self.requested = None;
+            if let Some(snapshot) = snapshot {
+                self.keep(snapshot);
+            }
+        }
+        taken
+    }
+
+    /// The player ran out of time, as with `GameState::time_out`
+    pub fn time_out(&mut self, player: Player) -> Result<(), InvalidAction> {
+        self.game.time_out(player)?;
+        self.requested = None;
+        Ok(())
+    }
+
+    /// Stops the game without a winner, as with `GameState::abort`
+    pub fn abort(&mut self) -> Result<(), InvalidAction> {
+        self.game.abort()?;
+        self.requested = None;
+        Ok(())
+    }
+
+    /// Ends the game with the winner an operator decided on, as with `GameState::adjudicate`
+    pub fn adjudicate(&mut self, winner: Option<Player>) -> Result<(), InvalidAction> {
+        self.game.adjudicate(winner)?;
+        self.requested = None;
+        Ok(())
+    }
+
+    /// A snapshot of the game as it is now, if rewinding is allowed
+    fn snapshot(&self) -> Option<GameState> {
+        if self.config.allow_rewind && self.config.history > 0 {
+            Some(self.game.snapshot())
+        } else {
+            None
+        }
+    }
+
+    fn keep(&mut self, snapshot: GameState) {
+        if self.snapshots.len() == self.config.history {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(snapshot);
+    }
+
+    /// The player waiting for their opponent to agree to rewind, if any
+    pub fn pending_rewind(&self) -> Option<Player> {
+        self.requested
+    }
+
+    /// Asks the player's opponent to agree to undo the last option taken
+    pub fn request_rewind(&mut self, player: Player) -> Result<(), InvalidAction> {
+        if !self.config.allow_rewind || self.snapshots.is_empty() || self.requested.is_some() {
+            return Err(InvalidAction);
+        }
+        self.requested = Some(player);
+        Ok(())
+    }
+
+    /// Answers the opponent's request to rewind, undoing the last option taken if the player
+    /// agrees. Returns if the game was rewound.
+    pub fn respond_to_rewind(&mut self, player: Player, agree: bool) -> Result<bool, InvalidAction> {
+        let requester = match self.requested {
+            Some(requester) if requester != player => requester,
+            _ => return Err(InvalidAction),
+        };
+        self.requested = None;
+        if !agree {
+            return Ok(false);
+        }
+        let snapshot = self.snapshots.pop_back().ok_or(InvalidAction)?;
+        self.game.rewind_to(snapshot, requester);
+        Ok(true)
+    }
+}
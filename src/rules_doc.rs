@@ -0,0 +1,202 @@
+use crate::card_type::Keyword;
+use crate::driver::ENGINE_VERSION;
+use crate::state::{Column, EndReason, GameConfig, GameStateType, Phase};
+
+use serde::Serialize;
+
+use std::fmt::Write;
+
+/// One rule, with a short name a rulebook can list it under
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct Rule {
+    pub name: String,
+    pub text: String,
+}
+
+impl Rule {
+    fn new(name: impl Into<String>, text: impl Into<String>) -> Self {
+        Rule { name: name.into(), text: text.into() }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct Section {
+    pub title: &'static str,
+    pub rules: Vec<Rule>,
+}
+
+/// A rules reference generated from the engine and a game config rather than written by hand,
+/// so a client's rulebook always describes the rules its games are played by. Serializes to
+/// JSON or any other format serde supports, or can be written out as Markdown.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct RulesDoc {
+    pub engine_version: &'static str,
+    pub sections: Vec<Section>,
+}
+
+impl RulesDoc {
+    /// The rules of games played with the config
+    pub fn new(config: &GameConfig) -> Self {
+        RulesDoc {
+            engine_version: ENGINE_VERSION,
+            sections: vec![
+                setup(config),
+                field(config),
+                turns(config),
+                chains(config),
+                Section {
+                    title: "Keywords",
+                    rules: Keyword::all().map(|keyword| Rule::new(format!("{:?}", keyword), keyword_text(keyword))).collect(),
+                },
+                Section {
+                    title: "Ending the game",
+                    rules: EndReason::all().map(|reason| Rule::new(format!("{:?}", reason), end_text(reason, config))).collect(),
+                },
+            ],
+        }
+    }
+
+    pub fn section(&self, title: &str) -> Option<&Section> {
+        self.sections.iter().find(|section| section.title == title)
+    }
+
+    /// The rules as a Markdown document, with a heading for each section
+    pub fn to_markdown(&self) -> String {
+        let mut text = format!("# Rules\n\nEngine version {}\n", self.engine_version);
+        for section in &self.sections {
+            let _ = write!(text, "\n## {}\n\n", section.title);
+            for rule in &section.rules {
+                let _ = writeln!(text, "- **{}**: {}", rule.name, rule.text);
+            }
+        }
+        text
+    }
+}
+
+fn setup(config: &GameConfig) -> Section {
+    let hand = match config.starting_hand {
+        0 => "Players start with no cards in hand.".to_owned(),
+        cards => format!(
+            "Before the game starts each player draws {} cards, alternating between their left and right decks starting with the left.",
+            cards,
+        ),
+    };
+    let mut rules = vec![
+        Rule::new("Decks", "Each player has a left, center and right deck. The left and right decks are face down stacks which can't be searched or shuffled."),
+        Rule::new("Center deck", "The center deck is face up and public knowledge, and its cards are summoned straight to the field for their cost."),
+        Rule::new("Starting hand", hand),
+    ];
+    if !config.disabled_packs.is_empty() {
+        rules.push(Rule::new("Card packs", format!("Cards from these packs may not be played: {}.", config.disabled_packs.join(", "))));
+    }
+    Section { title: "Setup", rules }
+}
+
+fn field(config: &GameConfig) -> Section {
+    let columns = Column::all().count();
+    let removal = if config.removal {
+        "Cards removed from the game are face up and can't be moved again."
+    } else {
+        "Cards can't be removed from the game. Cards on the field which would be removed are destroyed instead, and destroyed cards stay in their pile."
+    };
+    Section {
+        title: "Field",
+        rules: vec![
+            Rule::new("Slots", format!("Each field has {} columns with a front and back row, so {} slots a card may be summoned to.", columns, columns * 2)),
+            Rule::new("Damage", "Attacks place damage counters on the card attacked equal to the attack. A card is destroyed once it has as many damage counters as its defense."),
+            Rule::new("Destroyed piles", "Destroyed cards go face up to the pile for the column they were destroyed in, losing their counters."),
+            Rule::new("Removal", removal),
+        ],
+    }
+}
+
+fn turns(config: &GameConfig) -> Section {
+    let phase = |phase: Phase| match phase {
+        Phase::MayDraw if config.mandatory_draw => Rule::new(
+            "Draw",
+            "At the start of their turn the turn player must draw the top card of their left or right deck if they can, which can't be responded to.",
+        ),
+        Phase::MayDraw => Rule::new(
+            "Draw",
+            "At the start of their turn the turn player may draw the top card of their left or right deck, which can't be responded to.",
+        ),
+        Phase::MayTakeAction => Rule::new(
+            "Action",
+            "The turn player then summons a card, attacks with a card or activates an effect, or ends their turn. Cards can't attack on the turn they are summoned.",
+        ),
+    };
+    let state_rule = |state: GameStateType| match state {
+        GameStateType::Open { phase: current } => phase(current),
+        GameStateType::Closed => Rule::new(
+            "Responses",
+            "After an action the other player gets priority to activate an effect in response or pass. Players take turns responding until both pass in a row.",
+        ),
+    };
+    let mut rules = vec![];
+    if config.resources_per_turn > 0 {
+        rules.push(Rule::new(
+            "Resources",
+            format!(
+                "Each player gains {} resource points at the start of their turn, which carry over between turns. Cards cost their resource cost to summon.",
+                config.resources_per_turn,
+            ),
+        ));
+    }
+    rules.extend([
+        GameStateType::Open { phase: Phase::MayDraw },
+        GameStateType::Open { phase: Phase::MayTakeAction },
+        GameStateType::Closed,
+    ].iter().map(|&state| state_rule(state)));
+    Section { title: "Turns", rules }
+}
+
+fn chains(config: &GameConfig) -> Section {
+    let mut rules = vec![
+        Rule::new("Resolution", "Once both players pass, the effects activated resolve from the last activated to the first, and can't be responded to while resolving."),
+        Rule::new("Mandatory effects", "A player with mandatory effects which can respond must activate them before any optional effects."),
+        Rule::new("Triggers", "When one event triggers effects for both players, the turn player puts all of theirs on the chain first, so the other player's resolve first."),
+        Rule::new("Fizzling", "An effect whose target leaves the field before it resolves does nothing to that card."),
+    ];
+    if let Some(links) = config.max_chain {
+        rules.push(Rule::new("Chain limit", format!("A chain may have at most {} links, after which no more effects may be activated in response.", links)));
+    }
+    if let Some(budget) = config.effect_budget {
+        rules.push(Rule::new(
+            "Effect budget",
+            format!(
+                "An effect which makes more than {} changes to the game or moves more than {} cards fizzles, undoing everything it did.",
+                budget.actions, budget.movements,
+            ),
+        ));
+    }
+    rules.push(Rule::new(
+        "Loops",
+        format!("If effects trigger each other {} times in a row without the game reopening, the game ends in a draw.", config.loop_limit),
+    ));
+    Section { title: "Chains", rules }
+}
+
+fn keyword_text(keyword: Keyword) -> &'static str {
+    match keyword {
+        Keyword::Swift => "May attack on the turn it is summoned.",
+        Keyword::Guard => "Attacks against this card's column must target this card.",
+        Keyword::Piercing => "Damage in excess of the attacked card's remaining defense spills onto the card behind it.",
+        Keyword::Undying => "May be summoned from its destroyed pile as an action, for its cost, entering the field with damage counters equal to half its defense rounded down.",
+    }
+}
+
+fn end_text(reason: EndReason, config: &GameConfig) -> &'static str {
+    match reason {
+        EndReason::NoDrawOrAction if config.mandatory_draw => {
+            "A player whose turn starts with no cards left in their left and right decks, or who can neither draw a card nor take an action, loses."
+        },
+        EndReason::NoDrawOrAction => "A player whose turn starts who can neither draw a card nor take an action loses.",
+        EndReason::SimultaneousLoss => "If both players run out of cards to play at the same time, the game is a draw.",
+        EndReason::Concession => "A player may concede, losing the game.",
+        EndReason::Timeout => "A player who runs out of time loses.",
+        EndReason::AlternativeWin => "A card effect may win the game for its controller.",
+        EndReason::Loop => "Effects which keep triggering each other end the game in a draw.",
+        EndReason::Aborted => "A game which can't continue may be stopped without a winner.",
+        EndReason::Adjudicated => "An operator may decide the result of a game which can't finish.",
+    }
+}
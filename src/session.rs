@@ -0,0 +1,158 @@
+use crate::admin::resume_token;
+use crate::archive::{ArchivedGame, GameStore, HeadToHead};
+use crate::cards::Cards;
+use crate::deck::Decks;
+use crate::driver::{DriverConfig, GameDriver, GameSetup, ReplayError};
+use crate::ids::{GameId, PlayerId};
+use crate::state::{GameConfig, Player};
+
+use std::fmt;
+use std::time::SystemTime;
+
+/// Why a session couldn't do what it was asked
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SessionError {
+    /// No preconstructed deck has the name
+    UnknownDeck(String),
+    /// A game is already being played, so it must end or be saved first
+    GameInProgress,
+    /// No game is being played
+    NoGame,
+    /// There is no game to rematch yet
+    NoPreviousGame,
+    /// No saved game is stored with the resume token
+    NoSuchCheckpoint(String),
+    /// The saved game couldn't be resumed
+    Resume(ReplayError),
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionError::UnknownDeck(name) => write!(f, "no deck named {:?}", name),
+            SessionError::GameInProgress => write!(f, "a game is already being played"),
+            SessionError::NoGame => write!(f, "no game is being played"),
+            SessionError::NoPreviousGame => write!(f, "no game has been played to rematch"),
+            SessionError::NoSuchCheckpoint(token) => write!(f, "no saved game with resume token {:?}", token),
+            SessionError::Resume(error) => write!(f, "the saved game couldn't be resumed: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for SessionError {}
+
+/// A run of games between the same two players at one client. Decks are picked from the
+/// preconstructed decks, each finished game is archived with its replay in the store, and an
+/// unfinished game can be saved to the store and resumed later with its resume token.
+pub struct Session<S> {
+    decks: Decks,
+    config: GameConfig,
+    driver_config: DriverConfig,
+    players: [PlayerId ; 2],
+    store: S,
+    game: Option<GameDriver>,
+    last_setup: Option<GameSetup>,
+    next_id: GameId,
+}
+
+impl<S: GameStore> Session<S> {
+    /// Starts a session between the players, player one first, numbering its games after any
+    /// already in the store
+    pub fn new(decks: Decks, config: GameConfig, driver_config: DriverConfig, players: [PlayerId ; 2], store: S) -> Self {
+        let next_id = store.games().map(|game| game.id.next()).max().unwrap_or_default();
+        Session { decks, config, driver_config, players, store, game: None, last_setup: None, next_id }
+    }
+
+    /// The names of the decks players can pick from, in the order to list them
+    pub fn deck_names(&self) -> Vec<&str> {
+        self.decks.iter().map(|(name, _)| name).collect()
+    }
+
+    /// Starts a game with the named decks, player one's first
+    pub fn start(&mut self, card_pool: &Cards, decks: [&str ; 2]) -> Result<&mut GameDriver, SessionError> {
+        if self.game.is_some() {
+            return Err(SessionError::GameInProgress);
+        }
+        let deck = |name: &str| self.decks.deck(name).cloned().ok_or_else(|| SessionError::UnknownDeck(name.to_owned()));
+        let setup = GameSetup { decks: [deck(decks[0])?, deck(decks[1])?], config: self.config.clone() };
+        Ok(self.play(card_pool, setup))
+    }
+
+    /// Starts the last game played again with the same decks and rules
+    pub fn rematch(&mut self, card_pool: &Cards) -> Result<&mut GameDriver, SessionError> {
+        if self.game.is_some() {
+            return Err(SessionError::GameInProgress);
+        }
+        let setup = self.last_setup.clone().ok_or(SessionError::NoPreviousGame)?;
+        Ok(self.play(card_pool, setup))
+    }
+
+    fn play(&mut self, card_pool: &Cards, setup: GameSetup) -> &mut GameDriver {
+        self.last_setup = Some(setup.clone());
+        let mut driver = GameDriver::from_setup(card_pool, setup, self.driver_config);
+        for player in Player::all() {
+            let _ = driver.bind(player, self.players[player.index()].clone());
+        }
+        self.game.insert(driver)
+    }
+
+    /// The game being played
+    pub fn game(&mut self) -> Option<&mut GameDriver> {
+        self.game.as_mut()
+    }
+
+    /// Archives the game being played if it has ended, returning its id in the store
+    pub fn finish(&mut self, card_pool: &Cards) -> Option<GameId> {
+        let id = self.next_id;
+        let archived = ArchivedGame::of(id, self.game.as_ref()?, card_pool, SystemTime::now())?;
+        self.store.store(archived);
+        self.game = None;
+        self.next_id = id.next();
+        Some(id)
+    }
+
+    /// Saves the unfinished game being played to the store, returning the token to resume it with
+    pub fn save(&mut self, card_pool: &Cards) -> Result<String, SessionError> {
+        let checkpoint = self.game.as_ref().ok_or(SessionError::NoGame)?
+            .checkpoint(card_pool)
+            .ok_or(SessionError::NoGame)?;
+        let token = resume_token(self.next_id);
+        self.store.store_checkpoint(token.clone(), checkpoint);
+        self.game = None;
+        Ok(token)
+    }
+
+    /// Resumes a saved game, which can then be rematched like any other
+    pub fn resume(&mut self, card_pool: &Cards, resume_token: &str) -> Result<&mut GameDriver, SessionError> {
+        if self.game.is_some() {
+            return Err(SessionError::GameInProgress);
+        }
+        let checkpoint = self.store.take_checkpoint(resume_token)
+            .ok_or_else(|| SessionError::NoSuchCheckpoint(resume_token.to_owned()))?;
+        match GameDriver::resume(card_pool, &checkpoint) {
+            Ok(driver) => {
+                self.last_setup = Some(checkpoint.setup);
+                Ok(self.game.insert(driver))
+            },
+            Err(error) => {
+                // kept so the game can still be resumed with the card pool it was played with
+                self.store.store_checkpoint(resume_token.to_owned(), checkpoint);
+                Err(SessionError::Resume(error))
+            },
+        }
+    }
+
+    /// How player one has done against player two over every game in the store
+    pub fn record(&self) -> HeadToHead {
+        self.store.head_to_head(&self.players[0], &self.players[1])
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Ends the session, giving back the store with every game it archived and saved
+    pub fn into_store(self) -> S {
+        self.store
+    }
+}
@@ -0,0 +1,122 @@
+use crate::cards::Cards;
+use crate::deck::Deck;
+use crate::state::{CardInstances, GameResult, GameState, Player};
+
+/// A small xorshift generator, so simulated games can be played again from their seed without
+/// depending on anything outside the crate
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // xorshift never leaves a state of 0, and nearby seeds should still play differently
+        let mut state = seed.wrapping_add(0x9E3779B97F4A7C15);
+        state = (state ^ (state >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        state = (state ^ (state >> 27)).wrapping_mul(0x94D049BB133111EB);
+        Rng((state ^ (state >> 31)) | 1)
+    }
+
+    /// A number from 0 up to but not including the bound
+    pub fn below(&mut self, bound: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % bound as u64) as usize
+    }
+
+    /// The generator's state, for hashing games which use one
+    pub(crate) fn state(&self) -> u64 {
+        self.0
+    }
+}
+
+/// How one simulated game went
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Outcome {
+    pub seed: u64,
+    /// None if the game was still going when it reached the option limit
+    pub result: Option<GameResult>,
+    pub options_taken: u32,
+}
+
+/// The outcomes of many simulated games added up
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct SimStats {
+    pub games: usize,
+    /// How many games each player won, player one first
+    pub wins: [usize ; 2],
+    pub draws: usize,
+    /// Games which were still going when they reached the option limit
+    pub unfinished: usize,
+    pub options_taken: u64,
+}
+
+impl SimStats {
+    pub fn record(&mut self, outcome: &Outcome) {
+        self.games += 1;
+        self.options_taken += u64::from(outcome.options_taken);
+        match outcome.result {
+            None => self.unfinished += 1,
+            Some(result) => match result.winner() {
+                Some(Player::One) => self.wins[0] += 1,
+                Some(Player::Two) => self.wins[1] += 1,
+                None => self.draws += 1,
+            },
+        }
+    }
+
+    /// Adds the games of other stats to these, which gives the same totals in any order
+    pub fn merge(&mut self, other: &SimStats) {
+        self.games += other.games;
+        self.wins[0] += other.wins[0];
+        self.wins[1] += other.wins[1];
+        self.draws += other.draws;
+        self.unfinished += other.unfinished;
+        self.options_taken += other.options_taken;
+    }
+}
+
+/// Plays one game between the decks where both players take random options, stopping after the
+/// limit of options if the game hasn't ended
+pub fn play(card_pool: &Cards, decks: [&Deck ; 2], seed: u64, max_options: u32) -> Outcome {
+    let mut instances = CardInstances::default();
+    let mut game = GameState::start(
+        decks[0].instantiate(card_pool, &mut instances),
+        decks[1].instantiate(card_pool, &mut instances),
+    );
+    let mut random = Rng::new(seed);
+    let mut options_taken = 0;
+    while options_taken < max_options && game.result(card_pool).is_none() {
+        let mut options = game.priority_player_options(card_pool);
+        let option = options.swap_remove(random.below(options.len()));
+        game.take_option_unchecked(card_pool, option)
+            .expect("options the engine gives are always legal");
+        options_taken += 1;
+    }
+    Outcome { seed, result: game.result(card_pool), options_taken }
+}
+
+/// Plays a game for each seed and returns the outcomes in the order of the seeds. Each game only
+/// depends on its seed, so the same seeds always give the same outcomes. With the `parallel`
+/// feature the games are spread across every core, except on WebAssembly where they're played
+/// one after another.
+pub fn simulate(card_pool: &Cards, decks: [&Deck ; 2], seeds: &[u64], max_options: u32) -> Vec<Outcome> {
+    #[cfg(all(feature = "parallel", not(target_family = "wasm")))]
+    {
+        use rayon::prelude::*;
+        seeds.par_iter().map(|&seed| play(card_pool, decks, seed, max_options)).collect()
+    }
+    #[cfg(not(all(feature = "parallel", not(target_family = "wasm"))))]
+    {
+        seeds.iter().map(|&seed| play(card_pool, decks, seed, max_options)).collect()
+    }
+}
+
+/// Adds up the outcomes of simulated games
+pub fn stats(outcomes: &[Outcome]) -> SimStats {
+    let mut stats = SimStats::default();
+    for outcome in outcomes {
+        stats.record(outcome);
+    }
+    stats
+}
@@ -1,38 +1,130 @@
 use std::fmt;
-use std::sync::atomic::{AtomicU32, Ordering};
-use crate::cards::Cards;
-use crate::card_type::{CardTypeIdentifier, CardType};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::sync::{Mutex, OnceLock};
+use crate::cards::{Cards, Fnv};
+use crate::combat;
+use crate::card_type::{Adjacency, Aura, CardTypeIdentifier, CardType, Keyword, SummonRestriction, TriggerTiming};
+use crate::deck::MAX_CENTER_DECK;
+use crate::ids::{ChainLinkId, EventSeq};
+use crate::position::{ImportError, PositionSpec, SideSpec};
+use crate::sim::Rng;
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
+
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum ActivatableType {
     Can,
     Mandatory,
 }
 
-static CARD_INSTANCES: AtomicU32 = AtomicU32::new(0);
-
 // purposely not copy or clone so we never dupe cards by accident
 #[derive(Debug, Eq, PartialEq)]
 pub struct Card {
     pub card_type: CardTypeIdentifier,
     pub instance: CardInstance,
+    /// Damage counters on this card, these only exist while the card is on the field
+    pub damage: u32,
+    // kept up to date as the card moves between zones
+    status: CardStatus,
+    // the player whose decks the card started the game in, who may not be the player controlling
+    // it on the field
+    owner: Player,
 }
 
-impl Card {
+/// Hands out the card instances for a single game. Instances only need to be unique within the
+/// game they're in, so each game uses its own allocator rather than sharing state across games,
+/// which also keeps instances small enough for a game of up to `MAX_CARDS` cards.
+#[derive(Debug, Default)]
+pub struct CardInstances {
+    // counts one past the last instance, so it can reach MAX_CARDS once every instance is used
+    next: u32,
+}
+
+/// The most cards a game can have, as each card's instance is 16 bits
+pub const MAX_CARDS: usize = 1 << 16;
+
+/// Every card instance in the game has already been handed out
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct TooManyCards;
+
+impl fmt::Display for TooManyCards {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a game can't have more than {} cards", MAX_CARDS)
+    }
+}
+
+impl std::error::Error for TooManyCards {}
+
+impl CardInstances {
     /// creates a new card (intended for initialisation of a game only)
-    pub fn instantiate(card_type: &CardType) -> Card {
-        Card {
+    ///
+    /// Panics if the game already has `MAX_CARDS` cards, which is far more than any deck limits
+    /// allow. Use `try_instantiate` where the number of cards isn't limited.
+    pub fn instantiate(&mut self, card_type: &CardType) -> Card {
+        self.try_instantiate(card_type).expect("a game has at most MAX_CARDS cards")
+    }
+
+    /// Creates a new card, unless the game already has `MAX_CARDS` cards
+    pub fn try_instantiate(&mut self, card_type: &CardType) -> Result<Card, TooManyCards> {
+        let instance = u16::try_from(self.next).map(CardInstance).map_err(|_| TooManyCards)?;
+        self.next += 1;
+        Ok(Card {
             card_type: card_type.id,
-            instance: CardInstance(CARD_INSTANCES.fetch_add(1, Ordering::SeqCst)),
+            instance,
+            damage: 0,
+            status: CardStatus::Deck,
+            // set by the game the card is dealt into
+            owner: Player::One,
+        })
+    }
+}
+
+impl Card {
+    /// Copies this card for a snapshot of the game, which must never be played alongside the
+    /// game the original is in
+    fn snapshot(&self) -> Card {
+        Card {
+            card_type: self.card_type,
+            instance: self.instance,
+            damage: self.damage,
+            status: self.status,
+            owner: self.owner,
         }
     }
 
+    /// The player whose decks this card started the game in. Cards leaving the field always go
+    /// to their owner's zones, even if another player has taken control of them.
+    pub fn owner(&self) -> Player {
+        self.owner
+    }
+
+    /// Where the card is in its lifecycle
+    pub fn status(&self) -> CardStatus {
+        self.status
+    }
+
+    /// Clears all the state this card only has while it is on the field
+    fn leave_field(&mut self) {
+        self.damage = 0;
+    }
+
+    /// How much more damage this card can take before being destroyed, ignoring any auras on it
+    pub fn remaining_defense(&self, card_pool: &Cards) -> u32 {
+        self.lookup_self(card_pool).defense.saturating_sub(self.damage)
+    }
+
+    fn can_attack(&self, card_pool: &Cards) -> bool {
+        self.status != CardStatus::Field { just_summoned: true } || self.lookup_self(card_pool).has_keyword(Keyword::Swift)
+    }
+
     pub fn instance_of(&self, card_type: &CardType) -> bool {
         self.card_type == card_type.id
     }
 
     pub fn has_name(&self, card_pool: &Cards, name: &str) -> bool {
-        self.lookup_self(card_pool).name == name
+        card_pool.ids_named(name).contains(&self.card_type)
     }
 
     pub fn lookup_self<'a>(&self, card_pool: &'a Cards) -> &'a CardType {
@@ -42,9 +134,9 @@ impl Card {
     }
 }
 
-/// A unique id assigned to a Card to uniquely identify the copy
-#[derive(Copy, Clone, Eq, PartialEq)]
-pub struct CardInstance(pub u32);
+/// A unique id assigned to a Card to uniquely identify the copy within its game
+#[derive(Copy, Clone, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct CardInstance(pub u16);
 
 impl fmt::Debug for CardInstance {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -53,7 +145,7 @@ impl fmt::Debug for CardInstance {
 }
 
 /// The ith card effect a CardType may have
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub struct CardEffect(pub u32);
 
 impl From<usize> for CardEffect {
@@ -61,6 +153,74 @@ impl From<usize> for CardEffect {
         CardEffect(index as u32)
     }
 }
+
+/// A particular way a card effect can be activated
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Activation {
+    pub status: ActivatableType,
+    pub data: ActivationData,
+}
+
+/// The choices made when activating a card effect
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ActivationData {
+    pub slot: Option<FieldSlot>,
+    pub targets: Vec<CardInstance>,
+    pub deck: Option<FaceDownDeck>,
+    pub column: Option<Column>,
+}
+
+/// An effect activated on to the chain
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChainLink {
+    pub player: Player,
+    pub instance: CardInstance,
+    /// The type of the link's card, so the link can still be named if it fizzles
+    pub card: CardTypeIdentifier,
+    pub effect: CardEffect,
+    pub activation: Activation,
+    /// The targets which were still on the field once the link was activated. The link fizzles
+    /// if any of them leave the field before it resolves.
+    pub on_field: Vec<CardInstance>,
+}
+
+/// One card attacking another
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Attack {
+    pub attacker: CardInstance,
+    pub target: CardInstance,
+}
+
+/// What happened in the game state that a card effect may be triggered by
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Trigger {
+    Summoned(CardInstance),
+    Drawn(CardInstance),
+    Attacked(Attack),
+    Activated(ChainLink),
+    /// The cards were destroyed from the field while the last chain resolved
+    Destroyed(Vec<CardInstance>),
+    /// The player's face down deck ran out of cards
+    DeckEmptied {
+        player: Player,
+        deck: FaceDownDeck,
+    },
+    /// The player's turn started, before they drew
+    TurnStarted(Player),
+}
+
+/// The circumstances a card effect is being activated or resolved in. Effects which are not
+/// triggered by anything and are not part of a chain get the default empty context.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ResolutionContext {
+    /// What triggered this effect, if anything
+    pub trigger: Option<Trigger>,
+    /// The attack the chain was opened by, if any
+    pub attack: Option<Attack>,
+    /// The links of the chain below this effect, from the bottom of the chain upwards
+    pub chain: Vec<ChainLink>,
+}
+
 #[derive(Debug, Clone)]
 pub struct InvalidAction;
 
@@ -72,11 +232,25 @@ impl fmt::Display for InvalidAction {
 
 impl std::error::Error for InvalidAction {}
 
-enum CardState {
+/// Where a card is in its lifecycle, which the game keeps up to date as the card moves between
+/// zones
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CardStatus {
+    /// In the left, center or right deck
     Deck,
     Hand,
-    Field,
+    /// On the field. Cards just summoned, or just taken control of, may not attack until their
+    /// controller's next turn starts.
+    Field { just_summoned: bool },
     Destroyed,
+    /// Out of the game, where effects can't move it again
+    Removed,
+}
+
+impl CardStatus {
+    pub fn on_field(self) -> bool {
+        matches!(self, CardStatus::Field { .. })
+    }
 }
 
 // Players choose the allocation and order of their left + center + right decks prior to turn 1
@@ -111,12 +285,116 @@ pub struct Field {
     // revival effects would typically involve the column they were destroyed in
     // The destroyed column is also face up and public knowledge, with no order to the stacked
     // cards.
-    destroyed: [Vec<Card> ; 7],
+    destroyed: [Pile ; 7],
     // The hand is orderless private knowledge for each player.
-    hand: Vec<Card>,
+    hand: Hand,
+    // Cards removed from the game are face up and can't be moved again.
+    removed: Vec<Card>,
+    // Resource points carry over between turns in formats which have them.
+    resources: u32,
 }
 
+// Hands and destroyed piles rarely hold more than a few cards, so they're kept inline to save
+// allocating for every one of them in every game
+type Hand = SmallVec<[Card ; 8]>;
+type Pile = SmallVec<[Card ; 2]>;
+
 impl Field {
+    fn snapshot(&self) -> Field {
+        fn slot(card: &Option<Card>) -> Option<Card> {
+            card.as_ref().map(Card::snapshot)
+        }
+        fn zone<Z: std::iter::FromIterator<Card>>(cards: &[Card]) -> Z {
+            cards.iter().map(Card::snapshot).collect()
+        }
+        Field {
+            front: [0, 1, 2, 3, 4, 5, 6].map(|i| slot(&self.front[i])),
+            back: [0, 1, 2, 3, 4, 5, 6].map(|i| slot(&self.back[i])),
+            left_deck: zone(&self.left_deck),
+            center_deck: zone(&self.center_deck),
+            right_deck: zone(&self.right_deck),
+            destroyed: [0, 1, 2, 3, 4, 5, 6].map(|i| zone(&self.destroyed[i])),
+            hand: zone(&self.hand),
+            removed: zone(&self.removed),
+            resources: self.resources,
+        }
+    }
+
+    /// Adds every card in every zone, in order, to the hash
+    fn hash_into(&self, hash: &mut Fnv) {
+        let card = |hash: &mut Fnv, card: &Card| {
+            hash.write(&card.card_type.0.to_le_bytes());
+            hash.write(&card.instance.0.to_le_bytes());
+            hash.write(&card.damage.to_le_bytes());
+            hash.write(&[card.owner as u8, match card.status {
+                CardStatus::Deck => 0,
+                CardStatus::Hand => 1,
+                CardStatus::Field { just_summoned: false } => 2,
+                CardStatus::Field { just_summoned: true } => 3,
+                CardStatus::Destroyed => 4,
+                CardStatus::Removed => 5,
+            }]);
+        };
+        for slot in self.field_slots() {
+            match slot {
+                Some(slot) => card(hash, slot),
+                None => hash.write(&[0xFF]),
+            }
+        }
+        let piles = self.destroyed.iter().map(|pile| &pile[..]);
+        let zones = [&self.left_deck[..], &self.center_deck[..], &self.right_deck[..], &self.hand[..], &self.removed[..]];
+        for zone in zones.iter().copied().chain(piles) {
+            hash.write(&(zone.len() as u64).to_le_bytes());
+            for zone_card in zone {
+                card(hash, zone_card);
+            }
+        }
+        hash.write(&self.resources.to_le_bytes());
+    }
+
+    /// Iterates through every card this field holds in any zone
+    fn cards(&self) -> impl Iterator<Item = &Card> {
+        self.field_slots()
+            .flatten()
+            .chain(self.left_deck.iter())
+            .chain(self.center_deck.iter())
+            .chain(self.right_deck.iter())
+            .chain(self.destroyed.iter().flatten())
+            .chain(self.hand.iter())
+            .chain(self.removed.iter())
+    }
+
+    /// Iterates through the cards in zones which are public knowledge to both players
+    fn public_cards(&self) -> impl Iterator<Item = &Card> {
+        self.field_slots()
+            .flatten()
+            .chain(self.center_deck.iter())
+            .chain(self.destroyed.iter().flatten())
+            .chain(self.removed.iter())
+    }
+
+    /// The card in a slot on the field, if there is one
+    pub fn slot(&self, slot: FieldSlot) -> Option<&Card> {
+        self[slot].as_ref()
+    }
+
+    /// Iterates through the occupied slots on the field, front row first
+    pub fn occupied_slots(&self) -> impl Iterator<Item = (FieldSlot, &Card)> {
+        self.field_slots()
+            .enumerate()
+            .filter_map(|(i, slot)| slot.as_ref().map(|card| (FieldSlot::from_index(i), card)))
+    }
+
+    /// Has this player run out of every card they could still play, so that they can never
+    /// draw or act again?
+    fn exhausted(&self, card_pool: &Cards) -> bool {
+        !self.has_cards_to_draw()
+            && self.hand.is_empty()
+            && self.center_deck.is_empty()
+            && self.field_slots().flatten().next().is_none()
+            && self.cards_to_revive(card_pool).is_empty()
+    }
+
     fn has_cards_to_draw(&self) -> bool {
         self.has_cards_to_draw_left() || self.has_cards_to_draw_right()
     }
@@ -129,51 +407,259 @@ impl Field {
         !self.right_deck.is_empty()
     }
 
+    /// The cards in one of the face down decks, with the top of the deck last
+    pub(crate) fn deck(&self, deck: FaceDownDeck) -> &[Card] {
+        match deck {
+            FaceDownDeck::Left => &self.left_deck,
+            FaceDownDeck::Right => &self.right_deck,
+        }
+    }
+
+    fn deck_mut(&mut self, deck: FaceDownDeck) -> &mut Vec<Card> {
+        match deck {
+            FaceDownDeck::Left => &mut self.left_deck,
+            FaceDownDeck::Right => &mut self.right_deck,
+        }
+    }
+
+    /// How many cards are in each deck, which both players know even for the face down decks
+    pub fn deck_sizes(&self) -> DeckSizes {
+        DeckSizes {
+            left: self.left_deck.len(),
+            center: self.center_deck.len(),
+            right: self.right_deck.len(),
+        }
+    }
+
+    /// The center deck, which is public knowledge, in no particular order
+    pub fn center_deck(&self) -> &[Card] {
+        &self.center_deck
+    }
+
+    /// How many cards are in the hand, which is all the opponent knows about it
+    pub fn hand_size(&self) -> usize {
+        self.hand.len()
+    }
+
+    /// The hand, in no particular order
+    pub fn hand(&self) -> &[Card] {
+        &self.hand
+    }
+
+    /// The destroyed pile of a column, in no particular order
+    pub fn destroyed(&self, column: Column) -> &[Card] {
+        self.destroyed.get(column.index()).map_or(&[], |pile| pile)
+    }
+
+    /// The cards removed from the game, in the order they were removed
+    pub fn removed(&self) -> &[Card] {
+        &self.removed
+    }
+
+    /// The resource points this player has to spend, which are always 0 in formats without
+    /// resources
+    pub fn resources(&self) -> u32 {
+        self.resources
+    }
+
+    /// Iterates through the cards on the field in a column, front row first. Cards in the
+    /// column's destroyed pile are not included.
+    pub fn cards_in_column(&self, column: Column) -> impl Iterator<Item = &Card> {
+        let slots = if column.is_valid() { vec![column.front(), column.back()] } else { vec![] };
+        slots.into_iter().filter_map(move |slot| self[slot].as_ref())
+    }
+
+    /// The column of a card on the field or in a destroyed pile
+    pub fn column_of(&self, instance: CardInstance) -> Option<Column> {
+        self.slot_of(instance).map(FieldSlot::column).or_else(|| {
+            Column::all().find(|&column| self.destroyed(column).iter().any(|card| card.instance == instance))
+        })
+    }
+
     fn field_slots(&self) -> impl Iterator<Item = &Option<Card>> {
         self.front.iter().chain(self.back.iter())
     }
 
+    /// The card if it is somewhere its effects can be activated from, which is on the field, in
+    /// the hand, in the center deck or in a destroyed pile
+    fn activatable_card(&self, instance: CardInstance) -> Option<&Card> {
+        self.field_slots()
+            .flatten()
+            .chain(self.hand.iter())
+            .chain(self.center_deck.iter())
+            .chain(self.destroyed.iter().flatten())
+            .find(|card| card.instance == instance)
+    }
+
     fn space_on_field(&self) -> bool {
         self.field_slots().any(|slot| slot.is_none())
     }
 
-    fn cards_to_summon(&self) -> Vec<CardInstance> {
+    /// The cards in hand, and if `center` the cards in the center deck, while there is space to
+    /// summon them
+    fn cards_to_summon(&self, center: bool) -> Vec<&Card> {
+        if !self.space_on_field() {
+            return vec![];
+        }
+        let center_deck: &[Card] = if center { &self.center_deck } else { &[] };
+        self.hand.iter().chain(center_deck).collect()
+    }
+
+    /// The Undying cards in this field's destroyed piles, which may be summoned from there
+    fn cards_to_revive(&self, card_pool: &Cards) -> Vec<&Card> {
         if self.space_on_field() {
-            self.hand.iter().map(|card| card.instance).collect()
+            self.destroyed.iter()
+                .flatten()
+                .filter(|card| card.lookup_self(card_pool).has_keyword(Keyword::Undying))
+                .collect()
         } else {
             vec![]
         }
     }
 
-    fn cards_to_attack(&self) -> Vec<CardInstance> {
-        self.field_slots().filter_map(|slot| slot.as_ref().map(|card| card.instance)).collect()
+    fn cards_to_attack(&self, card_pool: &Cards) -> Vec<CardInstance> {
+        self.field_slots()
+            .filter_map(|slot| slot.as_ref())
+            .filter(|card| card.can_attack(card_pool))
+            .map(|card| card.instance)
+            .collect()
+    }
+
+    /// The slots an opponent's attack may target. Cards in the back row are protected by the card
+    /// in front of them, and Guard cards must be attacked before anything else in their column.
+    pub(crate) fn attack_targets(&self, card_pool: &Cards) -> Vec<FieldSlot> {
+        let mut targets = vec![];
+        for column in Column::all() {
+            let front = column.front();
+            let back = column.back();
+            let guards: Vec<FieldSlot> = [front, back].iter()
+                .copied()
+                .filter(|&slot| self[slot].as_ref()
+                    .is_some_and(|card| card.lookup_self(card_pool).has_keyword(Keyword::Guard)))
+                .collect();
+            if !guards.is_empty() {
+                targets.extend(guards);
+            } else if self[front].is_some() {
+                targets.push(front);
+            } else if self[back].is_some() {
+                targets.push(back);
+            }
+        }
+        targets
+    }
+
+    /// The slot a card is in on the field, if it is on this field
+    pub fn slot_of(&self, instance: CardInstance) -> Option<FieldSlot> {
+        self.occupied_slots()
+            .find(|(_, card)| card.instance == instance)
+            .map(|(slot, _)| slot)
+    }
+
+    /// The row a card is in on the field, if it is on this field
+    pub fn row_of(&self, instance: CardInstance) -> Option<Row> {
+        self.slot_of(instance).map(FieldSlot::row)
+    }
+
+    /// The cards in a row of this field, from left to right
+    pub fn cards_in_row(&self, row: Row) -> impl Iterator<Item = &Card> {
+        row.slots().filter_map(move |slot| self[slot].as_ref())
+    }
+
+    /// Every slot adjacent to the slot, in the same column or the neighbouring columns
+    pub fn adjacent_slots(&self, slot: FieldSlot) -> Vec<FieldSlot> {
+        slot.adjacent(Adjacency::Any)
+    }
+
+    /// The auras of the cards adjacent to the slot which apply to it
+    fn auras_on<'a>(&'a self, card_pool: &'a Cards, slot: FieldSlot) -> impl Iterator<Item = &'a Aura> {
+        self.adjacent_slots(slot)
+            .into_iter()
+            .filter_map(move |other| self[other].as_ref().map(|card| (other, card)))
+            .flat_map(move |(other, card)| {
+                card.lookup_self(card_pool).auras.iter().filter(move |aura| {
+                    other.adjacent(aura.adjacency).contains(&slot) && aura.while_in.is_none_or(|row| other.row() == row)
+                })
+            })
+    }
+
+    /// The attack of the card in the slot, including the auras of cards adjacent to it
+    pub fn attack_of(&self, card_pool: &Cards, slot: FieldSlot) -> Option<u32> {
+        let card = self[slot].as_ref()?;
+        Some(card.lookup_self(card_pool).attack + self.auras_on(card_pool, slot).map(|aura| aura.attack).sum::<u32>())
+    }
+
+    /// The defense of the card in the slot, including the auras of cards adjacent to it
+    pub fn defense_of(&self, card_pool: &Cards, slot: FieldSlot) -> Option<u32> {
+        let card = self[slot].as_ref()?;
+        Some(card.lookup_self(card_pool).defense + self.auras_on(card_pool, slot).map(|aura| aura.defense).sum::<u32>())
+    }
+
+    /// How much more damage the card in the slot can take before being destroyed
+    pub(crate) fn remaining_defense_of(&self, card_pool: &Cards, slot: FieldSlot) -> Option<u32> {
+        let damage = self[slot].as_ref()?.damage;
+        self.defense_of(card_pool, slot).map(|defense| defense.saturating_sub(damage))
+    }
+
+    /// Places damage counters on the card in the slot, removing it from the field if it has as
+    /// many or more damage counters than defense. The removed card must be sent to its owner's
+    /// destroyed pile.
+    fn take_damage(&mut self, card_pool: &Cards, slot: FieldSlot, damage: u32) -> Option<Card> {
+        let card = self[slot].as_mut()?;
+        card.damage += damage;
+        if self.remaining_defense_of(card_pool, slot) == Some(0) {
+            self.leave_field(slot)
+        } else {
+            None
+        }
+    }
+
+    /// Removes the card in the slot from the field, clearing its counters
+    fn leave_field(&mut self, slot: FieldSlot) -> Option<Card> {
+        let mut card = self[slot].take()?;
+        card.leave_field();
+        Some(card)
+    }
+
+    /// Places a card in an empty slot, where it can't attack until its controller's next turn
+    fn summon(&mut self, mut card: Card, slot: FieldSlot) {
+        card.status = CardStatus::Field { just_summoned: true };
+        self[slot] = Some(card);
+    }
+
+    /// Wears off summoning sickness as the controller of this field starts their turn
+    fn start_turn(&mut self) {
+        for card in self.front.iter_mut().chain(self.back.iter_mut()).flatten() {
+            card.status = CardStatus::Field { just_summoned: false };
+        }
     }
 
     fn slot_is_empty(&self, slot: FieldSlot) -> bool {
         self[slot].is_none()
     }
 
-    fn empty_slots(&self) -> Vec<FieldSlot> {
-        self.field_slots().enumerate().filter(|(_, s)| s.is_none()).map(|(i, _)| match i {
-            0 => FieldSlot::F0,
-            1 => FieldSlot::F1,
-            2 => FieldSlot::F2,
-            3 => FieldSlot::F3,
-            4 => FieldSlot::F4,
-            5 => FieldSlot::F5,
-            6 => FieldSlot::F6,
-            7 => FieldSlot::B0,
-            8 => FieldSlot::B1,
-            9 => FieldSlot::B2,
-            10 => FieldSlot::B3,
-            11 => FieldSlot::B4,
-            12 => FieldSlot::B5,
-            13 => FieldSlot::B6,
-            _ => panic!(),
-        }).collect()
+    /// The slots on the field with no card in them, front row first
+    pub fn empty_slots(&self) -> Vec<FieldSlot> {
+        self.field_slots()
+            .enumerate()
+            .filter(|(_, s)| s.is_none())
+            .map(|(i, _)| FieldSlot::from_index(i))
+            .collect()
     }
 }
 
+/// How many cards are in each of a player's decks
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct DeckSizes {
+    pub left: usize,
+    pub center: usize,
+    pub right: usize,
+}
+
+/// Where a card instance is in a zone that is a plain list of cards
+fn position_of(zone: &[Card], instance: CardInstance) -> Option<usize> {
+    zone.iter().position(|card| card.instance == instance)
+}
+
 impl std::ops::Index<FieldSlot> for Field {
     type Output = Option<Card>;
 
@@ -218,19 +704,33 @@ impl std::ops::IndexMut<FieldSlot> for Field {
     }
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
 pub enum Player {
     One,
     Two,
 }
 
 impl Player {
-    fn next(&self) -> Player {
+    /// Iterates through both players, player one first
+    pub fn all() -> impl Iterator<Item = Player> {
+        [Player::One, Player::Two].iter().copied()
+    }
+
+    /// The other player
+    pub fn next(&self) -> Player {
         match self {
             Player::One => Player::Two,
             Player::Two => Player::One,
         }
     }
+
+    /// 0 for player one and 1 for player two, for indexing per player arrays
+    pub fn index(&self) -> usize {
+        match self {
+            Player::One => 0,
+            Player::Two => 1,
+        }
+    }
 }
 
 #[derive(Eq, PartialEq)]
@@ -239,243 +739,2532 @@ pub struct GameState {
     player_two: Field,
     active: Player,
     open: GameStateType,
+    // whose turn it is, the other player gets priority in an open game state after resolution
+    turn: Player,
+    // what happened to open the game state that effects may be triggered by
+    trigger: Option<Trigger>,
+    // the attack that closed the game state, if any, which is pending until the chain resolves
+    attack: Option<Attack>,
+    // the effects activated since the game state closed, which resolve in reverse order
+    chain: Vec<ChainLink>,
+    // if the last player with priority passed instead of responding
+    passed: bool,
+    // the hidden cards of their opponent each player has seen
+    knowledge: Knowledge,
+    // everything the players have done so far, in order
+    events: Vec<GameEvent>,
+    // what caused each event, in the same order
+    causes: Vec<EventCause>,
+    // what is causing the events being added
+    cause: EventCause,
+    // the priority player's options, computed at most once between changes to the game state
+    options: OptionsCache,
+    // the ways card effects can activate, computed at most once between changes to the game state
+    activations: ActivationCache,
+    // every card's effects by what they wait on, computed at most once per game
+    subscriptions: SubscriptionCache,
+    // how the game ended, if it ended other than by a player being unable to draw or act
+    ended: Option<GameResult>,
+    // how many trigger windows have opened in a row since the game state was last open
+    windows: u32,
+    // if the trigger windows since the game state was last open began with the turn starting,
+    // so the turn player draws rather than the turn passing once they close
+    starting: bool,
+    // rules which differ between formats of the game
+    config: GameConfig,
+    // changes card effects have made to how players may draw, in the order they were made
+    draw_rules: Vec<DrawModifier>,
+    // how many cards the turn player has drawn this turn
+    draws: u32,
+    // what the effect activating or resolving has used of its budget, if budgets are enforced
+    spent: Option<Spent>,
+    // the random numbers card effects use, seeded by the config
+    rng: Rng,
+    // the decks which ran out since they were last offered as a trigger window, oldest first
+    emptied: Vec<(Player, FaceDownDeck)>,
+}
+
+/// What an effect has used of its budget so far
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+struct Spent {
+    actions: u32,
+    movements: u32,
+    exceeded: bool,
 }
 
+/// A hash of everything about a game which decides how it plays out from here. The rules only
+/// use integer arithmetic and never depend on the iteration order of hash maps, so two games
+/// started with the same cards and given the same options with the same card pool have the same
+/// hash on every platform. Lockstep clients can compare hashes to find where they diverged.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct StateHash(pub u64);
+
+/// How many trigger windows may open in a row by default before the effects causing them are
+/// treated as an infinite loop
+pub const DEFAULT_LOOP_LIMIT: u32 = 100;
+
+/// Rules which hosts can change for the format they're running
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub enum ActionType {
-    Effect,
-    Summon,
-    Attack,
+pub struct GameConfig {
+    /// How many trigger windows may open in a row before the effects causing them are treated
+    /// as an infinite loop, which ends the game as a draw
+    pub loop_limit: u32,
+    /// If cards can be removed from the game. Without it, cards on the field which would be
+    /// removed are destroyed instead, and cards in destroyed piles stay there.
+    pub removal: bool,
+    /// How many resource points each player gains at the start of their turn. Cards then cost
+    /// their resource cost to summon from the hand. With 0 the format has no resources.
+    pub resources_per_turn: u32,
+    /// How many cards each player draws before the game starts, alternating between their left
+    /// and right decks starting with the left. Hands given to `GameState::start` are kept.
+    pub starting_hand: usize,
+    /// How many links a chain may have before no more effects may be activated in response,
+    /// None for no limit
+    pub max_chain: Option<usize>,
+    /// What to do when every option the priority player has fails to be taken, which only a
+    /// bug in a card's effect can cause
+    pub soft_lock: SoftLockPolicy,
+    /// How much each effect may do each time it activates or resolves, None for no limit
+    pub effect_budget: Option<EffectBudget>,
+    /// Card packs whose cards may not be played in the format, by name. Every pack loaded into
+    /// the card pool is enabled otherwise.
+    pub disabled_packs: Vec<String>,
+    /// Seeds the random numbers card effects use, such as to generate random cards, so games
+    /// with the same seed given the same options play out the same
+    pub seed: u64,
+    /// If the turn player must draw at the start of their turn whenever they can, rather than
+    /// choosing to. A player whose turn starts with no cards left in their left and right decks
+    /// then loses, even if they could still take an action.
+    pub mandatory_draw: bool,
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
-pub enum GameStateType {
-    /// The active player may draw and/or then take an action.
-    Open {
-        phase: Phase,
-    },
-    /// An effect has been activated and now both players may respond in turn.
-    Closed,
+impl GameConfig {
+    /// If cards from the pack may be played in the format
+    pub fn pack_enabled(&self, pack: &str) -> bool {
+        !self.disabled_packs.iter().any(|disabled| disabled == pack)
+    }
+
+    /// A hash of the rules the config sets, which is the same on every platform, so servers and
+    /// clients can check they play the same format
+    pub fn fingerprint(&self) -> u64 {
+        let mut hash = Fnv::new();
+        self.hash_into(&mut hash);
+        hash.write(&(self.starting_hand as u64).to_le_bytes());
+        hash.finish()
+    }
+
+    /// Hashes every rule which decides how a game plays out once it has started, for state
+    /// hashes and the fingerprint
+    pub(crate) fn hash_into(&self, hash: &mut Fnv) {
+        // taken apart so a new rule can't be added without deciding how it is hashed
+        let GameConfig {
+            loop_limit,
+            removal,
+            resources_per_turn,
+            max_chain,
+            soft_lock,
+            effect_budget,
+            disabled_packs,
+            mandatory_draw,
+            // the hand is dealt before the game starts, and the seed is hashed as the state of
+            // the random numbers, so neither decides how the game plays out from here
+            starting_hand: _,
+            seed: _,
+        } = self;
+        hash.write(&loop_limit.to_le_bytes());
+        hash.write(&[*removal as u8]);
+        hash.write(&resources_per_turn.to_le_bytes());
+        // left out without a limit, so games in the default format hash as they always have
+        if let Some(max_chain) = max_chain {
+            hash.write(&(*max_chain as u64).to_le_bytes());
+        }
+        // likewise only once they differ from the default
+        if *soft_lock != SoftLockPolicy::Abort {
+            hash.write(&[*soft_lock as u8]);
+        }
+        if let Some(budget) = effect_budget {
+            hash.write(&budget.actions.to_le_bytes());
+            hash.write(&budget.movements.to_le_bytes());
+        }
+        if !disabled_packs.is_empty() {
+            hash.write(&(disabled_packs.len() as u64).to_le_bytes());
+            for pack in disabled_packs {
+                hash.write(pack.as_bytes());
+                hash.write(&[0]);
+            }
+        }
+        if *mandatory_draw {
+            hash.write(&[1]);
+        }
+    }
 }
 
+/// Limits on how much one effect may do each time it activates or resolves, so a buggy card
+/// can't stall a server's game. An effect which goes over its budget fizzles, leaving the game
+/// as it was before the effect started. Effects which keep triggering each other are bounded by
+/// the loop limit instead.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
-pub enum Phase {
-    MayDraw, MayTakeAction,
+pub struct EffectBudget {
+    /// How many changes the effect may make to the game state
+    pub actions: u32,
+    /// How many cards the effect may move between zones or slots
+    pub movements: u32,
+}
+
+impl Default for EffectBudget {
+    fn default() -> Self {
+        EffectBudget {
+            actions: 1000,
+            movements: 100,
+        }
+    }
 }
 
+/// How a game continues when the priority player is soft locked, with options which all fail
+/// when taken, so the game would otherwise never progress
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
-pub enum FaceDownDeck {
-    Left, Right,
+pub enum SoftLockPolicy {
+    /// The player passes, ends their turn or skips their draw, skipping the effect which failed
+    Skip,
+    /// The game ends without a winner
+    Abort,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub enum PlayerOption {
-    Draw(FaceDownDeck),
-    SkipDraw,
-    Action(Action),
-    SkipAction,
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            loop_limit: DEFAULT_LOOP_LIMIT,
+            removal: true,
+            resources_per_turn: 0,
+            starting_hand: 0,
+            max_chain: None,
+            soft_lock: SoftLockPolicy::Abort,
+            effect_budget: None,
+            disabled_packs: vec![],
+            seed: 0,
+            mandatory_draw: false,
+        }
+    }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub struct Action {
-    pub action_type: ActionType,
-    pub instance: CardInstance,
-    pub slot: Option<FieldSlot>,
+/// Why a game ended
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum EndReason {
+    /// The loser's turn started and they could neither draw a card nor take an action
+    NoDrawOrAction,
+    /// Both players ran out of cards to play at the same time, such as when the last cards on
+    /// both fields were destroyed together, so the game is a draw
+    SimultaneousLoss,
+    /// The loser conceded
+    Concession,
+    /// The loser ran out of time
+    Timeout,
+    /// A card effect won the game for the winner
+    AlternativeWin,
+    /// Effects kept triggering each other without the game state reopening, so the loop was
+    /// broken and the game is a draw
+    Loop,
+    /// The game was stopped without a winner, such as when the game state could no longer be
+    /// trusted
+    Aborted,
+    /// An operator decided the result of a game which couldn't finish, with no winner if they
+    /// declared it drawn
+    Adjudicated,
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
-#[rustfmt::skip]
-pub enum FieldSlot {
-    F0, F1, F2, F3, F4, F5, F6,
-    B0, B1, B2, B3, B4, B5, B6,
+impl EndReason {
+    /// Iterates through every way a game can end
+    pub fn all() -> impl Iterator<Item = EndReason> {
+        [
+            EndReason::NoDrawOrAction, EndReason::SimultaneousLoss, EndReason::Concession, EndReason::Timeout,
+            EndReason::AlternativeWin, EndReason::Loop, EndReason::Aborted, EndReason::Adjudicated,
+        ].iter().copied()
+    }
 }
 
-use Phase::{MayDraw, MayTakeAction};
-use GameStateType::{Open, Closed};
+/// How a game ended
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct GameResult {
+    winner: Option<Player>,
+    reason: EndReason,
+}
 
-// Each player gets one action before passing priority to the other player.
-// When in an open game state, a player may optionally draw a card from the top of either their
-// left or right decks (this **cannot** be responded to). Regardless, the player must then take an
-// action or pass to the other player. If a player is unable to draw a card or to take an action
-// then they immediately lose the game. (If a player may draw but not take actions or take actions
-// but not draw they are still in the game for as long as they can limp on).
-// An action consists of activating an effect of a card or summoning a card to the field.
-// When we pass priority to the other player after an action is taken, the game state is closed.
-// The other player must choose to activate an appropriate effect of a card in response or pass
-// back to the opponent.
-// When both players pass, we resolve in reverse order all the activations activated in response
-// to the initial action (effects during resolution **cannot** be responded to).
-// After resolution, the other player gets priority and the game state is back to open.
+impl GameResult {
+    /// The player who won, if the game wasn't aborted
+    pub fn winner(&self) -> Option<Player> {
+        self.winner
+    }
 
-// If a player has a card(s) with a mandatory effect that may be activated in response, they must
-// choose one of the mandatory effects to activate in response. Only after all mandatory effects
-// have responded may a player elect to respond with optional effects.
+    pub fn reason(&self) -> EndReason {
+        self.reason
+    }
 
-// Unlike YuGiOh, interactivity is built into the priority passing, not just for chain links.
-// OTKs and FTKs are not a thing because you can only summon one card before your opponent gets
-// priority to summon their own.
-// Also unlike YuGiOh, there is no randomness in deck construction to worry about, and no searching
-// or shuffling of the deck, so in person play should be a lot more fluid.
+    /// Did the game end without either player winning or it being aborted?
+    pub fn is_draw(&self) -> bool {
+        match self.reason {
+            EndReason::SimultaneousLoss | EndReason::Loop => true,
+            EndReason::Adjudicated => self.winner.is_none(),
+            _ => false,
+        }
+    }
+}
 
-// There is no such thing as spell/trap/monster, cards are just Cards, and may be summoned to
-// any of the 14 positions on the field. There is also no 'one' grave, cards retain their column
-// on death. The 'extra deck' in the center is also a lot more loose, containing only cards
-// with summoning costs means there's no 'extra deck' type of card either, a card in the center
-// deck could also be in the left/right deck and drawn, or a player may elect that a card
-// 'returned to the deck' by card effect goes back to their center deck so they can summon it
-// again on the following turn.
+/// A lazily computed copy of the priority player's options. This assumes a game is always played
+/// with the same card pool, and it must be cleared whenever the game state changes.
+#[derive(Debug, Default)]
+struct OptionsCache(OnceLock<Vec<PlayerOption>>);
 
-impl GameState {
-    /// Initialise a game state with both players having drawn hands and supplied decks
-    pub fn start(
-        player_one: (Vec<Card>, Vec<Card>, Vec<Card>, Vec<Card>),
-        player_two: (Vec<Card>, Vec<Card>, Vec<Card>, Vec<Card>),
-    ) -> Self {
-        GameState {
+// The cache is derived from the rest of the game state so never makes two game states different
+impl PartialEq for OptionsCache {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for OptionsCache {}
+
+/// The ways each card effect can activate in the current game state and context, by card and
+/// effect. Since the context is derived from the game state, this must also be cleared whenever
+/// the game state changes.
+#[derive(Debug, Default)]
+struct ActivationCache(Mutex<HashMap<(CardInstance, CardEffect), Vec<Activation>>>);
+
+impl PartialEq for ActivationCache {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for ActivationCache {}
+
+/// The effects of every card in the game by the timing they wait on, so finding the effects
+/// which can activate only asks the effects subscribed to what happened. Cards and their types
+/// never change once a game starts, so unlike the other caches this is kept for the whole game,
+/// and likewise assumes a game is always played with the same card pool.
+#[derive(Clone, Debug, Default)]
+struct Subscriptions {
+    // effects which wait on something happening to their own card, by card
+    by_card: HashMap<CardInstance, Vec<(CardEffect, TriggerTiming)>>,
+    // every other effect, by the timing it waits on
+    by_timing: HashMap<TriggerTiming, Vec<(CardInstance, CardEffect)>>,
+}
+
+#[derive(Debug, Default)]
+struct SubscriptionCache(OnceLock<Subscriptions>);
+
+impl PartialEq for SubscriptionCache {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for SubscriptionCache {}
+
+// A GameState owns all of its cards, and its only interior mutability is in the caches of options,
+// activations and subscriptions. Those are behind OnceLock and Mutex, so threads reading a game at
+// once may wait on each other or repeat work but never see a half-written cache. Games can then
+// be moved to and shared between threads, which servers hosting many games at once rely on
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<GameState>();
+};
+
+/// Something a player did, recorded in order for match logs and summaries
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum GameEvent {
+    Drew {
+        player: Player,
+        deck: FaceDownDeck,
+    },
+    Summoned {
+        player: Player,
+        card: CardTypeIdentifier,
+        slot: FieldSlot,
+    },
+    Attacked {
+        player: Player,
+        attacker: CardTypeIdentifier,
+        target: CardTypeIdentifier,
+    },
+    /// A card effect was activated, either as an action or in response to one
+    Activated {
+        player: Player,
+        card: CardTypeIdentifier,
+        response: bool,
+    },
+    /// The player passed instead of responding
+    Passed {
+        player: Player,
+    },
+    /// The player ended their turn without taking an action
+    EndedTurn {
+        player: Player,
+    },
+    /// Both players passed, so the links of the chain started resolving from the last activated
+    ChainResolutionStarted {
+        links: usize,
+    },
+    /// A link of the chain resolved
+    LinkResolved {
+        link: ChainLinkId,
+        player: Player,
+        card: CardTypeIdentifier,
+    },
+    /// A link of the chain did nothing instead of resolving, as a card it targeted left the field
+    /// in response, or the effect went over the effect budget
+    LinkFizzled {
+        link: ChainLinkId,
+        player: Player,
+        card: CardTypeIdentifier,
+    },
+    /// Both players passed and the chain resolved
+    Resolved,
+    /// The game ended early, by concession, timeout, card effect or being aborted
+    Ended(GameResult),
+    /// The player's only option was taken for them, and the events it caused follow
+    Forced {
+        player: Player,
+    },
+    /// Effects kept triggering each other for longer than the loop limit, so resolution stopped
+    LoopBroken,
+    /// The effect of the player's card went over the effect budget, so it fizzled
+    EffectBudgetExceeded {
+        player: Player,
+        card: CardTypeIdentifier,
+    },
+    /// Every option the player had failed to be taken, so the game continued by the config's
+    /// soft lock policy
+    SoftLocked {
+        player: Player,
+    },
+    /// Both players agreed to undo the last option taken, at the player's request. The events of
+    /// the option undone are kept before this one.
+    Rewound {
+        player: Player,
+    },
+    /// A message from a tutorial script telling the player what to do next
+    Instruction(String),
+    /// A card left the field for its owner's destroyed pile of the column
+    Destroyed {
+        player: Player,
+        card: CardTypeIdentifier,
+        column: Column,
+    },
+    /// A card effect created a card of the type, chosen at random, in the player's hand
+    Generated {
+        player: Player,
+        card: CardTypeIdentifier,
+    },
+    /// The last card left one of the player's face down decks
+    DeckEmptied {
+        player: Player,
+        deck: FaceDownDeck,
+    },
+}
+
+/// What caused an event, so clients can show which option or effect led to it, such as the
+/// card whose effect destroyed another
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum EventCause {
+    /// The rules of the game, such as a turn passing or a loop being broken
+    Rules,
+    /// An option the player took
+    Option(Player),
+    /// A link of the chain being activated or resolving
+    Link {
+        link: ChainLinkId,
+        player: Player,
+        card: CardTypeIdentifier,
+    },
+    /// An attack dealing its damage once the chain it opened resolved
+    Attack {
+        player: Player,
+        attacker: CardTypeIdentifier,
+    },
+    /// A static effect of a card, such as a combat modifier reacting to damage
+    Static {
+        card: CardTypeIdentifier,
+    },
+}
+
+/// The cards each player knows the identity of despite them being in their opponent's hidden
+/// zones, such as a card returned from the field to its owner's hand
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+struct Knowledge {
+    player_one: Vec<CardInstance>,
+    player_two: Vec<CardInstance>,
+}
+
+impl Knowledge {
+    fn of(&self, player: Player) -> &Vec<CardInstance> {
+        match player {
+            Player::One => &self.player_one,
+            Player::Two => &self.player_two,
+        }
+    }
+
+    fn of_mut(&mut self, player: Player) -> &mut Vec<CardInstance> {
+        match player {
+            Player::One => &mut self.player_one,
+            Player::Two => &mut self.player_two,
+        }
+    }
+
+    /// Records that the player has seen the card
+    fn reveal(&mut self, player: Player, instance: CardInstance) {
+        let known = self.of_mut(player);
+        if !known.contains(&instance) {
+            known.push(instance);
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum GameStateType {
+    /// The active player may draw and/or then take an action.
+    Open {
+        phase: Phase,
+    },
+    /// An effect has been activated and now both players may respond in turn.
+    Closed,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub enum Phase {
+    MayDraw, MayTakeAction,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub enum FaceDownDeck {
+    Left, Right,
+}
+
+/// A change card effects make to how a player may draw
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum DrawRule {
+    /// The player can't draw from the deck, or from either deck if none is given
+    CannotDraw(Option<FaceDownDeck>),
+    /// The player may draw this many more cards in each of their turns. The extra cards are drawn
+    /// in the action phase alongside the actions, and drawing one doesn't use up the turn's action.
+    ExtraDraws(u32),
+}
+
+impl fmt::Display for DrawRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DrawRule::CannotDraw(Some(FaceDownDeck::Left)) => write!(f, "can't draw from their left deck"),
+            DrawRule::CannotDraw(Some(FaceDownDeck::Right)) => write!(f, "can't draw from their right deck"),
+            DrawRule::CannotDraw(None) => write!(f, "can't draw"),
+            DrawRule::ExtraDraws(1) => write!(f, "may draw 1 more card a turn"),
+            DrawRule::ExtraDraws(draws) => write!(f, "may draw {} more cards a turn", draws),
+        }
+    }
+}
+
+/// A draw rule on a player, which lasts until the end of a number of their turns, counting the
+/// turn it was made in if it is theirs
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct DrawModifier {
+    pub player: Player,
+    pub rule: DrawRule,
+    /// How many more of the player's turns the rule lasts until the end of
+    pub turns: u32,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum PlayerOption {
+    Draw(FaceDownDeck),
+    SkipDraw,
+    Action(Action),
+    SkipAction,
+    SkipResponse,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Action {
+    /// Summon a card from hand to an empty slot on the field. In formats with resources, cards
+    /// with a resource cost may also be summoned this way from the center deck.
+    Summon {
+        instance: CardInstance,
+        slot: FieldSlot,
+    },
+    /// Summon an Undying card from one of the player's destroyed piles to an empty slot on the
+    /// field, where it enters with damage counters equal to half its defense
+    Revive {
+        instance: CardInstance,
+        slot: FieldSlot,
+    },
+    /// Attack the card in a slot of the opponent's field with a card on the field. This is the
+    /// attacker and target chosen together, which clients can also choose one at a time with
+    /// `GameState::attackers` then `GameState::attack_targets`.
+    Attack {
+        instance: CardInstance,
+        target: FieldSlot,
+    },
+    /// Activate a card's effect, either to take an action or in response to one
+    Effect {
+        instance: CardInstance,
+        effect_index: CardEffect,
+        activation: Activation,
+    },
+}
+
+/// A card an attacker could attack, for choosing the target once the attacker is chosen. Cards
+/// in the back row behind another card are protected and never targets.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct AttackTarget {
+    pub slot: FieldSlot,
+    pub instance: CardInstance,
+    /// If the target is a Guard card, which must be attacked before anything else in its column
+    pub guard: bool,
+}
+
+/// The atomic mutations of the game state that player actions and card effects are made of. All
+/// changes to where cards are and what state they're in go through `GameState::take_action`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EngineAction {
+    /// Moves the top card of one of a player's face down decks to their hand
+    Draw(Player, FaceDownDeck),
+    /// Moves a card from its controller's hand to an empty slot on their field
+    SummonFromHandToSlot(CardInstance, FieldSlot),
+    /// Moves a card from its controller's center deck to an empty slot on their field
+    SummonFromCenterDeckToSlot(CardInstance, FieldSlot),
+    /// Moves a card from one of its owner's destroyed piles to an empty slot on their field
+    SummonFromDestroyedToSlot(CardInstance, FieldSlot),
+    /// Places damage counters on a card on the field, destroying it if it then has as many or
+    /// more damage counters than defense
+    DamageOnField(CardInstance, u32),
+    /// Moves a card from the field to its owner's destroyed pile of its column
+    DestroyOnField(CardInstance),
+    /// Destroys every one of the cards on the field at once, failing without destroying any if
+    /// one isn't on the field. The cards reach their destroyed piles in a fixed order: the turn
+    /// player's cards before their opponent's, and each field's front row then back row, left to
+    /// right.
+    DestroyAllOnField(Vec<CardInstance>),
+    /// Moves a card from the field back to its owner's hand, where the opponent still knows what
+    /// it is
+    ReturnFieldToHand(CardInstance),
+    /// Moves a card from the opponent's field to an empty slot on the player's field, giving the
+    /// player control of it until it leaves the field
+    TakeControl(Player, CardInstance, FieldSlot),
+    /// Exchanges the slots of two cards on the same field, with the flag for if the cards keep
+    /// their counters
+    SwapOnField(CardInstance, CardInstance, bool),
+    /// Moves the top card of one of a player's face down decks to the destroyed pile of a column
+    MillToDestroyed(Player, FaceDownDeck, Column),
+    /// Moves a card from the field or a destroyed pile out of the game, into its owner's removed
+    /// zone where both players can see it
+    RemoveFromGame(CardInstance),
+    /// Gives a player resource points
+    GainResources(Player, u32),
+    /// Takes resource points from a player, failing if they don't have that many
+    SpendResources(Player, u32),
+    /// Shows a card to a player without moving it
+    Reveal(Player, CardInstance),
+    /// Changes the target of the pending attack to another card on the same field
+    RetargetAttack(CardInstance),
+    /// Ends the game with the player as the winner
+    WinGame(Player),
+    /// Changes how a player may draw for a number of their turns
+    ModifyDraws(DrawModifier),
+    /// Adds damage counters to or removes them from several cards on the field in one step,
+    /// failing without changing any card if one isn't on the field or would be left with fewer
+    /// counters than none. Cards left with as many or more damage counters than defense are
+    /// destroyed together once every change is made.
+    ChangeCounters(Vec<(CardInstance, i32)>),
+    /// Creates a new card in a player's hand, owned by them, of one of the card types chosen
+    /// with the game's random numbers. Fails if there are no card types to choose from.
+    GenerateToHand(Player, Vec<CardTypeIdentifier>),
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[rustfmt::skip]
+pub enum FieldSlot {
+    F0, F1, F2, F3, F4, F5, F6,
+    B0, B1, B2, B3, B4, B5, B6,
+}
+
+impl FieldSlot {
+    /// The slot for an index into the front then back rows
+    fn from_index(index: usize) -> FieldSlot {
+        match index {
+            0 => FieldSlot::F0,
+            1 => FieldSlot::F1,
+            2 => FieldSlot::F2,
+            3 => FieldSlot::F3,
+            4 => FieldSlot::F4,
+            5 => FieldSlot::F5,
+            6 => FieldSlot::F6,
+            7 => FieldSlot::B0,
+            8 => FieldSlot::B1,
+            9 => FieldSlot::B2,
+            10 => FieldSlot::B3,
+            11 => FieldSlot::B4,
+            12 => FieldSlot::B5,
+            13 => FieldSlot::B6,
+            _ => panic!("Field slot index out of range: {}", index),
+        }
+    }
+
+    /// The column of the field this slot is in
+    pub fn column(self) -> Column {
+        Column((self as u8) % 7)
+    }
+
+    /// The row of the field this slot is in
+    pub fn row(self) -> Row {
+        if (self as usize) < 7 { Row::Front } else { Row::Back }
+    }
+
+    /// The slots next to this one on the same field
+    pub fn adjacent(self, adjacency: Adjacency) -> Vec<FieldSlot> {
+        let column = self.column();
+        let front_row = (self as usize) < 7;
+        let in_row = |column: Column| if front_row { column.front() } else { column.back() };
+        let mut slots = vec![];
+        if matches!(adjacency, Adjacency::SameColumn | Adjacency::Any) {
+            slots.push(if front_row { column.back() } else { column.front() });
+        }
+        if matches!(adjacency, Adjacency::NeighbouringColumns | Adjacency::Any) {
+            let neighbours = [column.0.checked_sub(1), Some(column.0 + 1)];
+            slots.extend(neighbours.iter().flatten().map(|&i| Column(i)).filter(|c| c.is_valid()).map(in_row));
+        }
+        slots
+    }
+
+    /// The slot in the back row of this slot's column, if this slot is in the front row
+    pub(crate) fn behind(self) -> Option<FieldSlot> {
+        if (self as usize) < 7 {
+            Some(self.column().back())
+        } else {
+            None
+        }
+    }
+}
+
+/// One of the 7 columns of a field, from 0 to 6. Each column has a front and back slot and its own
+/// destroyed pile.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub struct Column(pub u8);
+
+impl Column {
+    /// Iterates through every column from left to right
+    pub fn all() -> impl Iterator<Item = Column> {
+        (0..7).map(Column)
+    }
+
+    pub fn front(self) -> FieldSlot {
+        FieldSlot::from_index(self.index())
+    }
+
+    pub fn back(self) -> FieldSlot {
+        FieldSlot::from_index(self.index() + 7)
+    }
+
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    /// If the column is one of the 7 on the field, as columns read from a file may not be
+    pub fn is_valid(self) -> bool {
+        self.0 < 7
+    }
+}
+
+/// The front or back row of a field
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub enum Row {
+    Front,
+    Back,
+}
+
+impl Row {
+    /// The slots in this row from left to right
+    pub fn slots(self) -> impl Iterator<Item = FieldSlot> {
+        Column::all().map(move |column| match self {
+            Row::Front => column.front(),
+            Row::Back => column.back(),
+        })
+    }
+}
+
+impl fmt::Display for Row {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Row::Front => write!(f, "front row"),
+            Row::Back => write!(f, "back row"),
+        }
+    }
+}
+
+use Phase::{MayDraw, MayTakeAction};
+use GameStateType::{Open, Closed};
+
+// Each player gets one action before passing priority to the other player.
+// When in an open game state, a player may optionally draw a card from the top of either their
+// left or right decks (this **cannot** be responded to). Regardless, the player must then take an
+// action or pass to the other player. If a player is unable to draw a card or to take an action
+// then they immediately lose the game. (If a player may draw but not take actions or take actions
+// but not draw they are still in the game for as long as they can limp on).
+// An action consists of activating an effect of a card or summoning a card to the field.
+// When we pass priority to the other player after an action is taken, the game state is closed.
+// The other player must choose to activate an appropriate effect of a card in response or pass
+// back to the opponent.
+// When both players pass, we resolve in reverse order all the activations activated in response
+// to the initial action (effects during resolution **cannot** be responded to).
+// After resolution, the other player gets priority and the game state is back to open.
+
+// If a player has a card(s) with a mandatory effect that may be activated in response, they must
+// choose one of the mandatory effects to activate in response. Only after all mandatory effects
+// have responded may a player elect to respond with optional effects.
+
+// When one event triggers effects for both players, such as cards on both fields being destroyed
+// together, the turn player puts all of theirs on the chain first, in the order they choose by
+// which trigger they activate next, keeping priority until they have none left or pass. Then the
+// other player does the same, so the other player's triggers resolve first.
+
+// Unlike YuGiOh, interactivity is built into the priority passing, not just for chain links.
+// OTKs and FTKs are not a thing because you can only summon one card before your opponent gets
+// priority to summon their own.
+// Also unlike YuGiOh, there is no randomness in deck construction to worry about, and no searching
+// or shuffling of the deck, so in person play should be a lot more fluid.
+
+// There is no such thing as spell/trap/monster, cards are just Cards, and may be summoned to
+// any of the 14 positions on the field. There is also no 'one' grave, cards retain their column
+// on death. The 'extra deck' in the center is also a lot more loose, containing only cards
+// with summoning costs means there's no 'extra deck' type of card either, a card in the center
+// deck could also be in the left/right deck and drawn, or a player may elect that a card
+// 'returned to the deck' by card effect goes back to their center deck so they can summon it
+// again on the following turn.
+
+impl GameState {
+    /// Initialise a game state with both players having drawn hands and supplied decks
+    pub fn start(
+        mut player_one: (Vec<Card>, Vec<Card>, Vec<Card>, Vec<Card>),
+        mut player_two: (Vec<Card>, Vec<Card>, Vec<Card>, Vec<Card>),
+    ) -> Self {
+        for (zones, owner) in [(&mut player_one, Player::One), (&mut player_two, Player::Two)] {
+            let (left, center, right, hand) = zones;
+            for card in left.iter_mut().chain(center.iter_mut()).chain(right.iter_mut()) {
+                card.owner = owner;
+                card.status = CardStatus::Deck;
+            }
+            for card in hand.iter_mut() {
+                card.owner = owner;
+                card.status = CardStatus::Hand;
+            }
+        }
+        GameState {
             player_one: Field {
                 front: [None, None, None, None, None, None, None],
                 back: [None, None, None, None, None, None, None],
                 left_deck: player_one.0,
                 center_deck: player_one.1,
                 right_deck: player_one.2,
-                destroyed: [vec![], vec![], vec![], vec![], vec![], vec![], vec![]],
-                hand: player_one.3,
+                destroyed: Default::default(),
+                hand: Hand::from_vec(player_one.3),
+                removed: vec![],
+                resources: 0,
+            },
+            player_two: Field {
+                front: [None, None, None, None, None, None, None],
+                back: [None, None, None, None, None, None, None],
+                left_deck: player_two.0,
+                center_deck: player_two.1,
+                right_deck: player_two.2,
+                destroyed: Default::default(),
+                hand: Hand::from_vec(player_two.3),
+                removed: vec![],
+                resources: 0,
+            },
+            active: Player::One,
+            open: Open {
+                phase: Phase::MayDraw,
+            },
+            turn: Player::One,
+            trigger: None,
+            attack: None,
+            chain: vec![],
+            passed: false,
+            knowledge: Knowledge::default(),
+            events: vec![],
+            causes: vec![],
+            cause: EventCause::Rules,
+            options: OptionsCache::default(),
+            activations: ActivationCache::default(),
+            subscriptions: SubscriptionCache::default(),
+            ended: None,
+            windows: 0,
+            starting: false,
+            config: GameConfig::default(),
+            draw_rules: vec![],
+            draws: 0,
+            spent: None,
+            rng: Rng::new(0),
+            emptied: vec![],
+        }
+    }
+
+    /// Initialise a game state with the rules of a format, drawing each player's starting hand
+    pub fn start_with(
+        config: GameConfig,
+        player_one: (Vec<Card>, Vec<Card>, Vec<Card>, Vec<Card>),
+        player_two: (Vec<Card>, Vec<Card>, Vec<Card>, Vec<Card>),
+    ) -> Self {
+        let starting_hand = config.starting_hand;
+        let mut game = GameState::start(player_one, player_two);
+        game.configure(config);
+        for player in Player::all() {
+            for i in 0..starting_hand {
+                let (first, second) = if i % 2 == 0 {
+                    (FaceDownDeck::Left, FaceDownDeck::Right)
+                } else {
+                    (FaceDownDeck::Right, FaceDownDeck::Left)
+                };
+                // a deck which runs out leaves the rest of the hand to come from the other
+                let drew = game.draw(player, first).or_else(|_| game.draw(player, second));
+                if drew.is_err() {
+                    break;
+                }
+            }
+        }
+        game
+    }
+
+    /// Starts a game from a position written outside the engine, checking it is one the rules
+    /// could reach: every card is in the card pool, no two cards share a slot, cards on the field
+    /// could have been summoned to their slots, no card has enough damage to be destroyed, no
+    /// center deck is too large and no center deck holds a card without a cost. The game is open in the phase given, with the default config, so configuring
+    /// it afterwards gives the turn player a new turn's resources.
+    pub fn import(spec: PositionSpec, card_pool: &Cards) -> Result<GameState, ImportError> {
+        let mut instances = CardInstances::default();
+        let sides = [(&spec.player_one, Player::One), (&spec.player_two, Player::Two)];
+        let mut zones = vec![];
+        let mut placed = vec![];
+        for &(side, player) in &sides {
+            if side.center_deck.len() > MAX_CENTER_DECK {
+                return Err(ImportError::CenterDeckTooLarge { player, size: side.center_deck.len() });
+            }
+            let mut deck = |names: &[String]| {
+                names.iter().map(|name| instantiate(&mut instances, card_pool, name)).collect::<Result<Vec<_>, _>>()
+            };
+            let center_deck = deck(&side.center_deck)?;
+            // the same cards deck validation warns are free to summon from the center deck
+            if let Some(card) = center_deck.iter().find(|card| card.lookup_self(card_pool).summon_cost() == Some(0)) {
+                return Err(ImportError::FreeCardInCenter { player, card: card.lookup_self(card_pool).name.clone() });
+            }
+            zones.push((deck(&side.left_deck)?, center_deck, deck(&side.right_deck)?, deck(&side.hand)?));
+            placed.push(place(side, player, card_pool, &mut instances)?);
+        }
+        let player_two = zones.pop().unwrap();
+        let player_one = zones.pop().unwrap();
+        let mut game = GameState::start(player_one, player_two);
+        for ((field, destroyed, removed), (side, player)) in placed.into_iter().zip(sides) {
+            let zones = game.field_of_mut(player);
+            for (slot, card) in field {
+                zones[slot] = Some(card);
+            }
+            for (column, card) in destroyed {
+                zones.destroyed[column.index()].push(card);
+            }
+            zones.removed = removed;
+            zones.resources = side.resources;
+        }
+        for player in Player::all() {
+            let field = game.field_of(player);
+            for (slot, card) in field.occupied_slots() {
+                let card_type = card.lookup_self(card_pool);
+                // only a card's own effects summon it when it may only be summoned by effects,
+                // and effects keep to every other restriction, such as the destroyed piles
+                let summonable = match card_type.summon_restriction {
+                    Some(SummonRestriction::EffectOnly) => !card_type.effects.is_empty(),
+                    _ => card_type.can_summon_to(field, slot, true),
+                };
+                if !summonable {
+                    return Err(ImportError::Unsummonable { card: card_type.name.clone(), slot });
+                }
+                if field.remaining_defense_of(card_pool, slot) == Some(0) {
+                    return Err(ImportError::LethalDamage {
+                        card: card.lookup_self(card_pool).name.clone(),
+                        damage: card.damage,
+                        defense: field.defense_of(card_pool, slot).unwrap_or(0),
+                    });
+                }
+            }
+        }
+        game.turn = spec.turn;
+        game.active = spec.turn;
+        game.open = Open { phase: spec.phase };
+        Ok(game)
+    }
+
+    /// Sets how many trigger windows may open in a row before the effects causing them are
+    /// treated as an infinite loop, which ends the game as a draw
+    pub fn set_loop_limit(&mut self, limit: u32) {
+        self.config.loop_limit = limit;
+    }
+
+    pub fn loop_limit(&self) -> u32 {
+        self.config.loop_limit
+    }
+
+    /// A hash of everything which decides how the game plays out from here, which is the same on
+    /// every platform
+    pub fn state_hash(&self) -> StateHash {
+        let mut hash = Fnv::new();
+        self.player_one.hash_into(&mut hash);
+        self.player_two.hash_into(&mut hash);
+        let open = match self.open {
+            Open { phase: MayDraw } => 0,
+            Open { phase: MayTakeAction } => 1,
+            Closed => 2,
+        };
+        hash.write(&[self.active as u8, self.turn as u8, open, self.passed as u8]);
+        let instances = |hash: &mut Fnv, instances: &[CardInstance]| {
+            hash.write(&(instances.len() as u64).to_le_bytes());
+            for instance in instances {
+                hash.write(&instance.0.to_le_bytes());
+            }
+        };
+        match &self.trigger {
+            None => hash.write(&[0]),
+            Some(Trigger::Summoned(instance)) => { hash.write(&[1]); instances(&mut hash, &[*instance]); },
+            Some(Trigger::Drawn(instance)) => { hash.write(&[2]); instances(&mut hash, &[*instance]); },
+            Some(Trigger::Attacked(attack)) => { hash.write(&[3]); instances(&mut hash, &[attack.attacker, attack.target]); },
+            Some(Trigger::Activated(link)) => { hash.write(&[4]); instances(&mut hash, &[link.instance]); },
+            Some(Trigger::Destroyed(destroyed)) => { hash.write(&[5]); instances(&mut hash, destroyed); },
+            Some(Trigger::DeckEmptied { player, deck }) => hash.write(&[6, *player as u8, *deck as u8]),
+            Some(Trigger::TurnStarted(player)) => hash.write(&[7, *player as u8]),
+        }
+        match self.attack {
+            None => hash.write(&[0]),
+            Some(attack) => { hash.write(&[1]); instances(&mut hash, &[attack.attacker, attack.target]); },
+        }
+        hash.write(&(self.chain.len() as u64).to_le_bytes());
+        for link in &self.chain {
+            hash.write(&[link.player as u8]);
+            hash.write(&link.effect.0.to_le_bytes());
+            instances(&mut hash, &[link.instance]);
+            instances(&mut hash, &link.activation.data.targets);
+            let data = &link.activation.data;
+            hash.write(&[
+                data.slot.map_or(0xFF, |slot| slot as u8),
+                data.deck.map_or(0xFF, |deck| deck as u8),
+                data.column.map_or(0xFF, |column| column.index() as u8),
+            ]);
+        }
+        hash.write(&self.windows.to_le_bytes());
+        // left out outside of start of turn windows, so games hash as they always have
+        if self.starting {
+            hash.write(&[1]);
+        }
+        self.config.hash_into(&mut hash);
+        // left out until the game is seeded or uses a random number
+        if self.rng != Rng::new(0) {
+            hash.write(&self.rng.state().to_le_bytes());
+        }
+        match self.ended {
+            None => hash.write(&[0xFF]),
+            Some(result) => hash.write(&[result.reason as u8, result.winner.map_or(0xFF, |winner| winner as u8)]),
+        }
+        hash.write(&(self.events.len() as u64).to_le_bytes());
+        // left out while no deck is waiting to be offered, so games hash as they always have
+        if !self.emptied.is_empty() {
+            for &(player, deck) in &self.emptied {
+                hash.write(&[player as u8, deck as u8]);
+            }
+        }
+        // left out while there are none, so games without draw rules hash as they always have
+        if !self.draw_rules.is_empty() {
+            hash.write(&self.draws.to_le_bytes());
+            for modifier in &self.draw_rules {
+                let (rule, value) = match modifier.rule {
+                    DrawRule::CannotDraw(deck) => (0, deck.map_or(0xFF, |deck| deck as u32)),
+                    DrawRule::ExtraDraws(draws) => (1, draws),
+                };
+                hash.write(&[modifier.player as u8, rule]);
+                hash.write(&value.to_le_bytes());
+                hash.write(&modifier.turns.to_le_bytes());
+            }
+        }
+        StateHash(hash.finish())
+    }
+
+    /// Changes the rules of the game, which should only be done before it starts. The first
+    /// player's turn has already started, so they're given its resources here, and the random
+    /// numbers start again from the config's seed.
+    pub fn configure(&mut self, config: GameConfig) {
+        let resources = config.resources_per_turn;
+        self.rng = Rng::new(config.seed);
+        self.config = config;
+        if self.events.is_empty() {
+            self.priority_player_mut().resources = resources;
+        }
+    }
+
+    /// The resource points a card costs to summon as an action, from wherever it is summoned
+    /// from, which is 0 in formats without resources
+    fn resource_cost(&self, card_pool: &Cards, card: &Card) -> u32 {
+        if self.config.resources_per_turn == 0 {
+            0
+        } else {
+            card.lookup_self(card_pool).resource_cost
+        }
+    }
+
+    pub fn config(&self) -> &GameConfig {
+        &self.config
+    }
+
+    /// Returns which player has priority
+    pub fn priority(&self) -> Player {
+        self.active
+    }
+
+    pub fn open(&self) -> GameStateType {
+        self.open
+    }
+
+    /// The effects activated since the game state closed which are yet to resolve, in the order
+    /// they were activated, so the last link resolves first
+    pub fn pending_chain(&self) -> &[ChainLink] {
+        &self.chain
+    }
+
+    /// The link of the pending chain, if the chain has that many links
+    pub fn chain_link(&self, id: ChainLinkId) -> Option<&ChainLink> {
+        self.chain.get(id.index())
+    }
+
+    /// The attack waiting on the chain to resolve before it deals damage, if any
+    pub fn pending_attack(&self) -> Option<Attack> {
+        self.attack
+    }
+
+    /// Returns whose turn it is
+    pub fn turn(&self) -> Player {
+        self.turn
+    }
+
+    /// The changes card effects have made to how players may draw which are still in effect
+    pub fn draw_rules(&self) -> &[DrawModifier] {
+        &self.draw_rules
+    }
+
+    /// If the player has cards in the deck and no draw rule stops them drawing from it
+    pub fn can_draw_from(&self, player: Player, deck: FaceDownDeck) -> bool {
+        !self.field_of(player).deck(deck).is_empty()
+            && !self.draw_rules.iter().any(|modifier| modifier.player == player && match modifier.rule {
+                DrawRule::CannotDraw(None) => true,
+                DrawRule::CannotDraw(Some(forbidden)) => forbidden == deck,
+                DrawRule::ExtraDraws(_) => false,
+            })
+    }
+
+    /// How many more cards than one the player may draw in a turn
+    fn extra_draws(&self, player: Player) -> u32 {
+        self.draw_rules.iter()
+            .filter(|modifier| modifier.player == player)
+            .map(|modifier| match modifier.rule {
+                DrawRule::ExtraDraws(draws) => draws,
+                DrawRule::CannotDraw(_) => 0,
+            })
+            .fold(0, u32::saturating_add)
+    }
+
+    /// The draw options the priority player has from the decks they may draw from
+    fn draw_options(&self) -> impl Iterator<Item = PlayerOption> + '_ {
+        [FaceDownDeck::Left, FaceDownDeck::Right].iter()
+            .copied()
+            .filter(move |&deck| self.can_draw_from(self.active, deck))
+            .map(PlayerOption::Draw)
+    }
+
+    /// Everything the players have done so far, in order
+    pub fn events(&self) -> &[GameEvent] {
+        &self.events
+    }
+
+    /// The position after every event so far, which the events to come will start from
+    pub fn event_seq(&self) -> EventSeq {
+        EventSeq::from(self.events.len())
+    }
+
+    /// The events from the position on, empty if the position is past every event so far
+    pub fn events_since(&self, seq: EventSeq) -> &[GameEvent] {
+        self.events.get(seq.index()..).unwrap_or(&[])
+    }
+
+    /// What caused each event, in the same order as `events`
+    pub fn event_causes(&self) -> &[EventCause] {
+        &self.causes
+    }
+
+    /// What caused the event at the position, if there is one
+    pub fn cause_of(&self, seq: EventSeq) -> Option<EventCause> {
+        self.causes.get(seq.index()).copied()
+    }
+
+    /// Sets what is causing the events added from now on, returning the cause it replaces so it
+    /// can be put back
+    pub(crate) fn caused_by(&mut self, cause: EventCause) -> EventCause {
+        std::mem::replace(&mut self.cause, cause)
+    }
+
+    fn log(&mut self, event: GameEvent) {
+        self.events.push(event);
+        self.causes.push(self.cause);
+    }
+
+    /// A copy of the game as it is now, which can be rewound to later
+    pub(crate) fn snapshot(&self) -> GameState {
+        GameState {
+            player_one: self.player_one.snapshot(),
+            player_two: self.player_two.snapshot(),
+            active: self.active,
+            open: self.open,
+            turn: self.turn,
+            trigger: self.trigger.clone(),
+            attack: self.attack,
+            chain: self.chain.clone(),
+            passed: self.passed,
+            knowledge: self.knowledge.clone(),
+            events: self.events.clone(),
+            causes: self.causes.clone(),
+            cause: self.cause,
+            options: OptionsCache::default(),
+            activations: ActivationCache::default(),
+            subscriptions: SubscriptionCache(self.subscriptions.0.clone()),
+            ended: self.ended,
+            windows: self.windows,
+            starting: self.starting,
+            config: self.config.clone(),
+            draw_rules: self.draw_rules.clone(),
+            draws: self.draws,
+            spent: None,
+            rng: self.rng.clone(),
+            emptied: self.emptied.clone(),
+        }
+    }
+
+    /// Adds an event which didn't come from the rules, such as a tutorial's instructions
+    pub(crate) fn record(&mut self, event: GameEvent) {
+        self.log(event);
+    }
+
+    /// Returns the game to an earlier snapshot of it at the player's request, keeping every
+    /// event so far so the record of the game still shows what was undone
+    pub(crate) fn rewind_to(&mut self, mut snapshot: GameState, player: Player) {
+        snapshot.events = std::mem::take(&mut self.events);
+        snapshot.causes = std::mem::take(&mut self.causes);
+        snapshot.cause = EventCause::Rules;
+        snapshot.log(GameEvent::Rewound { player });
+        *self = snapshot;
+    }
+
+    /// The type of a card instance in any zone of either player's field
+    fn card_type_of(&self, instance: CardInstance) -> Result<CardTypeIdentifier, InvalidAction> {
+        self.card(instance).map(|card| card.card_type).ok_or(InvalidAction)
+    }
+
+    pub fn priority_player(&self) -> &Field {
+        self.field_of(self.priority())
+    }
+
+    /// The field of the player who doesn't have priority
+    pub fn non_priority_player(&self) -> &Field {
+        self.opponent_of(self.priority())
+    }
+
+    fn priority_player_mut(&mut self) -> &mut Field {
+        match self.priority() {
+            Player::One => &mut self.player_one,
+            Player::Two => &mut self.player_two,
+        }
+    }
+
+    pub fn field_of(&self, player: Player) -> &Field {
+        match player {
+            Player::One => &self.player_one,
+            Player::Two => &self.player_two,
+        }
+    }
+
+    /// The field of the player's opponent
+    pub fn opponent_of(&self, player: Player) -> &Field {
+        self.field_of(player.next())
+    }
+
+    fn field_of_mut(&mut self, player: Player) -> &mut Field {
+        match player {
+            Player::One => &mut self.player_one,
+            Player::Two => &mut self.player_two,
+        }
+    }
+
+    /// Which player's field holds the card instance, in any zone
+    pub fn controller_of(&self, instance: CardInstance) -> Option<Player> {
+        Player::all()
+            .find(|&player| self.field_of(player).cards().any(|card| card.instance == instance))
+    }
+
+    /// Finds a card instance in any zone of either player's field
+    pub fn card(&self, instance: CardInstance) -> Option<&Card> {
+        self.player_one.cards()
+            .chain(self.player_two.cards())
+            .find(|card| card.instance == instance)
+    }
+
+    /// Iterates through every card on either player's field
+    pub fn cards_on_field(&self) -> impl Iterator<Item = &Card> {
+        self.player_one.field_slots().chain(self.player_two.field_slots()).flatten()
+    }
+
+    pub fn card_on_field(&self, instance: CardInstance) -> Option<&Card> {
+        self.cards_on_field().find(|card| card.instance == instance)
+    }
+
+    pub fn card_in_hand(&self, instance: CardInstance) -> Option<&Card> {
+        self.player_one.hand.iter()
+            .chain(self.player_two.hand.iter())
+            .find(|card| card.instance == instance)
+    }
+
+    /// The column of a card on either player's field or in either player's destroyed piles
+    pub fn column_of(&self, instance: CardInstance) -> Option<Column> {
+        self.player_one.column_of(instance).or_else(|| self.player_two.column_of(instance))
+    }
+
+    pub fn card_in_destroyed_pile(&self, instance: CardInstance) -> Option<&Card> {
+        self.player_one.destroyed.iter()
+            .chain(self.player_two.destroyed.iter())
+            .flatten()
+            .find(|card| card.instance == instance)
+    }
+
+    pub fn card_in_center_deck(&self, instance: CardInstance) -> Option<&Card> {
+        self.player_one.center_deck.iter()
+            .chain(self.player_two.center_deck.iter())
+            .find(|card| card.instance == instance)
+    }
+
+    /// If the player knows the identity of the card instance, which is true of their own cards, of
+    /// cards in public zones and of hidden cards of their opponent's they have seen
+    pub fn knows(&self, player: Player, instance: CardInstance) -> bool {
+        self.player_one.public_cards()
+            .chain(self.player_two.public_cards())
+            .any(|card| card.instance == instance)
+            || self.controller_of(instance) == Some(player)
+            || self.knowledge.of(player).contains(&instance)
+    }
+
+    /// One of a player's face down decks as another player sees it, with the top of the deck last
+    /// and only the cards the viewer knows the identity of present
+    pub fn deck_view(&self, viewer: Player, owner: Player, deck: FaceDownDeck) -> Vec<Option<&Card>> {
+        self.field_of(owner)
+            .deck(deck)
+            .iter()
+            .map(|card| Some(card).filter(|card| self.knows(viewer, card.instance)))
+            .collect()
+    }
+
+    /// The game as the player could picture it, with `guess` giving the type of every card in a
+    /// hand or face down deck the player doesn't know the identity of, so it can be searched
+    /// without learning the cards hidden from them
+    pub fn guessed_by(&self, player: Player, mut guess: impl FnMut() -> CardTypeIdentifier) -> GameState {
+        let hidden: Vec<CardInstance> = Player::all()
+            .flat_map(|owner| {
+                let field = self.field_of(owner);
+                field.hand.iter().chain(&field.left_deck).chain(&field.right_deck)
+            })
+            .filter(|card| !self.knows(player, card.instance))
+            .map(|card| card.instance)
+            .collect();
+        let mut game = self.snapshot();
+        // the subscriptions were worked out from the cards' real types
+        game.subscriptions = SubscriptionCache::default();
+        for owner in Player::all() {
+            let field = game.field_of_mut(owner);
+            for card in field.hand.iter_mut().chain(&mut field.left_deck).chain(&mut field.right_deck) {
+                if hidden.contains(&card.instance) {
+                    card.card_type = guess();
+                }
+            }
+        }
+        game
+    }
+
+    /// Applies a mutation to the game state, failing if the cards involved are not where the
+    /// action requires them to be
+    pub fn take_action(&mut self, card_pool: &Cards, action: EngineAction) -> Result<(), InvalidAction> {
+        self.clear_caches();
+        self.spend(&action)?;
+        match action {
+            EngineAction::Draw(player, deck) => self.draw(player, deck).map(|()| self.note_if_emptied(player, deck)),
+            EngineAction::SummonFromHandToSlot(instance, slot) => self.summon_from_hand_to_slot(instance, slot),
+            EngineAction::SummonFromCenterDeckToSlot(instance, slot) => self.summon_from_center_deck_to_slot(instance, slot),
+            EngineAction::SummonFromDestroyedToSlot(instance, slot) => self.summon_from_destroyed_to_slot(instance, slot),
+            EngineAction::DamageOnField(instance, damage) => self.damage_on_field(card_pool, instance, damage),
+            EngineAction::DestroyOnField(instance) => self.destroy_on_field(instance),
+            EngineAction::DestroyAllOnField(instances) => self.destroy_all_on_field(&instances),
+            EngineAction::ReturnFieldToHand(instance) => self.return_field_to_hand(instance),
+            EngineAction::TakeControl(player, instance, slot) => self.take_control(player, instance, slot),
+            EngineAction::SwapOnField(first, second, keep_counters) => self.swap_on_field(first, second, keep_counters),
+            EngineAction::MillToDestroyed(player, deck, column) => {
+                self.mill_to_destroyed(player, deck, column).map(|()| self.note_if_emptied(player, deck))
+            },
+            EngineAction::RemoveFromGame(instance) => self.remove_from_game(instance),
+            EngineAction::GainResources(player, resources) => {
+                let field = self.field_of_mut(player);
+                field.resources = field.resources.saturating_add(resources);
+                Ok(())
+            }
+            EngineAction::SpendResources(player, resources) => {
+                let field = self.field_of_mut(player);
+                field.resources = field.resources.checked_sub(resources).ok_or(InvalidAction)?;
+                Ok(())
+            }
+            EngineAction::Reveal(player, instance) => self.reveal(player, instance),
+            EngineAction::RetargetAttack(target) => self.retarget_attack(target),
+            EngineAction::WinGame(player) => self.end(Some(player), EndReason::AlternativeWin),
+            EngineAction::ChangeCounters(changes) => self.change_counters(card_pool, &changes),
+            EngineAction::GenerateToHand(player, card_types) => self.generate_to_hand(card_pool, player, &card_types),
+            EngineAction::ModifyDraws(modifier) => {
+                if modifier.turns > 0 {
+                    self.draw_rules.push(modifier);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Counts the action against the budget of the effect taking it, failing once the budget is
+    /// used up
+    fn spend(&mut self, action: &EngineAction) -> Result<(), InvalidAction> {
+        let (budget, spent) = match (self.config.effect_budget, self.spent.as_mut()) {
+            (Some(budget), Some(spent)) => (budget, spent),
+            _ => return Ok(()),
+        };
+        let movements = match action {
+            EngineAction::DestroyAllOnField(instances) => instances.len() as u32,
+            EngineAction::SwapOnField(..) => 2,
+            EngineAction::Draw(..) | EngineAction::SummonFromHandToSlot(..) | EngineAction::SummonFromCenterDeckToSlot(..)
+                | EngineAction::SummonFromDestroyedToSlot(..)
+                | EngineAction::DestroyOnField(_) | EngineAction::ReturnFieldToHand(_) | EngineAction::TakeControl(..)
+                | EngineAction::MillToDestroyed(..) | EngineAction::RemoveFromGame(_) | EngineAction::GenerateToHand(..) => 1,
+            _ => 0,
+        };
+        spent.actions += 1;
+        spent.movements += movements;
+        if spent.exceeded || spent.actions > budget.actions || spent.movements > budget.movements {
+            spent.exceeded = true;
+            return Err(InvalidAction);
+        }
+        Ok(())
+    }
+
+    /// Runs an effect, undoing everything it did if it goes over the effect budget. Returns if
+    /// the effect stayed within budget.
+    fn within_budget(&mut self, run: impl FnOnce(&mut GameState)) -> bool {
+        if self.config.effect_budget.is_none() {
+            run(self);
+            return true;
+        }
+        let before = self.snapshot();
+        self.spent = Some(Spent::default());
+        run(self);
+        let exceeded = self.spent.take().is_some_and(|spent| spent.exceeded);
+        if exceeded {
+            // the events the effect added are undone along with everything else it did
+            *self = before;
+        }
+        !exceeded
+    }
+
+    fn generate_to_hand(&mut self, card_pool: &Cards, player: Player, card_types: &[CardTypeIdentifier]) -> Result<(), InvalidAction> {
+        if card_types.is_empty() {
+            return Err(InvalidAction);
+        }
+        let card_type = card_types[self.rng.below(card_types.len())];
+        card_pool.card(card_type).ok_or(InvalidAction)?;
+        let next = self.player_one.cards()
+            .chain(self.player_two.cards())
+            .map(|card| card.instance.0)
+            .max()
+            .map_or(Some(0), |last| last.checked_add(1))
+            .ok_or(InvalidAction)?;
+        self.field_of_mut(player).hand.push(Card {
+            card_type,
+            instance: CardInstance(next),
+            damage: 0,
+            status: CardStatus::Hand,
+            owner: player,
+        });
+        // the new card's effects wait on events like every other card's
+        self.subscriptions = SubscriptionCache::default();
+        self.log(GameEvent::Generated { player, card: card_type });
+        Ok(())
+    }
+
+    fn draw(&mut self, player: Player, deck: FaceDownDeck) -> Result<(), InvalidAction> {
+        let field = self.field_of_mut(player);
+        let mut card = field.deck_mut(deck).pop().ok_or(InvalidAction)?;
+        card.status = CardStatus::Hand;
+        field.hand.push(card);
+        Ok(())
+    }
+
+    /// Records the deck running out if the last card just left it, so it can be offered as a
+    /// trigger window once the chain resolves
+    fn note_if_emptied(&mut self, player: Player, deck: FaceDownDeck) {
+        if self.field_of(player).deck(deck).is_empty() {
+            self.log(GameEvent::DeckEmptied { player, deck });
+            self.emptied.push((player, deck));
+        }
+    }
+
+    fn retarget_attack(&mut self, target: CardInstance) -> Result<(), InvalidAction> {
+        let attack = self.attack.ok_or(InvalidAction)?;
+        let defender = self.controller_of(attack.target).ok_or(InvalidAction)?;
+        let field = self.field_of(defender);
+        field.slot_of(attack.target).ok_or(InvalidAction)?;
+        field.slot_of(target).ok_or(InvalidAction)?;
+        self.attack = Some(Attack { target, ..attack });
+        Ok(())
+    }
+
+    fn reveal(&mut self, player: Player, instance: CardInstance) -> Result<(), InvalidAction> {
+        self.card(instance).ok_or(InvalidAction)?;
+        self.knowledge.reveal(player, instance);
+        Ok(())
+    }
+
+    fn mill_to_destroyed(&mut self, player: Player, deck: FaceDownDeck, column: Column) -> Result<(), InvalidAction> {
+        if !column.is_valid() {
+            return Err(InvalidAction);
+        }
+        let field = self.field_of_mut(player);
+        let mut card = field.deck_mut(deck).pop().ok_or(InvalidAction)?;
+        card.status = CardStatus::Destroyed;
+        field.destroyed[column.index()].push(card);
+        Ok(())
+    }
+
+    fn remove_from_game(&mut self, instance: CardInstance) -> Result<(), InvalidAction> {
+        let player = self.controller_of(instance).ok_or(InvalidAction)?;
+        let removal = self.config.removal;
+        let field = self.field_of_mut(player);
+        let mut card = match field.slot_of(instance) {
+            Some(slot) if !removal => {
+                let card = field.leave_field(slot).ok_or(InvalidAction)?;
+                self.send_to_destroyed(card, slot.column());
+                return Ok(());
             },
-            player_two: Field {
-                front: [None, None, None, None, None, None, None],
-                back: [None, None, None, None, None, None, None],
-                left_deck: player_two.0,
-                center_deck: player_two.1,
-                right_deck: player_two.2,
-                destroyed: [vec![], vec![], vec![], vec![], vec![], vec![], vec![]],
-                hand: player_two.3,
+            Some(slot) => field.leave_field(slot).ok_or(InvalidAction)?,
+            None if !removal => {
+                return field.column_of(instance).map(|_| ()).ok_or(InvalidAction);
             },
-            active: Player::One,
-            open: Open {
-                phase: Phase::MayDraw,
+            None => {
+                let pile = field.destroyed.iter_mut()
+                    .find(|pile| pile.iter().any(|card| card.instance == instance))
+                    .ok_or(InvalidAction)?;
+                let index = position_of(pile, instance).ok_or(InvalidAction)?;
+                pile.remove(index)
             },
+        };
+        card.status = CardStatus::Removed;
+        let owner = card.owner;
+        self.field_of_mut(owner).removed.push(card);
+        Ok(())
+    }
+
+    fn damage_on_field(&mut self, card_pool: &Cards, instance: CardInstance, damage: u32) -> Result<(), InvalidAction> {
+        let player = self.controller_of(instance).ok_or(InvalidAction)?;
+        let field = self.field_of_mut(player);
+        let slot = field.slot_of(instance).ok_or(InvalidAction)?;
+        if let Some(card) = field.take_damage(card_pool, slot, damage) {
+            self.send_to_destroyed(card, slot.column());
         }
+        Ok(())
     }
 
-    /// Returns which player has priority
-    pub fn priority(&self) -> Player {
-        self.active
+    fn change_counters(&mut self, card_pool: &Cards, changes: &[(CardInstance, i32)]) -> Result<(), InvalidAction> {
+        // every change is checked before any is made, adding up changes to the same card
+        let mut counters: Vec<(CardInstance, u32)> = vec![];
+        for &(instance, change) in changes {
+            let damage = match counters.iter().find(|(changed, _)| *changed == instance) {
+                Some(&(_, damage)) => damage,
+                None => self.card_on_field(instance).ok_or(InvalidAction)?.damage,
+            };
+            let damage = damage.checked_add_signed(change).ok_or(InvalidAction)?;
+            match counters.iter_mut().find(|(changed, _)| *changed == instance) {
+                Some(changed) => changed.1 = damage,
+                None => counters.push((instance, damage)),
+            }
+        }
+        let mut destroyed = vec![];
+        for (instance, damage) in counters {
+            let player = self.controller_of(instance).ok_or(InvalidAction)?;
+            let field = self.field_of_mut(player);
+            let slot = field.slot_of(instance).ok_or(InvalidAction)?;
+            field[slot].as_mut().ok_or(InvalidAction)?.damage = damage;
+            if field.remaining_defense_of(card_pool, slot) == Some(0) {
+                destroyed.push(instance);
+            }
+        }
+        self.destroy_all_on_field(&destroyed)
     }
 
-    pub fn open(&self) -> GameStateType {
-        self.open
+    /// Puts a card which left the field in its owner's destroyed pile of the column
+    fn send_to_destroyed(&mut self, mut card: Card, column: Column) {
+        self.log(GameEvent::Destroyed { player: card.owner, card: card.card_type, column });
+        card.status = CardStatus::Destroyed;
+        self.field_of_mut(card.owner).destroyed[column.index()].push(card);
     }
 
-    pub fn priority_player(&self) -> &Field {
-        match self.priority() {
-            Player::One => &self.player_one,
-            Player::Two => &self.player_two,
+    fn swap_on_field(&mut self, first: CardInstance, second: CardInstance, keep_counters: bool) -> Result<(), InvalidAction> {
+        let player = self.controller_of(first).ok_or(InvalidAction)?;
+        let field = self.field_of_mut(player);
+        let first = field.slot_of(first).ok_or(InvalidAction)?;
+        let second = field.slot_of(second).ok_or(InvalidAction)?;
+        let mut first_card = field[first].take();
+        let mut second_card = field[second].take();
+        if !keep_counters {
+            for card in first_card.iter_mut().chain(second_card.iter_mut()) {
+                card.damage = 0;
+            }
         }
+        field[first] = second_card;
+        field[second] = first_card;
+        Ok(())
     }
 
-    fn priority_player_mut(&mut self) -> &mut Field {
-        match self.priority() {
-            Player::One => &mut self.player_one,
-            Player::Two => &mut self.player_two,
+    fn destroy_on_field(&mut self, instance: CardInstance) -> Result<(), InvalidAction> {
+        let player = self.controller_of(instance).ok_or(InvalidAction)?;
+        let field = self.field_of_mut(player);
+        let slot = field.slot_of(instance).ok_or(InvalidAction)?;
+        let card = field.leave_field(slot).ok_or(InvalidAction)?;
+        self.send_to_destroyed(card, slot.column());
+        Ok(())
+    }
+
+    fn destroy_all_on_field(&mut self, instances: &[CardInstance]) -> Result<(), InvalidAction> {
+        if instances.iter().any(|&instance| self.card_on_field(instance).is_none()) {
+            return Err(InvalidAction);
+        }
+        let mut destroyed = vec![];
+        for player in [self.turn, self.turn.next()] {
+            let field = self.field_of_mut(player);
+            let slots: Vec<FieldSlot> = field.occupied_slots()
+                .filter(|(_, card)| instances.contains(&card.instance))
+                .map(|(slot, _)| slot)
+                .collect();
+            // every card leaves the field before any reach a destroyed pile, so none of them see
+            // the others being destroyed
+            destroyed.extend(slots.into_iter().filter_map(|slot| field.leave_field(slot).map(|card| (card, slot.column()))));
+        }
+        for (card, column) in destroyed {
+            self.send_to_destroyed(card, column);
+        }
+        Ok(())
+    }
+
+    fn return_field_to_hand(&mut self, instance: CardInstance) -> Result<(), InvalidAction> {
+        let player = self.controller_of(instance).ok_or(InvalidAction)?;
+        let field = self.field_of_mut(player);
+        let slot = field.slot_of(instance).ok_or(InvalidAction)?;
+        let mut card = field.leave_field(slot).ok_or(InvalidAction)?;
+        let owner = card.owner;
+        card.status = CardStatus::Hand;
+        self.field_of_mut(owner).hand.push(card);
+        // the card was face up on the field so the opponent still knows what it is
+        self.knowledge.reveal(owner.next(), instance);
+        Ok(())
+    }
+
+    fn take_control(&mut self, player: Player, instance: CardInstance, slot: FieldSlot) -> Result<(), InvalidAction> {
+        let controller = self.controller_of(instance).ok_or(InvalidAction)?;
+        if controller == player || !self.field_of(player).slot_is_empty(slot) {
+            return Err(InvalidAction);
+        }
+        let field = self.field_of_mut(controller);
+        let from = field.slot_of(instance).ok_or(InvalidAction)?;
+        let card = field[from].take().ok_or(InvalidAction)?;
+        // the card keeps its damage but can't attack for its new controller straight away
+        self.field_of_mut(player).summon(card, slot);
+        Ok(())
+    }
+
+    fn summon_from_hand_to_slot(&mut self, instance: CardInstance, slot: FieldSlot) -> Result<(), InvalidAction> {
+        let player = self.controller_of(instance).ok_or(InvalidAction)?;
+        let field = self.field_of_mut(player);
+        if !field.slot_is_empty(slot) {
+            return Err(InvalidAction);
         }
+        let index = position_of(&field.hand, instance).ok_or(InvalidAction)?;
+        let card = field.hand.remove(index);
+        field.summon(card, slot);
+        Ok(())
+    }
+
+    fn summon_from_center_deck_to_slot(&mut self, instance: CardInstance, slot: FieldSlot) -> Result<(), InvalidAction> {
+        let player = self.controller_of(instance).ok_or(InvalidAction)?;
+        let field = self.field_of_mut(player);
+        if !field.slot_is_empty(slot) {
+            return Err(InvalidAction);
+        }
+        let index = position_of(&field.center_deck, instance).ok_or(InvalidAction)?;
+        let card = field.center_deck.remove(index);
+        field.summon(card, slot);
+        Ok(())
+    }
+
+    fn summon_from_destroyed_to_slot(&mut self, instance: CardInstance, slot: FieldSlot) -> Result<(), InvalidAction> {
+        let player = self.controller_of(instance).ok_or(InvalidAction)?;
+        let field = self.field_of_mut(player);
+        if !field.slot_is_empty(slot) {
+            return Err(InvalidAction);
+        }
+        let pile = field.destroyed.iter_mut().find(|pile| position_of(pile, instance).is_some()).ok_or(InvalidAction)?;
+        let index = position_of(pile, instance).ok_or(InvalidAction)?;
+        let card = pile.remove(index);
+        field.summon(card, slot);
+        Ok(())
+    }
+
+    /// Passes priority to the other player to start their turn
+    fn pass_turn(&mut self, card_pool: &Cards) {
+        self.active = self.active.next();
+        self.start_turn(card_pool);
+    }
+
+    /// Opens the game state for the priority player's turn, after ending the draw rules which
+    /// lasted until the end of the last turn, unless effects waiting on the turn starting can
+    /// activate first
+    fn start_turn(&mut self, card_pool: &Cards) {
+        let ended = self.turn;
+        for modifier in self.draw_rules.iter_mut().filter(|modifier| modifier.player == ended) {
+            modifier.turns -= 1;
+        }
+        self.draw_rules.retain(|modifier| modifier.turns > 0);
+        self.draws = 0;
+        self.open = Open { phase: MayDraw };
+        self.turn = self.active;
+        self.trigger = None;
+        self.attack = None;
+        self.passed = false;
+        let resources = self.config.resources_per_turn;
+        let field = self.priority_player_mut();
+        field.start_turn();
+        field.resources = field.resources.saturating_add(resources);
+        self.starting = self.open_turn_start_window(card_pool);
+    }
+
+    /// Keeps the game state closed at the start of the turn if either player can activate an
+    /// effect waiting on the turn starting, leaving the turn player with priority. Effects with
+    /// other timings, which could be activated in any window, don't open it on their own.
+    fn open_turn_start_window(&mut self, card_pool: &Cards) -> bool {
+        let (turn, open) = (self.turn, self.open);
+        self.open = Closed;
+        self.trigger = Some(Trigger::TurnStarted(turn));
+        for player in [turn.next(), turn] {
+            self.active = player;
+            self.clear_caches();
+            let waiting = self.effect_activations(card_pool).iter().any(|action| match action {
+                Action::Effect { instance, effect_index, .. } => self.card(*instance).is_some_and(|card| {
+                    card.lookup_self(card_pool).effects[effect_index.0 as usize].timing() == TriggerTiming::StartOfTurn
+                }),
+                _ => false,
+            });
+            if waiting {
+                self.active = turn;
+                self.clear_caches();
+                return true;
+            }
+        }
+        self.active = turn;
+        self.open = open;
+        self.trigger = None;
+        self.clear_caches();
+        false
+    }
+
+    /// The context effects are activated in at this point in the game
+    fn context(&self) -> ResolutionContext {
+        ResolutionContext {
+            trigger: self.chain.last()
+                .cloned()
+                .map(Trigger::Activated)
+                .or_else(|| self.trigger.clone()),
+            attack: self.attack,
+            chain: self.chain.clone(),
+        }
+    }
+
+    /// Forgets everything worked out from the game state, which must be done after every change
+    fn clear_caches(&mut self) {
+        self.options = OptionsCache::default();
+        self.activations = ActivationCache::default();
+    }
+
+    /// The ways a card's effect can activate in the current context, which are only worked out
+    /// once between each change to the game state
+    fn can_activate(&self, card_pool: &Cards, card_type: &CardType, instance: CardInstance, effect_index: CardEffect, context: &ResolutionContext) -> Vec<Activation> {
+        let key = (instance, effect_index);
+        if let Some(activations) = self.activations.0.lock().unwrap().get(&key) {
+            return activations.clone();
+        }
+        // the lock isn't held while the effect looks at the game state
+        let activations = card_type.effects[effect_index.0 as usize].can_activate(card_pool, card_type, self, instance, context);
+        self.activations.0.lock().unwrap().insert(key, activations.clone());
+        activations
+    }
+
+    /// Subscribes every card's effects to the timing they wait on
+    fn subscribe(&self, card_pool: &Cards) -> Subscriptions {
+        let mut subscriptions = Subscriptions::default();
+        for card in self.player_one.cards().chain(self.player_two.cards()) {
+            for (i, effect) in card.lookup_self(card_pool).effects.iter().enumerate() {
+                let (effect_index, timing) = (CardEffect::from(i), effect.timing());
+                if timing.targets_own_card() {
+                    subscriptions.by_card.entry(card.instance).or_default().push((effect_index, timing));
+                } else {
+                    subscriptions.by_timing.entry(timing).or_default().push((card.instance, effect_index));
+                }
+            }
+        }
+        subscriptions
+    }
+
+    /// The effects subscribed to what the game is waiting on in the context, in the order of
+    /// their cards and then of the effects on each card
+    fn subscribed_effects(&self, card_pool: &Cards, context: &ResolutionContext, open: bool) -> Vec<(CardInstance, CardEffect)> {
+        let subscriptions = self.subscriptions.0.get_or_init(|| self.subscribe(card_pool));
+        let targeted = match &context.trigger {
+            Some(Trigger::Summoned(instance)) | Some(Trigger::Drawn(instance)) => std::slice::from_ref(instance),
+            Some(Trigger::Destroyed(instances)) => instances.as_slice(),
+            _ => &[],
+        };
+        let mut effects: Vec<(CardInstance, CardEffect)> = targeted.iter()
+            .flat_map(|&instance| subscriptions.by_card.get(&instance)
+                .into_iter()
+                .flatten()
+                .filter(|(_, timing)| timing.could_activate(context, open))
+                .map(move |&(effect_index, _)| (instance, effect_index)))
+            .chain(subscriptions.by_timing.iter()
+                .filter(|(timing, _)| timing.could_activate(context, open))
+                .flat_map(|(_, effects)| effects.iter().copied()))
+            .collect();
+        effects.sort_by_key(|(instance, effect_index)| (instance.0, effect_index.0));
+        effects
+    }
+
+    /// The ways the priority player can activate the effects of cards on their field, in their
+    /// hand, in their center deck or in their destroyed piles in the current context. Only the
+    /// effects subscribed to what the game is waiting on are asked. Each effect may only be on
+    /// the chain once.
+    fn effect_activations(&self, card_pool: &Cards) -> Vec<Action> {
+        let context = self.context();
+        let open = matches!(self.open, Open { .. });
+        let field = self.priority_player();
+        let mut effects = self.subscribed_effects(card_pool, &context, open);
+        let queue = self.trigger_queue_context(card_pool);
+        if let Some(queue) = &queue {
+            for effect in self.subscribed_effects(card_pool, queue, open) {
+                if !effects.contains(&effect) && self.is_triggered(card_pool, effect.0, effect.1) {
+                    effects.push(effect);
+                }
+            }
+            effects.sort_by_key(|(instance, effect_index)| (instance.0, effect_index.0));
+        }
+        let mut actions = vec![];
+        for (instance, effect_index) in effects {
+            let card = match field.activatable_card(instance) {
+                Some(card) => card,
+                None => continue,
+            };
+            if self.chain.iter().any(|link| link.instance == instance && link.effect == effect_index) {
+                continue;
+            }
+            let card_type = card.lookup_self(card_pool);
+            let context = match &queue {
+                Some(queue) if self.is_triggered(card_pool, instance, effect_index) => queue,
+                _ => &context,
+            };
+            for activation in self.can_activate(card_pool, card_type, instance, effect_index, context) {
+                actions.push(Action::Effect {
+                    instance,
+                    effect_index,
+                    activation,
+                });
+            }
+        }
+        actions
+    }
+
+    /// While every link on the chain was triggered by the event the game is waiting on, more
+    /// effects triggered by the event may still be put on the chain, and they activate in the
+    /// context of the event rather than of the last link
+    fn trigger_queue_context(&self, card_pool: &Cards) -> Option<ResolutionContext> {
+        let queueing = self.trigger.is_some()
+            && !self.chain.is_empty()
+            && self.chain.iter().all(|link| self.is_triggered(card_pool, link.instance, link.effect));
+        if !queueing {
+            return None;
+        }
+        Some(ResolutionContext {
+            trigger: self.trigger.clone(),
+            attack: self.attack,
+            chain: self.chain.clone(),
+        })
+    }
+
+    /// If effects may still be activated in the closed game state, which they can't once the
+    /// chain is as long as the format allows or when the last link cannot be responded to
+    fn can_respond(&self, card_pool: &Cards) -> bool {
+        if self.config.max_chain.is_some_and(|max_chain| self.chain.len() >= max_chain) {
+            return false;
+        }
+        self.chain.last().is_none_or(|link| {
+            self.card(link.instance)
+                .and_then(|card| card.lookup_self(card_pool).effects.get(link.effect.0 as usize))
+                .is_none_or(|effect| effect.can_be_responded_to())
+        })
+    }
+
+    /// The cards the priority player could summon from their hand, or from their center deck for
+    /// cards with a resource cost in formats with resources, and where to
+    fn summons<'a>(&'a self, card_pool: &'a Cards) -> impl Iterator<Item = Action> + 'a {
+        let field = self.priority_player();
+        let empty = field.empty_slots();
+        // cards without a cost in the center deck can only be summoned by their own effects
+        let costed = move |card: &Card| card.status != CardStatus::Deck || card.lookup_self(card_pool).resource_cost > 0;
+        field.cards_to_summon(self.config.resources_per_turn > 0)
+            .into_iter()
+            .filter(move |card| costed(card) && self.resource_cost(card_pool, card) <= field.resources)
+            .flat_map(move |card| {
+                let card_type = card.lookup_self(card_pool);
+                empty.clone()
+                    .into_iter()
+                    .filter(move |&slot| card_type.can_summon_to(field, slot, false))
+                    .map(move |slot| Action::Summon { instance: card.instance, slot })
+            })
     }
 
-    pub fn priority_player_options(&self) -> Vec<PlayerOption> {
+    /// The Undying cards the priority player could summon from their destroyed piles and where to
+    fn revivals<'a>(&'a self, card_pool: &'a Cards) -> impl Iterator<Item = Action> + 'a {
         let field = self.priority_player();
+        let empty = field.empty_slots();
+        field.cards_to_revive(card_pool)
+            .into_iter()
+            .filter(move |card| self.resource_cost(card_pool, card) <= field.resources)
+            .flat_map(move |card| {
+                let card_type = card.lookup_self(card_pool);
+                empty.clone()
+                    .into_iter()
+                    .filter(move |&slot| card_type.can_summon_to(field, slot, false))
+                    .map(move |slot| Action::Revive { instance: card.instance, slot })
+            })
+    }
+
+    /// The attacks the priority player could make
+    fn attacks<'a>(&'a self, card_pool: &'a Cards) -> impl Iterator<Item = Action> + 'a {
+        let targets = self.non_priority_player().attack_targets(card_pool);
+        self.priority_player()
+            .cards_to_attack(card_pool)
+            .into_iter()
+            .flat_map(move |instance| targets.clone().into_iter().map(move |target| Action::Attack { instance, target }))
+    }
+
+    /// The actions the priority player could take in an open game state
+    fn actions(&self, card_pool: &Cards) -> Vec<Action> {
+        let mut actions: Vec<Action> = self.summons(card_pool)
+            .chain(self.revivals(card_pool))
+            .chain(self.attacks(card_pool))
+            .collect();
+        actions.extend(self.effect_activations(card_pool));
+        actions
+    }
+
+    /// If the priority player could take any action in an open game state, stopping at the first
+    fn has_any_action(&self, card_pool: &Cards) -> bool {
+        self.summons(card_pool).next().is_some()
+            || self.revivals(card_pool).next().is_some()
+            || self.attacks(card_pool).next().is_some()
+            || !self.effect_activations(card_pool).is_empty()
+    }
+
+    /// The priority player's cards which can attack, the first step of choosing an attack
+    pub fn attackers(&self, card_pool: &Cards) -> Vec<CardInstance> {
+        let mut attackers = vec![];
+        for option in self.legal_options(card_pool) {
+            if let PlayerOption::Action(Action::Attack { instance, .. }) = *option {
+                if !attackers.contains(&instance) {
+                    attackers.push(instance);
+                }
+            }
+        }
+        attackers
+    }
+
+    /// The cards the attacker can attack, the second step of choosing an attack, which is then
+    /// taken as the `Action::Attack` option for the attacker and target
+    pub fn attack_targets(&self, card_pool: &Cards, attacker: CardInstance) -> Vec<AttackTarget> {
+        let defender = self.non_priority_player();
+        self.legal_options(card_pool)
+            .iter()
+            .filter_map(|option| match *option {
+                PlayerOption::Action(Action::Attack { instance, target }) if instance == attacker => Some(target),
+                _ => None,
+            })
+            .filter_map(|slot| defender[slot].as_ref().map(|card| AttackTarget {
+                slot,
+                instance: card.instance,
+                guard: card.lookup_self(card_pool).has_keyword(Keyword::Guard),
+            }))
+            .collect()
+    }
+
+    /// How the game ended, if it is over. Besides ending early, a player loses when their turn
+    /// starts and they can neither draw a card nor take an action, or when draws are mandatory
+    /// and their left and right decks are empty. If both players have run out
+    /// of cards to play once the chain has resolved, they both lose and the game is a draw,
+    /// whoever's turn it is.
+    pub fn result(&self, card_pool: &Cards) -> Option<GameResult> {
+        if self.ended.is_some() {
+            return self.ended;
+        }
+        let resolved = matches!(self.open, Open { .. });
+        if resolved && self.player_one.exhausted(card_pool) && self.player_two.exhausted(card_pool) {
+            return Some(GameResult {
+                winner: None,
+                reason: EndReason::SimultaneousLoss,
+            });
+        }
+        let decked = self.config.mandatory_draw && !self.priority_player().has_cards_to_draw();
+        let stuck = self.open == Open { phase: MayDraw } && (decked || !self.has_any_option(card_pool));
+        if stuck {
+            Some(GameResult {
+                winner: Some(self.active.next()),
+                reason: EndReason::NoDrawOrAction,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// If the game is over, which is cheaper to check than working out every option
+    pub fn is_terminal(&self, card_pool: &Cards) -> bool {
+        self.result(card_pool).is_some()
+    }
+
+    /// If the priority player can do anything other than skip or pass: draw, take an action or
+    /// respond. While they may draw, the actions they could take after choosing not to count too,
+    /// so a player whose turn starts without any option has lost. Stops at the first option found
+    /// rather than working them all out.
+    pub fn has_any_option(&self, card_pool: &Cards) -> bool {
+        if self.ended.is_some() {
+            return false;
+        }
+        match self.open {
+            Open { phase: Phase::MayDraw } => self.draw_options().next().is_some() || self.has_any_action(card_pool),
+            Open { phase: Phase::MayTakeAction } => {
+                let extra = self.extra_draws(self.active);
+                (extra > 0 && self.draws <= extra && self.draw_options().next().is_some()) || self.has_any_action(card_pool)
+            },
+            Closed => self.legal_options(card_pool).iter().any(|option| *option != PlayerOption::SkipResponse),
+        }
+    }
+
+    /// How many options the priority player has, the same as the length of `legal_options` but
+    /// counting summons and attacks without building every one
+    pub fn legal_option_count(&self, card_pool: &Cards) -> usize {
+        if let Some(options) = self.options.0.get() {
+            return options.len();
+        }
+        if self.ended.is_some() {
+            return 0;
+        }
+        match self.open {
+            Open { phase: Phase::MayDraw } => 1 + self.draw_options().count(),
+            Open { phase: Phase::MayTakeAction } => {
+                let extra = self.extra_draws(self.active);
+                let draws = if extra > 0 && self.draws <= extra { self.draw_options().count() } else { 0 };
+                1 + draws
+                    + self.summons(card_pool).count()
+                    + self.revivals(card_pool).count()
+                    + self.attacks(card_pool).count()
+                    + self.effect_activations(card_pool).len()
+            },
+            // responses are few, and which are options depends on if any are mandatory
+            Closed => self.legal_options(card_pool).len(),
+        }
+    }
+
+    /// The player who has won the game, if it is over
+    pub fn winner(&self, card_pool: &Cards) -> Option<Player> {
+        self.result(card_pool).and_then(|result| result.winner)
+    }
+
+    /// Ends the game early, unless it has already ended
+    fn end(&mut self, winner: Option<Player>, reason: EndReason) -> Result<(), InvalidAction> {
+        if self.ended.is_some() {
+            return Err(InvalidAction);
+        }
+        let result = GameResult { winner, reason };
+        self.ended = Some(result);
+        self.log(GameEvent::Ended(result));
+        self.clear_caches();
+        Ok(())
+    }
+
+    /// The player gives up, and their opponent wins
+    pub fn concede(&mut self, player: Player) -> Result<(), InvalidAction> {
+        self.end(Some(player.next()), EndReason::Concession)
+    }
+
+    /// The player ran out of time, and their opponent wins
+    pub fn time_out(&mut self, player: Player) -> Result<(), InvalidAction> {
+        self.end(Some(player.next()), EndReason::Timeout)
+    }
+
+    /// Stops the game without a winner
+    pub fn abort(&mut self) -> Result<(), InvalidAction> {
+        self.end(None, EndReason::Aborted)
+    }
+
+    /// Ends the game with the winner an operator decided on, or as a draw
+    pub fn adjudicate(&mut self, winner: Option<Player>) -> Result<(), InvalidAction> {
+        self.end(winner, EndReason::Adjudicated)
+    }
+
+    pub fn priority_player_options(&self, card_pool: &Cards) -> Vec<PlayerOption> {
+        self.legal_options(card_pool).to_vec()
+    }
+
+    /// The priority player's options, which are only worked out once between each change to the
+    /// game state so repeatedly asking for them is cheap
+    pub fn legal_options(&self, card_pool: &Cards) -> &[PlayerOption] {
+        self.options.0.get_or_init(|| self.compute_options(card_pool))
+    }
+
+    /// The player's options, which are empty unless they have priority
+    pub fn options_of(&self, player: Player, card_pool: &Cards) -> &[PlayerOption] {
+        if player == self.priority() {
+            self.legal_options(card_pool)
+        } else {
+            &[]
+        }
+    }
+
+    fn compute_options(&self, card_pool: &Cards) -> Vec<PlayerOption> {
+        if self.ended.is_some() {
+            return vec![];
+        }
         match self.open {
             Open { phase: Phase::MayDraw } => {
-                let mut options = vec![ PlayerOption::SkipDraw ];
-                if field.has_cards_to_draw() {
-                    if field.has_cards_to_draw_left() {
-                        options.push(PlayerOption::Draw(FaceDownDeck::Left));
-                    }
-                    if field.has_cards_to_draw_right() {
-                        options.push(PlayerOption::Draw(FaceDownDeck::Right));
-                    }
+                let draws: Vec<PlayerOption> = self.draw_options().collect();
+                if self.config.mandatory_draw && !draws.is_empty() {
+                    return draws;
                 }
+                let mut options = vec![ PlayerOption::SkipDraw ];
+                options.extend(draws);
                 options
             }
             Open { phase: Phase::MayTakeAction } => {
                 let mut options = vec![ PlayerOption::SkipAction ];
-                for card in field.cards_to_summon() {
-                    for slot in field.empty_slots() {
-                        options.push(PlayerOption::Action(Action {
-                            action_type: ActionType::Summon,
-                            instance: card,
-                            slot: Some(slot),
-                        }));
-                    }
+                let extra = self.extra_draws(self.active);
+                if extra > 0 && self.draws <= extra {
+                    options.extend(self.draw_options());
                 }
-                for card in field.cards_to_attack() {
-                    options.push(PlayerOption::Action(Action {
-                        action_type: ActionType::Attack,
-                        instance: card,
-                        slot: None, // TODO
-                    }))
-                }
-                // TODO: Activating effects of cards summoned on the field
+                options.extend(self.actions(card_pool).into_iter().map(PlayerOption::Action));
                 options
             },
             Closed => {
-                // TODO: Responding to effects
-                vec![]
+                if !self.can_respond(card_pool) {
+                    return vec![ PlayerOption::SkipResponse ];
+                }
+                let responses = self.effect_activations(card_pool);
+                let mandatory: Vec<Action> = responses.iter()
+                    .filter(|action| matches!(
+                        action,
+                        Action::Effect { activation: Activation { status: ActivatableType::Mandatory, .. }, .. }
+                    ))
+                    .cloned()
+                    .collect();
+                if !mandatory.is_empty() {
+                    // mandatory effects must be activated before the player may pass or respond
+                    // with anything else
+                    return mandatory.into_iter().map(PlayerOption::Action).collect();
+                }
+                let mut options = vec![ PlayerOption::SkipResponse ];
+                options.extend(responses.into_iter().map(PlayerOption::Action));
+                options
             }
         }
     }
 
-    pub fn priorty_player_take_option(&mut self, option: PlayerOption) -> Result<(), InvalidAction> {
-        if !self.priority_player_options().contains(&option) {
+    pub fn priorty_player_take_option(&mut self, card_pool: &Cards, option: PlayerOption) -> Result<(), InvalidAction> {
+        if !self.legal_options(card_pool).contains(&option) {
             return Err(InvalidAction);
         }
+        let result = self.apply_option(card_pool, option);
+        self.clear_caches();
+        self.unless_soft_locked(card_pool, result)
+    }
+
+    /// Takes an option without checking it is one of the priority player's options, for callers
+    /// such as AI rollouts which only ever take options the engine gave them. Taking an option
+    /// that isn't legal is only caught in debug builds, and otherwise leaves the game in a state
+    /// the rules don't allow.
+    pub fn take_option_unchecked(&mut self, card_pool: &Cards, option: PlayerOption) -> Result<(), InvalidAction> {
+        debug_assert!(
+            self.legal_options(card_pool).contains(&option),
+            "{:?} is not one of the priority player's options", option
+        );
+        let result = self.apply_option(card_pool, option);
+        self.clear_caches();
+        self.unless_soft_locked(card_pool, result)
+    }
+
+    /// The result of taking an option, unless it failed because the priority player is soft
+    /// locked, in which case the game continues by the soft lock policy and the option counts as
+    /// taken
+    fn unless_soft_locked(&mut self, card_pool: &Cards, result: Result<(), InvalidAction>) -> Result<(), InvalidAction> {
+        match result {
+            Err(InvalidAction) if self.adjudicate_soft_lock(card_pool) => Ok(()),
+            result => result,
+        }
+    }
+
+    /// If the game isn't over but every option the priority player has fails when taken, which
+    /// would leave the game stuck forever. Each option is tried on a copy of the game, so this
+    /// is only worth checking once an option has failed.
+    pub fn is_soft_locked(&self, card_pool: &Cards) -> bool {
+        self.result(card_pool).is_none() && self.legal_options(card_pool).iter().all(|option| {
+            let mut game = self.snapshot();
+            game.apply_option(card_pool, option.clone()).is_err()
+        })
+    }
+
+    /// Continues a soft locked game by the soft lock policy, returning if it was soft locked
+    pub fn adjudicate_soft_lock(&mut self, card_pool: &Cards) -> bool {
+        if !self.is_soft_locked(card_pool) {
+            return false;
+        }
+        self.log(GameEvent::SoftLocked { player: self.active });
+        let skipped = self.config.soft_lock == SoftLockPolicy::Skip && {
+            let pass = match self.open {
+                Open { phase: MayDraw } => PlayerOption::SkipDraw,
+                Open { phase: MayTakeAction } => PlayerOption::SkipAction,
+                Closed => PlayerOption::SkipResponse,
+            };
+            self.carry_out_option(card_pool, pass).is_ok()
+        };
+        if !skipped {
+            let _ = self.end(None, EndReason::Aborted);
+        }
+        self.clear_caches();
+        true
+    }
+
+    /// Takes the priority player's option for them for as long as they only have one, such as
+    /// skipping a draw with no cards left to draw or passing with nothing to respond with, so
+    /// drivers and bots only need to ask for real choices. Returns how many options were taken.
+    pub fn take_forced_options(&mut self, card_pool: &Cards) -> usize {
+        let mut taken = 0;
+        // a player who can neither draw nor act only has options which pass the turn back and
+        // forth, so stop once the game is over
+        while self.result(card_pool).is_none() {
+            let option = match self.legal_options(card_pool) {
+                [option] => option.clone(),
+                _ => break,
+            };
+            self.log(GameEvent::Forced { player: self.active });
+            let result = self.apply_option(card_pool, option);
+            self.clear_caches();
+            if self.unless_soft_locked(card_pool, result).is_err() {
+                break;
+            }
+            taken += 1;
+        }
+        taken
+    }
+
+    fn apply_option(&mut self, card_pool: &Cards, option: PlayerOption) -> Result<(), InvalidAction> {
+        self.cause = EventCause::Option(self.active);
+        let result = self.carry_out_option(card_pool, option);
+        self.cause = EventCause::Rules;
+        result
+    }
+
+    fn carry_out_option(&mut self, card_pool: &Cards, option: PlayerOption) -> Result<(), InvalidAction> {
+        self.clear_caches();
         match option {
             PlayerOption::SkipDraw => {
                 self.open = GameStateType::Open { phase: MayTakeAction };
             },
             PlayerOption::Draw(deck) => {
-                let player = self.priority_player_mut();
-                // move the card from deck to hand
-                match deck {
-                    FaceDownDeck::Left => {
-                        player.hand.push(player.left_deck.pop().ok_or(InvalidAction)?);
-                    }
-                    FaceDownDeck::Right => {
-                        player.hand.push(player.right_deck.pop().ok_or(InvalidAction)?);
-                    }
-                }
+                let player = self.priority_player();
+                let instance = match deck {
+                    FaceDownDeck::Left => player.left_deck.last(),
+                    FaceDownDeck::Right => player.right_deck.last(),
+                }.ok_or(InvalidAction)?.instance;
+                // logged first so the deck running out, which drawing the last card logs, follows it
+                self.log(GameEvent::Drew { player: self.active, deck });
+                self.take_action(card_pool, EngineAction::Draw(self.active, deck))?;
+                self.draws += 1;
+                self.trigger = Some(Trigger::Drawn(instance));
                 self.open = GameStateType::Open { phase: MayTakeAction };
             },
             PlayerOption::SkipAction => {
                 // immediately passes priority
-                self.active = self.active.next();
-                self.open = GameStateType::Open { phase: MayDraw };
+                self.log(GameEvent::EndedTurn { player: self.active });
+                self.pass_turn(card_pool);
+            },
+            PlayerOption::SkipResponse => {
+                self.log(GameEvent::Passed { player: self.active });
+                if self.passed {
+                    // both players have passed in turn, so we resolve the chain and the other
+                    // player now gets priority in an open game state, unless resolving the
+                    // chain triggered more effects
+                    let on_field: Vec<CardInstance> = self.cards_on_field().map(|card| card.instance).collect();
+                    self.resolve_chain(card_pool);
+                    self.log(GameEvent::Resolved);
+                    let destroyed = on_field.into_iter()
+                        .filter(|&instance| self.card_in_destroyed_pile(instance).is_some())
+                        .collect();
+                    if !self.open_trigger_window(card_pool, destroyed) {
+                        self.windows = 0;
+                        if self.starting {
+                            // the turn player draws now the effects of the turn starting have
+                            // resolved, rather than the turn passing
+                            self.starting = false;
+                            self.active = self.turn;
+                            self.open = Open { phase: MayDraw };
+                            self.trigger = None;
+                            self.attack = None;
+                            self.passed = false;
+                        } else {
+                            self.active = self.turn.next();
+                            self.start_turn(card_pool);
+                        }
+                    } else if self.windows >= self.config.loop_limit {
+                        // every chain resolved so far stands, but nothing more is triggered
+                        self.log(GameEvent::LoopBroken);
+                        self.trigger = None;
+                        let _ = self.end(None, EndReason::Loop);
+                    } else {
+                        self.windows += 1;
+                    }
+                } else {
+                    self.passed = true;
+                    self.active = self.active.next();
+                }
             },
             PlayerOption::Action(action) => {
-                let player = self.priority_player_mut();
                 // passes priority but game state is now closed, other player may only respond
                 // to the action
-                match action.action_type {
-                    ActionType::Summon => {
-                        let slot = action.slot.ok_or(InvalidAction)?; //should this be defind on the summon subtype?
-                        let card_index = player
-                            .hand
-                            .iter()
-                            .position(|card| card.instance == action.instance)
-                            .ok_or(InvalidAction)?;
-                        if player.slot_is_empty(slot) {
-                            let card = player.hand.remove(card_index);
-                            player[slot] = Some(card);
+                let player = self.active;
+                let mut keep_priority = false;
+                match action {
+                    Action::Summon { instance, slot } => {
+                        let cost = self.resource_cost(card_pool, self.card(instance).ok_or(InvalidAction)?);
+                        self.take_action(card_pool, EngineAction::SpendResources(player, cost))?;
+                        if self.card_in_center_deck(instance).is_some() {
+                            self.take_action(card_pool, EngineAction::SummonFromCenterDeckToSlot(instance, slot))?;
                         } else {
-                            return Err(InvalidAction);
+                            self.take_action(card_pool, EngineAction::SummonFromHandToSlot(instance, slot))?;
+                        }
+                        self.trigger = Some(Trigger::Summoned(instance));
+                        self.log(GameEvent::Summoned { player, card: self.card_type_of(instance)?, slot });
+                    }
+                    Action::Revive { instance, slot } => {
+                        let card = self.card(instance).ok_or(InvalidAction)?;
+                        let cost = self.resource_cost(card_pool, card);
+                        let penalty = card.lookup_self(card_pool).defense / 2;
+                        self.take_action(card_pool, EngineAction::SpendResources(player, cost))?;
+                        self.take_action(card_pool, EngineAction::SummonFromDestroyedToSlot(instance, slot))?;
+                        if penalty > 0 {
+                            self.take_action(card_pool, EngineAction::ChangeCounters(vec![(instance, penalty as i32)]))?;
                         }
+                        self.trigger = Some(Trigger::Summoned(instance));
+                        self.log(GameEvent::Summoned { player, card: self.card_type_of(instance)?, slot });
                     }
-                    ActionType::Effect => (),
-                    ActionType::Attack => (),
+                    Action::Attack { instance, target } => {
+                        self.attack(instance, target)?;
+                        let target = self.non_priority_player()[target].as_ref().ok_or(InvalidAction)?.card_type;
+                        self.log(GameEvent::Attacked { player, attacker: self.card_type_of(instance)?, target });
+                    },
+                    Action::Effect { instance, effect_index, activation } => {
+                        let response = self.open == Closed;
+                        self.activate_effect(card_pool, instance, effect_index, activation)?;
+                        // a player puts every effect an event triggered for them on the chain,
+                        // in the order they choose, before the other player gets priority
+                        if response && self.is_triggered(card_pool, instance, effect_index) {
+                            self.clear_caches();
+                            keep_priority = self.has_triggers_left(card_pool);
+                        }
+                    },
+                }
+                self.passed = false;
+                if !keep_priority {
+                    self.active = self.active.next();
                 }
                 self.open = GameStateType::Closed;
             },
         }
         Ok(())
     }
+
+    /// If the effect waits on something happening to its own card, so is triggered rather than
+    /// activated by choice
+    fn is_triggered(&self, card_pool: &Cards, instance: CardInstance, effect_index: CardEffect) -> bool {
+        self.card(instance)
+            .and_then(|card| card.lookup_self(card_pool).effects.get(effect_index.0 as usize))
+            .is_some_and(|effect| effect.timing().targets_own_card())
+    }
+
+    /// If the priority player can still activate effects triggered by what the game is waiting on
+    fn has_triggers_left(&self, card_pool: &Cards) -> bool {
+        self.effect_activations(card_pool).iter().any(|action| match action {
+            Action::Effect { instance, effect_index, .. } => self.is_triggered(card_pool, *instance, *effect_index),
+            _ => false,
+        })
+    }
+
+    /// Activates the priority player's card effect and adds it to the chain
+    fn activate_effect(&mut self, card_pool: &Cards, instance: CardInstance, effect_index: CardEffect, activation: Activation) -> Result<(), InvalidAction> {
+        let card_type = self.card(instance).ok_or(InvalidAction)?.lookup_self(card_pool);
+        let effect = card_type.effects.get(effect_index.0 as usize).ok_or(InvalidAction)?;
+        let context = match self.trigger_queue_context(card_pool) {
+            Some(queue) if self.is_triggered(card_pool, instance, effect_index) => queue,
+            _ => self.context(),
+        };
+        if !self.can_activate(card_pool, card_type, instance, effect_index, &context).contains(&activation) {
+            return Err(InvalidAction);
+        }
+        let (player, card) = (self.active, card_type.id);
+        self.log(GameEvent::Activated { player, card, response: self.open == Closed });
+        // paying the effect's costs is caused by the link it is about to become
+        let cause = self.caused_by(EventCause::Link { link: ChainLinkId::from(self.chain.len()), player, card });
+        let within_budget = self.within_budget(|game| effect.activate(card_pool, card_type, game, instance, &activation, &context));
+        self.cause = cause;
+        if !within_budget {
+            // the effect fizzles without reaching the chain
+            self.log(GameEvent::EffectBudgetExceeded { player, card });
+            return Ok(());
+        }
+        let on_field = activation.data.targets.iter()
+            .copied()
+            .filter(|&target| self.card_on_field(target).is_some())
+            .collect();
+        self.chain.push(ChainLink {
+            player: self.active,
+            instance,
+            card,
+            effect: effect_index,
+            activation,
+            on_field,
+        });
+        Ok(())
+    }
+
+    /// Resolves each link of the chain in reverse order, and then the attack that opened the
+    /// chain if there was one, these resolutions cannot be responded to
+    /// Keeps the game state closed after a chain resolves so effects triggered by cards destroyed
+    /// during it, or by decks which ran out since the last window, can be activated as a new
+    /// chain, starting with the turn player. Cards destroyed are offered first, then each deck in
+    /// the order it ran out, and any not offered yet wait for the next window. Returns if there
+    /// were any such effects to open the window for.
+    fn open_trigger_window(&mut self, card_pool: &Cards, destroyed: Vec<CardInstance>) -> bool {
+        let destroyed = Some(destroyed).filter(|destroyed| !destroyed.is_empty()).map(Trigger::Destroyed);
+        let emptied = std::mem::take(&mut self.emptied);
+        let mut triggers = destroyed.into_iter()
+            .chain(emptied.into_iter().map(|(player, deck)| Trigger::DeckEmptied { player, deck }));
+        while let Some(trigger) = triggers.next() {
+            if self.open_window_for(card_pool, trigger) {
+                self.emptied = triggers
+                    .filter_map(|trigger| match trigger {
+                        Trigger::DeckEmptied { player, deck } => Some((player, deck)),
+                        _ => None,
+                    })
+                    .collect();
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Returns if either player can activate an effect triggered by the trigger, leaving the
+    /// turn player with priority if so
+    fn open_window_for(&mut self, card_pool: &Cards, trigger: Trigger) -> bool {
+        self.trigger = Some(trigger);
+        self.attack = None;
+        self.passed = false;
+        for player in [self.turn.next(), self.turn] {
+            self.active = player;
+            self.clear_caches();
+            if !self.effect_activations(card_pool).is_empty() {
+                // either player being able to respond opens the window, and the turn player
+                // always gets the first chance to
+                self.active = self.turn;
+                self.clear_caches();
+                return true;
+            }
+        }
+        false
+    }
+
+    fn resolve_chain(&mut self, card_pool: &Cards) {
+        if !self.chain.is_empty() {
+            self.log(GameEvent::ChainResolutionStarted { links: self.chain.len() });
+        }
+        while let Some(link) = self.chain.pop() {
+            let (link_id, player, card) = (ChainLinkId::from(self.chain.len()), link.player, link.card);
+            // a link whose card or targets are gone does nothing, rather than resolving against
+            // whatever is left
+            let card_type = match self.card(link.instance) {
+                Some(found) if link.on_field.iter().all(|&target| self.card_on_field(target).is_some()) => {
+                    found.lookup_self(card_pool)
+                },
+                _ => {
+                    self.log(GameEvent::LinkFizzled { link: link_id, player, card });
+                    continue;
+                },
+            };
+            let effect = &card_type.effects[link.effect.0 as usize];
+            // the context is now the same as when the link was activated
+            let context = self.context();
+            let within_budget = self.within_budget(|game| {
+                game.log(GameEvent::LinkResolved { link: link_id, player, card });
+                let cause = game.caused_by(EventCause::Link { link: link_id, player, card });
+                effect.resolve(card_pool, card_type, game, link.instance, &link.activation, &context);
+                game.cause = cause;
+            });
+            if !within_budget {
+                self.log(GameEvent::EffectBudgetExceeded { player, card });
+                self.log(GameEvent::LinkFizzled { link: link_id, player, card });
+            }
+        }
+        if let Some(attack) = self.attack {
+            let context = self.context();
+            combat::resolve(card_pool, self, attack, &context);
+        }
+    }
+
+    /// The priority player's card declares an attack on the card in the target slot of the
+    /// opponent's field. The attack is pending until the chain it opens resolves.
+    fn attack(&mut self, attacker: CardInstance, target: FieldSlot) -> Result<(), InvalidAction> {
+        self.priority_player().slot_of(attacker).ok_or(InvalidAction)?;
+        let attack = Attack {
+            attacker,
+            target: self.non_priority_player()[target].as_ref().ok_or(InvalidAction)?.instance,
+        };
+        self.trigger = Some(Trigger::Attacked(attack));
+        self.attack = Some(attack);
+        Ok(())
+    }
 }
 
 impl fmt::Debug for GameState {
@@ -496,3 +3285,56 @@ impl fmt::Debug for GameState {
         Ok(())
     }
 }
+
+/// The cards a side of a position has on the field, in its destroyed piles and removed from the
+/// game, owned by the player and checked against the slots and piles they're in
+#[allow(clippy::type_complexity)]
+fn place(
+    side: &SideSpec,
+    player: Player,
+    card_pool: &Cards,
+    instances: &mut CardInstances,
+) -> Result<(Vec<(FieldSlot, Card)>, Vec<(Column, Card)>, Vec<Card>), ImportError> {
+    let mut field: Vec<(FieldSlot, Card)> = vec![];
+    for spec in &side.field {
+        if field.iter().any(|&(slot, _)| slot == spec.slot) {
+            return Err(ImportError::SlotTaken { player, slot: spec.slot });
+        }
+        let mut card = instantiate(instances, card_pool, &spec.card)?;
+        let wrong_row = match card.lookup_self(card_pool).summon_restriction {
+            Some(SummonRestriction::FrontRowOnly) => spec.slot.row() != Row::Front,
+            Some(SummonRestriction::BackRowOnly) => spec.slot.row() != Row::Back,
+            _ => false,
+        };
+        if wrong_row {
+            return Err(ImportError::WrongRow { card: spec.card.clone(), slot: spec.slot });
+        }
+        card.owner = player;
+        card.damage = spec.damage;
+        card.status = CardStatus::Field { just_summoned: spec.just_summoned };
+        field.push((spec.slot, card));
+    }
+    let mut destroyed = vec![];
+    for spec in &side.destroyed {
+        if spec.column.0 >= 7 {
+            return Err(ImportError::NoSuchColumn(spec.column.0));
+        }
+        let mut card = instantiate(instances, card_pool, &spec.card)?;
+        card.owner = player;
+        card.status = CardStatus::Destroyed;
+        destroyed.push((spec.column, card));
+    }
+    let mut removed = vec![];
+    for name in &side.removed {
+        let mut card = instantiate(instances, card_pool, name)?;
+        card.owner = player;
+        card.status = CardStatus::Removed;
+        removed.push(card);
+    }
+    Ok((field, destroyed, removed))
+}
+
+fn instantiate(instances: &mut CardInstances, card_pool: &Cards, name: &str) -> Result<Card, ImportError> {
+    let card_type = card_pool.card(name.to_owned()).ok_or_else(|| ImportError::UnknownCard(name.to_owned()))?;
+    instances.try_instantiate(card_type).map_err(|TooManyCards| ImportError::TooManyCards)
+}
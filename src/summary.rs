@@ -0,0 +1,162 @@
+use crate::card_type::CardTypeIdentifier;
+use crate::cards::Cards;
+use crate::state::{Column, EndReason, EventCause, FaceDownDeck, GameEvent, GameState, Player};
+
+fn player_name(player: Player) -> &'static str {
+    match player {
+        Player::One => "P1",
+        Player::Two => "P2",
+    }
+}
+
+fn card_name(card_pool: &Cards, card: CardTypeIdentifier) -> &str {
+    card_pool.card(card).map_or("an unknown card", |card_type| &card_type.name)
+}
+
+/// What the event says the player did, without the player
+fn describe_event(event: &GameEvent, card_pool: &Cards) -> String {
+    match *event {
+        GameEvent::Drew { deck, .. } => match deck {
+            FaceDownDeck::Left => "drew from the left deck".to_owned(),
+            FaceDownDeck::Right => "drew from the right deck".to_owned(),
+        },
+        GameEvent::Summoned { card, slot, .. } => format!("summoned {} to {:?}", card_name(card_pool, card), slot),
+        GameEvent::Attacked { attacker, target, .. } => format!(
+            "attacked {} with {}", card_name(card_pool, target), card_name(card_pool, attacker)
+        ),
+        GameEvent::Activated { card, response: true, .. } => format!("responded with {}", card_name(card_pool, card)),
+        GameEvent::Activated { card, response: false, .. } => format!("activated {}", card_name(card_pool, card)),
+        GameEvent::Passed { .. } => "passed".to_owned(),
+        GameEvent::EndedTurn { .. } => "ended their turn".to_owned(),
+        GameEvent::ChainResolutionStarted { links: 1 } => "the chain of 1 link started resolving".to_owned(),
+        GameEvent::ChainResolutionStarted { links } => format!("the chain of {} links started resolving", links),
+        GameEvent::LinkResolved { card, .. } => format!("{} resolved", card_name(card_pool, card)),
+        GameEvent::LinkFizzled { card, .. } => format!("{} fizzled", card_name(card_pool, card)),
+        GameEvent::Resolved => "the chain resolved".to_owned(),
+        GameEvent::LoopBroken => "the effects were stopped from looping forever".to_owned(),
+        GameEvent::EffectBudgetExceeded { card, .. } => format!("{} did too much and fizzled", card_name(card_pool, card)),
+        GameEvent::SoftLocked { .. } => "was stuck as none of their options worked".to_owned(),
+        GameEvent::Ended(result) => match (result.reason(), result.winner()) {
+            (EndReason::SimultaneousLoss, _) => "both players ran out of cards and the game was drawn".to_owned(),
+            (EndReason::Loop, _) => "the game was drawn".to_owned(),
+            (EndReason::Adjudicated, None) => "the game was adjudicated a draw".to_owned(),
+            (EndReason::Aborted, _) | (_, None) => "the game was aborted".to_owned(),
+            (EndReason::NoDrawOrAction, Some(winner)) => format!(
+                "{} won as {} could neither draw nor act", player_name(winner), player_name(winner.next())
+            ),
+            (EndReason::Concession, Some(winner)) => format!("{} conceded", player_name(winner.next())),
+            (EndReason::Timeout, Some(winner)) => format!("{} ran out of time", player_name(winner.next())),
+            (EndReason::AlternativeWin, Some(winner)) => format!("{} won by card effect", player_name(winner)),
+            (EndReason::Adjudicated, Some(winner)) => format!("{} was awarded the game", player_name(winner)),
+        },
+        GameEvent::Forced { .. } => "had only one option".to_owned(),
+        GameEvent::Rewound { .. } => "rewound one action".to_owned(),
+        GameEvent::Instruction(ref message) => message.clone(),
+        GameEvent::Destroyed { card, .. } => format!("{} was destroyed", card_name(card_pool, card)),
+        GameEvent::Generated { card, .. } => format!("got a random {}", card_name(card_pool, card)),
+        GameEvent::DeckEmptied { deck, .. } => match deck {
+            FaceDownDeck::Left => "ran out of cards in the left deck".to_owned(),
+            FaceDownDeck::Right => "ran out of cards in the right deck".to_owned(),
+        },
+    }
+}
+
+fn player_of(event: &GameEvent) -> Option<Player> {
+    match *event {
+        GameEvent::Drew { player, .. }
+        | GameEvent::Summoned { player, .. }
+        | GameEvent::Attacked { player, .. }
+        | GameEvent::Activated { player, .. }
+        | GameEvent::Passed { player }
+        | GameEvent::EndedTurn { player }
+        | GameEvent::Forced { player }
+        | GameEvent::SoftLocked { player }
+        | GameEvent::Rewound { player }
+        | GameEvent::Generated { player, .. }
+        | GameEvent::DeckEmptied { player, .. } => Some(player),
+        GameEvent::ChainResolutionStarted { .. }
+        | GameEvent::LinkResolved { .. }
+        | GameEvent::LinkFizzled { .. }
+        | GameEvent::EffectBudgetExceeded { .. }
+        | GameEvent::Resolved
+        | GameEvent::LoopBroken
+        | GameEvent::Ended(_)
+        | GameEvent::Instruction(_)
+        | GameEvent::Destroyed { .. } => None,
+    }
+}
+
+/// Describes a run of game events in plain English for match logs and chat recaps, such as
+/// "P1 drew from the left deck and summoned Staple Dragon to F4; P2 passed"
+pub fn describe_turn(events: &[GameEvent], card_pool: &Cards) -> String {
+    let mut sentences: Vec<String> = vec![];
+    let mut last_player = None;
+    for event in events {
+        let player = player_of(event);
+        let description = describe_event(event, card_pool);
+        match (sentences.last_mut(), player) {
+            // consecutive events by the same player are joined into one sentence
+            (Some(sentence), Some(player)) if last_player == Some(player) => {
+                sentence.push_str(" and ");
+                sentence.push_str(&description);
+            },
+            (_, Some(player)) => sentences.push(format!("{} {}", player_name(player), description)),
+            (_, None) => sentences.push(description),
+        }
+        last_player = player;
+    }
+    sentences.join("; ")
+}
+
+/// Describes what caused an event, such as "Staple Dragon's effect", for clients to explain
+/// events with
+pub fn describe_cause(cause: EventCause, card_pool: &Cards) -> String {
+    match cause {
+        EventCause::Rules => "the rules".to_owned(),
+        EventCause::Option(player) => format!("{}'s choice", player_name(player)),
+        EventCause::Link { card, .. } => format!("{}'s effect", card_name(card_pool, card)),
+        EventCause::Attack { attacker, .. } => format!("{}'s attack", card_name(card_pool, attacker)),
+        EventCause::Static { card } => format!("{}'s static effect", card_name(card_pool, card)),
+    }
+}
+
+/// Describes what both players can see of the game, such as for an operator inspecting a live
+/// game, one line per player after a line for whose turn it is
+pub fn describe_state(game: &GameState, card_pool: &Cards) -> String {
+    let mut lines = vec![format!(
+        "{}'s turn, {} has priority", player_name(game.turn()), player_name(game.priority())
+    )];
+    for player in Player::all() {
+        let field = game.field_of(player);
+        let names = |cards: &mut dyn Iterator<Item = CardTypeIdentifier>| {
+            cards.map(|card| card_name(card_pool, card)).collect::<Vec<_>>().join(", ")
+        };
+        let on_field = field.occupied_slots()
+            .map(|(slot, card)| format!("{} at {:?}", card_name(card_pool, card.card_type), slot))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let destroyed = names(&mut Column::all().flat_map(|column| field.destroyed(column)).map(|card| card.card_type));
+        // most games never remove a card, so the zone is only described once one has been
+        let removed = match field.removed() {
+            [] => String::new(),
+            removed => format!("; removed [{}]", names(&mut removed.iter().map(|card| card.card_type))),
+        };
+        let resources = match game.config().resources_per_turn {
+            0 => String::new(),
+            _ => format!("; {} resources", field.resources()),
+        };
+        lines.push(format!(
+            "{}: {} in hand, {} and {} in the left and right decks; field [{}]; center deck [{}]; destroyed [{}]{}{}",
+            player_name(player),
+            field.hand_size(),
+            field.deck_sizes().left,
+            field.deck_sizes().right,
+            on_field,
+            names(&mut field.center_deck().iter().map(|card| card.card_type)),
+            destroyed,
+            removed,
+            resources,
+        ));
+    }
+    lines.join("\n")
+}
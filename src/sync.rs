@@ -0,0 +1,185 @@
+#[cfg(feature = "http")]
+use crate::cards::HttpSource;
+use crate::cards::{Cards, MemorySource};
+
+use std::fmt;
+
+use ed25519_dalek::{Signature, Signer};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha512};
+
+/// The keys manifests are signed and checked with, re-exported so servers and clients don't need
+/// to depend on ed25519-dalek themselves
+pub use ed25519_dalek::{SigningKey, VerifyingKey};
+
+/// A server card pools are synced from
+pub trait Remote {
+    /// The contents of the file at the path
+    fn fetch(&self, path: &str) -> Result<String, Box<dyn std::error::Error>>;
+}
+
+#[cfg(feature = "http")]
+impl Remote for HttpSource {
+    fn fetch(&self, path: &str) -> Result<String, Box<dyn std::error::Error>> {
+        HttpSource::fetch(self, path)
+    }
+}
+
+/// Every card file of a card pool with the SHA-512 hash of its contents. Servers publish the
+/// manifest with a signature of it, so clients can check each card file they fetch came from
+/// whoever holds the secret key.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Manifest {
+    pub cards: Vec<ManifestEntry>,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ManifestEntry {
+    /// Where the card file is on the server
+    pub path: String,
+    /// The hash of the card file, as from `content_hash`
+    pub hash: String,
+}
+
+impl Manifest {
+    /// The manifest of card files by their paths and contents, for servers publishing a card pool
+    pub fn of<'a>(files: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+        Manifest {
+            cards: files.into_iter()
+                .map(|(path, contents)| ManifestEntry { path: path.to_owned(), hash: content_hash(contents) })
+                .collect(),
+        }
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).expect("manifests are always valid TOML")
+    }
+}
+
+/// The SHA-512 hash of a card file in hex, as listed in manifests
+pub fn content_hash(contents: &str) -> String {
+    to_hex(&Sha512::digest(contents.as_bytes()))
+}
+
+/// The signature of a manifest in hex, which servers publish next to the manifest with `.sig`
+/// added to its path
+pub fn sign_manifest(secret_key: &SigningKey, manifest: &str) -> String {
+    to_hex(&secret_key.sign(manifest.as_bytes()).to_bytes())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| hex.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+        .collect()
+}
+
+/// Why a card pool couldn't be synced. A failed sync leaves the files from the last sync as they
+/// were.
+#[derive(Debug)]
+pub enum SyncError {
+    /// A file couldn't be fetched from the server
+    Fetch { path: String, error: Box<dyn std::error::Error> },
+    /// The manifest's signature isn't the server's, so nothing it lists can be trusted
+    BadSignature,
+    /// The manifest is signed but isn't a valid manifest
+    Manifest(String),
+    /// A card file isn't the one the manifest lists
+    HashMismatch { path: String },
+    /// The card files couldn't be loaded as a card pool
+    Load(Box<dyn std::error::Error>),
+}
+
+impl fmt::Display for SyncError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyncError::Fetch { path, error } => write!(f, "fetching {} failed: {}", path, error),
+            SyncError::BadSignature => write!(f, "the manifest's signature is not valid"),
+            SyncError::Manifest(error) => write!(f, "the manifest is not valid: {}", error),
+            SyncError::HashMismatch { path } => write!(f, "{} doesn't match its hash in the manifest", path),
+            SyncError::Load(error) => write!(f, "the card pool couldn't be loaded: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for SyncError {}
+
+/// A card file kept from a sync
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SyncedFile {
+    pub path: String,
+    pub contents: String,
+}
+
+/// A card pool kept in step with a server, so balance patches reach clients without a new build.
+/// Each sync checks the manifest's signature with the server's public key, then only fetches the
+/// card files whose hashes have changed.
+#[derive(Clone, Debug)]
+pub struct CardSync {
+    /// Where the manifest is on the server
+    pub manifest_path: String,
+    public_key: VerifyingKey,
+    files: Vec<SyncedFile>,
+}
+
+impl CardSync {
+    pub fn new(manifest_path: impl Into<String>, public_key: VerifyingKey) -> Self {
+        CardSync {
+            manifest_path: manifest_path.into(),
+            public_key,
+            files: vec![],
+        }
+    }
+
+    /// Starts from card files saved from an earlier sync, so only the files which have changed
+    /// since are fetched. Saved files are checked against the manifest like fetched ones.
+    pub fn with_files(mut self, files: Vec<SyncedFile>) -> Self {
+        self.files = files;
+        self
+    }
+
+    /// The card files from the last successful sync, for saving between sessions
+    pub fn files(&self) -> &[SyncedFile] {
+        &self.files
+    }
+
+    /// Fetches and checks the manifest, fetches the card files which have changed, and loads
+    /// the card pool they make up
+    pub fn sync(&mut self, remote: &impl Remote) -> Result<Cards, SyncError> {
+        let fetch = |path: &str| remote.fetch(path).map_err(|error| SyncError::Fetch { path: path.to_owned(), error });
+        let manifest = fetch(&self.manifest_path)?;
+        let signature = fetch(&format!("{}.sig", self.manifest_path))?;
+        let signature = from_hex(signature.trim())
+            .and_then(|bytes| Signature::from_slice(&bytes).ok())
+            .ok_or(SyncError::BadSignature)?;
+        self.public_key.verify_strict(manifest.as_bytes(), &signature).map_err(|_| SyncError::BadSignature)?;
+        let manifest: Manifest = toml::from_str(&manifest).map_err(|error| SyncError::Manifest(error.to_string()))?;
+        let mut files = Vec::with_capacity(manifest.cards.len());
+        for entry in &manifest.cards {
+            let kept = self.files.iter()
+                .find(|file| file.path == entry.path && content_hash(&file.contents) == entry.hash);
+            let file = match kept {
+                Some(file) => file.clone(),
+                None => {
+                    let contents = fetch(&entry.path)?;
+                    if content_hash(&contents) != entry.hash {
+                        return Err(SyncError::HashMismatch { path: entry.path.clone() });
+                    }
+                    SyncedFile { path: entry.path.clone(), contents }
+                },
+            };
+            files.push(file);
+        }
+        let cards = Cards::from_source(MemorySource { files: files.iter().map(|file| file.contents.clone()).collect() })
+            .map_err(SyncError::Load)?;
+        self.files = files;
+        Ok(cards)
+    }
+}
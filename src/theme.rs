@@ -0,0 +1,143 @@
+use crate::card_type::CardType;
+use crate::cards::Cards;
+
+use serde::Deserialize;
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// One of the eight colors every terminal can draw text in
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+}
+
+impl Color {
+    /// The ANSI escape code which draws the text after it in the color
+    pub fn escape(self) -> &'static str {
+        match self {
+            Color::Black => "\x1b[30m",
+            Color::Red => "\x1b[31m",
+            Color::Green => "\x1b[32m",
+            Color::Yellow => "\x1b[33m",
+            Color::Blue => "\x1b[34m",
+            Color::Magenta => "\x1b[35m",
+            Color::Cyan => "\x1b[36m",
+            Color::White => "\x1b[37m",
+        }
+    }
+}
+
+/// How to draw a card in place of its art. Anything left out falls back to the next style which
+/// applies to the card.
+#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CardStyle {
+    pub color: Option<Color>,
+    /// Drawn before the card's name
+    pub glyph: Option<char>,
+}
+
+impl CardStyle {
+    /// This style with anything it leaves out taken from the other style
+    fn or(self, other: CardStyle) -> CardStyle {
+        CardStyle { color: self.color.or(other.color), glyph: self.glyph.or(other.glyph) }
+    }
+}
+
+/// A problem with a theme file
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ThemeError {
+    /// The file isn't a valid theme
+    Parse(String),
+    /// The theme styles a card which isn't in the card pool
+    UnknownCard(String),
+}
+
+impl fmt::Display for ThemeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThemeError::Parse(error) => write!(f, "invalid theme file: {}", error),
+            ThemeError::UnknownCard(card) => write!(f, "the theme styles {:?} which is not in the card pool", card),
+        }
+    }
+}
+
+impl std::error::Error for ThemeError {}
+
+/// How a terminal client draws cards in the small space it has for each
+pub trait CardRenderer {
+    /// The text for the card, at most `width` characters long
+    fn label(&self, card_type: &CardType, width: usize) -> String;
+
+    /// Wraps text which has already been laid out in whatever escape codes color it, so the
+    /// escape codes don't count towards its width
+    fn paint(&self, card_type: &CardType, text: &str) -> String;
+}
+
+/// The colors and glyphs to draw cards with, by card name or tag, so playtest boards can be read
+/// at a glance. A card's own style comes first, then the styles of its tags in the order the card
+/// lists them, then the default style. The default theme draws every card as its plain name.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Theme {
+    #[serde(default)]
+    default: CardStyle,
+    #[serde(default)]
+    tags: BTreeMap<String, CardStyle>,
+    #[serde(default)]
+    cards: BTreeMap<String, CardStyle>,
+}
+
+impl Theme {
+    /// Loads the theme in the data directory, checking each card it styles is in the card pool
+    #[cfg(feature = "fs")]
+    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+        let file = std::fs::read_to_string("data/theme.toml")?;
+        Ok(Theme::parse(&file, Cards::get())?)
+    }
+
+    /// Parses a theme file which has already been read, checking each card it styles is in the
+    /// card pool
+    pub fn parse(file: &str, card_pool: &Cards) -> Result<Self, ThemeError> {
+        let theme: Theme = toml::from_str(file).map_err(|error| ThemeError::Parse(error.to_string()))?;
+        if let Some(unknown) = theme.cards.keys().find(|name| card_pool.card((*name).clone()).is_none()) {
+            return Err(ThemeError::UnknownCard(unknown.clone()));
+        }
+        Ok(theme)
+    }
+
+    /// The style to draw the card with
+    pub fn style(&self, card_type: &CardType) -> CardStyle {
+        let own = self.cards.get(&card_type.name).copied().unwrap_or_default();
+        card_type.tags.iter()
+            .filter_map(|tag| self.tags.get(tag))
+            .fold(own, |style, &tag| style.or(tag))
+            .or(self.default)
+    }
+}
+
+impl CardRenderer for Theme {
+    fn label(&self, card_type: &CardType, width: usize) -> String {
+        let text = match self.style(card_type).glyph {
+            Some(glyph) => format!("{}{}", glyph, card_type.name),
+            None => card_type.name.clone(),
+        };
+        text.chars().take(width).collect()
+    }
+
+    fn paint(&self, card_type: &CardType, text: &str) -> String {
+        match self.style(card_type).color {
+            Some(color) => format!("{}{}\x1b[0m", color.escape(), text),
+            None => text.to_owned(),
+        }
+    }
+}
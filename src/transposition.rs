@@ -0,0 +1,190 @@
+use crate::agent;
+use crate::cards::Cards;
+use crate::state::{GameState, Player, StateHash};
+
+use std::sync::Mutex;
+
+/// What a search found a position to be worth, which is exact or only a bound when alpha-beta
+/// pruning cut the search short
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Bound {
+    Exact,
+    /// The position is worth at least the score
+    Lower,
+    /// The position is worth at most the score
+    Upper,
+}
+
+impl Bound {
+    /// The bound from the other player's side, whose scores are the negative
+    fn flip(self) -> Bound {
+        match self {
+            Bound::Exact => Bound::Exact,
+            Bound::Lower => Bound::Upper,
+            Bound::Upper => Bound::Lower,
+        }
+    }
+}
+
+/// How often lookups into a table found what they were after
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct TableStats {
+    pub lookups: u64,
+    pub hits: u64,
+    pub stores: u64,
+    /// Stores which replaced a different position, because the table is bounded
+    pub overwrites: u64,
+}
+
+impl TableStats {
+    /// The fraction of lookups which were hits, 0 before any lookups
+    pub fn hit_rate(&self) -> f64 {
+        if self.lookups == 0 { 0.0 } else { self.hits as f64 / self.lookups as f64 }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Search {
+    depth: u32,
+    // scores are kept for player one, and negated for player two
+    score: i64,
+    bound: Bound,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Entry {
+    hash: StateHash,
+    evaluation: Option<i64>,
+    option_count: Option<usize>,
+    search: Option<Search>,
+}
+
+struct Table {
+    entries: Vec<Option<Entry>>,
+    stats: TableStats,
+}
+
+/// What agents have worked out about positions, keyed by state hash so the same position is
+/// only worked out once however it was reached. A table can be shared by every agent and game in
+/// a batch run. It never uses more than the memory it was given: each position has one place in
+/// the table it can go, and a new position replaces whatever was there before.
+pub struct TranspositionTable {
+    table: Mutex<Table>,
+}
+
+impl TranspositionTable {
+    /// A table using at most about this many bytes, with room for at least one position
+    pub fn new(max_bytes: usize) -> Self {
+        let capacity = (max_bytes / std::mem::size_of::<Option<Entry>>()).max(1);
+        TranspositionTable {
+            table: Mutex::new(Table {
+                entries: vec![None ; capacity],
+                stats: TableStats::default(),
+            }),
+        }
+    }
+
+    /// How many positions the table can hold at once
+    pub fn capacity(&self) -> usize {
+        self.table.lock().unwrap().entries.len()
+    }
+
+    pub fn stats(&self) -> TableStats {
+        self.table.lock().unwrap().stats
+    }
+
+    /// Forgets every position, keeping the statistics
+    pub fn clear(&self) {
+        let mut table = self.table.lock().unwrap();
+        table.entries.iter_mut().for_each(|entry| *entry = None);
+    }
+
+    /// Looks the position up, counting a hit if it has what the closure is after
+    fn lookup<T>(&self, hash: StateHash, get: impl FnOnce(&Entry) -> Option<T>) -> Option<T> {
+        let mut table = self.table.lock().unwrap();
+        let index = (hash.0 % table.entries.len() as u64) as usize;
+        table.stats.lookups += 1;
+        let found = table.entries[index].as_ref().filter(|entry| entry.hash == hash).and_then(get);
+        if found.is_some() {
+            table.stats.hits += 1;
+        }
+        found
+    }
+
+    /// Updates the position's entry, replacing any other position in its place
+    fn store(&self, hash: StateHash, update: impl FnOnce(&mut Entry)) {
+        let mut table = self.table.lock().unwrap();
+        let table = &mut *table;
+        let index = (hash.0 % table.entries.len() as u64) as usize;
+        table.stats.stores += 1;
+        let entry = &mut table.entries[index];
+        if entry.is_some_and(|entry| entry.hash != hash) {
+            *entry = None;
+            table.stats.overwrites += 1;
+        }
+        update(entry.get_or_insert(Entry { hash, evaluation: None, option_count: None, search: None }));
+    }
+
+    /// The same as `agent::evaluate`, only worked out once for each position
+    pub fn evaluate(&self, card_pool: &Cards, game: &GameState, player: Player) -> i64 {
+        let hash = game.state_hash();
+        let score = match self.lookup(hash, |entry| entry.evaluation) {
+            Some(score) => score,
+            None => {
+                let score = agent::evaluate(card_pool, game, Player::One);
+                self.store(hash, |entry| entry.evaluation = Some(score));
+                score
+            },
+        };
+        for_player(score, player)
+    }
+
+    /// The same as `GameState::legal_option_count`, only worked out once for each position
+    pub fn option_count(&self, card_pool: &Cards, game: &GameState) -> usize {
+        let hash = game.state_hash();
+        match self.lookup(hash, |entry| entry.option_count) {
+            Some(count) => count,
+            None => {
+                let count = game.legal_option_count(card_pool);
+                self.store(hash, |entry| entry.option_count = Some(count));
+                count
+            },
+        }
+    }
+
+    /// The score of a search of the position for the player at least as deep as asked for, if
+    /// one was stored which is exact or whose bound falls outside the alpha-beta window
+    pub fn search_score(&self, hash: StateHash, player: Player, depth: u32, alpha: i64, beta: i64) -> Option<i64> {
+        self.lookup(hash, |entry| {
+            let search = entry.search.filter(|search| search.depth >= depth)?;
+            let score = for_player(search.score, player);
+            let bound = if player == Player::One { search.bound } else { search.bound.flip() };
+            match bound {
+                Bound::Exact => Some(score),
+                Bound::Lower if score >= beta => Some(score),
+                Bound::Upper if score <= alpha => Some(score),
+                _ => None,
+            }
+        })
+    }
+
+    /// Keeps the score a search of the position found for the player, unless a deeper search of
+    /// it is already kept
+    pub fn store_search(&self, hash: StateHash, player: Player, depth: u32, score: i64, bound: Bound) {
+        let bound = if player == Player::One { bound } else { bound.flip() };
+        let search = Search { depth, score: for_player(score, player), bound };
+        self.store(hash, |entry| {
+            if entry.search.is_none_or(|kept| kept.depth <= depth) {
+                entry.search = Some(search);
+            }
+        });
+    }
+}
+
+/// The score for player one from the player's side, which is the same both ways round
+fn for_player(score: i64, player: Player) -> i64 {
+    match player {
+        Player::One => score,
+        Player::Two => -score,
+    }
+}
@@ -0,0 +1,271 @@
+use crate::card_type::CardType;
+use crate::cards::Cards;
+use crate::ids::EventSeq;
+use crate::state::{GameState, Player, Row};
+use crate::summary::describe_turn;
+use crate::theme::{self, CardRenderer, Theme};
+use crate::view::{FieldView, PlayerView, VisibleCard};
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::Frame;
+
+use std::io;
+
+const CELL: usize = 9;
+const LOG_LINES: usize = 6;
+
+/// A key press the interface responds to
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Key {
+    Up,
+    Down,
+    Enter,
+    Quit,
+    Other,
+}
+
+impl Key {
+    /// Waits for the next key press, from a terminal the host has put in raw mode such as with
+    /// `ratatui::init`
+    pub fn read() -> io::Result<Key> {
+        loop {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    return Ok(Key::from(key));
+                }
+            }
+        }
+    }
+}
+
+impl From<KeyEvent> for Key {
+    /// The arrow keys move up and down, as do `k` and `j`
+    fn from(key: KeyEvent) -> Self {
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => Key::Up,
+            KeyCode::Down | KeyCode::Char('j') => Key::Down,
+            KeyCode::Enter | KeyCode::Char(' ') => Key::Enter,
+            KeyCode::Char('q') | KeyCode::Esc => Key::Quit,
+            _ => Key::Other,
+        }
+    }
+}
+
+impl From<theme::Color> for Color {
+    fn from(color: theme::Color) -> Self {
+        match color {
+            theme::Color::Black => Color::Black,
+            theme::Color::Red => Color::Red,
+            theme::Color::Green => Color::Green,
+            theme::Color::Yellow => Color::Yellow,
+            theme::Color::Blue => Color::Blue,
+            theme::Color::Magenta => Color::Magenta,
+            theme::Color::Cyan => Color::Cyan,
+            // the eight colors every terminal has only go up to this light gray
+            theme::Color::White => Color::Gray,
+        }
+    }
+}
+
+/// A full screen terminal interface for playtesting, drawn with ratatui: a board panel for each
+/// player, the pending chain, the viewer's hand, their options with one selected, and a log of
+/// what has happened. The interface only keeps what to draw, so the host sets up the terminal,
+/// decides who plays each side and passes on the option chosen.
+#[derive(Clone, Debug, Default)]
+pub struct Tui {
+    selected: usize,
+    log: Vec<String>,
+    seen: EventSeq,
+    theme: Theme,
+}
+
+impl Tui {
+    pub fn new() -> Self {
+        Tui::default()
+    }
+
+    /// Draws cards on the boards and in hand with the theme's colors and glyphs
+    pub fn with_theme(theme: Theme) -> Self {
+        Tui { theme, ..Tui::default() }
+    }
+
+    /// Adds what has happened since the last update to the log
+    pub fn update(&mut self, game: &GameState, card_pool: &Cards) {
+        let events = game.events_since(self.seen);
+        if !events.is_empty() {
+            self.log.push(describe_turn(events, card_pool));
+            self.seen = game.event_seq();
+        }
+    }
+
+    /// Every line of the log, oldest first
+    pub fn log(&self) -> &[String] {
+        &self.log
+    }
+
+    /// The index of the selected option
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Moves the selection between the options, returning the index of the option chosen when
+    /// Enter is pressed
+    pub fn handle_key(&mut self, key: Key, options: usize) -> Option<usize> {
+        self.selected = self.selected.min(options.saturating_sub(1));
+        match key {
+            Key::Up => self.selected = self.selected.checked_sub(1).unwrap_or(options.saturating_sub(1)),
+            Key::Down if self.selected + 1 < options => self.selected += 1,
+            Key::Down => self.selected = 0,
+            Key::Enter if options > 0 => {
+                let chosen = self.selected;
+                self.selected = 0;
+                return Some(chosen);
+            },
+            Key::Enter | Key::Quit | Key::Other => (),
+        }
+        None
+    }
+
+    /// Draws the game as the viewer sees it over the whole frame
+    pub fn render(&self, frame: &mut Frame, game: &GameState, viewer: Player, card_pool: &Cards) {
+        let view = PlayerView::of(game, viewer, card_pool);
+        let name = |card_type| card_pool.card(card_type).map_or("?", |card_type| card_type.name.as_str());
+        let opponent = viewer.next();
+
+        let mut chain: Vec<Line> = game.pending_chain()
+            .iter()
+            .enumerate()
+            .map(|(i, link)| {
+                // the last link activated is the first to resolve
+                let first = if i + 1 == game.pending_chain().len() { " <- resolves first" } else { "" };
+                Line::from(format!("{}. {:?} {}{}", i + 1, link.player, name(link.card), first))
+            })
+            .collect();
+        if chain.is_empty() {
+            chain.push(Line::from("empty"));
+        }
+
+        let selected = self.selected.min(view.options.len().saturating_sub(1));
+        let mut options: Vec<Line> = view.annotations
+            .iter()
+            .enumerate()
+            .map(|(i, annotation)| {
+                let mut label = annotation.verb.to_owned();
+                if let Some(card) = annotation.source.and_then(|instance| game.card(instance)) {
+                    label.push(' ');
+                    label.push_str(name(card.card_type));
+                }
+                for (player, slot) in &annotation.slots {
+                    label.push_str(&format!(" {:?}:{:?}", player, slot));
+                }
+                if i == selected {
+                    Line::styled(format!("> {}", label), Style::new().add_modifier(Modifier::REVERSED))
+                } else {
+                    Line::from(format!("  {}", label))
+                }
+            })
+            .collect();
+        if view.options.is_empty() {
+            options.push(Line::from("waiting for the other player"));
+        }
+
+        let log: Vec<Line> = self.log[self.log.len().saturating_sub(LOG_LINES)..]
+            .iter()
+            .map(|entry| Line::from(entry.as_str()))
+            .collect();
+
+        let opponent_board: Vec<Line> = [Row::Back, Row::Front].iter().map(|&row| self.row_line(view.field(opponent), row, card_pool)).collect();
+        let own_board: Vec<Line> = [Row::Front, Row::Back].iter().map(|&row| self.row_line(view.field(viewer), row, card_pool)).collect();
+        let hand = vec![self.card_list(&view.field(viewer).hand, card_pool)];
+        // every panel is as tall as its lines and borders, except the options which fit in
+        // whatever is left over
+        let height = |lines: &[Line]| Constraint::Length(lines.len() as u16 + 2);
+        let areas = Layout::vertical([
+            height(&opponent_board),
+            height(&chain),
+            height(&own_board),
+            height(&hand),
+            Constraint::Min(3),
+            Constraint::Length(LOG_LINES as u16 + 2),
+        ])
+        .split(frame.area());
+        render_panel(frame, areas[0], board_title(&view, opponent, "opponent"), opponent_board, 0);
+        render_panel(frame, areas[1], "Chain".to_owned(), chain, 0);
+        render_panel(frame, areas[2], board_title(&view, viewer, "you"), own_board, 0);
+        render_panel(frame, areas[3], "Hand".to_owned(), hand, 0);
+        // scrolls the options down as far as the selected option if it wouldn't be shown
+        let shown = usize::from(areas[4].height.saturating_sub(2)).max(1);
+        let scroll = (selected + 1).saturating_sub(shown);
+        render_panel(frame, areas[4], format!("Options ({:?} has priority)", view.priority), options, scroll);
+        render_panel(frame, areas[5], "Log".to_owned(), log, 0);
+    }
+
+    fn row_line(&self, field: &FieldView, row: Row, card_pool: &Cards) -> Line<'static> {
+        row.slots()
+            .map(|slot| match field.field.iter().find(|card| card.slot == slot) {
+                Some(card) => match card_pool.card(card.card_type) {
+                    Some(card_type) => {
+                        let text = match card.damage {
+                            0 => self.theme.label(card_type, CELL - 1),
+                            damage => format!("{}*{}", self.theme.label(card_type, CELL - 3), damage),
+                        };
+                        Span::styled(pad(&text, CELL), self.style(card_type))
+                    },
+                    None => Span::raw(pad("?", CELL)),
+                },
+                None => Span::raw(pad(&format!("{:?}", slot).to_lowercase(), CELL)),
+            })
+            .collect()
+    }
+
+    /// The cards in a line, which is cut off at the edge of the panel if they don't fit
+    fn card_list(&self, cards: &[VisibleCard], card_pool: &Cards) -> Line<'static> {
+        if cards.is_empty() {
+            return Line::from("empty");
+        }
+        let mut spans = vec![];
+        for card in cards {
+            if !spans.is_empty() {
+                spans.push(Span::raw(", "));
+            }
+            spans.push(match card.known().and_then(|card_type| card_pool.card(card_type)) {
+                Some(card_type) => Span::styled(self.theme.label(card_type, usize::MAX), self.style(card_type)),
+                None => Span::raw("?"),
+            });
+        }
+        Line::from(spans)
+    }
+
+    fn style(&self, card_type: &CardType) -> Style {
+        match self.theme.style(card_type).color {
+            Some(color) => Style::new().fg(color.into()),
+            None => Style::new(),
+        }
+    }
+}
+
+/// Draws the lines in a bordered panel, skipping the number of lines scrolled past
+fn render_panel(frame: &mut Frame, area: Rect, title: String, lines: Vec<Line>, scroll: usize) {
+    let panel = Paragraph::new(lines)
+        .block(Block::bordered().title(format!(" {} ", title)))
+        .scroll((scroll as u16, 0));
+    frame.render_widget(panel, area);
+}
+
+fn board_title(view: &PlayerView, player: Player, who: &str) -> String {
+    let field = view.field(player);
+    format!(
+        "{:?} ({}): hand {}, left {}, right {}, center {}, resources {}",
+        player, who, field.hand.len(), field.left_deck.len(), field.right_deck.len(), field.center_deck.len(), field.resources,
+    )
+}
+
+fn pad(text: &str, width: usize) -> String {
+    let text: String = text.chars().take(width).collect();
+    let padding = width - text.chars().count();
+    format!("{}{}", text, " ".repeat(padding))
+}
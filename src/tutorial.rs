@@ -0,0 +1,164 @@
+use crate::cards::Cards;
+use crate::state::{GameEvent, GameState, InvalidAction, PlayerOption};
+
+use std::fmt;
+
+type OptionFilter = Box<dyn Fn(&PlayerOption) -> bool + Send + Sync>;
+type GameCheck = Box<dyn Fn(&GameState) -> bool + Send + Sync>;
+
+/// One move of a tutorial script, for whichever player has priority
+pub struct Step {
+    /// What the player is told before taking the step, recorded as a `GameEvent::Instruction`
+    pub message: Option<String>,
+    allowed: OptionFilter,
+    expected: Option<GameCheck>,
+}
+
+impl Step {
+    /// A step which allows any of the priority player's options
+    pub fn any() -> Self {
+        Step {
+            message: None,
+            allowed: Box::new(|_| true),
+            expected: None,
+        }
+    }
+
+    /// A step which only allows the one option
+    pub fn only(option: PlayerOption) -> Self {
+        Step::allowing(move |allowed| *allowed == option)
+    }
+
+    /// A step which only allows the options the filter accepts
+    pub fn allowing(filter: impl Fn(&PlayerOption) -> bool + Send + Sync + 'static) -> Self {
+        Step {
+            allowed: Box::new(filter),
+            ..Step::any()
+        }
+    }
+
+    pub fn message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    /// Checks the game once the step's option is taken. If the check fails the option is undone.
+    pub fn expect(mut self, check: impl Fn(&GameState) -> bool + Send + Sync + 'static) -> Self {
+        self.expected = Some(Box::new(check));
+        self
+    }
+}
+
+/// Why a tutorial didn't take an option
+#[derive(Clone, Debug)]
+pub enum TutorialError {
+    /// The option isn't one the current step allows
+    NotAllowed,
+    /// The game didn't look as the step expected after the option, so it was undone
+    Unexpected { step: usize },
+    /// The script has no steps left
+    Finished,
+    /// The engine rejected the option
+    Invalid(InvalidAction),
+}
+
+impl fmt::Display for TutorialError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TutorialError::NotAllowed => write!(f, "the tutorial doesn't allow that option yet"),
+            TutorialError::Unexpected { step } => write!(f, "step {} of the tutorial didn't go as expected", step),
+            TutorialError::Finished => write!(f, "the tutorial has finished"),
+            TutorialError::Invalid(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for TutorialError {}
+
+impl From<InvalidAction> for TutorialError {
+    fn from(error: InvalidAction) -> Self {
+        TutorialError::Invalid(error)
+    }
+}
+
+/// A game played through a script of steps on the real engine, so clients can guide a new
+/// player through the rules one move at a time
+pub struct TutorialGame {
+    game: GameState,
+    steps: Vec<Step>,
+    // the step the game is waiting on
+    step: usize,
+}
+
+impl TutorialGame {
+    pub fn new(mut game: GameState, steps: Vec<Step>) -> Self {
+        if let Some(message) = steps.first().and_then(|step| step.message.clone()) {
+            game.record(GameEvent::Instruction(message));
+        }
+        TutorialGame {
+            game,
+            steps,
+            step: 0,
+        }
+    }
+
+    pub fn game(&self) -> &GameState {
+        &self.game
+    }
+
+    /// Ends the script and returns the game, which can be played on freely
+    pub fn into_game(self) -> GameState {
+        self.game
+    }
+
+    /// The step the game is waiting on, if the script hasn't finished
+    pub fn current_step(&self) -> Option<&Step> {
+        self.steps.get(self.step)
+    }
+
+    /// The index of the step the game is waiting on, which is the number of steps once the
+    /// script has finished
+    pub fn step_index(&self) -> usize {
+        self.step
+    }
+
+    pub fn finished(&self) -> bool {
+        self.step >= self.steps.len()
+    }
+
+    /// The priority player's options which the current step allows, which is none once the
+    /// script has finished
+    pub fn options(&self, card_pool: &Cards) -> Vec<PlayerOption> {
+        match self.current_step() {
+            Some(step) => self.game.legal_options(card_pool)
+                .iter()
+                .filter(|option| (step.allowed)(option))
+                .cloned()
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// Takes an option for the priority player if the current step allows it, then moves on to
+    /// the next step and records its message
+    pub fn take_option(&mut self, card_pool: &Cards, option: PlayerOption) -> Result<(), TutorialError> {
+        let step = self.current_step().ok_or(TutorialError::Finished)?;
+        if !(step.allowed)(&option) {
+            return Err(TutorialError::NotAllowed);
+        }
+        let snapshot = step.expected.as_ref().map(|_| self.game.snapshot());
+        self.game.priorty_player_take_option(card_pool, option)?;
+        let step = &self.steps[self.step];
+        if let (Some(expected), Some(snapshot)) = (&step.expected, snapshot) {
+            if !expected(&self.game) {
+                self.game = snapshot;
+                return Err(TutorialError::Unexpected { step: self.step });
+            }
+        }
+        self.step += 1;
+        if let Some(message) = self.current_step().and_then(|step| step.message.clone()) {
+            self.game.record(GameEvent::Instruction(message));
+        }
+        Ok(())
+    }
+}
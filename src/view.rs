@@ -0,0 +1,222 @@
+use crate::card_type::CardTypeIdentifier;
+use crate::cards::Cards;
+use crate::state::{Action, Card, CardInstance, Column, DrawRule, FaceDownDeck, FieldSlot, GameState, Player, PlayerOption};
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use std::sync::Arc;
+
+/// A card on the field, which both players can see
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct FieldCard {
+    pub slot: FieldSlot,
+    pub card_type: CardTypeIdentifier,
+    pub damage: u32,
+}
+
+/// A card in a hidden zone, which the viewer can see is there but may not know the identity of
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum VisibleCard {
+    Known(CardTypeIdentifier),
+    Hidden,
+}
+
+impl VisibleCard {
+    /// The card's type, if the viewer knows it
+    pub fn known(self) -> Option<CardTypeIdentifier> {
+        match self {
+            VisibleCard::Known(card_type) => Some(card_type),
+            VisibleCard::Hidden => None,
+        }
+    }
+
+    pub fn is_hidden(self) -> bool {
+        self == VisibleCard::Hidden
+    }
+}
+
+impl From<Option<CardTypeIdentifier>> for VisibleCard {
+    fn from(card_type: Option<CardTypeIdentifier>) -> Self {
+        card_type.map_or(VisibleCard::Hidden, VisibleCard::Known)
+    }
+}
+
+/// One player's zones as a viewer sees them, where the viewer sees how many cards are in each
+/// hidden zone even if they don't know what the cards are
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct FieldView {
+    pub field: Vec<FieldCard>,
+    pub hand: Vec<VisibleCard>,
+    /// The left deck, with the top of the deck last
+    pub left_deck: Vec<VisibleCard>,
+    /// The right deck, with the top of the deck last
+    pub right_deck: Vec<VisibleCard>,
+    pub center_deck: Vec<CardTypeIdentifier>,
+    pub destroyed: Vec<(Column, CardTypeIdentifier)>,
+    pub removed: Vec<CardTypeIdentifier>,
+    pub resources: u32,
+    /// How card effects have changed the way the player may draw, with how many more of their
+    /// turns each change lasts, so clients can explain why a deck can't be drawn from
+    pub draw_rules: Vec<(DrawRule, u32)>,
+}
+
+/// Everything a player could see of the game at one point, using what they knew then rather
+/// than what either player knows later
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PlayerView {
+    pub viewer: Player,
+    pub turn: Player,
+    pub priority: Player,
+    fields: [FieldView ; 2],
+    /// The viewer's options, empty unless they had priority
+    pub options: Vec<PlayerOption>,
+    /// How to present each of the options, in the same order
+    pub annotations: Vec<OptionAnnotation>,
+    #[serde(skip)]
+    game: Unseen,
+}
+
+/// The game a view was made from with every card the viewer didn't know swapped for the same
+/// placeholder, so fair agents can search it once they've guessed those cards. Only views made
+/// while the viewer had priority have one, never views read back from a client or a replay, so
+/// views are equal whatever their games are.
+#[derive(Clone, Debug, Default)]
+struct Unseen(Option<Arc<GameState>>);
+
+impl PartialEq for Unseen {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for Unseen {}
+
+/// What a client needs to present an option on the board, without working out which cards and
+/// slots it involves from the option itself
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct OptionAnnotation {
+    /// The card taking the action, if any
+    pub source: Option<CardInstance>,
+    /// The slots the option touches on each player's field, such as where a card is summoned to
+    /// or the cards an effect targets
+    pub slots: Vec<(Player, FieldSlot)>,
+    /// A short verb for labelling the option, such as "summon"
+    pub verb: &'static str,
+}
+
+/// Every verb `OptionAnnotation::of` labels options with
+const VERBS: [&str ; 9] = ["draw left", "draw right", "skip draw", "end turn", "pass", "summon", "revive", "attack", "activate"];
+
+impl<'de> Deserialize<'de> for OptionAnnotation {
+    /// Reads a saved annotation back, with the same verb `OptionAnnotation::of` gives
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Saved {
+            source: Option<CardInstance>,
+            slots: Vec<(Player, FieldSlot)>,
+            verb: String,
+        }
+        let saved = Saved::deserialize(deserializer)?;
+        let verb = VERBS.iter()
+            .find(|&&known| known == saved.verb)
+            .copied()
+            .ok_or_else(|| D::Error::unknown_variant(&saved.verb, &VERBS))?;
+        Ok(OptionAnnotation { source: saved.source, slots: saved.slots, verb })
+    }
+}
+
+impl OptionAnnotation {
+    /// Annotates one of the priority player's options in the game
+    pub fn of(game: &GameState, option: &PlayerOption) -> Self {
+        let player = game.priority();
+        let slot_of = |instance: CardInstance| game.controller_of(instance)
+            .and_then(|controller| game.field_of(controller).slot_of(instance).map(|slot| (controller, slot)));
+        let (source, slots, verb) = match option {
+            PlayerOption::Draw(FaceDownDeck::Left) => (None, vec![], "draw left"),
+            PlayerOption::Draw(FaceDownDeck::Right) => (None, vec![], "draw right"),
+            PlayerOption::SkipDraw => (None, vec![], "skip draw"),
+            PlayerOption::SkipAction => (None, vec![], "end turn"),
+            PlayerOption::SkipResponse => (None, vec![], "pass"),
+            PlayerOption::Action(Action::Summon { instance, slot }) => (Some(*instance), vec![(player, *slot)], "summon"),
+            PlayerOption::Action(Action::Revive { instance, slot }) => (Some(*instance), vec![(player, *slot)], "revive"),
+            PlayerOption::Action(Action::Attack { instance, target }) => (
+                Some(*instance),
+                slot_of(*instance).into_iter().chain(Some((player.next(), *target))).collect(),
+                "attack",
+            ),
+            PlayerOption::Action(Action::Effect { instance, activation, .. }) => {
+                let mut slots: Vec<(Player, FieldSlot)> = slot_of(*instance).into_iter()
+                    .chain(activation.data.slot.map(|slot| (player, slot)))
+                    .chain(activation.data.targets.iter().filter_map(|&target| slot_of(target)))
+                    .collect();
+                slots.dedup();
+                (Some(*instance), slots, "activate")
+            },
+        };
+        OptionAnnotation { source, slots, verb }
+    }
+}
+
+impl PlayerView {
+    /// What the viewer sees of the game as it is now
+    pub fn of(game: &GameState, viewer: Player, card_pool: &Cards) -> Self {
+        let field = |owner: Player| {
+            let zone = game.field_of(owner);
+            let known = |card: &Card| VisibleCard::from(Some(card.card_type).filter(|_| game.knows(viewer, card.instance)));
+            let deck = |deck| game.deck_view(viewer, owner, deck).into_iter()
+                .map(|card| VisibleCard::from(card.map(|card| card.card_type)))
+                .collect();
+            FieldView {
+                field: zone.occupied_slots()
+                    .map(|(slot, card)| FieldCard { slot, card_type: card.card_type, damage: card.damage })
+                    .collect(),
+                hand: zone.hand().iter().map(known).collect(),
+                left_deck: deck(FaceDownDeck::Left),
+                right_deck: deck(FaceDownDeck::Right),
+                center_deck: zone.center_deck().iter().map(|card| card.card_type).collect(),
+                destroyed: Column::all()
+                    .flat_map(|column| zone.destroyed(column).iter().map(move |card| (column, card.card_type)))
+                    .collect(),
+                removed: zone.removed().iter().map(|card| card.card_type).collect(),
+                resources: zone.resources(),
+                draw_rules: game.draw_rules()
+                    .iter()
+                    .filter(|modifier| modifier.player == owner)
+                    .map(|modifier| (modifier.rule, modifier.turns))
+                    .collect(),
+            }
+        };
+        let options = if game.priority() == viewer {
+            game.priority_player_options(card_pool)
+        } else {
+            vec![]
+        };
+        PlayerView {
+            viewer,
+            turn: game.turn(),
+            priority: game.priority(),
+            fields: [field(Player::One), field(Player::Two)],
+            annotations: options.iter().map(|option| OptionAnnotation::of(game, option)).collect(),
+            game: Unseen(Some(game)
+                .filter(|_| !options.is_empty())
+                .map(|game| Arc::new(game.guessed_by(viewer, CardTypeIdentifier::default)))),
+            options,
+        }
+    }
+
+    /// A game the view could have been seen from, with `guess` giving the type of each card the
+    /// viewer didn't know, for agents to search without learning the hidden cards. None unless
+    /// the view was made from a game while the viewer had priority.
+    pub fn guess_game(&self, guess: impl FnMut() -> CardTypeIdentifier) -> Option<GameState> {
+        self.game.0.as_ref().map(|game| game.guessed_by(self.viewer, guess))
+    }
+
+    /// A player's zones as the viewer saw them
+    pub fn field(&self, player: Player) -> &FieldView {
+        match player {
+            Player::One => &self.fields[0],
+            Player::Two => &self.fields[1],
+        }
+    }
+}